@@ -4,6 +4,8 @@ use serde::Deserialize;
 use std::{
     env, fs,
     path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 mod config_defs {
@@ -12,6 +14,13 @@ mod config_defs {
     use proc_macro2::TokenStream;
     use quote::{ToTokens, quote};
 
+    fn quote_option_string(value: &Option<String>) -> TokenStream {
+        match value {
+            Some(value) => quote! { Some(#value.to_string()) },
+            None => quote! { None },
+        }
+    }
+
     impl ToTokens for StaticConfig<String> {
         fn to_tokens(&self, tokens: &mut TokenStream) {
             let contact = &self.contact;
@@ -61,8 +70,70 @@ mod config_defs {
         }
     }
 
+    impl ToTokens for HttpConfig {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let connect_timeout_secs = match self.connect_timeout_secs {
+                Some(secs) => quote! { Some(#secs) },
+                None => quote! { None },
+            };
+            let request_timeout_secs = match self.request_timeout_secs {
+                Some(secs) => quote! { Some(#secs) },
+                None => quote! { None },
+            };
+            let proxy = quote_option_string(&self.proxy);
+
+            tokens.extend(quote! {
+                HttpConfig {
+                    connect_timeout_secs: #connect_timeout_secs,
+                    request_timeout_secs: #request_timeout_secs,
+                    proxy: #proxy,
+                }
+            })
+        }
+    }
+
+    fn quote_option_strings(value: &Option<Vec<String>>) -> TokenStream {
+        match value {
+            Some(values) => quote! { Some(vec![#(#values.to_string()),*]) },
+            None => quote! { None },
+        }
+    }
+
+    impl ToTokens for Profile {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let properties = match &self.properties {
+                Some(properties) => {
+                    let key_value_pairs = properties.iter().map(|(k, v)| {
+                        quote! { ( #k.to_string(), #v.to_string() )}
+                    });
+
+                    quote! {
+                        Some(std::collections::HashMap::from([
+                            #(#key_value_pairs),*
+                        ]))
+                    }
+                }
+                None => quote! { None },
+            };
+
+            let gamerules = quote_option_strings(&self.gamerules);
+            let plugins = quote_option_strings(&self.plugins);
+            let java_args = quote_option_string(&self.java_args);
+
+            tokens.extend(quote! {
+                Profile {
+                    properties: #properties,
+                    gamerules: #gamerules,
+                    plugins: #plugins,
+                    java_args: #java_args,
+                }
+            })
+        }
+    }
+
     impl ToTokens for DynamicConfig {
         fn to_tokens(&self, tokens: &mut TokenStream) {
+            let config_version = &self.config_version;
             let default_java_args = &self.default_java_args;
             let nogui = &self.nogui;
             let servers_directory = &self.servers_directory;
@@ -87,13 +158,103 @@ mod config_defs {
                 quote! { None }
             };
 
+            let open_command = quote_option_string(&self.open_command);
+            let editor_command = quote_option_string(&self.editor_command);
+            let download_directory = quote_option_string(&self.download_directory);
+            let backup_directory = quote_option_string(&self.backup_directory);
+            let time_zone = quote_option_string(&self.time_zone);
+            let tab_title = quote_option_string(&self.tab_title);
+            let session_namespace = quote_option_string(&self.session_namespace);
+            let download_rate_limit = quote_option_string(&self.download_rate_limit);
+            let verify_jar_checksums = match self.verify_jar_checksums {
+                Some(verify) => quote! { Some(#verify) },
+                None => quote! { None },
+            };
+            let command_timeout = match self.command_timeout {
+                Some(secs) => quote! { Some(#secs) },
+                None => quote! { None },
+            };
+            let restricted_mode = match self.restricted_mode {
+                Some(restricted) => quote! { Some(#restricted) },
+                None => quote! { None },
+            };
+            let http_cache_ttl = match self.http_cache_ttl {
+                Some(secs) => quote! { Some(#secs) },
+                None => quote! { None },
+            };
+            let http_cache_fresh = match self.http_cache_fresh {
+                Some(secs) => quote! { Some(#secs) },
+                None => quote! { None },
+            };
+            let http_attempts = match self.http_attempts {
+                Some(attempts) => quote! { Some(#attempts) },
+                None => quote! { None },
+            };
+            let http_retry_delay_ms = match self.http_retry_delay_ms {
+                Some(ms) => quote! { Some(#ms) },
+                None => quote! { None },
+            };
+
+            let http = match &self.http {
+                Some(http) => quote! { Some(#http) },
+                None => quote! { None },
+            };
+
+            let groups_quote = if let Some(groups) = &self.groups {
+                let key_value_pairs = groups.iter().map(|(k, v)| {
+                    quote! { ( #k.to_string(), vec![#(#v.to_string()),*] )}
+                });
+
+                quote! {
+                    Some(std::collections::HashMap::from([
+                        #(#key_value_pairs),*
+                    ]))
+                }
+            } else {
+                quote! { None }
+            };
+
+            let profiles_quote = if let Some(profiles) = &self.profiles {
+                let key_value_pairs = profiles.iter().map(|(k, v)| {
+                    quote! { ( #k.to_string(), #v )}
+                });
+
+                quote! {
+                    Some(std::collections::HashMap::from([
+                        #(#key_value_pairs),*
+                    ]))
+                }
+            } else {
+                quote! { None }
+            };
+
             tokens.extend(quote! {
                 DynamicConfig {
+                    config_version: #config_version,
                     default_java_args: #default_java_args.to_string(),
                     nogui: #nogui,
                     servers_directory: #servers_directory.to_string(),
                     default_server: #default_server_quote,
                     rcon: #rcon_quote,
+                    open_command: #open_command,
+                    editor_command: #editor_command,
+                    download_directory: #download_directory,
+                    backup_directory: #backup_directory,
+                    time_zone: #time_zone,
+                    tab_title: #tab_title,
+                    session_namespace: #session_namespace,
+                    download_rate_limit: #download_rate_limit,
+                    verify_jar_checksums: #verify_jar_checksums,
+                    command_timeout: #command_timeout,
+                    restricted_mode: #restricted_mode,
+                    http_cache_ttl: #http_cache_ttl,
+                    http_cache_fresh: #http_cache_fresh,
+                    http_attempts: #http_attempts,
+                    http_retry_delay_ms: #http_retry_delay_ms,
+                    http: #http,
+                    groups: #groups_quote,
+                    profiles: #profiles_quote,
+                    extra: toml::Table::new(),
                 }
             });
         }
@@ -145,6 +306,45 @@ fn handle_static_config(cargo_manifest_dir: &Path) -> Result<Option<StaticConfig
         .unwrap_or(None))
 }
 
+/// The commit being built. Crates built from a published tarball have no `.git` directory, and
+/// asking git anyway could find the commit of whatever repository the tarball was unpacked in.
+fn git_commit(cargo_manifest_dir: &Path) -> Option<String> {
+    if !cargo_manifest_dir.join(".git").exists() {
+        build_log!("Not building from a git checkout");
+        return None;
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .current_dir(cargo_manifest_dir)
+        .output()
+        .inspect_err(|_err| {
+            build_log!("Failed to run git: {_err}");
+        })
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    Some(commit).filter(|commit| !commit.is_empty())
+}
+
+/// When the build happened as a unix timestamp, which reproducible builds fix with
+/// `SOURCE_DATE_EPOCH`
+fn build_date() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        })
+}
+
 fn main() -> Result<()> {
     build_log!("Build script running...");
     println!("cargo:rerun-if-changed=");
@@ -188,14 +388,25 @@ fn main() -> Result<()> {
 
     let default_dynamic_config = config.default_dynamic_config;
 
+    let git_commit = match git_commit(&cargo_manifest_dir) {
+        Some(commit) => quote! { Some(#commit) },
+        None => quote! { None },
+    };
+    let build_date = build_date();
+    let target = env::var("TARGET")?;
+
     let tokens = quote! {
         mod generated_cfg {
             use crate::config_defs::{StaticConfig, DynamicConfig};
             #[allow(unused)]
-            use crate::config_defs::{RconConfig, Password};
+            use crate::config_defs::{HttpConfig, Password, Profile, RconConfig};
             use std::sync::OnceLock;
 
             pub const STATIC_CONFIG: StaticConfig = #static_config;
+
+            pub const GIT_COMMIT: Option<&str> = #git_commit;
+            pub const BUILD_DATE: u64 = #build_date;
+            pub const TARGET: &str = #target;
             pub static DEFAULT_DYNAMIC_CONFIG: OnceLock<DynamicConfig> = OnceLock::new();
 
             pub fn get_default_dynamic_config() -> &'static DynamicConfig {
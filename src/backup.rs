@@ -0,0 +1,1053 @@
+//! Backups of whole servers, kept in a directory per server under the backup directory. A backup
+//! is either a gzipped tarball or, with `--incremental`, a snapshot directory whose files that
+//! haven't changed since the previous snapshot are hard links into it, so that only changes take
+//! up space.
+//!
+//! Every backup carries a manifest of the size and SHA-256 of each file, written as it is taken:
+//! as the last entry of a tarball, and next to a snapshot's tree. `verify` checks backups against
+//! their manifests, so that a backup is known to be good before it is needed.
+
+use crate::{
+    config::{self, get_expanded_servers_dir},
+    copy,
+    error::{Error, Result},
+    ignore::IgnoreRules,
+    nbt,
+    properties::Properties,
+    server::{self, get_server_dir_required},
+    session::get_alive_server_sessions,
+    timeutil::unix_now,
+    worlds,
+};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+const TARBALL_EXTENSION: &str = ".tar.gz";
+
+/// The manifest's entry in a tarball, which comes last so that it can list everything before it
+const TARBALL_MANIFEST: &str = ".mcserver-backup.toml";
+
+const SNAPSHOT_MANIFEST: &str = "manifest.toml";
+const SNAPSHOT_TREE: &str = "tree";
+
+/// Files that only make sense for a running server, as with copies
+const TRANSIENT_FILES: [&str; 1] = ["session.lock"];
+
+/// How many of a backup's problems are listed before the rest are only counted
+const MAX_LISTED_PROBLEMS: usize = 20;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct FileEntry {
+    size: u64,
+    sha256: String,
+    /// Whether a snapshot's file is a hard link into the snapshot it was based on
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    linked: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Manifest {
+    server: String,
+    created: u64,
+    /// The snapshot an incremental snapshot links its unchanged files into
+    base: Option<String>,
+    files: BTreeMap<String, FileEntry>,
+}
+
+impl Manifest {
+    fn new(server: &str, base: Option<String>) -> Result<Self> {
+        Ok(Self {
+            server: server.to_string(),
+            created: unix_now()?,
+            base,
+            files: BTreeMap::new(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Tarball,
+    Snapshot,
+}
+
+struct Backup {
+    name: String,
+    path: PathBuf,
+    kind: Kind,
+}
+
+/// The size and SHA-256 of each file found in a backup, by its path relative to the server
+type Found = BTreeMap<String, (u64, String)>;
+
+/// Which of a server's backups to verify
+pub enum Selection {
+    Named(String),
+    Latest,
+    All,
+}
+
+/// Returns where a server's backups are kept, which is the configured backup directory or a
+/// Backups directory beside the servers directory
+fn get_backups_dir(server: &str) -> Result<PathBuf> {
+    let root = match config::snapshot()?.backup_directory {
+        Some(dir) => PathBuf::from(shellexpand::full(&dir)?.as_ref()),
+        None => get_expanded_servers_dir()?
+            .parent()
+            .unwrap_or(Path::new("/"))
+            .join("Backups"),
+    };
+
+    Ok(root.join(server))
+}
+
+/// Lists a server's backups, oldest first. Backups are named after when they were taken, so that
+/// their names sort by age.
+fn list_backups(server: &str) -> Result<Vec<Backup>> {
+    let dir = get_backups_dir(server)?;
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = vec![];
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        // backups being taken or restore tests start with a dot
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        if let Some(name) = file_name.strip_suffix(TARBALL_EXTENSION)
+            && path.is_file()
+        {
+            backups.push(Backup {
+                name: name.to_string(),
+                path,
+                kind: Kind::Tarball,
+            });
+        } else if path.join(SNAPSHOT_MANIFEST).is_file() {
+            backups.push(Backup {
+                name: file_name.to_string(),
+                path,
+                kind: Kind::Snapshot,
+            });
+        }
+    }
+
+    backups.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(backups)
+}
+
+/// Names a new backup after the time it is taken, numbering it when another was taken within
+/// the same second
+fn unused_name(server: &str) -> Result<String> {
+    let taken = jiff::Timestamp::now()
+        .strftime("%Y%m%dT%H%M%SZ")
+        .to_string();
+    let existing = list_backups(server)?;
+
+    Ok((1..)
+        .map(|n| match n {
+            1 => taken.clone(),
+            n => format!("{taken}-{n}"),
+        })
+        .find(|name| existing.iter().all(|backup| backup.name != *name))
+        .expect("some number is unused"))
+}
+
+fn with_path_context(err: io::Error, path: &Path) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+/// Lists the files and directories of a server to back up, relative to it and parents first,
+/// following links as copies do
+fn walk(
+    root: &Path,
+    relative: &Path,
+    dirs: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let dir = root.join(relative);
+    let mut entries = fs::read_dir(&dir)
+        .map_err(|err| with_path_context(err, &dir))?
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if TRANSIENT_FILES.contains(&entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
+
+        let relative = relative.join(entry.file_name());
+        let metadata =
+            fs::metadata(entry.path()).map_err(|err| with_path_context(err, &entry.path()))?;
+
+        if metadata.is_dir() {
+            dirs.push(relative.clone());
+            walk(root, &relative, dirs, files)?;
+        } else if metadata.is_file() {
+            files.push(relative);
+        } else {
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m skipping {}, which is not a regular file",
+                entry.path().display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Passes what is read through to a hasher, counting it
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.len += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            len: 0,
+        }
+    }
+
+    fn finish(self) -> (u64, String) {
+        (self.len, format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+/// A file that changed size while it was read, which leaves the backup of it unusable
+fn changed_while_read(path: &Path) -> Error {
+    io::Error::other(format!(
+        "{} changed while it was backed up, stop the server or try again",
+        path.display()
+    ))
+    .into()
+}
+
+fn relative_name(relative: &Path) -> String {
+    relative.to_string_lossy().into_owned()
+}
+
+fn write_tarball(server_dir: &Path, dst: &Path, manifest: &mut Manifest) -> Result<()> {
+    let (mut dirs, mut files) = (vec![], vec![]);
+    walk(server_dir, Path::new(""), &mut dirs, &mut files)?;
+
+    let encoder = GzEncoder::new(BufWriter::new(File::create(dst)?), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for dir in &dirs {
+        builder.append_dir(dir, server_dir.join(dir))?;
+    }
+
+    for relative in files {
+        let path = server_dir.join(&relative);
+        let metadata = fs::metadata(&path)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&metadata);
+
+        // only the size in the header is read, so a file growing meanwhile can't overrun it
+        let mut reader = HashingReader::new(File::open(&path)?.take(metadata.len()));
+        builder.append_data(&mut header, &relative, &mut reader)?;
+        let (size, sha256) = reader.finish();
+        if size != metadata.len() {
+            return Err(changed_while_read(&path));
+        }
+
+        manifest.files.insert(
+            relative_name(&relative),
+            FileEntry {
+                size,
+                sha256,
+                linked: false,
+            },
+        );
+    }
+
+    let contents = toml::to_string(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created);
+    builder.append_data(&mut header, TARBALL_MANIFEST, contents.as_bytes())?;
+
+    let mut writer = builder.into_inner()?.finish()?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+/// Copies a file into a snapshot, returning the size and SHA-256 of what was copied. The copy is
+/// given the original's modification time, which the next snapshot compares against.
+fn copy_into_snapshot(src: &Path, dst: &Path) -> Result<(u64, String)> {
+    let metadata = fs::metadata(src)?;
+    let mut reader = HashingReader::new(BufReader::new(File::open(src)?));
+    let mut target = File::create(dst)?;
+    io::copy(&mut reader, &mut target)?;
+
+    target.set_permissions(metadata.permissions())?;
+    target.set_modified(metadata.modified()?)?;
+    Ok(reader.finish())
+}
+
+/// Whether a file is unchanged since a snapshot took it, going by its size and modification time
+fn is_unchanged(src: &Path, previous: &Path, entry: &FileEntry) -> bool {
+    let (Ok(src), Ok(previous)) = (fs::metadata(src), fs::metadata(previous)) else {
+        return false;
+    };
+
+    src.len() == entry.size
+        && previous.len() == entry.size
+        && src
+            .modified()
+            .ok()
+            .is_some_and(|src| previous.modified().ok() == Some(src))
+}
+
+fn write_snapshot(
+    server_dir: &Path,
+    dst: &Path,
+    base: Option<(&Backup, &Manifest)>,
+    manifest: &mut Manifest,
+) -> Result<()> {
+    let (mut dirs, mut files) = (vec![], vec![]);
+    walk(server_dir, Path::new(""), &mut dirs, &mut files)?;
+
+    let tree = dst.join(SNAPSHOT_TREE);
+    fs::create_dir_all(&tree)?;
+    for dir in &dirs {
+        fs::create_dir_all(tree.join(dir))?;
+    }
+
+    for relative in files {
+        let name = relative_name(&relative);
+        let src = server_dir.join(&relative);
+
+        let previous = base.as_ref().and_then(|(backup, manifest)| {
+            let entry = manifest.files.get(&name)?;
+            let path = backup.path.join(SNAPSHOT_TREE).join(&relative);
+            is_unchanged(&src, &path, entry).then_some((path, entry))
+        });
+
+        let entry = match previous {
+            Some((path, entry)) => {
+                fs::hard_link(&path, tree.join(&relative))
+                    .map_err(|err| with_path_context(err, &path))?;
+                FileEntry {
+                    linked: true,
+                    ..entry.clone()
+                }
+            }
+            None => {
+                let expected = fs::metadata(&src)?.len();
+                let (size, sha256) = copy_into_snapshot(&src, &tree.join(&relative))?;
+                if size != expected {
+                    return Err(changed_while_read(&src));
+                }
+                FileEntry {
+                    size,
+                    sha256,
+                    linked: false,
+                }
+            }
+        };
+
+        manifest.files.insert(name, entry);
+    }
+
+    fs::write(dst.join(SNAPSHOT_MANIFEST), toml::to_string(manifest)?)?;
+    Ok(())
+}
+
+fn read_snapshot_manifest(backup: &Backup) -> Result<Manifest> {
+    Ok(toml::from_str(&fs::read_to_string(
+        backup.path.join(SNAPSHOT_MANIFEST),
+    )?)?)
+}
+
+/// Backs a server up, as a snapshot linked to its latest one with `incremental` and otherwise as
+/// a tarball. The backup is written under a temporary name and renamed once it is complete, so
+/// that one cut short is never mistaken for a backup.
+pub fn create(server: &str, incremental: bool) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+
+    if get_alive_server_sessions()?.contains(server) {
+        eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m {server} is running, so its world may be backed up while \
+             it is being saved, consider running save-off first"
+        );
+    }
+
+    let dir = get_backups_dir(server)?;
+    fs::create_dir_all(&dir)?;
+    let name = unused_name(server)?;
+
+    let path = if incremental {
+        let base = list_backups(server)?
+            .into_iter()
+            .rfind(|backup| backup.kind == Kind::Snapshot);
+        let base = match base {
+            Some(backup) => {
+                let manifest = read_snapshot_manifest(&backup)?;
+                Some((backup, manifest))
+            }
+            None => None,
+        };
+
+        let mut manifest =
+            Manifest::new(server, base.as_ref().map(|(backup, _)| backup.name.clone()))?;
+        let temp = dir.join(format!(".{name}.tmp"));
+        let result = write_snapshot(
+            &server_dir,
+            &temp,
+            base.as_ref().map(|(backup, manifest)| (backup, manifest)),
+            &mut manifest,
+        );
+        if let Err(err) = result {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(err);
+        }
+
+        let linked = manifest.files.values().filter(|entry| entry.linked).count();
+        let path = dir.join(&name);
+        fs::rename(&temp, &path)?;
+        println!(
+            "Linked {linked} of {} file(s) to the previous snapshot",
+            manifest.files.len()
+        );
+        path
+    } else {
+        let mut manifest = Manifest::new(server, None)?;
+        let temp = dir.join(format!(".{name}{TARBALL_EXTENSION}.tmp"));
+        if let Err(err) = write_tarball(&server_dir, &temp, &mut manifest) {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+
+        let path = dir.join(format!("{name}{TARBALL_EXTENSION}"));
+        fs::rename(&temp, &path)?;
+        path
+    };
+
+    println!("{}", path.display());
+    Ok(())
+}
+
+pub fn list(server: &str) -> Result<()> {
+    get_server_dir_required(server)?;
+
+    for backup in list_backups(server)? {
+        let kind = match backup.kind {
+            Kind::Tarball => "tarball",
+            Kind::Snapshot => "snapshot",
+        };
+        println!("{} ({kind})", backup.name);
+    }
+
+    Ok(())
+}
+
+/// Compares the files found in a backup with its manifest
+fn compare(manifest: &Manifest, found: &Found, problems: &mut Vec<String>) {
+    for (name, entry) in &manifest.files {
+        match found.get(name) {
+            None => problems.push(format!("{name} is missing")),
+            Some((size, _)) if *size != entry.size => problems.push(format!(
+                "{name} is {size} bytes, {} were backed up",
+                entry.size
+            )),
+            Some((_, sha256)) if *sha256 != entry.sha256 => {
+                problems.push(format!("{name} doesn't match its checksum"))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for name in found
+        .keys()
+        .filter(|name| !manifest.files.contains_key(*name))
+    {
+        problems.push(format!("{name} is not in the manifest"));
+    }
+}
+
+/// Hashes every file under a directory, by its path relative to `root`
+fn hash_tree(root: &Path) -> Result<Found> {
+    let (mut dirs, mut files) = (vec![], vec![]);
+    walk(root, Path::new(""), &mut dirs, &mut files)?;
+
+    files
+        .into_iter()
+        .map(|relative| {
+            let mut reader = HashingReader::new(BufReader::new(File::open(root.join(&relative))?));
+            io::copy(&mut reader, &mut io::sink())?;
+            Ok((relative_name(&relative), reader.finish()))
+        })
+        .collect()
+}
+
+/// Checks that a restored tree has the files a server needs to start: a `level.dat` that can be
+/// read, and the jar its metadata names, which has to be a readable archive
+fn check_key_files(tree: &Path, problems: &mut Vec<String>) {
+    let level_name = Properties::read(tree)
+        .map(|properties| worlds::level_name(&properties))
+        .unwrap_or_else(|_| worlds::DEFAULT_LEVEL_NAME.to_string());
+    let level = tree.join(level_name).join("level.dat");
+
+    if level.is_file() {
+        if let Err(err) = nbt::read_gzip(&level) {
+            problems.push(format!("{} can't be read: {err}", level.display()));
+        }
+    } else {
+        problems.push(format!("{} is missing", level.display()));
+    }
+
+    // bedrock servers have no jar
+    if server::read_start_command(tree).ok().flatten().is_some() {
+        return;
+    }
+
+    match server::read_server_jar_path(tree) {
+        Ok(jar) => {
+            if let Err(err) = File::open(&jar)
+                .map_err(zip::result::ZipError::from)
+                .and_then(zip::ZipArchive::new)
+            {
+                problems.push(format!("{} is not a readable jar: {err}", jar.display()));
+            }
+        }
+        Err(err) => problems.push(err.to_string()),
+    }
+}
+
+/// Reads a tarball from start to end, hashing each file, which also checks its structure and the
+/// gzip checksum at its end. Returns the files along with the manifest, if it has one.
+fn read_tarball(path: &Path) -> Result<(Found, Option<Manifest>)> {
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(File::open(path)?)));
+    let mut files = BTreeMap::new();
+    let mut manifest = None;
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let name = relative_name(&entry.path()?);
+
+        if name == TARBALL_MANIFEST {
+            let mut contents = String::new();
+            BufReader::new(entry).read_to_string(&mut contents)?;
+            manifest = Some(toml::from_str(&contents)?);
+        } else if entry.header().entry_type().is_file() {
+            let mut reader = HashingReader::new(entry);
+            io::copy(&mut reader, &mut io::sink())?;
+            files.insert(name, reader.finish());
+        }
+    }
+
+    // the archive ends before the gzip stream does, so its checksum is only read by reading on
+    io::copy(&mut archive.into_inner(), &mut io::sink())?;
+
+    Ok((files, manifest))
+}
+
+/// Restores a tarball into a directory, leaving its manifest out
+fn unpack_tarball(path: &Path, dst: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(File::open(path)?)));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if relative_name(&entry.path()?) != TARBALL_MANIFEST {
+            entry.unpack_in(dst)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that a snapshot's files linked to its base still are, where the base is still kept.
+/// A broken link means an edit to one snapshot no longer shows up in the other, or that the
+/// space the snapshots were meant to share isn't.
+fn check_links(backup: &Backup, manifest: &Manifest, problems: &mut Vec<String>) -> Result<()> {
+    let Some(base) = &manifest.base else {
+        return Ok(());
+    };
+    let base_tree = backup
+        .path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(base)
+        .join(SNAPSHOT_TREE);
+    if !base_tree.is_dir() {
+        return Ok(());
+    }
+
+    let tree = backup.path.join(SNAPSHOT_TREE);
+    for (name, _) in manifest.files.iter().filter(|(_, entry)| entry.linked) {
+        let (Ok(file), Ok(base_file)) = (
+            fs::metadata(tree.join(name)),
+            fs::metadata(base_tree.join(name)),
+        ) else {
+            // a missing file is reported by the comparison with the manifest
+            continue;
+        };
+
+        if (file.dev(), file.ino()) != (base_file.dev(), base_file.ino()) {
+            problems.push(format!("{name} is no longer linked to snapshot {base}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies one backup, returning what is wrong with it. A snapshot's key files are checked in
+/// place, while a tarball's are only checked once restored, as they can't be read inside it.
+fn verify_backup(backup: &Backup, restore_test: bool) -> Result<Vec<String>> {
+    let mut problems = vec![];
+
+    let manifest = match backup.kind {
+        Kind::Tarball => {
+            let (files, manifest) = match read_tarball(&backup.path) {
+                Ok(read) => read,
+                Err(err) => return Ok(vec![format!("the archive can't be read: {err}")]),
+            };
+            let Some(manifest) = manifest else {
+                return Ok(vec![
+                    "the archive has no manifest, so it may be cut short".to_string(),
+                ]);
+            };
+            compare(&manifest, &files, &mut problems);
+            manifest
+        }
+        Kind::Snapshot => {
+            let manifest = match read_snapshot_manifest(backup) {
+                Ok(manifest) => manifest,
+                Err(err) => return Ok(vec![format!("the manifest can't be read: {err}")]),
+            };
+            let tree = backup.path.join(SNAPSHOT_TREE);
+            compare(&manifest, &hash_tree(&tree)?, &mut problems);
+            check_links(backup, &manifest, &mut problems)?;
+            check_key_files(&tree, &mut problems);
+            manifest
+        }
+    };
+
+    if restore_test && problems.is_empty() {
+        restore_and_check(backup, &manifest, &mut problems)?;
+    }
+
+    Ok(problems)
+}
+
+/// Restores a backup into a temporary directory beside it and checks the restored tree against
+/// the manifest, removing it afterwards whatever the outcome
+fn restore_and_check(
+    backup: &Backup,
+    manifest: &Manifest,
+    problems: &mut Vec<String>,
+) -> Result<()> {
+    let temp = backup
+        .path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(format!(".restore-test-{}", backup.name));
+    let _ = fs::remove_dir_all(&temp);
+    fs::create_dir_all(&temp)?;
+
+    let result = match backup.kind {
+        Kind::Tarball => unpack_tarball(&backup.path, &temp),
+        Kind::Snapshot => copy::copy_tree(
+            &backup.path.join(SNAPSHOT_TREE),
+            &temp,
+            &IgnoreRules::default(),
+        )
+        .map_err(Error::from),
+    };
+
+    let outcome = result.and_then(|()| {
+        let mut restored = vec![];
+        compare(manifest, &hash_tree(&temp)?, &mut restored);
+        check_key_files(&temp, &mut restored);
+        Ok(restored)
+    });
+    let _ = fs::remove_dir_all(&temp);
+
+    match outcome {
+        Ok(restored) => problems.extend(
+            restored
+                .into_iter()
+                .map(|problem| format!("after restoring, {problem}")),
+        ),
+        Err(err) => problems.push(format!("restoring failed: {err}")),
+    }
+
+    Ok(())
+}
+
+fn print_result(backup: &Backup, problems: &[String]) {
+    let kind = match backup.kind {
+        Kind::Tarball => "tarball",
+        Kind::Snapshot => "snapshot",
+    };
+
+    if problems.is_empty() {
+        println!("[\x1b[32;1mok\x1b[0m] {} ({kind})", backup.name);
+        return;
+    }
+
+    println!("[\x1b[31;1mfail\x1b[0m] {} ({kind})", backup.name);
+    for problem in problems.iter().take(MAX_LISTED_PROBLEMS) {
+        println!("  {problem}");
+    }
+    if problems.len() > MAX_LISTED_PROBLEMS {
+        println!("  … {} more", problems.len() - MAX_LISTED_PROBLEMS);
+    }
+}
+
+/// Verifies a server's backups against their manifests, and with `restore_test` restores each
+/// one that passes into a temporary directory and checks what was restored. Snapshots are also
+/// checked for a readable `level.dat` and jar either way, but tarballs only with `restore_test`.
+/// Fails when any backup does.
+pub fn verify(server: &str, selection: Selection, restore_test: bool) -> Result<()> {
+    let mut backups = list_backups(server)?;
+
+    let selected = match selection {
+        Selection::All => backups,
+        Selection::Latest => backups.pop().into_iter().collect(),
+        Selection::Named(name) => {
+            let name = name.strip_suffix(TARBALL_EXTENSION).unwrap_or(&name);
+            match backups.into_iter().find(|backup| backup.name == name) {
+                Some(backup) => vec![backup],
+                None => {
+                    return Err(Error::BackupNotFound {
+                        server: server.to_string(),
+                        backup: name.to_string(),
+                    });
+                }
+            }
+        }
+    };
+
+    if selected.is_empty() {
+        return Err(Error::NoBackups(server.to_string()));
+    }
+
+    let mut failures = 0;
+    for backup in &selected {
+        let problems = verify_backup(backup, restore_test)?;
+        print_result(backup, &problems);
+        if !problems.is_empty() {
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        return Err(Error::BackupVerificationFailed(failures));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// The smallest `level.dat` that reads: an empty root compound
+    fn level_dat() -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&[10, 0, 0, 0]).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// An empty zip archive, which is all a jar has to be to read
+    const EMPTY_JAR: &[u8] = b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    /// A server with the files it needs to start and a few others, and a directory to back it up
+    /// into
+    fn server() -> (TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let server_dir = dir.path().join("server");
+        let backups_dir = dir.path().join("backups");
+
+        fs::create_dir_all(server_dir.join("world/region")).unwrap();
+        fs::create_dir_all(server_dir.join("logs")).unwrap();
+        fs::create_dir_all(server_dir.join(".mcserver")).unwrap();
+        fs::create_dir_all(&backups_dir).unwrap();
+        fs::write(server_dir.join("world/level.dat"), level_dat()).unwrap();
+        fs::write(server_dir.join("world/region/r.0.0.mca"), [7; 4096]).unwrap();
+        fs::write(server_dir.join("server.jar"), EMPTY_JAR).unwrap();
+        fs::write(server_dir.join(".mcserver/jar_file.txt"), "server.jar").unwrap();
+        fs::write(server_dir.join("eula.txt"), "eula=true\n").unwrap();
+
+        (dir, server_dir, backups_dir)
+    }
+
+    fn tarball(server_dir: &Path, backups_dir: &Path, name: &str) -> (Backup, Manifest) {
+        let path = backups_dir.join(format!("{name}{TARBALL_EXTENSION}"));
+        let mut manifest = Manifest::new("server", None).unwrap();
+        write_tarball(server_dir, &path, &mut manifest).unwrap();
+
+        let backup = Backup {
+            name: name.to_string(),
+            path,
+            kind: Kind::Tarball,
+        };
+        (backup, manifest)
+    }
+
+    fn snapshot(
+        server_dir: &Path,
+        backups_dir: &Path,
+        name: &str,
+        base: Option<(&Backup, &Manifest)>,
+    ) -> (Backup, Manifest) {
+        let path = backups_dir.join(name);
+        let mut manifest =
+            Manifest::new("server", base.map(|(backup, _)| backup.name.clone())).unwrap();
+        write_snapshot(server_dir, &path, base, &mut manifest).unwrap();
+
+        let backup = Backup {
+            name: name.to_string(),
+            path,
+            kind: Kind::Snapshot,
+        };
+        (backup, manifest)
+    }
+
+    fn entry(contents: &[u8]) -> FileEntry {
+        FileEntry {
+            size: contents.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(contents)),
+            linked: false,
+        }
+    }
+
+    #[test]
+    fn compare_reports_each_difference() {
+        let mut manifest = Manifest::new("server", None).unwrap();
+        for name in ["same", "missing", "resized", "changed"] {
+            manifest.files.insert(name.to_string(), entry(b"four"));
+        }
+
+        let found = Found::from([
+            ("same".to_string(), (4, entry(b"four").sha256)),
+            ("resized".to_string(), (5, entry(b"five!").sha256)),
+            ("changed".to_string(), (4, entry(b"FOUR").sha256)),
+            ("extra".to_string(), (4, entry(b"four").sha256)),
+        ]);
+
+        let mut problems = vec![];
+        compare(&manifest, &found, &mut problems);
+        problems.sort();
+        assert_eq!(
+            problems,
+            [
+                "changed doesn't match its checksum",
+                "extra is not in the manifest",
+                "missing is missing",
+                "resized is 5 bytes, 4 were backed up",
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_accepts_a_matching_backup() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (backup, manifest) = tarball(&server_dir, &backups_dir, "good");
+
+        let (found, read) = read_tarball(&backup.path).unwrap();
+        let mut problems = vec![];
+        compare(&manifest, &found, &mut problems);
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(read.unwrap().files, manifest.files);
+    }
+
+    #[test]
+    fn truncated_tarballs_cant_be_read() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (backup, _) = tarball(&server_dir, &backups_dir, "truncated");
+
+        let bytes = fs::read(&backup.path).unwrap();
+        fs::write(&backup.path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(read_tarball(&backup.path).is_err());
+
+        let problems = verify_backup(&backup, false).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("the archive can't be read"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn corrupt_tarballs_cant_be_read() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (backup, _) = tarball(&server_dir, &backups_dir, "corrupt");
+        let bytes = fs::read(&backup.path).unwrap();
+
+        // in the compressed data, and in the checksum at the end of the gzip stream
+        for at in [bytes.len() / 2, bytes.len() - 8] {
+            let mut corrupt = bytes.clone();
+            corrupt[at] ^= 0xff;
+            fs::write(&backup.path, &corrupt).unwrap();
+
+            assert!(read_tarball(&backup.path).is_err(), "corrupted at {at}");
+        }
+    }
+
+    #[test]
+    fn tarballs_without_a_manifest_fail() {
+        let (_dir, server_dir, backups_dir) = server();
+        let path = backups_dir.join(format!("bare{TARBALL_EXTENSION}"));
+
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        builder
+            .append_path_with_name(server_dir.join("eula.txt"), "eula.txt")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let (found, manifest) = read_tarball(&path).unwrap();
+        assert!(found.contains_key("eula.txt"));
+        assert!(manifest.is_none());
+
+        let backup = Backup {
+            name: "bare".to_string(),
+            path,
+            kind: Kind::Tarball,
+        };
+        assert_eq!(
+            verify_backup(&backup, false).unwrap(),
+            ["the archive has no manifest, so it may be cut short"]
+        );
+    }
+
+    #[test]
+    fn snapshots_stay_linked_to_their_base() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (base, base_manifest) = snapshot(&server_dir, &backups_dir, "1", None);
+        fs::write(server_dir.join("eula.txt"), "eula=false\n").unwrap();
+        let (backup, manifest) = snapshot(
+            &server_dir,
+            &backups_dir,
+            "2",
+            Some((&base, &base_manifest)),
+        );
+
+        assert!(manifest.files["world/level.dat"].linked);
+        assert!(!manifest.files["eula.txt"].linked);
+
+        let mut problems = vec![];
+        check_links(&backup, &manifest, &mut problems).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(verify_backup(&backup, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn broken_links_are_reported() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (base, base_manifest) = snapshot(&server_dir, &backups_dir, "1", None);
+        let (backup, manifest) = snapshot(
+            &server_dir,
+            &backups_dir,
+            "2",
+            Some((&base, &base_manifest)),
+        );
+
+        // a copy with the same contents still matches the manifest, but no longer shares space
+        let region = backup
+            .path
+            .join(SNAPSHOT_TREE)
+            .join("world/region/r.0.0.mca");
+        let contents = fs::read(&region).unwrap();
+        fs::remove_file(&region).unwrap();
+        fs::write(&region, contents).unwrap();
+
+        let mut problems = vec![];
+        check_links(&backup, &manifest, &mut problems).unwrap();
+        assert_eq!(
+            problems,
+            ["world/region/r.0.0.mca is no longer linked to snapshot 1"]
+        );
+        assert_eq!(verify_backup(&backup, false).unwrap(), problems);
+
+        // without the base there is nothing to be linked to
+        fs::remove_dir_all(&base.path).unwrap();
+        let mut problems = vec![];
+        check_links(&backup, &manifest, &mut problems).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[test]
+    fn restored_backups_are_checked_and_cleaned_up() {
+        let (_dir, server_dir, backups_dir) = server();
+        let backups = [
+            tarball(&server_dir, &backups_dir, "tarball"),
+            snapshot(&server_dir, &backups_dir, "snapshot", None),
+        ];
+
+        for (backup, manifest) in &backups {
+            let mut problems = vec![];
+            restore_and_check(backup, manifest, &mut problems).unwrap();
+            assert!(problems.is_empty(), "{}: {problems:?}", backup.name);
+            assert!(
+                !backups_dir
+                    .join(format!(".restore-test-{}", backup.name))
+                    .exists()
+            );
+        }
+    }
+
+    #[test]
+    fn restoring_finds_unreadable_key_files() {
+        let (_dir, server_dir, backups_dir) = server();
+        fs::write(server_dir.join("world/level.dat"), b"not gzip").unwrap();
+        fs::write(server_dir.join("server.jar"), b"not a zip").unwrap();
+        let (backup, manifest) = tarball(&server_dir, &backups_dir, "broken");
+
+        // the files match the manifest, so only restoring them finds they are no use
+        assert!(verify_backup(&backup, false).unwrap().is_empty());
+
+        let mut problems = vec![];
+        restore_and_check(&backup, &manifest, &mut problems).unwrap();
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("after restoring, "));
+        assert!(problems[0].contains("level.dat can't be read"));
+        assert!(problems[1].contains("server.jar is not a readable jar"));
+        assert!(!backups_dir.join(".restore-test-broken").exists());
+    }
+
+    #[test]
+    fn restoring_a_tarball_that_cant_be_unpacked_fails() {
+        let (_dir, server_dir, backups_dir) = server();
+        let (backup, manifest) = tarball(&server_dir, &backups_dir, "cut");
+        let bytes = fs::read(&backup.path).unwrap();
+        fs::write(&backup.path, &bytes[..bytes.len() / 2]).unwrap();
+
+        let mut problems = vec![];
+        restore_and_check(&backup, &manifest, &mut problems).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("restoring failed"), "{problems:?}");
+        assert!(!backups_dir.join(".restore-test-cut").exists());
+    }
+}
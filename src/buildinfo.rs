@@ -0,0 +1,101 @@
+//! What this build of mcserver is and what it found on the machine, for bug reports.
+
+use crate::{
+    config::{BUILD_DATE, GIT_COMMIT, STATIC_CONFIG, TARGET},
+    error::Result,
+    java, session,
+};
+use jiff::Timestamp;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct JavaRuntime<'a> {
+    path: &'a Path,
+    version: &'a str,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    version: &'a str,
+    commit: Option<&'a str>,
+    build_date: String,
+    target: &'a str,
+    contact: &'a str,
+    dynamic_config_path: &'a str,
+    zellij: Option<String>,
+    java: Vec<JavaRuntime<'a>>,
+}
+
+fn build_date() -> String {
+    Timestamp::from_second(BUILD_DATE as i64)
+        .map(|date| date.strftime("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// The installed zellij version, which is `None` when zellij is missing rather than an error, as
+/// that is worth reporting too
+fn zellij_version() -> Option<String> {
+    session::version()
+        .ok()
+        .flatten()
+        .map(session::format_version)
+}
+
+/// Prints the version and build of mcserver. Verbose output and JSON add the compiled-in static
+/// configuration and the zellij and Java versions found, which takes a moment to probe.
+pub fn print(verbose: bool, json: bool) -> Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+
+    if json {
+        let report = Report {
+            version,
+            commit: GIT_COMMIT,
+            build_date: build_date(),
+            target: TARGET,
+            contact: STATIC_CONFIG.contact.trim(),
+            dynamic_config_path: STATIC_CONFIG.dynamic_config_path,
+            zellij: zellij_version(),
+            java: java::discover()
+                .iter()
+                .map(|runtime| JavaRuntime {
+                    path: &runtime.path,
+                    version: &runtime.version,
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "mcserver {version} ({} {})",
+        GIT_COMMIT.unwrap_or("unknown commit"),
+        build_date()
+    );
+
+    if !verbose {
+        return Ok(());
+    }
+
+    println!("Target: {TARGET}");
+    println!("Contact: {}", STATIC_CONFIG.contact.trim());
+    println!(
+        "Dynamic configuration path: {}",
+        STATIC_CONFIG.dynamic_config_path
+    );
+    println!(
+        "Zellij: {}",
+        zellij_version().as_deref().unwrap_or("not found")
+    );
+
+    let runtimes = java::discover();
+    if runtimes.is_empty() {
+        println!("Java: not found");
+    }
+    for runtime in runtimes {
+        println!("Java: {} ({})", runtime.version, runtime.path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+//! Builds Spigot jars with SpigotMC's BuildTools, which compiles the server locally instead of
+//! downloading it, so it needs git and a JDK and takes several minutes.
+
+use crate::{
+    error::{Error, Result},
+    progress::{Event, Reporter},
+    subprocess, verbose,
+};
+use std::{
+    collections::VecDeque,
+    env,
+    io::{BufRead, BufReader, Read},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const GIT_INSTALL_HINT: &str = "install git, which BuildTools uses to fetch the sources";
+const JAVA_INSTALL_HINT: &str =
+    "install a JDK (such as OpenJDK 21), which BuildTools compiles with";
+
+/// How many lines of output are kept to explain a failed build
+const OUTPUT_TAIL_LINES: usize = 20;
+/// How long BuildTools gets to exit after being interrupted before it is killed
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Checks that git and a JDK are installed, since BuildTools fails late and obscurely without them
+pub fn preflight() -> Result<()> {
+    for (program, arg, install_hint) in [
+        ("git", "--version", GIT_INSTALL_HINT),
+        ("javac", "-version", JAVA_INSTALL_HINT),
+    ] {
+        let status = subprocess::run_with_timeout(
+            Command::new(program)
+                .arg(arg)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+            |err| Error::from_spawn(err, program, install_hint),
+        )?
+        .status;
+
+        if !status.success() {
+            return Err(Error::BackendMissing {
+                backend: program,
+                install_hint,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a fresh directory for BuildTools to work in
+pub fn build_dir() -> PathBuf {
+    env::temp_dir().join(format!("mcserver-buildtools-{}", process::id()))
+}
+
+/// Runs BuildTools for a revision, writing the jar into the output directory and reporting each
+/// line of its output. Ctrl-C stops BuildTools along with every process it started.
+pub fn run(
+    build_tools: &Path,
+    revision: &str,
+    output_dir: &Path,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let build_dir = build_tools.parent().unwrap_or(Path::new("."));
+
+    let mut command = Command::new("java");
+    command
+        .arg("-jar")
+        .arg(build_tools)
+        .args(["--rev", revision, "--output-dir"])
+        .arg(output_dir)
+        .current_dir(build_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // in its own process group, so that Ctrl-C reaches mcserver first and the whole tree can
+        // then be stopped together
+        .process_group(0);
+
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+
+    let result = supervise(&mut command, reporter);
+
+    unsafe { libc::signal(libc::SIGINT, previous) };
+    result
+}
+
+fn supervise(command: &mut Command, reporter: &dyn Reporter) -> Result<()> {
+    let mut child = command
+        .spawn()
+        .map_err(|err| Error::from_spawn(err, "java", JAVA_INSTALL_HINT))?;
+
+    let (sender, receiver) = mpsc::channel();
+    for stream in [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            kill_tree(&mut child)?;
+            return Err(Error::Interrupted("BuildTools".to_string()));
+        }
+
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => {
+                reporter.report(Event::BuildOutput { line: &line });
+                if tail.len() == OUTPUT_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // both streams are closed, so BuildTools has exited or is about to
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        return Ok(());
+    }
+
+    let output = Vec::from(tail).join("\n").into_bytes();
+    Err(Error::command_failure(command, status.code(), Some(output)))
+}
+
+/// Asks the child's process group to stop, then kills it if it's still running after a grace
+/// period
+fn kill_tree(child: &mut process::Child) -> Result<()> {
+    let group = child.id() as libc::pid_t;
+    verbose!("Stopping BuildTools (process group {group})");
+    unsafe { libc::killpg(group, libc::SIGTERM) };
+
+    let deadline = Instant::now() + KILL_GRACE_PERIOD;
+    while Instant::now() < deadline {
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    unsafe { libc::killpg(group, libc::SIGKILL) };
+    child.wait()?;
+    Ok(())
+}
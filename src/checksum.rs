@@ -0,0 +1,101 @@
+//! Sha256 digests of server jars. The digest of a downloaded jar is recorded in its server's
+//! metadata, and checked again before the server is launched so that a truncated or corrupted jar
+//! is caught before java fails on it with something cryptic.
+
+use crate::{
+    config,
+    error::{Error, Result},
+    platforms::PublishedDigest,
+    server::{METADATA_DIRECTORY, get_server_dir_required, get_server_jar_path},
+};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+pub const JAR_SHA256_FILE: &str = "jar_sha256.txt";
+
+/// Returns the lowercase hex sha256 digest of a file
+pub fn sha256_file(path: impl AsRef<Path>) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Checks a download against the digest its platform published for it
+pub fn verify_published(path: impl AsRef<Path>, published: &PublishedDigest) -> Result<()> {
+    let (expected, actual) = match published {
+        PublishedDigest::Md5(expected) => {
+            let mut hasher = Md5::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            (expected, format!("{:x}", hasher.finalize()))
+        }
+        PublishedDigest::Sha1(expected) => {
+            let mut hasher = Sha1::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            (expected, format!("{:x}", hasher.finalize()))
+        }
+    };
+
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(Error::ChecksumMismatch {
+            expected: expected.clone(),
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Records the digest of a server's active jar
+pub fn record(metadata_dir: impl AsRef<Path>, sha256: &str) -> Result<()> {
+    fs::write(
+        metadata_dir.as_ref().join(JAR_SHA256_FILE),
+        format!("{sha256}\n"),
+    )?;
+    Ok(())
+}
+
+/// Drops the recorded digest, for when the active jar changes to one whose digest isn't known
+pub fn forget(metadata_dir: impl AsRef<Path>) -> Result<()> {
+    match fs::remove_file(metadata_dir.as_ref().join(JAR_SHA256_FILE)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn recorded(metadata_dir: impl AsRef<Path>) -> Result<Option<String>> {
+    match fs::read_to_string(metadata_dir.as_ref().join(JAR_SHA256_FILE)) {
+        Ok(sha256) => Ok(Some(sha256.trim().to_string()).filter(|sha256| !sha256.is_empty())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Re-hashes a server's jar and fails if it no longer matches the digest recorded when it was
+/// downloaded. Servers without a recorded digest, and setups that turned the check off, pass.
+pub fn verify_jar(server: &str) -> Result<()> {
+    if config::snapshot()?.verify_jar_checksums == Some(false) {
+        return Ok(());
+    }
+
+    let server_dir = get_server_dir_required(server)?;
+    let Some(expected) = recorded(server_dir.join(METADATA_DIRECTORY))? else {
+        return Ok(());
+    };
+
+    let actual = sha256_file(get_server_jar_path(&server_dir)?)?;
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(Error::JarCorrupted {
+            server: server.to_string(),
+            expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
@@ -1,10 +1,76 @@
-use crate::platforms::Platform;
+use crate::{
+    completion::Shell, containerize, fleet::FleetCheck, logformat::Level, logs,
+    platforms::Platform, ratelimit,
+};
 use clap::{ArgGroup, Parser, Subcommand};
 use std::path::PathBuf;
+use url::Url;
 
 #[derive(Parser)]
-#[command(name = "mcserver", version, about = "Minecraft server CLI tool")]
+#[command(
+    name = "mcserver",
+    version,
+    about = "Minecraft server CLI tool",
+    after_help = "When a command's server is left out, it is taken from the MCSERVER_SERVER environment variable, \
+                  and otherwise from the configured default server. Passing `.` explicitly selects the server \
+                  containing the current directory."
+)]
 pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "Print additional diagnostic output to stderr"
+    )]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Use an alternate dynamic configuration file"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Skip checking for free disk space before large writes"
+    )]
+    pub ignore_space: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Print progress events of long operations as JSON lines on stdout"
+    )]
+    pub json_lines: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "RATE",
+        value_parser = ratelimit::parse_rate,
+        help = "Limit the bandwidth of downloads, e.g. 500K or 2M per second"
+    )]
+    pub limit_rate: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        visible_alias = "no-cache",
+        help = "Fetch API responses afresh instead of using the ones kept from earlier runs"
+    )]
+    pub no_http_cache: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Stop external programs such as zellij and mcrcon, and give up on listing the \
+                servers directory, when they take longer than this"
+    )]
+    pub timeout: Option<u64>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -13,7 +79,77 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(visible_alias = "a", about = "Attach to a server session")]
-    Attach { server: Option<String> },
+    Attach {
+        server: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Attach even if the session was not created by mcserver"
+        )]
+        force: bool,
+    },
+
+    #[command(about = "Back up a server and check that its backups can be restored")]
+    Backup {
+        #[command(subcommand)]
+        action: BackupCommands,
+    },
+
+    #[command(about = "Manage the API responses and completions kept between runs")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+
+    #[command(about = "Print the command used to launch a server")]
+    Command { server: Option<String> },
+
+    #[command(about = "Check whether a server's plugins and mods support a game version")]
+    Compat {
+        server: String,
+
+        target_version: String,
+    },
+
+    #[command(
+        about = "Generate a Dockerfile for a server, and optionally a compose service",
+        long_about = "Generate a Dockerfile for a server, and optionally a compose service, which run \
+                      the server the same way its session does. Only the part between the \
+                      mcserver marker comments is replaced when regenerating, so edits around it \
+                      are kept."
+    )]
+    Containerize {
+        server: Option<String>,
+
+        #[arg(long, default_value = containerize::DEFAULT_IMAGE, help = "The image to run the server on")]
+        image: String,
+
+        #[arg(
+            long,
+            help = "Also generate a docker-compose.yml service with the worlds as volumes"
+        )]
+        compose: bool,
+
+        #[arg(long, help = "Replace files that weren't generated by mcserver")]
+        force: bool,
+    },
+
+    #[command(
+        name = "__complete",
+        hide = true,
+        about = "Print completion candidates, one per line"
+    )]
+    Complete {
+        kind: String,
+        platform: Option<String>,
+    },
+
+    #[command(about = "Print a shell completion script")]
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
 
     #[command(visible_alias = "cfg", about = "Query the configuration")]
     Config {
@@ -21,6 +157,16 @@ pub enum Commands {
         config_type: ConfigType,
     },
 
+    #[command(
+        about = "Print the server this shell is in",
+        long_about = "Print the server this shell is in, taken from the server session it runs in, or \
+                      otherwise from the server containing the current directory"
+    )]
+    Current {
+        #[arg(long, help = "Print the server's directory instead of its name")]
+        path: bool,
+    },
+
     #[command(visible_alias = "def", about = "Set or get the default server")]
     Default {
         #[command(subcommand)]
@@ -50,17 +196,147 @@ pub enum Commands {
     },
 
     #[command(visible_alias = "dpl", about = "Deploy a server")]
-    Deploy { server: Option<String> },
+    Deploy {
+        server: Option<String>,
+
+        #[arg(long, help = "Print the launch command instead of deploying")]
+        print_command: bool,
+
+        #[arg(
+            long,
+            help = "Launch without checking the jar against its recorded checksum"
+        )]
+        skip_verify: bool,
+
+        #[arg(
+            long,
+            help = "Deploy even if the jar is older than the version that last saved the world"
+        )]
+        allow_downgrade: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "print_command",
+            help = "Wait for the server to finish starting after leaving its session, and record \
+                    why if it doesn't"
+        )]
+        wait_ready: bool,
+
+        #[arg(
+            long,
+            default_value = "2m",
+            requires = "wait_ready",
+            help = "How long to wait for, e.g. 90s or 5m"
+        )]
+        ready_timeout: String,
+    },
+
+    #[command(
+        about = "Check that the environment is set up correctly",
+        long_about = "Check that the environment is set up correctly.\n\n\
+            With --fix, problems with a safe fix are fixed: a missing servers directory is \
+            created, a configuration that can't be loaded is restored from its backup, a \
+            default_server naming a deleted server is cleared, dead sessions of deleted servers \
+            are deleted and world locks left over from stopped servers are removed. Anything \
+            else is only reported."
+    )]
+    Doctor {
+        #[arg(long, help = "Apply the fixes that are safe to make without asking")]
+        fix: bool,
+
+        #[arg(
+            long,
+            requires = "fix",
+            help = "List the fixes instead of applying them"
+        )]
+        dry_run: bool,
+    },
 
     #[command(visible_alias = "exec", about = "Execute a command on a server")]
     Execute {
         #[arg(short, long)]
         server: Option<String>,
 
+        #[arg(
+            short = '1',
+            long,
+            help = "Join the arguments with spaces into one command instead of running each separately"
+        )]
+        one: bool,
+
+        #[arg(
+            long,
+            value_name = "HH:MM",
+            conflicts_with = "within",
+            help = "Wait until the next time this time of day comes before sending"
+        )]
+        at: Option<String>,
+
+        #[arg(
+            long = "in",
+            value_name = "DURATION",
+            help = "Wait this long before sending, e.g. 90s or 2h30m"
+        )]
+        within: Option<String>,
+
         #[arg(trailing_var_arg = true)]
         commands: Vec<String>,
     },
 
+    #[command(about = "Audit every server at once")]
+    Fleet {
+        #[command(subcommand)]
+        action: FleetCommands,
+    },
+
+    #[command(about = "Give the current user ownership of a server's files with sane modes")]
+    FixPermissions { server: Option<String> },
+
+    #[command(about = "Manage groups of servers, which commands accept as @<group>")]
+    Group {
+        #[command(subcommand)]
+        action: GroupCommands,
+    },
+
+    #[command(about = "Manage the paths a server's .mcserverignore leaves out")]
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreCommands,
+    },
+
+    #[command(about = "Import servers from elsewhere")]
+    Import {
+        #[command(subcommand)]
+        action: ImportCommands,
+    },
+
+    #[command(about = "Show the Java runtimes found and which one each server uses")]
+    Java {
+        #[command(subcommand)]
+        action: JavaCommands,
+    },
+
+    #[command(
+        name = "launchscript",
+        about = "Manage the script a server is launched through",
+        long_about = "Manage the script a server is launched through. When .mcserver/launch.sh \
+                      exists it runs in place of the java command, with the jar and java \
+                      arguments passed to it"
+    )]
+    LaunchScript {
+        #[command(subcommand)]
+        action: LaunchScriptCommands,
+    },
+
+    #[command(hide = true, about = "Tools for packaging and documentation")]
+    Internal {
+        #[command(subcommand)]
+        action: InternalCommands,
+    },
+
+    #[command(about = "Show information about a server")]
+    Info { server: Option<String> },
+
     #[command(visible_alias = "ls", about = "List all, active or inactive servers")]
     List {
         #[arg(short, long, conflicts_with_all = ["inactive", "dead"])]
@@ -71,25 +347,268 @@ pub enum Commands {
 
         #[arg(short, long, conflicts_with = "inactive")]
         dead: bool,
+
+        #[arg(
+            long,
+            visible_alias = "porcelain",
+            help = "Print one server name per line without tags, for use in scripts"
+        )]
+        names_only: bool,
     },
 
-    #[command(about = "Interact with a server, using the minecraft remote console")]
+    #[command(about = "Show a server's log, colored by level")]
+    Logs {
+        server: Option<String>,
+
+        #[arg(
+            short = 'n',
+            long,
+            default_value_t = logs::DEFAULT_LINES,
+            help = "How many lines to show"
+        )]
+        lines: usize,
+
+        #[arg(short, long, help = "Keep printing new lines as they are logged")]
+        follow: bool,
+
+        #[arg(long, value_enum, help = "Only show entries at or above this level")]
+        level: Option<Level>,
+
+        #[arg(
+            long,
+            value_name = "REGEX",
+            help = "Only show lines matching a pattern"
+        )]
+        grep: Option<String>,
+    },
+
+    #[command(about = "Save, list and run console command macros")]
+    Macro {
+        #[command(subcommand)]
+        action: MacroCommands,
+    },
+
+    #[command(
+        visible_alias = "mt",
+        about = "Toggle maintenance mode (whitelist and motd) for a server"
+    )]
+    Maintenance {
+        #[command(subcommand)]
+        action: MaintenanceCommands,
+    },
+
+    #[command(about = "Edit a server's operators")]
+    Op {
+        #[command(subcommand)]
+        action: PlayerListCommands,
+    },
+
+    #[command(about = "Open a server directory in a file manager or editor")]
+    Open {
+        server: Option<String>,
+
+        #[arg(short, long, conflicts_with = "files")]
+        editor: bool,
+
+        #[arg(short, long, help = "Open with the file manager (default)")]
+        files: bool,
+
+        #[arg(short, long, help = "A path within the server directory to open")]
+        path: Option<PathBuf>,
+    },
+
+    #[command(about = "Keep the plugins of servers the same as another server's")]
+    Plugins {
+        #[command(subcommand)]
+        action: PluginsCommands,
+    },
+
+    #[command(
+        about = "Pre-generate a world with Chunky over rcon",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
+    Pregen {
+        #[command(subcommand)]
+        action: Option<PregenCommands>,
+
+        server: Option<String>,
+
+        #[arg(long, required = true)]
+        radius: Option<u32>,
+
+        #[arg(long)]
+        world: Option<String>,
+
+        #[arg(long, help = "Start pre-generation without waiting for it to finish")]
+        detach: bool,
+    },
+
+    #[command(
+        about = "Interact with a server, using the minecraft remote console",
+        long_about = "Interact with a server, using the minecraft remote console. A server's \
+                      endpoint is taken from its entry in the rcon configuration, or else from its \
+                      server.properties when that enables rcon, or else from the \"*\" entry in the \
+                      rcon configuration. --host, --port and --password(-env) skip all of these \
+                      and send to that endpoint instead, in which case no server is given"
+    )]
     Rcon {
         server: Option<String>,
 
+        #[arg(long, help = "Send to this host instead of a server's endpoint")]
+        host: Option<String>,
+
+        #[arg(long, help = "Send to this port instead of a server's endpoint")]
+        port: Option<u16>,
+
+        #[arg(
+            long,
+            conflicts_with = "password_env",
+            help = "The password to log in with, which other users can see in the process list \
+                    (prefer --password-env)"
+        )]
+        password: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "VAR",
+            help = "Read the password to log in with from an environment variable"
+        )]
+        password_env: Option<String>,
+
+        #[arg(
+            short = '1',
+            long,
+            help = "Join the arguments with spaces into one command instead of sending each separately"
+        )]
+        one: bool,
+
+        #[arg(
+            long,
+            value_name = "HH:MM",
+            conflicts_with = "within",
+            help = "Wait until the next time this time of day comes before sending"
+        )]
+        at: Option<String>,
+
+        #[arg(
+            long = "in",
+            value_name = "DURATION",
+            help = "Wait this long before sending, e.g. 90s or 2h30m"
+        )]
+        within: Option<String>,
+
         commands: Vec<String>,
     },
 
-    #[command(about = "Create a new server")]
+    #[command(about = "Check that rcon endpoints accept a login, without running any command")]
+    RconTest {
+        #[arg(required_unless_present = "all")]
+        servers: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            conflicts_with = "servers",
+            help = "Test every configured endpoint"
+        )]
+        all: bool,
+
+        #[arg(long, help = "Print the results as JSON")]
+        json: bool,
+    },
+
+    #[command(about = "Create a new server",
+        group(
+                ArgGroup::new("source")
+                    .args(&["platform", "jar", "url"])
+                    .required(true)
+            )
+    )]
     New {
         #[clap(value_enum)]
-        platform: Platform,
+        platform: Option<Platform>,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Create the server around a local jar instead of one from a platform"
+        )]
+        jar: Option<PathBuf>,
+
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Create the server around a jar downloaded from a url instead of one from a \
+                    platform"
+        )]
+        url: Option<Url>,
 
         #[arg(short, long)]
         name: Option<String>,
 
-        #[arg(short, long)]
+        #[arg(
+            long,
+            help = "Number the name past servers that only left a zellij session behind, instead \
+                    of warning about the session"
+        )]
+        no_clobber_session: bool,
+
+        #[arg(short, long, conflicts_with_all = ["jar", "url"])]
         version: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["jar", "url"],
+            help = "The build of the version to install, for Paper, Folia, Purpur and Velocity"
+        )]
+        build: Option<String>,
+
+        #[arg(short, long, help = "Only print the name of the new server")]
+        quiet: bool,
+
+        #[arg(long, conflicts_with = "quiet", help = "Print the new server as JSON")]
+        json: bool,
+
+        #[arg(long, help = "Apply a profile from the configuration once created")]
+        profile: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["jar", "url"],
+            help = "Keep the BuildTools work directory after building a Spigot jar"
+        )]
+        keep_build_dir: bool,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["jar", "url"],
+            help = "Print the commits that went into the build, where published"
+        )]
+        changelog: bool,
+    },
+
+    #[command(about = "Rebuild a server's missing .mcserver metadata from its jars")]
+    Repair {
+        server: Option<String>,
+
+        #[arg(long, help = "The jar to use when there are several")]
+        jar: Option<String>,
+
+        #[arg(short, long, help = "Rebuild the metadata even if it is intact")]
+        force: bool,
+    },
+
+    #[command(about = "Get or set values in a server's server.properties")]
+    Properties {
+        #[command(subcommand)]
+        action: PropertiesCommands,
+    },
+
+    #[command(about = "List, export or import server profiles")]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommands,
     },
 
     #[command(visible_alias = "reinst", about = "Reinstall the server binary",
@@ -118,14 +637,75 @@ pub enum Commands {
         #[arg(short, long)]
         force: bool,
 
+        #[arg(long, help = "Allow removing the server this shell is running in")]
+        allow_current: bool,
+
         servers: Vec<String>,
     },
 
     #[command(visible_alias = "rst", about = "Restart the current server")]
-    Restart,
+    Restart {
+        #[arg(long, help = "Print the launch command instead of restarting")]
+        print_command: bool,
+
+        #[arg(
+            long,
+            help = "Launch without checking the jar against its recorded checksum"
+        )]
+        skip_verify: bool,
+    },
+
+    #[command(about = "List server sessions and whether mcserver created them")]
+    Sessions {
+        #[arg(long, help = "Rebuild the session registry from the current sessions")]
+        repair: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "repair",
+            help = "List the sessions left behind under another session namespace"
+        )]
+        orphans: bool,
+    },
+
+    #[command(
+        about = "Show what takes up a server's disk space, by directory and by world dimension"
+    )]
+    Size {
+        server: Option<String>,
+
+        #[arg(
+            long,
+            default_value_t = 2,
+            help = "How many directories deep to break the size down"
+        )]
+        depth: usize,
+
+        #[arg(long, help = "Print the breakdown as JSON")]
+        json: bool,
+    },
 
-    #[command(about = "Stop a server")]
-    Stop { server: Option<String> },
+    #[command(about = "Summarize every server: platforms, sessions, disk use and players")]
+    Stats {
+        #[arg(long, help = "Print the summary as JSON")]
+        json: bool,
+    },
+
+    #[command(about = "Stop a server over rcon, or through its session without rcon")]
+    Stop {
+        server: Option<String>,
+
+        #[arg(long, help = "Wait for the server's session to exit")]
+        wait: bool,
+
+        #[arg(
+            long,
+            default_value = "2m",
+            requires = "wait",
+            help = "How long to wait for, e.g. 90s or 5m"
+        )]
+        wait_timeout: String,
+    },
 
     #[command(visible_alias = "tmpl", about = "Create or use a template server")]
     Template {
@@ -135,20 +715,235 @@ pub enum Commands {
 
     #[command(about = "Update a server's .jar file and reference")]
     Update {
-        server: String,
+        #[arg(required_unless_present_any = ["all", "manifest"])]
+        server: Option<String>,
 
-        platform: Platform,
+        #[arg(help = "The platform to update on, by default the one the server runs")]
+        platform: Option<Platform>,
 
         version: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "manifest",
+            help = "The build of the version to update to, for Paper, Folia, Purpur and Velocity"
+        )]
+        build: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "PLATFORM",
+            num_args = 0..=1,
+            conflicts_with_all = ["server", "platform", "manifest"],
+            help = "Update every server, or those on a platform, resolving each platform once, and \
+                    print the run manifest"
+        )]
+        all: Option<Option<Platform>>,
+
+        #[arg(
+            short = 'v',
+            long = "version",
+            value_name = "VERSION",
+            conflicts_with = "version",
+            help = "The version to update to, which can be given without a platform"
+        )]
+        all_version: Option<String>,
+
+        #[arg(
+            long,
+            help = "Apply a run manifest, verifying checksums instead of resolving"
+        )]
+        manifest: Option<PathBuf>,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "all",
+            help = "Write a JSON report of each server's outcome, for CI"
+        )]
+        report: Option<PathBuf>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["all", "manifest"],
+            help = "Check plugins and mods against the target version before updating"
+        )]
+        check_compat: bool,
+
+        #[arg(long, requires = "check_compat")]
+        ignore_compat: bool,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["all", "manifest"],
+            help = "Print the commits since the installed build, where published"
+        )]
+        changelog: bool,
+
+        #[arg(
+            long,
+            help = "Allow updating the server this shell is running in, whose jar is in use"
+        )]
+        allow_current: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "all",
+            help = "Allow switching servers to another platform than the one they run"
+        )]
+        force: bool,
+    },
+
+    #[command(
+        about = "Print the version and build of mcserver, and with --verbose what it found \
+                 installed, for bug reports"
+    )]
+    Version {
+        #[arg(long, help = "Print everything as JSON")]
+        json: bool,
+    },
+
+    #[command(about = "List the versions a platform can be installed at, newest first")]
+    Versions {
+        #[clap(value_enum)]
+        platform: Platform,
+
+        #[arg(short, long, help = "Only list this many of the newest versions")]
+        limit: Option<usize>,
+    },
+
+    #[command(about = "Edit a server's whitelist")]
+    Whitelist {
+        #[command(subcommand)]
+        action: PlayerListCommands,
+    },
+}
+
+impl Commands {
+    /// Whether a command removes, updates or reconfigures anything, which restricted mode refuses.
+    /// Every command is listed, so that a new one has to be classified too.
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            Self::DeleteAllSessions { .. }
+            | Self::Containerize { .. }
+            | Self::DeleteSession { .. }
+            | Self::FixPermissions { .. }
+            | Self::Import { .. }
+            | Self::LaunchScript { .. }
+            | Self::Reinstall { .. }
+            | Self::Remove { .. }
+            | Self::Repair { .. }
+            | Self::Update { .. } => true,
+
+            Self::Doctor { fix, dry_run } => *fix && !*dry_run,
+            Self::Default { action } => matches!(action, DefaultCommands::Set { .. }),
+            Self::Group { action } => !matches!(action, GroupCommands::List),
+            Self::Ignore { action } => matches!(action, IgnoreCommands::Edit { .. }),
+            Self::Macro { action } => matches!(action, MacroCommands::Save { .. }),
+            Self::Maintenance { action } => !matches!(action, MaintenanceCommands::Status { .. }),
+            Self::Op { action } | Self::Whitelist { action } => match action {
+                PlayerListCommands::Add { .. } | PlayerListCommands::Remove { .. } => true,
+            },
+            Self::Plugins { action } => match action {
+                PluginsCommands::Sync { dry_run, .. } => !*dry_run,
+            },
+            Self::Properties { action } => matches!(action, PropertiesCommands::Set { .. }),
+            Self::Profile { action } => matches!(action, ProfileCommands::Import { .. }),
+            Self::Sessions { repair, .. } => *repair,
+            Self::Template { action } => matches!(action, TemplateCommands::Update { .. }),
+
+            Self::Attach { .. }
+            | Self::Backup { .. }
+            | Self::Cache { .. }
+            | Self::Command { .. }
+            | Self::Compat { .. }
+            | Self::Complete { .. }
+            | Self::Completions { .. }
+            | Self::Config { .. }
+            | Self::Current { .. }
+            | Self::Deploy { .. }
+            | Self::Execute { .. }
+            | Self::Fleet { .. }
+            | Self::Info { .. }
+            | Self::Internal { .. }
+            | Self::Java { .. }
+            | Self::List { .. }
+            | Self::Logs { .. }
+            | Self::New { .. }
+            | Self::Open { .. }
+            | Self::Pregen { .. }
+            | Self::Rcon { .. }
+            | Self::RconTest { .. }
+            | Self::Restart { .. }
+            | Self::Size { .. }
+            | Self::Stats { .. }
+            | Self::Stop { .. }
+            | Self::Version { .. }
+            | Self::Versions { .. } => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    #[command(about = "Back up a server into the backup directory")]
+    Create {
+        server: String,
+
+        #[arg(
+            long,
+            help = "Take a snapshot that hard links the files unchanged since the last one"
+        )]
+        incremental: bool,
+    },
+
+    #[command(about = "List a server's backups, oldest first")]
+    List { server: String },
+
+    #[command(about = "Check backups against the manifests written when they were taken")]
+    Verify {
+        server: String,
+
+        #[arg(conflicts_with_all = ["latest", "all"])]
+        backup: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "all",
+            help = "Verify the latest backup (the default)"
+        )]
+        latest: bool,
+
+        #[arg(long, help = "Verify every backup")]
+        all: bool,
+
+        #[arg(
+            long,
+            help = "Also restore each backup into a temporary directory and check what was restored, \
+                    the only way a tarball's level.dat and jar are checked"
+        )]
+        restore_test: bool,
     },
 }
 
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    #[command(about = "Remove everything kept in the cache")]
+    Clear,
+}
+
 #[derive(Subcommand)]
 pub enum ConfigType {
     Static,
 
     #[clap(visible_alias = "dyn")]
-    Dynamic,
+    Dynamic {
+        #[arg(
+            long,
+            help = "List the upgrades that were applied to the configuration file"
+        )]
+        migrations: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -158,16 +953,394 @@ pub enum DefaultCommands {
     Set { server: String },
 }
 
+#[derive(Subcommand)]
+pub enum FleetCommands {
+    #[command(
+        about = "Check every server's EULA, port, rcon, disk space and jar, failing if any check fails"
+    )]
+    Check {
+        #[arg(long, help = "Print the results as JSON")]
+        json: bool,
+
+        #[arg(long, value_delimiter = ',', help = "Checks to leave out")]
+        skip: Vec<FleetCheck>,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Write a JSON report of each server's outcome, for CI"
+        )]
+        report: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GroupCommands {
+    List,
+
+    #[command(about = "Add servers to a group, creating it if needed")]
+    Add {
+        group: String,
+
+        #[arg(required = true)]
+        servers: Vec<String>,
+    },
+
+    #[command(about = "Remove servers from a group, or the whole group if none are given")]
+    Remove {
+        group: String,
+        servers: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IgnoreCommands {
+    #[command(about = "Open a server's .mcserverignore in the editor")]
+    Edit { server: Option<String> },
+
+    #[command(about = "Check whether a path within a server is ignored")]
+    Test { server: String, path: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum PropertiesCommands {
+    #[command(about = "Print the value of a property")]
+    Get { server: String, key: String },
+
+    #[command(about = "Set a property")]
+    Set {
+        server: String,
+        key: String,
+        value: String,
+
+        #[arg(long, help = "Rename the world directories to match a new level-name")]
+        migrate: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum JavaCommands {
+    #[command(about = "List the Java runtimes found and the one each server would use")]
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum LaunchScriptCommands {
+    #[command(about = "Write a starter launch script that runs the server as it runs now")]
+    Init {
+        server: Option<String>,
+
+        #[arg(short, long, help = "Replace an existing launch script")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommands {
+    #[command(about = "Import every server listed in a CSV or TOML manifest")]
+    Bulk {
+        manifest: PathBuf,
+
+        #[arg(
+            long,
+            conflicts_with = "link",
+            help = "Move directories instead of copying them"
+        )]
+        r#move: bool,
+
+        #[arg(long, help = "Symlink directories instead of copying them")]
+        link: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum InternalCommands {
+    #[command(about = "Generate man pages for every command")]
+    GenMan { dir: PathBuf },
+
+    #[command(about = "Generate markdown pages for every command")]
+    GenMarkdown { dir: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum MacroCommands {
+    #[command(about = "Save a macro, reading one command per line from a file or stdin")]
+    Save {
+        name: String,
+
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+    },
+
+    List,
+
+    Run {
+        name: String,
+
+        server: Option<String>,
+
+        #[arg(long, conflicts_with = "session")]
+        rcon: bool,
+
+        #[arg(long)]
+        session: bool,
+
+        #[arg(long, help = "Print the commands instead of sending them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MaintenanceCommands {
+    On {
+        server: Option<String>,
+
+        #[arg(long, help = "The motd to show while under maintenance")]
+        motd: Option<String>,
+    },
+
+    Off {
+        server: Option<String>,
+    },
+
+    Status {
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PlayerListCommands {
+    Add {
+        player: String,
+
+        server: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "offline_uuid",
+            help = "Look the UUID up with Mojang"
+        )]
+        online_uuid: bool,
+
+        #[arg(long, help = "Derive the UUID the way offline mode servers do")]
+        offline_uuid: bool,
+    },
+
+    Remove {
+        player: String,
+
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PluginsCommands {
+    #[command(
+        about = "Copy a server's plugin jars to another server or a group",
+        long_about = "Copy a server's plugin jars to another server or a group. Jars are compared \
+                      by name and SHA-256, and only those at the top of plugins/ are copied, so \
+                      each server keeps its own plugin configuration."
+    )]
+    Sync {
+        source: String,
+
+        #[arg(help = "A server, or a group as @<group>")]
+        target: String,
+
+        #[arg(long, help = "Print the differences without changing anything")]
+        dry_run: bool,
+
+        #[arg(long, help = "Remove jars the source doesn't have")]
+        prune: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "dry_run",
+            help = "Restart running servers that changed one at a time, waiting for each to be \
+                    ready before the next"
+        )]
+        rolling_restart: bool,
+
+        #[arg(
+            long,
+            default_value = "2m",
+            requires = "rolling_restart",
+            help = "How long to wait for each server to stop and to be ready, e.g. 90s or 5m"
+        )]
+        restart_timeout: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PregenCommands {
+    Status { server: Option<String> },
+
+    Cancel { server: Option<String> },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommands {
+    List,
+
+    #[command(about = "Write a profile as standalone TOML for sharing")]
+    Export {
+        name: String,
+
+        #[arg(short, long, help = "The file to write to instead of stdout")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Add a profile from a standalone TOML file")]
+    Import {
+        path: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            help = "The name to save it under instead of the file's name"
+        )]
+        name: Option<String>,
+
+        #[arg(short, long, help = "Replace an existing profile with the same name")]
+        force: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum TemplateCommands {
     New {
         server: String,
     },
 
+    #[command(about = "Refresh a template from its server and bump its revision")]
+    Update {
+        server: String,
+    },
+
+    #[command(about = "List the servers created from a template")]
+    Children {
+        template: String,
+    },
+
     From {
         template: String,
 
         #[arg(short, long)]
         server: Option<String>,
+
+        #[arg(long, help = "Apply a profile from the configuration once created")]
+        profile: Option<String>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use std::collections::HashSet;
+
+    /// Every command and subcommand, with whether it changes anything
+    const CASES: &[(&str, bool)] = &[
+        ("attach survival", false),
+        ("backup create survival", false),
+        ("backup list survival", false),
+        ("backup verify survival --all", false),
+        ("cache clear", false),
+        ("command survival", false),
+        ("compat survival 1.21.1", false),
+        ("__complete servers", false),
+        ("completions bash", false),
+        ("config static", false),
+        ("config dynamic", false),
+        ("containerize survival", true),
+        ("current", false),
+        ("default get", false),
+        ("default set survival", true),
+        ("delete-all-sessions", true),
+        ("delete-session survival", true),
+        ("deploy survival", false),
+        ("doctor", false),
+        ("doctor --fix", true),
+        ("doctor --fix --dry-run", false),
+        ("execute list", false),
+        ("fix-permissions survival", true),
+        ("fleet check", false),
+        ("group list", false),
+        ("group add lobby survival", true),
+        ("group remove lobby", true),
+        ("ignore edit survival", true),
+        ("ignore test survival logs", false),
+        ("import bulk servers.toml", true),
+        ("info survival", false),
+        ("internal gen-man man", false),
+        ("java list", false),
+        ("launchscript init survival", true),
+        ("list", false),
+        ("logs survival", false),
+        ("macro save restart", true),
+        ("macro list", false),
+        ("macro run restart survival", false),
+        ("maintenance on survival", true),
+        ("maintenance off survival", true),
+        ("maintenance status survival", false),
+        ("new paper", false),
+        ("op add Notch survival", true),
+        ("op remove Notch survival", true),
+        ("open survival", false),
+        ("plugins sync lobby survival", true),
+        ("plugins sync lobby survival --dry-run", false),
+        ("pregen survival --radius 1000", false),
+        ("pregen status survival", false),
+        ("pregen cancel survival", false),
+        ("profile list", false),
+        ("profile export survival", false),
+        ("profile import profile.toml", true),
+        ("properties get survival motd", false),
+        ("properties set survival motd hello", true),
+        ("rcon survival list", false),
+        ("rcon-test survival", false),
+        ("reinstall --crate", true),
+        ("remove survival", true),
+        ("repair survival", true),
+        ("restart", false),
+        ("sessions", false),
+        ("sessions --repair", true),
+        ("size survival", false),
+        ("stats", false),
+        ("stop survival", false),
+        ("template new survival", false),
+        ("template update survival", true),
+        ("template children survival", false),
+        ("template from survival", false),
+        ("update survival", true),
+        ("version", false),
+        ("versions paper", false),
+        ("whitelist add Notch survival", true),
+        ("whitelist remove Notch survival", true),
+    ];
+
+    #[test]
+    fn classifies_destructive_commands() {
+        for &(args, destructive) in CASES {
+            let cli = Cli::try_parse_from(["mcserver"].into_iter().chain(args.split(' ')))
+                .unwrap_or_else(|err| panic!("{args}: {err}"));
+            assert_eq!(cli.command.is_destructive(), destructive, "{args}");
+        }
+    }
+
+    #[test]
+    fn covers_every_command() {
+        let covered: HashSet<&str> = CASES
+            .iter()
+            .map(|(args, _)| args.split(' ').next().unwrap())
+            .collect();
+
+        for command in Cli::command().get_subcommands() {
+            assert!(
+                covered.contains(command.get_name()),
+                "{} is missing from the cases",
+                command.get_name()
+            );
+        }
+    }
+}
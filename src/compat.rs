@@ -0,0 +1,140 @@
+use crate::{
+    error::{Error, Result},
+    modrinth,
+    server::get_server_dir_required,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
+
+const ADDON_DIRECTORIES: [&str; 2] = ["plugins", "mods"];
+
+pub enum Compatibility {
+    Compatible,
+    NeedsUpdate(String),
+    NoCompatibleRelease,
+    Unknown,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compatible => write!(f, "\x1b[32;1mcompatible\x1b[0m"),
+            Self::NeedsUpdate(version) => write!(f, "\x1b[33;1mneeds update\x1b[0m ({version})"),
+            Self::NoCompatibleRelease => write!(f, "\x1b[31;1mno compatible release\x1b[0m"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// How compatibility of plugins and mods is handled before an update
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CompatCheck {
+    Skip,
+    Enforce,
+    Ignore,
+}
+
+fn get_addon_jars(server_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut jars = vec![];
+
+    for directory in ADDON_DIRECTORIES {
+        let directory = server_dir.join(directory);
+        if !directory.is_dir() {
+            continue;
+        }
+
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "jar") {
+                jars.push(path);
+            }
+        }
+    }
+
+    jars.sort();
+    Ok(jars)
+}
+
+fn check_jar(jar: &Path, target_version: &str) -> Result<Compatibility> {
+    let Some(installed) = modrinth::version_from_file(jar)? else {
+        return Ok(Compatibility::Unknown);
+    };
+
+    if installed.game_versions.iter().any(|v| v == target_version) {
+        return Ok(Compatibility::Compatible);
+    }
+
+    let versions = modrinth::project_versions_for(
+        &installed.project_id,
+        Some(target_version),
+        &installed.loaders,
+    )?;
+
+    Ok(match versions.into_iter().next() {
+        Some(version) => Compatibility::NeedsUpdate(version.version_number),
+        None => Compatibility::NoCompatibleRelease,
+    })
+}
+
+/// Checks every plugin and mod jar of a server against a target game version
+pub fn check(
+    server: impl AsRef<Path>,
+    target_version: &str,
+) -> Result<Vec<(String, Compatibility)>> {
+    let server_dir = get_server_dir_required(server)?;
+
+    get_addon_jars(&server_dir)?
+        .into_iter()
+        .map(|jar| {
+            let name = jar
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            Ok((name, check_jar(&jar, target_version)?))
+        })
+        .collect()
+}
+
+pub fn print_report(
+    server: impl Display,
+    target_version: &str,
+    report: &[(String, Compatibility)],
+) {
+    if report.is_empty() {
+        println!("{server} has no plugins or mods to check");
+        return;
+    }
+
+    println!("Compatibility of {server} with {target_version}:");
+
+    let width = report.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, compatibility) in report {
+        println!("  {name:<width$}  {compatibility}");
+    }
+}
+
+/// Runs the check before updating a server, failing when anything has no compatible release
+/// unless told to ignore it
+pub fn check_before_update(server: &str, target_version: &str, policy: CompatCheck) -> Result<()> {
+    if policy == CompatCheck::Skip {
+        return Ok(());
+    }
+
+    let report = check(server, target_version)?;
+    print_report(server, target_version, &report);
+
+    let incompatible = report
+        .iter()
+        .filter(|(_, c)| matches!(c, Compatibility::NoCompatibleRelease))
+        .count();
+
+    if incompatible > 0 && policy == CompatCheck::Enforce {
+        return Err(Error::IncompatibleAddons(incompatible));
+    }
+
+    Ok(())
+}
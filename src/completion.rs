@@ -0,0 +1,272 @@
+//! Shell completion. The scripts printed by `mcserver completions` ask the hidden `__complete`
+//! command for candidates as they are needed, so that they stay current with the servers,
+//! templates and groups on the machine.
+//!
+//! Candidates are printed one per line, and any error prints nothing rather than breaking the
+//! shell.
+
+use crate::{
+    cli::Cli,
+    config, environment,
+    error::{Error, Result},
+    groups::GROUP_PREFIX,
+    platforms::{self, Platform},
+    server,
+};
+use clap::{CommandFactory, ValueEnum};
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// How long a platform's versions are kept for completion, which asks for them on every keystroke
+const VERSIONS_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+const BASH_SCRIPT: &str = r#"_mcserver() {
+    local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}
+    local command=${COMP_WORDS[1]} action=${COMP_WORDS[2]} kind=servers candidates
+
+    if (( COMP_CWORD == 1 )); then
+        kind=commands
+    elif [[ $prev == -v || $prev == --version ]]; then
+        kind=versions
+    elif [[ $cur == -* ]]; then
+        return
+    elif [[ $cur == @* ]]; then
+        kind=groups
+    elif [[ $prev == --all ]]; then
+        kind=platforms
+    else
+        case "$command/$COMP_CWORD" in
+            new/2 | versions/2 | update/3) kind=platforms ;;
+            update/4) [[ $action != -* ]] && kind=versions ;;
+            template/3 | tmpl/3) [[ $action == from || $action == children ]] && kind=templates ;;
+        esac
+    fi
+
+    case $kind in
+        versions)
+            local platforms word platform
+            platforms=$(mcserver __complete platforms)
+            for word in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+                grep -qxF -- "$word" <<< "$platforms" && platform=$word
+            done
+            [[ -n $platform ]] && candidates=$(mcserver __complete versions "$platform")
+            ;;
+        *) candidates=$(mcserver __complete "$kind") ;;
+    esac
+
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+}
+
+complete -o default -F _mcserver mcserver
+"#;
+
+const ZSH_SCRIPT: &str = r#"#compdef mcserver
+
+_mcserver() {
+    local cur=${words[CURRENT]} prev=${words[CURRENT-1]}
+    local command=${words[2]} action=${words[3]} position=$((CURRENT - 1)) kind=servers
+    local -a candidates
+
+    if (( position == 1 )); then
+        kind=commands
+    elif [[ $prev == -v || $prev == --version ]]; then
+        kind=versions
+    elif [[ $cur == -* ]]; then
+        return 1
+    elif [[ $cur == @* ]]; then
+        kind=groups
+    elif [[ $prev == --all ]]; then
+        kind=platforms
+    else
+        case "$command/$position" in
+            new/2 | versions/2 | update/3) kind=platforms ;;
+            update/4) [[ $action != -* ]] && kind=versions ;;
+            template/3 | tmpl/3) [[ $action == from || $action == children ]] && kind=templates ;;
+        esac
+    fi
+
+    case $kind in
+        versions)
+            local -a platforms=(${(f)"$(mcserver __complete platforms)"})
+            local word platform
+            for word in ${words[2,CURRENT-1]}; do
+                (( ${platforms[(Ie)$word]} )) && platform=$word
+            done
+            [[ -n $platform ]] && candidates=(${(f)"$(mcserver __complete versions $platform)"})
+            ;;
+        *) candidates=(${(f)"$(mcserver __complete $kind)"}) ;;
+    esac
+
+    # versions come newest first, which sorting would undo
+    compadd -V $kind -a candidates
+}
+
+compdef _mcserver mcserver
+"#;
+
+const FISH_SCRIPT: &str = r#"function __mcserver_complete
+    set -l words (commandline -opc)
+    set -l cur (commandline -ct)
+    set -l position (count $words)
+    set -l command $words[2]
+    set -l action $words[3]
+    set -l prev $words[-1]
+    set -l kind servers
+
+    if test $position -eq 1
+        set kind commands
+    else if contains -- $prev -v --version
+        set kind versions
+    else if string match -q -- '-*' $cur
+        return
+    else if string match -q -- '@*' $cur
+        set kind groups
+    else if test "$prev" = --all
+        set kind platforms
+    else
+        switch "$command/$position"
+            case new/2 versions/2 update/3
+                set kind platforms
+            case update/4
+                string match -q -- '-*' $action; or set kind versions
+            case template/3 tmpl/3
+                contains -- $action from children; and set kind templates
+        end
+    end
+
+    switch $kind
+        case versions
+            set -l platforms (mcserver __complete platforms)
+            set -l platform
+            for word in $words[2..-1]
+                contains -- $word $platforms; and set platform $word
+            end
+            test -n "$platform"; and mcserver __complete versions $platform
+        case '*'
+            mcserver __complete $kind
+    end
+end
+
+complete -c mcserver -f -k -a '(__mcserver_complete)'
+"#;
+
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH_SCRIPT,
+        Shell::Zsh => ZSH_SCRIPT,
+        Shell::Fish => FISH_SCRIPT,
+    }
+}
+
+/// The visible commands along with their visible aliases
+fn commands() -> Vec<String> {
+    Cli::command()
+        .get_subcommands()
+        .filter(|command| !command.is_hide_set())
+        .flat_map(|command| {
+            std::iter::once(command.get_name().to_string())
+                .chain(command.get_visible_aliases().map(String::from))
+        })
+        .collect()
+}
+
+fn servers(templates: bool) -> Result<Vec<String>> {
+    let mut servers = vec![];
+    server::for_each(|server| {
+        if server::is_template(&server) == templates {
+            servers.push(server);
+        }
+    })?;
+    servers.sort();
+    Ok(servers)
+}
+
+/// The groups, with the prefix that selects them in place of a server
+fn groups() -> Result<Vec<String>> {
+    let mut groups: Vec<_> = config::snapshot()?
+        .groups
+        .unwrap_or_default()
+        .into_keys()
+        .map(|group| format!("{GROUP_PREFIX}{group}"))
+        .collect();
+    groups.sort();
+    Ok(groups)
+}
+
+fn platforms() -> Vec<String> {
+    Platform::value_variants()
+        .iter()
+        .filter_map(|platform| platform.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn versions_cache_path(platform: Platform) -> Option<PathBuf> {
+    Some(
+        environment::get()
+            .cache_dir()?
+            .join("complete")
+            .join(format!("{platform}-versions.txt")),
+    )
+}
+
+/// Lists a platform's versions from a copy kept for a few minutes, only asking the platform once
+/// it is out of date, and then without retrying so that a slow API doesn't hold up the shell
+fn versions(platform: &str) -> Result<Vec<String>> {
+    let platform = Platform::from_str(platform, true)
+        .map_err(|_| Error::PlatformsNotFound(platform.to_string()))?;
+    let path = versions_cache_path(platform);
+
+    if let Some(path) = &path
+        && let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified())
+        && modified.elapsed().is_ok_and(|age| age < VERSIONS_MAX_AGE)
+    {
+        return Ok(fs::read_to_string(path)?
+            .lines()
+            .map(String::from)
+            .collect());
+    }
+
+    platforms::set_single_attempt(true);
+    let versions = platforms::list_versions(platform)?;
+
+    if let Some(path) = &path {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, versions.join("\n"))?;
+    }
+
+    Ok(versions)
+}
+
+/// Prints the candidates of a kind of argument, printing nothing when they can't be listed
+pub fn print_candidates(kind: &str, platform: Option<&str>) {
+    let candidates = match (kind, platform) {
+        ("commands", _) => Ok(commands()),
+        ("servers", _) => servers(false),
+        ("templates", _) => servers(true),
+        ("groups", _) => groups(),
+        ("platforms", _) => Ok(platforms()),
+        ("versions", Some(platform)) => versions(platform),
+        _ => Ok(vec![]),
+    };
+
+    let mut stdout = io::stdout().lock();
+    for candidate in candidates.unwrap_or_default() {
+        if writeln!(stdout, "{candidate}").is_err() {
+            break;
+        }
+    }
+}
@@ -1,59 +1,63 @@
 use crate::{
-    config_defs::{DynamicConfig, StaticConfig},
+    config_defs::{DynamicConfig, HttpConfig, StaticConfig},
+    environment,
     error::{Error, Result},
 };
+use reqwest::Proxy;
 use std::{
-    env, fs,
-    ops::Deref,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
     path::{Path, PathBuf},
-    sync::{Mutex, MutexGuard, OnceLock},
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
 };
 
 include!(concat!(env!("OUT_DIR"), "/generated_cfg.rs"));
 pub use generated_cfg::*;
 
+/// The dynamic configuration, loaded on first use. Readers take a [`snapshot`] and writers go
+/// through [`update`], so the lock is never held across other work.
 pub struct AutoConfig {
     value: OnceLock<Mutex<DynamicConfig>>,
-    initial_value: OnceLock<DynamicConfig>,
+    persisted: Mutex<Option<DynamicConfig>>,
+}
+
+/// Locks a mutex, recovering the value if a thread panicked while holding it, since the
+/// configuration is plain data that can't be left half updated by a panic in a closure
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 impl AutoConfig {
     const fn new() -> Self {
         Self {
             value: OnceLock::new(),
-            initial_value: OnceLock::new(),
+            persisted: Mutex::new(None),
         }
     }
 
+    /// Saves the configuration if it changed since it was loaded or last saved
     pub fn write(&self) -> Result<()> {
-        let Some(mutex) = self.get() else {
+        let Some(mutex) = self.value.get() else {
             return Ok(());
         };
 
-        let guard = mutex.lock().map_err(|_| Error::ConfigMutexPoisoned)?;
+        let mut current = lock(mutex).clone();
+        // whatever wrote the file last, it now has this version's layout
+        current.config_version = current.config_version.max(CONFIG_VERSION);
+        let mut persisted = lock(&self.persisted);
 
-        if let Some(initial_value) = self.initial_value.get() {
-            if *initial_value == *guard {
-                return Ok(());
-            }
-        } else {
-            eprintln!("Initial configuration value not set");
+        if persisted.as_ref() == Some(&current) {
+            return Ok(());
         }
 
         fs::create_dir_all(get_config_directory()?)?;
-        fs::write(get_config_file()?, toml::to_string(&*guard)?)?;
+        fs::write(get_config_file()?, toml::to_string(&current)?)?;
+        *persisted = Some(current);
         Ok(())
     }
 }
 
-impl Deref for AutoConfig {
-    type Target = OnceLock<Mutex<DynamicConfig>>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.value
-    }
-}
-
 pub static CONFIG: AutoConfig = AutoConfig::new();
 
 static CONFIG_DIRECTORY: OnceLock<PathBuf> = OnceLock::new();
@@ -61,15 +65,16 @@ static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
 
 static EXPANDED_SERVERS_DIR: OnceLock<PathBuf> = OnceLock::new();
 
-fn get_config_directory() -> Result<&'static Path> {
+pub fn get_config_directory() -> Result<&'static Path> {
     if let Some(path) = CONFIG_DIRECTORY.get() {
         return Ok(path.as_path());
     }
 
-    let path = shellexpand::full(STATIC_CONFIG.dynamic_config_path)?;
-    Ok(CONFIG_DIRECTORY
-        .get_or_init(|| PathBuf::from(&*path))
-        .as_path())
+    let path = match &environment::get().config_dir {
+        Some(dir) => dir.clone(),
+        None => PathBuf::from(&*shellexpand::full(STATIC_CONFIG.dynamic_config_path)?),
+    };
+    Ok(CONFIG_DIRECTORY.get_or_init(|| path).as_path())
 }
 
 fn get_config_file() -> Result<&'static Path> {
@@ -81,13 +86,249 @@ fn get_config_file() -> Result<&'static Path> {
     Ok(CONFIG_FILE.get_or_init(|| path).as_path())
 }
 
+/// The configuration file with a suffix added, such as its `.bak` backup
+fn get_config_file_with_suffix(suffix: &str) -> Result<PathBuf> {
+    let mut path = get_config_file()?.as_os_str().to_os_string();
+    path.push(suffix);
+    Ok(path.into())
+}
+
+/// Overrides the dynamic configuration file, which must happen before the configuration is first
+/// accessed
+pub fn set_config_file(file: impl Into<PathBuf>) -> Result<()> {
+    let file = file.into();
+    let directory = file.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    CONFIG_FILE
+        .set(file)
+        .map_err(|_| Error::ConfigPathAlreadySet)?;
+    CONFIG_DIRECTORY
+        .set(directory)
+        .map_err(|_| Error::ConfigPathAlreadySet)?;
+
+    Ok(())
+}
+
 pub fn get_static() -> &'static StaticConfig {
     &STATIC_CONFIG
 }
 
-pub fn get() -> Result<MutexGuard<'static, DynamicConfig>> {
-    if let Some(mutex) = CONFIG.get() {
-        return mutex.lock().map_err(|_| Error::ConfigMutexPoisoned);
+/// The layout version of the dynamic configuration that this version writes
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Where the changes made by migrations are recorded, next to the configuration
+const MIGRATION_LOG: &str = "migrations.log";
+
+/// An upgrade of the configuration file to a layout version, returning what it changed
+struct Migration {
+    to: u32,
+    apply: fn(&mut toml::Table, &toml::Table) -> Vec<String>,
+}
+
+const MIGRATIONS: [Migration; 1] = [Migration {
+    to: 1,
+    apply: add_missing_required,
+}];
+
+/// Adds the keys files written before versioning could lack, which the configuration can't be
+/// read without, with their default values
+fn add_missing_required(config: &mut toml::Table, defaults: &toml::Table) -> Vec<String> {
+    let mut changes = vec![];
+
+    for key in ["default_java_args", "nogui", "servers_directory"] {
+        if !config.contains_key(key)
+            && let Some(value) = defaults.get(key)
+        {
+            changes.push(format!("added {key} = {value}"));
+            config.insert(key.to_string(), value.clone());
+        }
+    }
+
+    changes
+}
+
+/// Upgrades a configuration file's table to the current layout, returning what changed. Files
+/// from a newer version are left alone.
+fn migrate(config: &mut toml::Table) -> Result<Vec<String>> {
+    let version = config
+        .get("config_version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or_default() as u32;
+
+    if version >= CONFIG_VERSION {
+        return Ok(vec![]);
+    }
+
+    let defaults = toml::Table::try_from(get_default_dynamic_config())?;
+    let mut changes = vec![];
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.to > version) {
+        changes.extend((migration.apply)(config, &defaults));
+    }
+
+    config.insert(
+        "config_version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION.into()),
+    );
+    changes.push(format!("config_version {version} -> {CONFIG_VERSION}"));
+
+    Ok(changes)
+}
+
+/// Writes a migrated configuration back, keeping the file it replaces as a backup, and records
+/// the changes
+fn save_migrated(
+    config_file: &Path,
+    original: &str,
+    config: &DynamicConfig,
+    changes: &[String],
+) -> Result<()> {
+    fs::write(get_config_file_with_suffix(".bak")?, original)?;
+    fs::write(config_file, toml::to_string(config)?)?;
+
+    let now = jiff::Timestamp::now().strftime("%Y-%m-%dT%H:%M:%SZ");
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_config_directory()?.join(MIGRATION_LOG))?;
+    for change in changes {
+        writeln!(log, "{now} {change}")?;
+    }
+
+    eprintln!(
+        "Upgraded the configuration to version {CONFIG_VERSION}, see `mcserver config dynamic --migrations`"
+    );
+    Ok(())
+}
+
+/// Prints the changes migrations have made to the configuration file
+pub fn print_migrations() -> Result<()> {
+    load()?;
+
+    match fs::read_to_string(get_config_directory()?.join(MIGRATION_LOG)) {
+        Ok(log) if !log.trim().is_empty() => print!("{log}"),
+        Ok(_) => println!("No migrations have been applied"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            println!("No migrations have been applied")
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    Ok(())
+}
+
+/// The top level keys of [`DynamicConfig`], for spotting typos among unknown keys
+const DYNAMIC_CONFIG_KEYS: [&str; 24] = [
+    "config_version",
+    "default_java_args",
+    "nogui",
+    "servers_directory",
+    "default_server",
+    "rcon",
+    "open_command",
+    "editor_command",
+    "download_directory",
+    "backup_directory",
+    "time_zone",
+    "tab_title",
+    "session_namespace",
+    "download_rate_limit",
+    "verify_jar_checksums",
+    "command_timeout",
+    "restricted_mode",
+    "http_cache_ttl",
+    "http_cache_fresh",
+    "http_attempts",
+    "http_retry_delay_ms",
+    "http",
+    "groups",
+    "profiles",
+];
+
+/// The number of single character insertions, deletions and substitutions between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Warns about unknown keys that are probably typos of known ones. Other unknown keys are left
+/// alone, since they may belong to a newer version.
+fn warn_about_typos(config: &DynamicConfig) {
+    for key in config.extra.keys() {
+        if let Some(known) = DYNAMIC_CONFIG_KEYS
+            .iter()
+            .find(|known| edit_distance(key, known) <= 2)
+        {
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m unknown configuration key `{key}`, did you mean `{known}`?"
+            );
+        }
+    }
+}
+
+/// The proxy configured under `[http]`, if any, which is also checked when the configuration is
+/// loaded so that a bad url is reported before any request is made
+pub fn configured_proxy(http: &HttpConfig) -> Result<Option<Proxy>> {
+    http.proxy
+        .as_deref()
+        .map(|url| Proxy::all(url).map_err(|_| Error::InvalidProxy(url.to_string())))
+        .transpose()
+}
+
+/// Parses a configuration file's contents, upgrading them to the current layout, and returns them
+/// along with what the upgrade changed
+fn parse(toml_string: &str) -> Result<(DynamicConfig, Vec<String>)> {
+    let mut table: toml::Table = toml::from_str(toml_string)?;
+    let changes = migrate(&mut table)?;
+    let config: DynamicConfig = table.try_into()?;
+
+    if let Some(http) = &config.http {
+        configured_proxy(http)?;
+    }
+
+    Ok((config, changes))
+}
+
+/// Returns the configuration file's backup, kept from before its last upgrade, if there is one
+/// that can be loaded
+pub fn get_valid_backup() -> Result<Option<PathBuf>> {
+    let backup = get_config_file_with_suffix(".bak")?;
+
+    match fs::read_to_string(&backup) {
+        Ok(toml_string) => Ok(parse(&toml_string).is_ok().then_some(backup)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Replaces the configuration file with its backup, keeping the replaced file as `.broken` next
+/// to it
+pub fn restore_backup() -> Result<()> {
+    let config_file = get_config_file()?;
+    let broken = get_config_file_with_suffix(".broken")?;
+
+    if config_file.exists() {
+        fs::rename(config_file, &broken)?;
+    }
+    fs::copy(get_config_file_with_suffix(".bak")?, config_file)?;
+
+    Ok(())
+}
+
+fn load() -> Result<&'static Mutex<DynamicConfig>> {
+    if let Some(mutex) = CONFIG.value.get() {
+        return Ok(mutex);
     }
 
     let config_dir = get_config_directory()?;
@@ -95,7 +336,14 @@ pub fn get() -> Result<MutexGuard<'static, DynamicConfig>> {
 
     let config: DynamicConfig = if config_file.exists() {
         let toml_string = fs::read_to_string(config_file)?;
-        toml::from_str(&toml_string)?
+        let (config, changes) = parse(&toml_string)?;
+
+        if !changes.is_empty() {
+            save_migrated(config_file, &toml_string, &config, &changes)?;
+        }
+
+        warn_about_typos(&config);
+        config
     } else {
         fs::create_dir_all(config_dir)?;
         let config = get_default_dynamic_config();
@@ -103,12 +351,25 @@ pub fn get() -> Result<MutexGuard<'static, DynamicConfig>> {
         config.clone()
     };
 
-    CONFIG.initial_value.get_or_init(|| config.clone());
+    let mutex = CONFIG.value.get_or_init(|| Mutex::new(config));
+
+    let mut persisted = lock(&CONFIG.persisted);
+    if persisted.is_none() {
+        *persisted = Some(lock(mutex).clone());
+    }
+
+    Ok(mutex)
+}
+
+/// Returns a copy of the current configuration
+pub fn snapshot() -> Result<DynamicConfig> {
+    Ok(lock(load()?).clone())
+}
 
-    CONFIG
-        .get_or_init(|| Mutex::new(config))
-        .lock()
-        .map_err(|_| Error::ConfigMutexPoisoned)
+/// Changes the configuration, holding the lock only for the duration of the closure, which must
+/// not access the configuration itself
+pub fn update<R>(f: impl FnOnce(&mut DynamicConfig) -> R) -> Result<R> {
+    Ok(f(&mut lock(load()?)))
 }
 
 pub fn get_expanded_servers_dir() -> Result<&'static Path> {
@@ -116,46 +377,71 @@ pub fn get_expanded_servers_dir() -> Result<&'static Path> {
         return Ok(dir.as_path());
     }
 
-    let config = get()?;
-    let dir = shellexpand::full(&config.servers_directory)?;
+    let servers_directory = snapshot()?.servers_directory;
+    let dir = shellexpand::full(&servers_directory)?;
     Ok(EXPANDED_SERVERS_DIR
         .get_or_init(|| PathBuf::from(&*dir))
         .as_path())
 }
 
 pub fn get_current_server_directory() -> Result<String> {
-    let servers_dir = get_expanded_servers_dir()?;
     let current_dir = env::current_dir()?;
 
-    if !current_dir.starts_with(servers_dir) {
-        return Err(Error::InvalidServersDirectory);
-    }
+    // the current directory comes resolved from the OS, so a servers directory behind a symlink
+    // only matches once resolved too, unless the directory can't be resolved right now
+    let servers_dir = [get_expanded_servers_dir()?.to_path_buf()]
+        .into_iter()
+        .chain(fs::canonicalize(get_expanded_servers_dir()?))
+        .find(|servers_dir| current_dir.starts_with(servers_dir))
+        .ok_or(Error::InvalidServersDirectory)?;
+
+    let Some(server) = current_dir.strip_prefix(&servers_dir)?.components().next() else {
+        let mut children = fs::read_dir(&servers_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        children.sort();
+
+        return Err(Error::NoServerChild { children });
+    };
 
-    let server = current_dir
-        .strip_prefix(servers_dir)?
-        .components()
-        .next()
-        .ok_or(Error::NoServerChild)?
-        .as_os_str()
-        .to_string_lossy()
-        .to_string();
+    Ok(server.as_os_str().to_string_lossy().to_string())
+}
 
-    Ok(server)
+pub fn has_rcon_config(server: &str) -> Result<bool> {
+    Ok(snapshot()?
+        .rcon
+        .as_ref()
+        .is_some_and(|rcon| rcon.contains_key(server)))
 }
 
-pub fn get_default_server_owned() -> Result<Option<String>> {
-    Ok(get()?.default_server.clone())
+/// The environment variable that selects a server for the current shell, taking precedence over
+/// the configured default server
+pub const SERVER_ENV_VAR: &str = "MCSERVER_SERVER";
+
+pub fn get_env_server() -> Option<String> {
+    env::var(SERVER_ENV_VAR)
+        .ok()
+        .filter(|server| !server.is_empty())
 }
 
-pub fn server_or_current<S>(server: S) -> Result<String>
-where
-    S: Into<String> + for<'a> PartialEq<&'a str>,
-{
-    if server == "." {
-        get_current_server_directory()
-    } else {
-        Ok(server.into())
+/// The environment variable that restricts mcserver to commands that don't remove, update or
+/// reconfigure anything, in addition to the `restricted_mode` key
+pub const RESTRICTED_ENV_VAR: &str = "MCSERVER_RESTRICTED";
+
+pub fn is_restricted() -> Result<bool> {
+    if env::var(RESTRICTED_ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0") {
+        return Ok(true);
     }
+
+    // an unreadable configuration restricts nothing, so that `doctor --fix` can still restore it.
+    // Whoever can break the file could also turn restricted mode off in it.
+    Ok(snapshot().is_ok_and(|config| config.restricted_mode == Some(true)))
+}
+
+pub fn get_default_server_owned() -> Result<Option<String>> {
+    Ok(snapshot()?.default_server)
 }
 
 #[macro_export]
@@ -163,18 +449,190 @@ macro_rules! unwrap_server_or_default {
     ($server:expr) => {
         (|| -> Result<String> {
             use $crate::{
-                config::{get_default_server_owned, server_or_current},
+                config::{get_default_server_owned, get_env_server},
                 error::Error,
+                server::server_or_current,
             };
 
-            let server = match $server {
-                Some(server) => server,
-                None => get_default_server_owned()
-                    .wrap_err("Failed to get configuration")?
-                    .ok_or(Error::NoDefaultServer)?,
-            };
-
-            Ok(server_or_current(server)?)
+            // "." is only resolved when it is passed explicitly
+            match $server {
+                Some(server) => Ok(server_or_current(server)?),
+                None => match get_env_server() {
+                    Some(server) => Ok(server),
+                    None => Ok(get_default_server_owned()
+                        .wrap_err("Failed to get configuration")?
+                        .ok_or(Error::NoDefaultServer)?),
+                },
+            }
         })()
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::thread;
+
+    /// A file from before versioning, which lacks keys that were added with it
+    const UNVERSIONED_PARTIAL: &str = r#"
+servers_directory = "/srv/minecraft"
+default_server = "survival"
+"#;
+
+    /// A file from before versioning that already has every required key
+    const UNVERSIONED_COMPLETE: &str = r#"
+default_java_args = "-Xmx4G"
+nogui = true
+servers_directory = "~/Servers"
+"#;
+
+    const CURRENT: &str = r#"
+config_version = 1
+default_java_args = ""
+nogui = false
+servers_directory = "~/Servers"
+"#;
+
+    /// A file from a newer version, with a key this version doesn't know
+    const NEWER: &str = r#"
+config_version = 99
+default_java_args = ""
+nogui = false
+servers_directory = "~/Servers"
+future_option = "kept"
+"#;
+
+    #[test]
+    fn unversioned_files_gain_missing_keys() {
+        let (config, changes) = parse(UNVERSIONED_PARTIAL).unwrap();
+        let defaults = get_default_dynamic_config();
+
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.servers_directory, "/srv/minecraft");
+        assert_eq!(config.default_server.as_deref(), Some("survival"));
+        assert_eq!(config.default_java_args, defaults.default_java_args);
+        assert_eq!(config.nogui, defaults.nogui);
+        assert_eq!(
+            changes,
+            [
+                format!("added default_java_args = {:?}", defaults.default_java_args),
+                format!("added nogui = {}", defaults.nogui),
+                format!("config_version 0 -> {CONFIG_VERSION}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unversioned_files_keep_their_values() {
+        let (config, changes) = parse(UNVERSIONED_COMPLETE).unwrap();
+
+        assert_eq!(config.default_java_args, "-Xmx4G");
+        assert!(config.nogui);
+        assert_eq!(changes, [format!("config_version 0 -> {CONFIG_VERSION}")]);
+    }
+
+    #[test]
+    fn current_files_are_unchanged() {
+        let (config, changes) = parse(CURRENT).unwrap();
+
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn newer_files_are_left_alone() {
+        let (config, changes) = parse(NEWER).unwrap();
+
+        assert_eq!(config.config_version, 99);
+        assert!(changes.is_empty());
+        assert_eq!(config.extra["future_option"].as_str(), Some("kept"));
+        assert!(toml::to_string(&config).unwrap().contains("future_option"));
+    }
+
+    #[test]
+    fn migrated_files_parse_again_unchanged() {
+        let (config, _) = parse(UNVERSIONED_PARTIAL).unwrap();
+        let (reparsed, changes) = parse(&toml::to_string(&config).unwrap()).unwrap();
+
+        assert!(changes.is_empty());
+        assert_eq!(reparsed.servers_directory, config.servers_directory);
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        assert!(parse("servers_directory = [").is_err());
+        assert!(parse(&CURRENT.replace("false", "\"no\"")).is_err());
+        assert!(matches!(
+            parse(&format!("{CURRENT}[http]\nproxy = \"not a url\"")),
+            Err(Error::InvalidProxy(_))
+        ));
+    }
+
+    #[test]
+    fn typos_are_near_known_keys() {
+        assert_eq!(edit_distance("server_directory", "servers_directory"), 1);
+        assert_eq!(edit_distance("nogiu", "nogui"), 2);
+        assert_eq!(edit_distance("", "rcon"), 4);
+        assert!(
+            DYNAMIC_CONFIG_KEYS
+                .iter()
+                .all(|known| edit_distance("future_option", known) > 2)
+        );
+    }
+
+    #[test]
+    fn snapshots_can_be_held_across_updates() {
+        testing::sandbox(&[]);
+
+        let before = snapshot().unwrap();
+        for i in 0..3 {
+            let title = update(|config| {
+                config.tab_title = Some(format!("title {i}"));
+                config.tab_title.clone()
+            })
+            .unwrap();
+            assert_eq!(snapshot().unwrap().tab_title, title);
+        }
+        // a snapshot is a copy, so updates don't reach it
+        assert_eq!(snapshot().unwrap().tab_title.as_deref(), Some("title 2"));
+        assert_ne!(before.tab_title.as_deref(), Some("title 2"));
+
+        thread::scope(|scope| {
+            for i in 0..4 {
+                scope.spawn(move || {
+                    for _ in 0..50 {
+                        let current = snapshot().unwrap().tab_title;
+                        update(|config| config.tab_title = current.or(Some(format!("{i}"))))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        update(|config| config.tab_title = before.tab_title).unwrap();
+    }
+
+    #[test]
+    fn panics_while_updating_dont_lock_the_configuration() {
+        testing::sandbox(&[]);
+
+        let panicked = thread::spawn(|| {
+            update(|config| {
+                config.download_rate_limit = Some("1M".to_string());
+                panic!("panicked while updating");
+            })
+        })
+        .join();
+        assert!(panicked.is_err());
+        assert!(CONFIG.value.get().unwrap().is_poisoned());
+
+        // the change made before the panic stands, and the lock is usable again
+        assert_eq!(
+            snapshot().unwrap().download_rate_limit.as_deref(),
+            Some("1M")
+        );
+        update(|config| config.download_rate_limit = None).unwrap();
+        assert_eq!(snapshot().unwrap().download_rate_limit, None);
+    }
+}
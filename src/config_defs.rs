@@ -40,11 +40,51 @@ pub struct RconConfig {
     pub password: Option<Password>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: Option<u64>,
+    pub request_timeout_secs: Option<u64>,
+    pub proxy: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Profile {
+    pub properties: Option<HashMap<String, String>>,
+    pub gamerules: Option<Vec<String>>,
+    pub plugins: Option<Vec<String>>,
+    pub java_args: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct DynamicConfig {
+    /// The layout version of the file, which is 0 for files written before it was recorded
+    #[serde(default)]
+    pub config_version: u32,
     pub default_java_args: String,
     pub nogui: bool,
     pub servers_directory: String,
     pub default_server: Option<String>,
     pub rcon: Option<HashMap<String, RconConfig>>,
+    pub open_command: Option<String>,
+    pub editor_command: Option<String>,
+    pub download_directory: Option<String>,
+    pub backup_directory: Option<String>,
+    pub time_zone: Option<String>,
+    pub tab_title: Option<String>,
+    pub session_namespace: Option<String>,
+    pub download_rate_limit: Option<String>,
+    pub verify_jar_checksums: Option<bool>,
+    pub command_timeout: Option<u64>,
+    pub restricted_mode: Option<bool>,
+    pub http_cache_ttl: Option<u64>,
+    pub http_cache_fresh: Option<u64>,
+    pub http_attempts: Option<u32>,
+    pub http_retry_delay_ms: Option<u64>,
+    pub http: Option<HttpConfig>,
+    pub groups: Option<HashMap<String, Vec<String>>>,
+    pub profiles: Option<HashMap<String, Profile>>,
+    /// Keys this version doesn't know, such as ones written by a newer version, which are kept so
+    /// that saving the configuration doesn't drop them
+    #[serde(flatten, default, skip_serializing_if = "toml::Table::is_empty")]
+    pub extra: toml::Table,
 }
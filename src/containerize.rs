@@ -0,0 +1,184 @@
+//! Generates a Dockerfile, and optionally a compose service, for servers that run in a container
+//! elsewhere while still being created and updated here.
+//!
+//! The generated part of each file sits between marker comments. Regenerating replaces only that
+//! part, keeping any edits around it, and a file without the markers is only replaced with
+//! `--force`.
+
+use crate::{
+    error::{Error, Result},
+    fleet,
+    server::{self, get_server_dir_required, read_start_command},
+    worlds,
+};
+use std::{fs, io, path::Path};
+
+pub const DEFAULT_IMAGE: &str = "eclipse-temurin:21-jre";
+
+const DOCKERFILE: &str = "Dockerfile";
+const COMPOSE_FILE: &str = "docker-compose.yml";
+const BEGIN_MARKER: &str = "# BEGIN mcserver containerize, replaced when regenerated";
+const END_MARKER: &str = "# END mcserver";
+
+/// Where the server is copied to in the image
+const CONTAINER_DIR: &str = "/server";
+/// Where Bedrock keeps its worlds, which isn't configurable
+const BEDROCK_WORLDS_DIR: &str = "worlds";
+
+/// A name docker accepts for an image or a compose service: lowercase letters, digits, dashes and
+/// underscores
+fn docker_name(server: &str) -> String {
+    let name: String = server
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "server".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn dockerfile(image: &str, port: &str, invocation: &str) -> String {
+    format!(
+        "FROM {image}\n\
+         WORKDIR {CONTAINER_DIR}\n\
+         COPY . {CONTAINER_DIR}\n\
+         EXPOSE {port}\n\
+         # exec hands the server the container's signals, so that `docker stop` shuts it down\n\
+         # cleanly. It reads console commands from stdin, so run it with `docker run -it`.\n\
+         ENTRYPOINT exec {}\n",
+        invocation.trim_end()
+    )
+}
+
+fn compose_service(service: &str, port: &str, volumes: &[String]) -> String {
+    let published = match port.split_once('/') {
+        Some((number, protocol)) => format!("{number}:{number}/{protocol}"),
+        None => format!("{port}:{port}"),
+    };
+
+    let mut service = format!("services:\n  {service}:\n");
+    for line in [
+        "build: .".to_string(),
+        "ports:".to_string(),
+        format!("  - \"{published}\""),
+        "stdin_open: true".to_string(),
+        "tty: true".to_string(),
+        "restart: unless-stopped".to_string(),
+    ] {
+        service.push_str(&format!("    {line}\n"));
+    }
+
+    if !volumes.is_empty() {
+        service.push_str("    volumes:\n");
+        for volume in volumes {
+            service.push_str(&format!("      - ./{volume}:{CONTAINER_DIR}/{volume}\n"));
+        }
+    }
+
+    service
+}
+
+/// Writes a generated block into a file: in place of its previous block if it has one, as the
+/// whole file if it doesn't exist yet, and otherwise only with `force`
+fn write_generated(path: &Path, generated: &str, force: bool) -> Result<()> {
+    let block = format!("{BEGIN_MARKER}\n{generated}{END_MARKER}\n");
+
+    let contents = match fs::read_to_string(path) {
+        Ok(existing) => match (existing.find(BEGIN_MARKER), existing.find(END_MARKER)) {
+            (Some(start), Some(end)) if start < end => {
+                let end = end + END_MARKER.len();
+                let end = end + usize::from(existing[end..].starts_with('\n'));
+                format!("{}{block}{}", &existing[..start], &existing[end..])
+            }
+            _ if force => block,
+            _ => {
+                return Err(Error::NotGenerated {
+                    path: path.to_path_buf(),
+                });
+            }
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => block,
+        Err(err) => return Err(err.into()),
+    };
+
+    fs::write(path, contents)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// The directories a server's worlds are kept in, relative to the server, which compose mounts as
+/// volumes so that they outlive the container
+fn world_volumes(server: &str, server_dir: &Path) -> Result<Vec<String>> {
+    if read_start_command(server_dir)?.is_some() {
+        return Ok(vec![BEDROCK_WORLDS_DIR.to_string()]);
+    }
+
+    if server::is_proxy(server_dir)? {
+        return Ok(vec![]);
+    }
+
+    Ok(worlds::get_world_dirs(server)?
+        .top_level()
+        .into_iter()
+        .filter_map(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Writes a Dockerfile into a server, and a compose file along with it if asked to, then prints
+/// how to build and run them. The container runs the same command as the server's session does.
+pub fn generate(server: &str, image: &str, compose: bool, force: bool) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let invocation = server::get_launch_invocation(server, true)?;
+
+    // Bedrock speaks UDP
+    let port = fleet::port_of(server)?;
+    let port = if read_start_command(&server_dir)?.is_some() {
+        format!("{port}/udp")
+    } else {
+        port
+    };
+
+    write_generated(
+        &server_dir.join(DOCKERFILE),
+        &dockerfile(image, &port, &invocation),
+        force,
+    )?;
+
+    let name = docker_name(server);
+    if compose {
+        let volumes = world_volumes(server, &server_dir)?;
+        write_generated(
+            &server_dir.join(COMPOSE_FILE),
+            &compose_service(&name, &port, &volumes),
+            force,
+        )?;
+    }
+
+    let published = port.split('/').next().unwrap_or(&port);
+    let protocol = port.strip_prefix(published).unwrap_or_default();
+    println!();
+    println!("Build and run it with:");
+    println!(
+        "  docker build -t mcserver-{name} {}",
+        server::shell_quote(server_dir.to_string_lossy())
+    );
+    println!("  docker run -it -p {published}:{published}{protocol} mcserver-{name}");
+    if compose {
+        println!("Or with compose, from the server directory:");
+        println!("  docker compose up -d");
+    }
+
+    Ok(())
+}
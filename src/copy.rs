@@ -0,0 +1,411 @@
+//! Copying server directories, which can be many gigabytes of worlds on storage as slow as an
+//! archive. Files are cloned where the filesystem can share their blocks, and otherwise copied by
+//! the kernel, several at a time. Permissions and modification times are kept.
+
+use crate::{humanize, ignore::IgnoreRules, progress, verbose};
+use std::{
+    cmp::Reverse,
+    fs::{self, File, FileTimes},
+    io::{self, IsTerminal},
+    num::NonZero,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Files that only make sense for a running server, and stop a copy of it from starting
+const TRANSIENT_FILES: [&str; 1] = ["session.lock"];
+
+/// How many files are copied at once, which is more than enough to keep a disk busy
+const MAX_WORKERS: usize = 8;
+
+/// Copies smaller than this finish before progress would be worth showing
+const PROGRESS_THRESHOLD: u64 = 256 * 1024 * 1024;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+fn with_path_context(err: io::Error, path: &Path) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+struct FileCopy {
+    src: PathBuf,
+    dst: PathBuf,
+    len: u64,
+}
+
+/// What a copy will do, worked out before any of it is done so that the files can be shared out
+/// and the progress measured against the total
+#[derive(Default)]
+struct Plan {
+    /// Each directory along with its copy, parents before their children
+    dirs: Vec<(PathBuf, PathBuf)>,
+    files: Vec<FileCopy>,
+    bytes: u64,
+}
+
+impl Plan {
+    fn walk(
+        &mut self,
+        src: &Path,
+        dst: &Path,
+        relative: &Path,
+        rules: &IgnoreRules,
+    ) -> io::Result<()> {
+        self.dirs.push((src.to_path_buf(), dst.to_path_buf()));
+
+        for entry in fs::read_dir(src).map_err(|err| with_path_context(err, src))? {
+            let entry = entry.map_err(|err| with_path_context(err, src))?;
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .map_err(|err| with_path_context(err, &path))?;
+
+            if TRANSIENT_FILES.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+
+            let relative = relative.join(entry.file_name());
+            if rules.excludes(&relative, file_type.is_dir()) {
+                verbose!("Skipping ignored {}", relative.display());
+                continue;
+            }
+
+            if file_type.is_dir() {
+                self.walk(&path, &dst.join(entry.file_name()), &relative, rules)?;
+            } else if file_type.is_socket()
+                || file_type.is_fifo()
+                || file_type.is_block_device()
+                || file_type.is_char_device()
+            {
+                eprintln!(
+                    "\x1b[33;1mWarning:\x1b[0m skipping {}, which is not a regular file",
+                    path.display()
+                );
+            } else {
+                // links are followed, so that the copy stands on its own
+                let len = fs::metadata(&path)
+                    .map_err(|err| with_path_context(err, &path))?
+                    .len();
+                self.bytes += len;
+                self.files.push(FileCopy {
+                    src: path,
+                    dst: dst.join(entry.file_name()),
+                    len,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows how far a large copy has got on a terminal, and how fast it went once it is done
+struct Progress {
+    total: u64,
+    copied: AtomicU64,
+    started: Instant,
+    last_drawn: Mutex<Instant>,
+    interactive: bool,
+}
+
+impl Progress {
+    fn new(total: u64) -> Self {
+        let started = Instant::now();
+        Self {
+            total,
+            copied: AtomicU64::new(0),
+            started,
+            last_drawn: Mutex::new(started),
+            interactive: total >= PROGRESS_THRESHOLD && io::stderr().is_terminal(),
+        }
+    }
+
+    fn rate(&self, bytes: u64) -> String {
+        let secs = self.started.elapsed().as_secs_f64().max(0.001);
+        humanize::bytes((bytes as f64 / secs) as u64)
+    }
+
+    fn add(&self, bytes: u64) {
+        let copied = self.copied.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if !self.interactive {
+            return;
+        }
+
+        let mut last_drawn = self
+            .last_drawn
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if last_drawn.elapsed() < REDRAW_INTERVAL {
+            return;
+        }
+        *last_drawn = Instant::now();
+
+        eprint!(
+            "\r\x1b[2KCopying {} {} of {} ({}/s)",
+            progress::progress_bar(copied, self.total),
+            humanize::bytes(copied),
+            humanize::bytes(self.total),
+            self.rate(copied)
+        );
+    }
+
+    fn finish(&self) {
+        let copied = self.copied.load(Ordering::Relaxed);
+        let summary = format!(
+            "Copied {} in {} ({}/s)",
+            humanize::bytes(copied),
+            humanize::duration(self.started.elapsed().as_secs()),
+            self.rate(copied)
+        );
+
+        if self.interactive {
+            eprint!("\r\x1b[2K");
+        }
+
+        if self.total >= PROGRESS_THRESHOLD {
+            eprintln!("{summary}");
+        } else {
+            verbose!("{summary}");
+        }
+    }
+}
+
+/// Clones a file on filesystems that can share blocks between files, such as Btrfs and XFS,
+/// returning whether it could
+#[cfg(target_os = "linux")]
+fn clone_file(src: &Path, dst: &Path) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    let source = File::open(src)?;
+    let target = File::create(dst)?;
+
+    // SAFETY: both descriptors are open for as long as the call lasts
+    if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        return Ok(false);
+    }
+
+    target.set_permissions(source.metadata()?.permissions())?;
+    Ok(true)
+}
+
+/// Other platforms clone what they can in `fs::copy` already
+#[cfg(not(target_os = "linux"))]
+fn clone_file(_src: &Path, _dst: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Gives a copy the access and modification times of the original
+fn copy_times(src: &Path, dst: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(src)?;
+    let times = FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+
+    // setting times only takes owning the file, so a read-only copy can still be opened for it
+    File::open(dst)?.set_times(times)
+}
+
+/// Copies a file along with its permissions and times. Where it can't be cloned, `fs::copy`
+/// leaves the copying to the kernel with `copy_file_range`, which falls back to reading and
+/// writing across filesystems.
+fn copy_file(src: &Path, dst: &Path) -> io::Result<()> {
+    if !clone_file(src, dst)? {
+        fs::copy(src, dst)?;
+    }
+
+    copy_times(src, dst)
+}
+
+/// Copies the files with a few workers, stopping them all at the first failure
+fn copy_files(files: &[FileCopy], progress: &Progress) -> io::Result<()> {
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .clamp(1, MAX_WORKERS)
+        .min(files.len().max(1));
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| -> io::Result<()> {
+                    loop {
+                        let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            return Ok(());
+                        };
+
+                        if let Err(err) = copy_file(&file.src, &file.dst) {
+                            next.store(files.len(), Ordering::Relaxed);
+                            return Err(with_path_context(err, &file.src));
+                        }
+
+                        progress.add(file.len);
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().expect("copy worker panicked"))
+    })
+}
+
+/// Copies a directory recursively, leaving out transient lock files and what `rules` exclude, and
+/// skipping sockets, FIFOs and devices with a warning since they can't be copied
+pub fn copy_tree(src: &Path, dst: &Path, rules: &IgnoreRules) -> io::Result<()> {
+    let mut plan = Plan::default();
+    plan.walk(src, dst, Path::new(""), rules)?;
+
+    for (_, dir) in &plan.dirs {
+        fs::create_dir_all(dir).map_err(|err| with_path_context(err, dir))?;
+    }
+
+    // the largest files go first, so that one of them isn't left copying on its own at the end
+    plan.files.sort_by_key(|file| Reverse(file.len));
+
+    let progress = Progress::new(plan.bytes);
+    copy_files(&plan.files, &progress)?;
+
+    // copying into a directory changes its times, and a read-only one couldn't be copied into,
+    // so directories are done last and from the bottom up
+    for (src, dst) in plan.dirs.iter().rev() {
+        copy_times(src, dst).map_err(|err| with_path_context(err, dst))?;
+        fs::set_permissions(dst, fs::metadata(src)?.permissions())
+            .map_err(|err| with_path_context(err, dst))?;
+    }
+
+    progress.finish();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::BTreeMap,
+        ffi::CString,
+        os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+        time::SystemTime,
+    };
+
+    fn mkfifo(path: &Path) {
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        // SAFETY: the path is a valid C string for as long as the call lasts
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o644) }, 0);
+    }
+
+    #[test]
+    fn fifos_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+        fs::create_dir_all(src.join("logs")).unwrap();
+        fs::write(src.join("eula.txt"), "eula=true\n").unwrap();
+        mkfifo(&src.join("console"));
+        mkfifo(&src.join("logs/pipe"));
+
+        // reading a FIFO would wait for a writer that never comes, so a copy that tried to
+        // wouldn't return
+        copy_tree(&src, &dst, &IgnoreRules::default()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dst.join("eula.txt")).unwrap(),
+            "eula=true\n"
+        );
+        assert!(dst.join("logs").is_dir());
+        assert!(fs::symlink_metadata(dst.join("console")).is_err());
+        assert!(fs::symlink_metadata(dst.join("logs/pipe")).is_err());
+    }
+
+    /// What a copy has to keep of each entry under a directory: whether it is a directory, its
+    /// permission bits, its modification time and a file's contents
+    type Entries = BTreeMap<PathBuf, (bool, u32, SystemTime, Option<Vec<u8>>)>;
+
+    fn entries(root: &Path, relative: &Path, entries: &mut Entries) {
+        for entry in fs::read_dir(root.join(relative)).unwrap() {
+            let relative = relative.join(entry.unwrap().file_name());
+            let path = root.join(&relative);
+            let metadata = fs::metadata(&path).unwrap();
+            let contents = metadata.is_file().then(|| fs::read(&path).unwrap());
+
+            entries.insert(
+                relative.clone(),
+                (
+                    metadata.is_dir(),
+                    metadata.permissions().mode() & 0o7777,
+                    metadata.modified().unwrap(),
+                    contents,
+                ),
+            );
+            if metadata.is_dir() {
+                self::entries(root, &relative, entries);
+            }
+        }
+    }
+
+    fn set_mode(path: &Path, mode: u32) {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn copies_match_their_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+
+        let files: [(&str, &[u8], u32); 5] = [
+            ("start.sh", b"#!/bin/sh\n", 0o755),
+            ("ops.json", b"[]", 0o600),
+            ("world/level.dat", &[0x1f, 0x8b, 0, 1, 2], 0o644),
+            ("world/region/r.0.0.mca", &[7; 64 * 1024], 0o644),
+            ("locked/readme.txt", b"read only", 0o444),
+        ];
+        for dir in ["world/region", "world/empty/nested", "empty", "locked"] {
+            fs::create_dir_all(src.join(dir)).unwrap();
+        }
+        for (n, (file, contents, mode)) in files.into_iter().enumerate() {
+            let path = src.join(file);
+            fs::write(&path, contents).unwrap();
+            set_mode(&path, mode);
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000 + n as u64);
+            File::open(&path).unwrap().set_modified(modified).unwrap();
+        }
+        fs::write(src.join("world/session.lock"), "").unwrap();
+
+        // directories' times are set once nothing more is created in them, children first
+        let dirs = [
+            ("world/empty/nested", 0o700),
+            ("world/empty", 0o755),
+            ("world/region", 0o750),
+            ("world", 0o755),
+            ("empty", 0o755),
+            ("locked", 0o555),
+        ];
+        for (n, (dir, mode)) in dirs.into_iter().enumerate() {
+            let path = src.join(dir);
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000 + n as u64);
+            File::open(&path).unwrap().set_modified(modified).unwrap();
+            set_mode(&path, mode);
+        }
+
+        copy_tree(&src, &dst, &IgnoreRules::default()).unwrap();
+
+        let (mut expected, mut copied) = (Entries::new(), Entries::new());
+        entries(&src, Path::new(""), &mut expected);
+        entries(&dst, Path::new(""), &mut copied);
+        expected.remove(Path::new("world/session.lock"));
+        assert_eq!(copied, expected);
+        assert_eq!(
+            fs::metadata(&dst).unwrap().permissions().mode(),
+            fs::metadata(&src).unwrap().permissions().mode()
+        );
+
+        // so that the temporary directory can be removed without root
+        set_mode(&src.join("locked"), 0o755);
+        set_mode(&dst.join("locked"), 0o755);
+    }
+}
@@ -0,0 +1,167 @@
+//! Generates man pages and markdown reference pages from the command line definitions, so that
+//! the documentation can't drift from the commands themselves.
+
+use crate::{cli::Cli, error::Result};
+use clap::{Arg, Command, CommandFactory};
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// Returns every visible command in the tree, starting with the root, after building it so that
+/// display names such as `mcserver-template-new` are filled in
+fn all_commands() -> Vec<Command> {
+    let mut root = Cli::command();
+    root.build();
+
+    let mut commands = vec![];
+    let mut pending = vec![root];
+
+    while let Some(command) = pending.pop() {
+        pending.extend(
+            command
+                .get_subcommands()
+                .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+                .cloned(),
+        );
+        commands.push(command);
+    }
+
+    commands.sort_by_key(page_name);
+    commands
+}
+
+fn page_name(command: &Command) -> String {
+    command
+        .get_display_name()
+        .unwrap_or_else(|| command.get_name())
+        .to_string()
+}
+
+/// Writes a man page for every command into a directory, returning the files written
+pub fn generate_man(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut written = vec![];
+    for command in all_commands() {
+        let path = dir.join(format!("{}.1", page_name(&command)));
+        clap_mangen::Man::new(command).render(&mut File::create(&path)?)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+fn markdown_arg(arg: &Arg) -> String {
+    let mut name = match (arg.get_short(), arg.get_long()) {
+        (Some(short), Some(long)) => format!("`-{short}`, `--{long}`"),
+        (Some(short), None) => format!("`-{short}`"),
+        (None, Some(long)) => format!("`--{long}`"),
+        (None, None) => format!("`<{}>`", arg.get_id().as_str().to_uppercase()),
+    };
+
+    if let Some(aliases) = arg.get_visible_aliases() {
+        for alias in aliases {
+            let _ = write!(name, ", `--{alias}`");
+        }
+    }
+
+    let help = arg
+        .get_help()
+        .map(|help| help.to_string())
+        .unwrap_or_default();
+
+    // flags have an implicit default of false, which isn't worth listing
+    let defaults: Vec<_> = arg
+        .get_default_values()
+        .iter()
+        .filter(|_| arg.get_action().takes_values())
+        .map(|value| value.to_string_lossy())
+        .collect();
+
+    if defaults.is_empty() {
+        format!("| {name} | {help} |")
+    } else {
+        format!("| {name} | {help} (default: `{}`) |", defaults.join(", "))
+    }
+}
+
+fn markdown_page(command: &mut Command) -> String {
+    let name = page_name(command).replace('-', " ");
+    let mut page = format!("# {name}\n\n");
+
+    if let Some(about) = command.get_long_about().or(command.get_about()) {
+        let _ = writeln!(page, "{about}\n");
+    }
+
+    let aliases: Vec<_> = command.get_visible_aliases().collect();
+    if !aliases.is_empty() {
+        let _ = writeln!(page, "Aliases: `{}`\n", aliases.join("`, `"));
+    }
+
+    let _ = writeln!(
+        page,
+        "## Usage\n\n```\n{}\n```\n",
+        command
+            .render_usage()
+            .to_string()
+            .trim_start_matches("Usage: ")
+    );
+
+    let args: Vec<_> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .collect();
+    if !args.is_empty() {
+        page.push_str("## Arguments\n\n| Argument | Description |\n| --- | --- |\n");
+        for arg in args {
+            let _ = writeln!(page, "{}", markdown_arg(arg));
+        }
+        page.push('\n');
+    }
+
+    let subcommands: Vec<_> = command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .collect();
+    if !subcommands.is_empty() {
+        page.push_str("## Subcommands\n\n");
+        for sub in subcommands {
+            let _ = writeln!(
+                page,
+                "- [{}]({}.md): {}",
+                sub.get_name(),
+                page_name(sub),
+                sub.get_about()
+                    .map(|about| about.to_string())
+                    .unwrap_or_default()
+            );
+        }
+        page.push('\n');
+    }
+
+    if let Some(after_help) = command.get_after_help() {
+        let _ = writeln!(page, "{after_help}\n");
+    }
+
+    page.truncate(page.trim_end().len());
+    page.push('\n');
+    page
+}
+
+/// Writes a markdown page for every command into a directory, returning the files written
+pub fn generate_markdown(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut written = vec![];
+    for mut command in all_commands() {
+        let path = dir.join(format!("{}.md", page_name(&command)));
+        fs::write(&path, markdown_page(&mut command))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,372 @@
+//! Checks that the environment is set up correctly, and applies the fixes that are safe to make
+//! without asking. Anything that needs a judgement call, such as a configuration entry for a
+//! deleted server holding its rcon password, is only reported.
+
+use crate::{
+    config,
+    error::{Error, Result},
+    registry,
+    server::{self, RCON_COMMAND, RCON_INSTALL_HINT},
+    session::{self, BASE_COMMAND, MIN_VERSION},
+    subprocess,
+};
+use std::{
+    fs::{self, File},
+    io, mem,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// The lock a Minecraft server holds on each of its worlds while it runs
+const SESSION_LOCK: &str = "session.lock";
+
+enum Check {
+    Ok(String),
+    Warning(String),
+    Problem(String),
+}
+
+impl Check {
+    fn print(&self) {
+        match self {
+            Self::Ok(message) => println!("[\x1b[32;1mok\x1b[0m] {message}"),
+            Self::Warning(message) => println!("[\x1b[33;1mwarn\x1b[0m] {message}"),
+            Self::Problem(message) => println!("[\x1b[31;1mfail\x1b[0m] {message}"),
+        }
+    }
+}
+
+fn check_zellij() -> Check {
+    match session::version() {
+        Ok(Some(version)) if version < MIN_VERSION => Check::Warning(format!(
+            "{BASE_COMMAND} {} is older than {}",
+            session::format_version(version),
+            session::format_version(MIN_VERSION)
+        )),
+        Ok(Some(version)) => Check::Ok(format!(
+            "{BASE_COMMAND} {}",
+            session::format_version(version)
+        )),
+        Ok(None) => Check::Warning(format!("{BASE_COMMAND} version could not be determined")),
+        Err(err) => Check::Problem(err.to_string()),
+    }
+}
+
+fn check_rcon() -> Check {
+    let result = subprocess::run_with_timeout(
+        Command::new(RCON_COMMAND)
+            .arg("-h")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+        |err| Error::from_spawn(err, RCON_COMMAND, RCON_INSTALL_HINT),
+    );
+
+    match result {
+        Ok(_) => Check::Ok(format!("{RCON_COMMAND} is installed")),
+        Err(err @ Error::BackendMissing { .. }) => Check::Warning(err.to_string()),
+        Err(err) => Check::Problem(format!("{RCON_COMMAND} could not be run: {err}")),
+    }
+}
+
+/// A fix for a problem found by a check, safe to apply without asking
+enum Fix {
+    CreateServersDirectory(PathBuf),
+    RestoreConfigBackup(PathBuf),
+    ClearDefaultServer(String),
+    DeleteOrphanedSession(String),
+    RemoveStaleLock(PathBuf),
+}
+
+impl Fix {
+    fn describe(&self) -> String {
+        match self {
+            Self::CreateServersDirectory(dir) => {
+                format!("create the servers directory {}", dir.display())
+            }
+            Self::RestoreConfigBackup(backup) => {
+                format!(
+                    "restore the configuration from {}, keeping the broken one as .broken",
+                    backup.display()
+                )
+            }
+            Self::ClearDefaultServer(server) => {
+                format!("clear default_server, which names the deleted server {server}")
+            }
+            Self::DeleteOrphanedSession(server) => {
+                format!("delete the dead session of the deleted server {server}")
+            }
+            Self::RemoveStaleLock(path) => format!("remove the stale lock {}", path.display()),
+        }
+    }
+
+    fn apply(&self) -> Result<()> {
+        match self {
+            Self::CreateServersDirectory(dir) => create_servers_directory(dir),
+            Self::RestoreConfigBackup(_) => restore_config_backup(),
+            Self::ClearDefaultServer(server) => clear_default_server(server),
+            Self::DeleteOrphanedSession(server) => delete_orphaned_session(server),
+            Self::RemoveStaleLock(path) => remove_stale_lock(path),
+        }
+    }
+}
+
+fn create_servers_directory(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+fn restore_config_backup() -> Result<()> {
+    config::restore_backup()?;
+    Ok(())
+}
+
+fn clear_default_server(server: &str) -> Result<()> {
+    // left alone if it was changed since the check
+    config::update(|config| {
+        if config.default_server.as_deref() == Some(server) {
+            config.default_server = None;
+        }
+    })?;
+    // saved straight away, as main doesn't get to save it when other problems are left
+    config::CONFIG.write()
+}
+
+fn delete_orphaned_session(server: &str) -> Result<()> {
+    session::delete_server_session(server, false)
+}
+
+fn remove_stale_lock(path: &Path) -> Result<()> {
+    // checked again, in case the server was started since
+    if let Some(pid) = lock_holder(path)? {
+        return Err(Error::LockHeld {
+            path: path.to_path_buf(),
+            pid,
+        });
+    }
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// The process holding a lock on a file, as servers do on their worlds' `session.lock` while
+/// they run
+fn lock_holder(path: &Path) -> Result<Option<i32>> {
+    let file = File::open(path)?;
+
+    // SAFETY: flock is plain data, for which all zeroes is valid
+    let mut lock: libc::flock = unsafe { mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+
+    // SAFETY: the descriptor is open for the duration of the call and lock outlives it
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok((lock.l_type != libc::F_UNLCK as libc::c_short).then_some(lock.l_pid))
+}
+
+fn check_config(fixes: &mut Vec<Fix>) -> Check {
+    let err = match config::snapshot() {
+        Ok(_) => return Check::Ok("Configuration loaded".to_string()),
+        Err(err) => err,
+    };
+
+    match config::get_valid_backup() {
+        Ok(Some(backup)) => {
+            fixes.push(Fix::RestoreConfigBackup(backup));
+            Check::Problem(format!(
+                "Configuration could not be loaded, but its backup can: {err}"
+            ))
+        }
+        Ok(None) | Err(_) => Check::Problem(format!("Configuration could not be loaded: {err}")),
+    }
+}
+
+fn check_servers_directory(fixes: &mut Vec<Fix>) -> Check {
+    match config::get_expanded_servers_dir() {
+        Ok(dir) if dir.is_dir() => Check::Ok(format!("Servers directory {}", dir.display())),
+        Ok(dir) if !dir.exists() => {
+            fixes.push(Fix::CreateServersDirectory(dir.to_path_buf()));
+            Check::Problem(format!("Servers directory {} is missing", dir.display()))
+        }
+        Ok(dir) => Check::Problem(format!(
+            "Servers directory {} is not a directory",
+            dir.display()
+        )),
+        Err(err) => Check::Problem(format!("Servers directory could not be resolved: {err}")),
+    }
+}
+
+fn check_references(fixes: &mut Vec<Fix>) -> Check {
+    let (servers, config) = match (server::get_all_hashed(), config::snapshot()) {
+        (Ok(servers), Ok(config)) => (servers, config),
+        _ => return Check::Warning("Configuration references could not be checked".to_string()),
+    };
+
+    let mut dangling = vec![];
+
+    if let Some(default_server) = &config.default_server
+        && !servers.contains(default_server)
+    {
+        dangling.push(format!("default_server = \"{default_server}\""));
+        fixes.push(Fix::ClearDefaultServer(default_server.clone()));
+    }
+
+    if let Some(rcon) = &config.rcon {
+        let mut missing: Vec<_> = rcon.keys().filter(|s| !servers.contains(*s)).collect();
+        missing.sort();
+        dangling.extend(missing.into_iter().map(|s| format!("rcon.{s}")));
+    }
+
+    if dangling.is_empty() {
+        Check::Ok("Configuration only references existing servers".to_string())
+    } else {
+        Check::Warning(format!(
+            "Configuration references deleted servers: {}",
+            dangling.join(", ")
+        ))
+    }
+}
+
+fn check_sessions(fixes: &mut Vec<Fix>) -> Check {
+    let (sessions, registry) = match (session::get_orphaned_sessions(), registry::read()) {
+        (Ok(sessions), Ok(registry)) => (sessions, registry),
+        _ => return Check::Warning("Sessions could not be checked".to_string()),
+    };
+
+    if sessions.is_empty() {
+        return Check::Ok("Sessions only belong to existing servers".to_string());
+    }
+
+    let mut names = vec![];
+    for (server, alive) in sessions {
+        // running sessions and ones this tool didn't create are left to the user
+        if !alive && registry.contains(&session::get_name(&server), &server) {
+            fixes.push(Fix::DeleteOrphanedSession(server.clone()));
+        }
+
+        names.push(if alive {
+            format!("{server} (alive)")
+        } else {
+            server
+        });
+    }
+
+    Check::Warning(format!(
+        "Sessions belong to deleted servers: {}",
+        names.join(", ")
+    ))
+}
+
+/// Finds the `session.lock` files in a server's worlds
+fn find_session_locks(server_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut locks = vec![];
+
+    for entry in fs::read_dir(server_dir)? {
+        let lock = entry?.path().join(SESSION_LOCK);
+        if lock.is_file() {
+            locks.push(lock);
+        }
+    }
+
+    locks.sort();
+    Ok(locks)
+}
+
+fn check_locks(fixes: &mut Vec<Fix>) -> Check {
+    let (servers, alive) = match (
+        server::get_all_hashed(),
+        session::get_alive_server_sessions(),
+    ) {
+        (Ok(servers), Ok(alive)) => (servers, alive),
+        _ => return Check::Warning("World locks could not be checked".to_string()),
+    };
+
+    let mut stale = vec![];
+    for server in servers.iter().filter(|server| !alive.contains(*server)) {
+        let Ok(locks) = server::get_server_dir_required(server)
+            .and_then(|server_dir| find_session_locks(&server_dir))
+        else {
+            continue;
+        };
+
+        for lock in locks {
+            if let Ok(None) = lock_holder(&lock) {
+                stale.push(lock.display().to_string());
+                fixes.push(Fix::RemoveStaleLock(lock));
+            }
+        }
+    }
+
+    if stale.is_empty() {
+        Check::Ok("No stale world locks".to_string())
+    } else {
+        Check::Warning(format!(
+            "World locks are left over from stopped servers: {}",
+            stale.join(", ")
+        ))
+    }
+}
+
+/// Runs every check, collecting the fixes for what they find
+fn check_all(fixes: &mut Vec<Fix>) -> Vec<Check> {
+    vec![
+        check_zellij(),
+        check_rcon(),
+        check_config(fixes),
+        check_servers_directory(fixes),
+        check_references(fixes),
+        check_sessions(fixes),
+        check_locks(fixes),
+    ]
+}
+
+fn count_problems(checks: &[Check]) -> usize {
+    checks
+        .iter()
+        .filter(|check| matches!(check, Check::Problem(_)))
+        .count()
+}
+
+/// Runs every check, printing the results and returning the number of problems found. With
+/// `fix`, the safe fixes are then applied, or only listed with `dry_run`, and the number of
+/// problems left afterwards is returned.
+pub fn run(fix: bool, dry_run: bool) -> Result<usize> {
+    let mut fixes = vec![];
+    let checks = check_all(&mut fixes);
+
+    for check in &checks {
+        check.print();
+    }
+
+    if !fix {
+        return Ok(count_problems(&checks));
+    }
+
+    println!();
+    if fixes.is_empty() {
+        println!("Nothing can be fixed automatically");
+        return Ok(count_problems(&checks));
+    }
+
+    for fix in &fixes {
+        if dry_run {
+            println!("Would {}", fix.describe());
+            continue;
+        }
+
+        match fix.apply() {
+            Ok(()) => println!("[\x1b[32;1mfixed\x1b[0m] {}", fix.describe()),
+            Err(err) => println!("[\x1b[31;1mfail\x1b[0m] {}: {err}", fix.describe()),
+        }
+    }
+
+    if dry_run {
+        return Ok(count_problems(&checks));
+    }
+
+    // checked again without printing, to count what is left
+    Ok(count_problems(&check_all(&mut vec![])))
+}
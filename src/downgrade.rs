@@ -0,0 +1,222 @@
+//! The check before deploying that a server's jar isn't older than its world. A world opened by an
+//! older version than the one that last saved it loses the chunks that version doesn't
+//! understand, without saying so, while a newer version upgrades the world for good.
+//!
+//! The jar's version comes from its `version.json`, or failing that its file name, and the world's
+//! from its `level.dat`. When either can't be told, nothing is checked.
+
+use crate::{
+    error::{Error, Result},
+    java, nbt, server, worlds,
+};
+use std::{cmp::Ordering, path::Path};
+
+const LEVEL_FILE: &str = "level.dat";
+
+/// A game version as far as it could be told. Data versions, which every version since 1.9 saves
+/// worlds with, are compared where both sides have one, since they also order snapshots.
+struct GameVersion {
+    name: String,
+    data_version: Option<i32>,
+}
+
+impl GameVersion {
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        if let (Some(ours), Some(theirs)) = (self.data_version, other.data_version) {
+            return Some(ours.cmp(&theirs));
+        }
+
+        let ours = java::parse_game_version(&self.name)?;
+        let theirs = java::parse_game_version(&other.name)?;
+        Some(ours.cmp(&theirs))
+    }
+}
+
+fn jar_version(jar: &Path) -> Option<GameVersion> {
+    if let Some(info) = java::read_version_info(jar)
+        && let Some(name) = info.name
+    {
+        return Some(GameVersion {
+            name,
+            data_version: info.world_version,
+        });
+    }
+
+    let (minor, patch) = java::game_version_from_name(jar)?;
+    Some(GameVersion {
+        name: format!("1.{minor}.{patch}"),
+        data_version: None,
+    })
+}
+
+fn world_version(server: &str) -> Option<GameVersion> {
+    let level = worlds::get_world_dirs(server)
+        .ok()?
+        .overworld
+        .join(LEVEL_FILE);
+    let root = nbt::read_gzip(&level).ok()?;
+
+    Some(GameVersion {
+        name: root
+            .get(&["Data", "Version", "Name"])?
+            .as_str()?
+            .to_string(),
+        data_version: root
+            .get(&["Data", "DataVersion"])
+            .and_then(nbt::Tag::as_int),
+    })
+}
+
+/// Refuses to deploy a server whose jar is older than its world unless `allow_downgrade` is set,
+/// and suggests a backup before a jar upgrades its world
+pub fn check(server: &str, allow_downgrade: bool) -> Result<()> {
+    let Ok(jar) = server::read_server_jar_path(server::get_server_dir_required(server)?) else {
+        return Ok(());
+    };
+    let (Some(jar), Some(world)) = (jar_version(&jar), world_version(server)) else {
+        return Ok(());
+    };
+
+    match jar.compare(&world) {
+        Some(Ordering::Less) if allow_downgrade => eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m deploying {server} with {}, older than its world's {}",
+            jar.name, world.name
+        ),
+        Some(Ordering::Less) => {
+            return Err(Error::WorldDowngrade {
+                server: server.to_string(),
+                jar: jar.name,
+                world: world.name,
+            });
+        }
+        Some(Ordering::Greater) => println!(
+            "Note: {server}'s world will be upgraded from {} to {}, which can't be undone, so \
+             consider backing it up first",
+            world.name, jar.name
+        ),
+        Some(Ordering::Equal) | None => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::{
+        fs,
+        io::{self, Write},
+    };
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn level_dat(version: &str) -> Vec<u8> {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(format!("level-{version}.dat"));
+        fs::read(fixture).unwrap()
+    }
+
+    /// A jar carrying a `version.json`, as vanilla and Paper jars do
+    fn jar_with_version(name: &str, world_version: i32) -> Vec<u8> {
+        let mut jar = ZipWriter::new(io::Cursor::new(vec![]));
+        jar.start_file("version.json", SimpleFileOptions::default())
+            .unwrap();
+        write!(
+            jar,
+            r#"{{"name": "{name}", "world_version": {world_version}}}"#
+        )
+        .unwrap();
+        jar.finish().unwrap().into_inner()
+    }
+
+    /// Creates a server in the sandbox with a jar and, where given, a world saved by a version
+    /// among the fixtures
+    fn server(server: &str, jar_name: &str, jar: &[u8], world: Option<&str>) -> String {
+        let server_dir = testing::sandbox(&[]).join(server);
+        fs::create_dir_all(server_dir.join(".mcserver")).unwrap();
+        fs::write(server_dir.join(".mcserver/jar_file.txt"), jar_name).unwrap();
+        fs::write(server_dir.join(jar_name), jar).unwrap();
+
+        if let Some(world) = world {
+            fs::create_dir_all(server_dir.join("world")).unwrap();
+            fs::write(server_dir.join("world").join(LEVEL_FILE), level_dat(world)).unwrap();
+        }
+
+        server.to_string()
+    }
+
+    fn compare(server: &str) -> Option<Ordering> {
+        let jar =
+            server::read_server_jar_path(server::get_server_dir_required(server).unwrap()).unwrap();
+        jar_version(&jar)?.compare(&world_version(server)?)
+    }
+
+    #[test]
+    fn world_versions_are_read_from_level_dat() {
+        let server = server("downgrade-read", "server.jar", b"", Some("1.20.4"));
+        let world = world_version(&server).unwrap();
+
+        assert_eq!(world.name, "1.20.4");
+        assert_eq!(world.data_version, Some(3700));
+    }
+
+    #[test]
+    fn older_jars_are_refused_unless_allowed() {
+        let server = server("downgrade-older", "paper-1.20.4-1.jar", b"", Some("1.21.4"));
+
+        assert_eq!(compare(&server), Some(Ordering::Less));
+        assert!(matches!(
+            check(&server, false),
+            Err(Error::WorldDowngrade { jar, world, .. }) if jar == "1.20.4" && world == "1.21.4"
+        ));
+        assert!(check(&server, true).is_ok());
+    }
+
+    #[test]
+    fn newer_and_equal_jars_are_deployed() {
+        let newer = server("downgrade-newer", "paper-1.21.4-1.jar", b"", Some("1.20.4"));
+        let equal = server("downgrade-equal", "paper-1.21.4-1.jar", b"", Some("1.21.4"));
+
+        assert_eq!(compare(&newer), Some(Ordering::Greater));
+        assert!(check(&newer, false).is_ok());
+        assert_eq!(compare(&equal), Some(Ordering::Equal));
+        assert!(check(&equal, false).is_ok());
+    }
+
+    #[test]
+    fn data_versions_order_snapshots() {
+        // a snapshot between the fixtures' versions, which its name can't be compared by
+        let jar = jar_with_version("24w14a", 3827);
+        let older_world = server(
+            "downgrade-snapshot-older",
+            "server.jar",
+            &jar,
+            Some("1.20.4"),
+        );
+        let newer_world = server(
+            "downgrade-snapshot-newer",
+            "server.jar",
+            &jar,
+            Some("1.21.4"),
+        );
+
+        assert_eq!(compare(&older_world), Some(Ordering::Greater));
+        assert_eq!(compare(&newer_world), Some(Ordering::Less));
+        assert!(matches!(
+            check(&newer_world, false),
+            Err(Error::WorldDowngrade { jar, .. }) if jar == "24w14a"
+        ));
+    }
+
+    #[test]
+    fn unknown_versions_are_not_checked() {
+        let no_world = server("downgrade-no-world", "paper-1.20.4-1.jar", b"", None);
+        let unnamed = server("downgrade-unnamed", "server.jar", b"", Some("1.21.4"));
+
+        assert_eq!(compare(&no_world), None);
+        assert!(check(&no_world, false).is_ok());
+        assert_eq!(compare(&unnamed), None);
+        assert!(check(&unnamed, false).is_ok());
+    }
+}
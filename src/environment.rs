@@ -0,0 +1,90 @@
+//! Overrides of where mcserver keeps its files and which APIs it talks to, so that it can be run
+//! against a temporary directory and a mock API without touching the real ones:
+//!
+//! - `MCSERVER_CONFIG_DIR`: the dynamic configuration directory, instead of the compiled-in one
+//! - `MCSERVER_CACHE_DIR`: where API responses and completion candidates are kept
+//! - `MCSERVER_API_URL`: a server every request is sent to instead, with the original host as the
+//!   first path segment, so that `https://api.papermc.io/v2/projects` is requested as
+//!   `<MCSERVER_API_URL>/api.papermc.io/v2/projects`
+//!
+//! Zellij, mcrcon and Java are looked up on the `PATH`, which can point at fakes the same way.
+//!
+//! The overrides are only honored in debug builds, which `cargo test` uses, so that a release
+//! build can't be pointed at another API by a stray variable.
+
+use std::{env, path::PathBuf, sync::OnceLock};
+use url::Url;
+
+pub const CONFIG_DIR_VAR: &str = "MCSERVER_CONFIG_DIR";
+pub const CACHE_DIR_VAR: &str = "MCSERVER_CACHE_DIR";
+pub const API_URL_VAR: &str = "MCSERVER_API_URL";
+
+static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    pub config_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub api_url: Option<Url>,
+}
+
+impl Environment {
+    fn from_vars() -> Self {
+        if !cfg!(debug_assertions) {
+            return Self::default();
+        }
+
+        let path = |var| {
+            env::var_os(var)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        };
+
+        Self {
+            config_dir: path(CONFIG_DIR_VAR),
+            cache_dir: path(CACHE_DIR_VAR),
+            api_url: env::var(API_URL_VAR)
+                .ok()
+                .and_then(|url| Url::parse(url.trim_end_matches('/')).ok()),
+        }
+    }
+
+    /// Where mcserver keeps cached data, under the user's cache directory unless overridden
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir
+            .clone()
+            .or_else(|| Some(dirs::cache_dir()?.join("mcserver")))
+    }
+
+    /// The url a request is actually sent to, which is the url itself unless an API override is
+    /// set
+    pub fn redirect(&self, url: &Url) -> Url {
+        let Some(api_url) = &self.api_url else {
+            return url.clone();
+        };
+
+        let mut redirected = api_url.clone();
+        let path = format!(
+            "{}/{}{}",
+            api_url.path().trim_end_matches('/'),
+            url.host_str().unwrap_or_default(),
+            url.path()
+        );
+        redirected.set_path(&path);
+        redirected.set_query(url.query());
+        redirected
+    }
+}
+
+/// Returns the environment, read once per process
+pub fn get() -> &'static Environment {
+    ENVIRONMENT.get_or_init(Environment::from_vars)
+}
+
+/// Sets the environment in place of the variables, for tests that run against a temporary
+/// directory and a fake API inside the test process. Returns the environment in effect, which is
+/// an earlier one if the environment was already read.
+#[cfg(test)]
+pub fn init(environment: Environment) -> &'static Environment {
+    ENVIRONMENT.get_or_init(|| environment)
+}
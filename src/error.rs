@@ -3,6 +3,7 @@ use std::{
     env::VarError,
     io,
     path::{self, PathBuf},
+    process::Command,
     result,
 };
 use thiserror::Error;
@@ -10,8 +11,32 @@ use thiserror::Error;
 #[non_exhaustive]
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("{backend} was not found, {install_hint}")]
+    BackendMissing {
+        backend: &'static str,
+        install_hint: &'static str,
+    },
+
+    #[error("{server} has no backup named {backup}")]
+    BackupNotFound { server: String, backup: String },
+
+    #[error("{0} backup(s) failed verification")]
+    BackupVerificationFailed(usize),
+
+    #[error("A build can only be selected along with a version other than latest")]
+    BuildWithoutVersion,
+
+    #[error("Builds of {0} can't be selected, pass only a version instead")]
+    BuildsNotSelectable(String),
+
+    #[error("{0} server(s) failed")]
+    BulkOperationFailed(usize),
+
+    #[error("Checksum mismatch (expected {expected}, got {actual})")]
+    ChecksumMismatch { expected: String, actual: String },
+
     #[error(
-        "Command failed with code {}{}",
+        "Command `{command}` failed with code {}{}",
         code.map(|c| c.to_string()).as_deref().unwrap_or("none"),
         stderr
             .as_ref()
@@ -20,70 +45,302 @@ pub enum Error {
             .unwrap_or("")
     )]
     CommandFailure {
+        command: String,
         code: Option<i32>,
         stderr: Option<Vec<u8>>,
     },
 
+    #[error("{program} did not finish within {secs}s and was stopped (see --timeout)")]
+    CommandTimeout { program: String, secs: u64 },
+
+    #[error(
+        "{server} is the server this shell is running in, pass --allow-current to {action} it anyway"
+    )]
+    CurrentServerTargeted {
+        server: String,
+        action: &'static str,
+    },
+
+    #[error("Custom jars have no platform to look up versions of, name a platform instead")]
+    CustomPlatformResolved,
+
+    #[error("The EULA is not accepted in {}", file.display())]
+    EulaNotAccepted { file: PathBuf },
+
+    #[error("{0} fleet check(s) failed")]
+    FleetChecksFailed(usize),
+
+    #[error("Group {0} was not found")]
+    GroupNotFound(String),
+
+    #[error("The {hook} hook failed: {reason}")]
+    HookFailed { hook: String, reason: String },
+
+    #[error(
+        "{0} plugin(s) or mod(s) have no release for the target version (use --ignore-compat to update anyway)"
+    )]
+    IncompatibleAddons(usize),
+
+    #[error("Download is incomplete ({actual} of {expected} bytes)")]
+    IncompleteDownload { expected: u64, actual: u64 },
+
+    #[error(
+        "Not enough space in {}: {} MiB needed, {} MiB available (use --ignore-space to continue anyway)",
+        path.display(),
+        needed / MIB,
+        available / MIB
+    )]
+    InsufficientSpace {
+        needed: u64,
+        available: u64,
+        path: PathBuf,
+    },
+
+    #[error("The import manifest has {0} problem(s)")]
+    InvalidImportManifest(usize),
+
+    #[error("The edit to {0} would not read back as written, so it was not saved")]
+    InvalidEdit(String),
+
+    #[error("Invalid duration {0} (expected e.g. 90s, 15m or 1d12h)")]
+    InvalidDuration(String),
+
+    #[error("{0} was interrupted")]
+    Interrupted(String),
+
     #[error(transparent)]
     InvalidHeaderValue(#[from] header::InvalidHeaderValue),
 
+    #[error("Invalid proxy {0:?} in the http configuration (expected e.g. http://proxy:3128)")]
+    InvalidProxy(String),
+
+    #[error("Invalid rate {0} (expected e.g. 500K or 2M)")]
+    InvalidRate(String),
+
+    #[error("Invalid server name {0:?}")]
+    InvalidServerName(String),
+
     #[error("Invalid server session: `{0}`")]
     InvalidServerSession(String),
 
     #[error("Invalid servers directory")]
     InvalidServersDirectory,
 
+    #[error("Invalid time of day {0} (expected e.g. 04:30)")]
+    InvalidTimeOfDay(String),
+
     #[error("Timestamp file ({0}) is invalid")]
     InvalidTimestampFile(String),
 
     #[error(transparent)]
     Io(#[from] io::Error),
 
+    #[error(
+        "The jar of {server} does not match the checksum recorded when it was installed \
+         (expected {expected}, got {actual}), run `mcserver update` to replace it or pass \
+         --skip-verify if it was changed on purpose"
+    )]
+    JarCorrupted {
+        server: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(
+        "Could not pick a server jar in {} (declare the platform, or leave only one jar)",
+        dir.display()
+    )]
+    JarNotDetected { dir: PathBuf },
+
+    #[error("No Java runtime of version {required} or newer was found")]
+    JavaNotFound { required: u32 },
+
+    #[error("{} already exists, use --force to replace it", path.display())]
+    LaunchScriptExists { path: PathBuf },
+
+    #[error("Launch script {} is not executable, run `chmod +x` on it", path.display())]
+    LaunchScriptNotExecutable { path: PathBuf },
+
+    #[error("{} is held by process {pid}", path.display())]
+    LockHeld { path: PathBuf, pid: i32 },
+
+    #[error("Macro {0} was not found")]
+    MacroNotFound(String),
+
+    #[error("Maintenance mode is already active for {0}")]
+    MaintenanceAlreadyActive(String),
+
+    #[error("Maintenance mode is not active for {0}")]
+    MaintenanceNotActive(String),
+
+    #[error("The metadata of {0} is intact, use --force to rebuild it anyway")]
+    MetadataIntact(String),
+
+    #[error("Only level-name can be migrated, not {0}")]
+    MigrationUnsupported(String),
+
     #[error("Missing directory: {}", dir.display())]
     MissingDirectory { dir: PathBuf },
 
+    #[error("The environment variable {0} is not set")]
+    MissingEnvVar(String),
+
     #[error("Missing file: {}", file.display())]
     MissingFile { file: PathBuf },
 
+    #[error(
+        "Several jars were found in {} ({}), pick one with --jar",
+        dir.display(),
+        jars.join(", ")
+    )]
+    MultipleJars { dir: PathBuf, jars: Vec<String> },
+
+    #[error("Group {group} contains another group, {member}, which is not supported")]
+    NestedGroup { group: String, member: String },
+
+    #[error("{} was not generated by mcserver, use --force to replace it", path.display())]
+    NotGenerated { path: PathBuf },
+
+    #[error("{0} has no backups")]
+    NoBackups(String),
+
     #[error("There is no default server")]
     NoDefaultServer,
 
-    #[error("Rcon config is not present, but required for remote connections")]
-    NoRconConfig,
+    #[error("{server} is not in group {group}")]
+    NotInGroup { server: String, group: String },
+
+    #[error("No jar was found in {}", dir.display())]
+    NoJarFound { dir: PathBuf },
 
-    #[error("No server child was given")]
-    NoServerChild,
+    #[error("{0} has no plugins directory")]
+    NoPluginsDirectory(String),
+
+    #[error(
+        "The current directory is the servers directory, not a server (servers: {})",
+        children.join(", ")
+    )]
+    NoServerChild { children: Vec<String> },
 
     #[error("No session name found")]
     NoSessionName,
 
+    #[error(
+        "Permission denied for {}, try running `mcserver fix-permissions {server}`",
+        path.display()
+    )]
+    PermissionDenied { path: PathBuf, server: String },
+
+    #[error("{0} path(s) still have the wrong owner or permissions")]
+    PermissionsNotFixed(usize),
+
+    #[error("{server} runs {current}, use --force to switch it to {requested}")]
+    PlatformMismatch {
+        server: String,
+        current: String,
+        requested: String,
+    },
+
+    #[error("The platform of {0} is not known, name one to update it on")]
+    PlatformUnknown(String),
+
     #[error("Platforms not found: {0}")]
     PlatformsNotFound(String),
 
-    #[error("The configuration mutex has been poisoned")]
-    ConfigMutexPoisoned,
+    #[error("Property {0} is not set")]
+    PropertyNotSet(String),
+
+    #[error("Player {0} was not found")]
+    PlayerNotFound(String),
+
+    #[error("The configuration path was set after the configuration was accessed")]
+    ConfigPathAlreadySet,
+
+    #[error("{0} has no running session to send commands to")]
+    SessionNotRunning(String),
 
     #[error("Rcon config is missing for server: {0}")]
     MissingRconConfig(String),
 
+    #[error("Port {port} is also used by {servers}")]
+    PortInUse { port: String, servers: String },
+
+    #[error("Profile {0} already exists, use --force to replace it")]
+    ProfileAlreadyExists(String),
+
+    #[error("{0} profile item(s) could not be applied")]
+    ProfileIncomplete(usize),
+
+    #[error(
+        "Profile {name} was not found (available: {})",
+        if available.is_empty() { "none".to_string() } else { available.join(", ") }
+    )]
+    ProfileNotFound {
+        name: String,
+        available: Vec<String>,
+    },
+
+    #[error("Rate limited by {host} after {attempts} attempts")]
+    RateLimited { host: String, attempts: u32 },
+
+    #[error("Request to {url} failed after {attempts} attempt(s): {reason}")]
+    RequestFailed {
+        url: String,
+        attempts: u32,
+        reason: String,
+    },
+
+    #[error("{0}, restart the server and try again")]
+    RestartRequired(String),
+
+    #[error("{0} is a Bedrock server, which has no rcon")]
+    RconUnsupported(String),
+
+    #[error("The rcon endpoint of {server} is unreachable: {reason}")]
+    RconUnreachable { server: String, reason: String },
+
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("This command is not allowed in restricted mode")]
+    RestrictedMode,
+
     #[error(transparent)]
     ShellexpandLookup(#[from] shellexpand::LookupError<VarError>),
 
+    #[error("The launch command for {0} contains its rcon password")]
+    SecretInCommand(String),
+
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
     #[error("Server {0} already exists")]
     ServerAlreadyExists(String),
 
     #[error("The machine's local time went backwards")]
     TimeWentBackwards,
 
+    #[error("{0} is running, stop it first")]
+    ServerRunning(String),
+
     #[error("Server {0} was not found")]
     ServerNotFound(String),
 
+    #[error("The servers directory {} is unavailable (network mount?): {reason}", dir.display())]
+    ServersDirectoryUnavailable { dir: PathBuf, reason: String },
+
+    #[error("{server} failed to start: {reason}")]
+    StartFailed { server: String, reason: String },
+
     #[error(transparent)]
     StripPrefix(#[from] path::StripPrefixError),
 
+    #[error("Timed out waiting for {0} to stop")]
+    StopTimedOut(String),
+
     #[error("Template {0} already exists")]
     TemplateAlreadyExists(String),
 
@@ -102,11 +359,106 @@ pub enum Error {
     #[error(transparent)]
     TomlSerialize(#[from] toml::ser::Error),
 
+    #[error("{tool} was not found, set `{config_key}` in the configuration")]
+    ToolNotFound {
+        tool: String,
+        config_key: &'static str,
+    },
+
     #[error(transparent)]
     ToStr(#[from] header::ToStrError),
 
+    #[error(
+        "{server}'s world was last saved by {world}, which is newer than its jar's {jar}. Older \
+         versions silently drop what they don't understand, so back the world up and pass \
+         --allow-downgrade to deploy anyway"
+    )]
+    WorldDowngrade {
+        server: String,
+        jar: String,
+        world: String,
+    },
+
+    #[error("World directory {} already exists", dir.display())]
+    WorldDirExists { dir: PathBuf },
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Unknown time zone {0}")]
+    UnknownTimeZone(String),
+
+    #[error("Unknown placeholder {{{0}}} (available: {{server}}, {{server_dir}})")]
+    UnknownPlaceholder(String),
+
+    #[error(
+        "Session {0} was not created by mcserver, use --force to use it anyway or `mcserver sessions --repair` to adopt it"
+    )]
+    UnregisteredSession(String),
+
+    #[error("Unsupported manifest version {0}")]
+    UnsupportedManifestVersion(u32),
+
     #[error(transparent)]
     UrlParse(#[from] url::ParseError),
+
+    #[error("Version {version} not found for {platform} (recent versions: {recent})")]
+    VersionNotFound {
+        platform: String,
+        version: String,
+        recent: String,
+    },
+
+    #[error("The versions of {0} can't be listed, pass one with --version instead")]
+    VersionsNotListed(String),
+}
+
+const MIB: u64 = 1024 * 1024;
+
+/// Arguments whose following value is a secret, and so is left out of error messages
+const SECRET_FLAGS: [&str; 2] = ["-p", "--password"];
+
+/// Renders a command line for error messages, redacting secrets
+pub fn describe_command(command: &Command) -> String {
+    let mut parts = vec![command.get_program().to_string_lossy().to_string()];
+    let mut redact_next = false;
+
+    for arg in command.get_args() {
+        let arg = arg.to_string_lossy();
+
+        if redact_next {
+            parts.push("<redacted>".to_string());
+            redact_next = false;
+        } else {
+            redact_next = SECRET_FLAGS.contains(&arg.as_ref());
+            parts.push(arg.to_string());
+        }
+    }
+
+    parts.join(" ")
+}
+
+impl Error {
+    pub fn command_failure(command: &Command, code: Option<i32>, stderr: Option<Vec<u8>>) -> Self {
+        Self::CommandFailure {
+            command: describe_command(command),
+            code,
+            stderr,
+        }
+    }
+
+    /// Converts an error from spawning an external program, turning a missing executable into
+    /// [`Error::BackendMissing`]
+    pub fn from_spawn(err: io::Error, backend: &'static str, install_hint: &'static str) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            Self::BackendMissing {
+                backend,
+                install_hint,
+            }
+        } else {
+            Self::Io(err)
+        }
+    }
 }
 
 pub type Result<T> = result::Result<T, Error>;
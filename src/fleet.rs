@@ -0,0 +1,362 @@
+//! A readiness audit across every server, for checking the whole fleet before an event.
+
+use crate::{
+    config,
+    error::{Error, Result},
+    progress::{Event, Reporter},
+    properties::Properties,
+    server::{
+        self, get_server_dir_required, rcon_output, read_server_jar_path, read_start_command,
+    },
+    session, space, worlds,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs, io, thread,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_PORT: &str = "25565";
+const DEFAULT_BEDROCK_PORT: &str = "19132";
+const DEFAULT_PROXY_PORT: &str = "25577";
+/// Where Velocity is configured, with the address it listens on as `bind`
+const VELOCITY_CONFIG: &str = "velocity.toml";
+
+/// How much room a world needs to be able to grow, as a fraction of its current size
+const GROWTH_DIVISOR: u64 = 5;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FleetCheck {
+    Eula,
+    Port,
+    Rcon,
+    Disk,
+    Jar,
+}
+
+impl Display for FleetCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eula => write!(f, "eula"),
+            Self::Port => write!(f, "port"),
+            Self::Rcon => write!(f, "rcon"),
+            Self::Disk => write!(f, "disk"),
+            Self::Jar => write!(f, "jar"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "lowercase")]
+enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(String),
+}
+
+impl Outcome {
+    fn from_result(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => Self::Pass,
+            Err(err) => Self::Fail(err.to_string()),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Pass => "\x1b[32;1m✓\x1b[0m",
+            Self::Fail(_) => "\x1b[31;1m✗\x1b[0m",
+            Self::Skip(_) => "-",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ServerReport {
+    server: String,
+    checks: BTreeMap<FleetCheck, Outcome>,
+    #[serde(skip)]
+    duration: Duration,
+}
+
+impl ServerReport {
+    /// The failed checks and why, such as `eula: ...; port: ...`, or `None` when all passed
+    fn failures(&self) -> Option<String> {
+        let failures: Vec<_> = self
+            .checks
+            .iter()
+            .filter_map(|(check, outcome)| match outcome {
+                Outcome::Fail(reason) => Some(format!("{check}: {reason}")),
+                _ => None,
+            })
+            .collect();
+
+        (!failures.is_empty()).then(|| failures.join("; "))
+    }
+}
+
+fn check_eula(server: &str) -> Result<()> {
+    let path = get_server_dir_required(server)?.join("eula.txt");
+    let accepted = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .any(|line| line.trim().eq_ignore_ascii_case("eula=true"));
+
+    if accepted {
+        Ok(())
+    } else {
+        Err(Error::EulaNotAccepted { file: path })
+    }
+}
+
+/// The port a server listens on, from its `server.properties`, or for proxies from the address in
+/// their own configuration
+pub fn port_of(server: &str) -> Result<String> {
+    let server_dir = get_server_dir_required(server)?;
+
+    if server::is_proxy(&server_dir)? {
+        let bind = match fs::read_to_string(server_dir.join(VELOCITY_CONFIG)) {
+            Ok(contents) => toml::from_str::<toml::Table>(&contents)?
+                .get("bind")
+                .and_then(toml::Value::as_str)
+                .and_then(|bind| bind.rsplit_once(':'))
+                .map(|(_, port)| port.to_string()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        return Ok(bind.unwrap_or_else(|| DEFAULT_PROXY_PORT.to_string()));
+    }
+
+    let default = if read_start_command(&server_dir)?.is_some() {
+        DEFAULT_BEDROCK_PORT
+    } else {
+        DEFAULT_PORT
+    };
+
+    let properties = Properties::read(server_dir)?;
+    Ok(properties
+        .get("server-port")
+        .filter(|port| !port.is_empty())
+        .unwrap_or(default)
+        .to_string())
+}
+
+fn check_port(server: &str, ports: &HashMap<String, Vec<String>>) -> Result<()> {
+    let port = port_of(server)?;
+    let others: Vec<_> = ports
+        .get(&port)
+        .into_iter()
+        .flatten()
+        .filter(|other| *other != server)
+        .cloned()
+        .collect();
+
+    if others.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::PortInUse {
+            port,
+            servers: others.join(", "),
+        })
+    }
+}
+
+fn check_rcon(server: &str, alive: &HashSet<String>) -> Outcome {
+    match config::has_rcon_config(server) {
+        Ok(false) => {
+            return Outcome::Fail(Error::MissingRconConfig(server.to_string()).to_string());
+        }
+        Err(err) => return Outcome::Fail(err.to_string()),
+        Ok(true) => {}
+    }
+
+    if !alive.contains(server) {
+        return Outcome::Skip("not running".to_string());
+    }
+
+    Outcome::from_result(rcon_output(server, "list").map(|_| ()))
+}
+
+fn check_disk(server: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+
+    let mut world_size = 0;
+    for dir in worlds::get_world_dirs(server)?.top_level() {
+        if dir.is_dir() {
+            world_size += space::dir_size(dir)?;
+        }
+    }
+    let needed = world_size / GROWTH_DIVISOR;
+    let available = space::available(&server_dir)?;
+
+    if available >= needed {
+        Ok(())
+    } else {
+        Err(Error::InsufficientSpace {
+            needed,
+            available,
+            path: server_dir,
+        })
+    }
+}
+
+/// Checks that a server has a jar to run, or the native binary its start command names
+fn check_jar(server: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+
+    match read_start_command(&server_dir)? {
+        Some(start_command) => {
+            let binary =
+                server_dir.join(start_command.split_whitespace().next().unwrap_or_default());
+            if binary.is_file() {
+                Ok(())
+            } else {
+                Err(Error::NoJarFound { dir: server_dir })
+            }
+        }
+        None => read_server_jar_path(server_dir).map(|_| ()),
+    }
+}
+
+fn check_server(
+    server: &str,
+    checks: &[FleetCheck],
+    ports: &HashMap<String, Vec<String>>,
+    alive: &HashSet<String>,
+) -> ServerReport {
+    let started = Instant::now();
+
+    // Bedrock servers have neither a EULA file nor rcon
+    let native = get_server_dir_required(server)
+        .and_then(read_start_command)
+        .is_ok_and(|start_command| start_command.is_some());
+
+    let checks = checks
+        .iter()
+        .map(|&check| {
+            let outcome = match check {
+                FleetCheck::Eula | FleetCheck::Rcon if native => {
+                    Outcome::Skip("bedrock".to_string())
+                }
+                FleetCheck::Eula => Outcome::from_result(check_eula(server)),
+                FleetCheck::Port => Outcome::from_result(check_port(server, ports)),
+                FleetCheck::Rcon => check_rcon(server, alive),
+                FleetCheck::Disk => Outcome::from_result(check_disk(server)),
+                FleetCheck::Jar => Outcome::from_result(check_jar(server)),
+            };
+            (check, outcome)
+        })
+        .collect();
+
+    ServerReport {
+        server: server.to_string(),
+        checks,
+        duration: started.elapsed(),
+    }
+}
+
+fn print_matrix(reports: &[ServerReport], checks: &[FleetCheck]) {
+    let width = reports
+        .iter()
+        .map(|report| report.server.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("server".len());
+
+    print!("{:width$}", "server");
+    for check in checks {
+        print!("  {check:4}");
+    }
+    println!();
+
+    for report in reports {
+        print!("{:width$}", report.server);
+        for outcome in report.checks.values() {
+            print!("  {}   ", outcome.symbol());
+        }
+        println!();
+    }
+
+    for report in reports {
+        for (check, outcome) in &report.checks {
+            if let Outcome::Fail(reason) = outcome {
+                println!("{} {check}: {reason}", report.server);
+            }
+        }
+    }
+}
+
+/// Runs every check that isn't skipped on every server at once, printing a matrix or JSON, and
+/// fails if any check failed
+pub fn check(skip: &[FleetCheck], json: bool, reporter: &dyn Reporter) -> Result<()> {
+    let checks: Vec<_> = FleetCheck::value_variants()
+        .iter()
+        .copied()
+        .filter(|check| !skip.contains(check))
+        .collect();
+
+    let mut servers = vec![];
+    server::for_each(|server| {
+        if !server::is_template(&server) && get_server_dir_required(&server).is_ok() {
+            servers.push(server);
+        }
+    })?;
+    servers.sort();
+
+    let mut ports: HashMap<String, Vec<String>> = HashMap::new();
+    if checks.contains(&FleetCheck::Port) {
+        for server in &servers {
+            if let Ok(port) = port_of(server) {
+                ports.entry(port).or_default().push(server.clone());
+            }
+        }
+    }
+
+    let alive = if checks.contains(&FleetCheck::Rcon) {
+        session::get_alive_server_sessions()?
+    } else {
+        HashSet::new()
+    };
+
+    let reports: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = servers
+            .iter()
+            .map(|server| scope.spawn(|| check_server(server, &checks, &ports, &alive)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("fleet checks don't panic"))
+            .collect()
+    });
+
+    for report in &reports {
+        reporter.report(Event::ServerFinished {
+            server: &report.server,
+            duration_ms: report.duration.as_millis() as u64,
+            error: report.failures().as_deref(),
+        });
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        print_matrix(&reports, &checks);
+    }
+
+    let failures = reports
+        .iter()
+        .flat_map(|report| report.checks.values())
+        .filter(|outcome| matches!(outcome, Outcome::Fail(_)))
+        .count();
+
+    if failures > 0 {
+        return Err(Error::FleetChecksFailed(failures));
+    }
+
+    Ok(())
+}
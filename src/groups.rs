@@ -0,0 +1,135 @@
+//! Named groups of servers from the configuration, which are passed as `@<group>` to commands
+//! that act on a server and run them on every member in order.
+
+use crate::{
+    config,
+    error::{Error, Result},
+    server::get_server_dir_required,
+};
+
+pub const GROUP_PREFIX: char = '@';
+
+/// Returns the group named by a server argument, if it refers to one
+pub fn group_name(server: &str) -> Option<&str> {
+    server.strip_prefix(GROUP_PREFIX)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Order {
+    /// The listed order, so that a proxy listed first starts first
+    Listed,
+    /// The reverse order, so that a proxy listed first stops last
+    Reversed,
+}
+
+/// Returns the members of a group in the given order
+pub fn members(group: &str, order: Order) -> Result<Vec<String>> {
+    let mut members = config::snapshot()?
+        .groups
+        .and_then(|mut groups| groups.remove(group))
+        .ok_or_else(|| Error::GroupNotFound(group.to_string()))?;
+
+    if let Some(member) = members.iter().find(|member| group_name(member).is_some()) {
+        return Err(Error::NestedGroup {
+            group: group.to_string(),
+            member: member.clone(),
+        });
+    }
+
+    if order == Order::Reversed {
+        members.reverse();
+    }
+
+    Ok(members)
+}
+
+/// Runs an operation on every member of a group, carrying on past failures and reporting each
+/// server's outcome
+pub fn for_each(group: &str, order: Order, mut f: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    let mut failures = 0;
+
+    for server in members(group, order)? {
+        match f(&server) {
+            Ok(()) => println!("[\x1b[32;1mok\x1b[0m] {server}"),
+            Err(err) => {
+                failures += 1;
+                println!("[\x1b[31;1mfail\x1b[0m] {server}: {err}");
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
+
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let mut groups: Vec<_> = config::snapshot()?
+        .groups
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    groups.sort();
+
+    for (group, members) in groups {
+        println!("{GROUP_PREFIX}{group}: {}", members.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Appends servers to a group, creating it if needed, after checking that they all exist
+pub fn add(group: &str, servers: Vec<String>) -> Result<()> {
+    for server in &servers {
+        if group_name(server).is_some() {
+            return Err(Error::NestedGroup {
+                group: group.to_string(),
+                member: server.clone(),
+            });
+        }
+        get_server_dir_required(server).map_err(|_| Error::ServerNotFound(server.clone()))?;
+    }
+
+    config::update(|cfg| {
+        let members = cfg
+            .groups
+            .get_or_insert_default()
+            .entry(group.to_string())
+            .or_default();
+
+        for server in servers {
+            if !members.contains(&server) {
+                members.push(server);
+            }
+        }
+    })
+}
+
+/// Removes servers from a group, or the whole group if no servers are given
+pub fn remove(group: &str, servers: Vec<String>) -> Result<()> {
+    config::update(|cfg| {
+        let groups = cfg
+            .groups
+            .as_mut()
+            .ok_or_else(|| Error::GroupNotFound(group.to_string()))?;
+        let members = groups
+            .get_mut(group)
+            .ok_or_else(|| Error::GroupNotFound(group.to_string()))?;
+
+        if let Some(server) = servers.iter().find(|server| !members.contains(server)) {
+            return Err(Error::NotInGroup {
+                server: server.clone(),
+                group: group.to_string(),
+            });
+        }
+
+        members.retain(|member| !servers.contains(member));
+        if servers.is_empty() || members.is_empty() {
+            groups.remove(group);
+        }
+
+        Ok(())
+    })?
+}
@@ -0,0 +1,106 @@
+use crate::{
+    error::{Error, Result},
+    server::{get_metadata_dir, get_server_dir_required},
+    subprocess, verbose,
+};
+use serde::Deserialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    process::{Command, Stdio},
+};
+
+/// Per-server hook commands, read from `.mcserver/hooks.toml` in the server directory
+const HOOKS_FILE: &str = "hooks.toml";
+
+#[derive(Default, Deserialize)]
+struct Hooks {
+    pre_deploy: Option<String>,
+    post_deploy: Option<String>,
+    pre_stop: Option<String>,
+    post_stop: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Hook {
+    PreDeploy,
+    PostDeploy,
+    PreStop,
+    PostStop,
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PreDeploy => write!(f, "pre_deploy"),
+            Self::PostDeploy => write!(f, "post_deploy"),
+            Self::PreStop => write!(f, "pre_stop"),
+            Self::PostStop => write!(f, "post_stop"),
+        }
+    }
+}
+
+fn read_hooks(server: &str) -> Result<Hooks> {
+    let path = get_metadata_dir(server)?.join(HOOKS_FILE);
+
+    if !path.exists() {
+        return Ok(Hooks::default());
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn run(server: &str, hook: Hook) -> Result<()> {
+    let hooks = read_hooks(server)?;
+    let hook_command = match hook {
+        Hook::PreDeploy => hooks.pre_deploy,
+        Hook::PostDeploy => hooks.post_deploy,
+        Hook::PreStop => hooks.pre_stop,
+        Hook::PostStop => hooks.post_stop,
+    };
+
+    let Some(hook_command) = hook_command else {
+        return Ok(());
+    };
+
+    let server_dir = get_server_dir_required(server)?;
+    verbose!("Running {hook} hook for {server}: {hook_command}");
+
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&hook_command)
+        .current_dir(&server_dir)
+        .env("MCSERVER_SERVER", server)
+        .env("MCSERVER_SERVER_DIR", &server_dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped());
+
+    let output = subprocess::run_with_timeout(&mut command, Error::from)?;
+
+    if output.status.success() {
+        verbose!("The {hook} hook for {server} succeeded");
+        Ok(())
+    } else {
+        Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        ))
+    }
+}
+
+/// Runs a hook that must succeed for the operation to go ahead
+pub fn run_pre(server: &str, hook: Hook) -> Result<()> {
+    run(server, hook).map_err(|err| Error::HookFailed {
+        hook: hook.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// Runs a hook after an operation, only reporting failures since the operation already happened
+pub fn run_post(server: &str, hook: Hook) {
+    if let Err(err) = run(server, hook) {
+        eprintln!("\x1b[33;1mWarning:\x1b[0m the {hook} hook for {server} failed: {err}");
+    }
+}
@@ -0,0 +1,202 @@
+//! API responses kept on disk between runs. A kept response is used as is for a few minutes after
+//! it was last checked, and is then revalidated with `If-None-Match` and `If-Modified-Since`, so
+//! the API or a caching proxy in front of it only has to answer that nothing changed. Downloads
+//! aren't kept here.
+//!
+//! The cache only ever saves work, so anything going wrong with it is logged and otherwise
+//! ignored.
+
+use crate::{config, environment, error::Result, timeutil::unix_now, verbose};
+use reqwest::header::{
+    ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use url::Url;
+
+/// How long responses are kept by default, in seconds
+const DEFAULT_TTL: u64 = 24 * 60 * 60;
+
+/// How long a response is used without checking it by default, in seconds
+const DEFAULT_FRESH: u64 = 10 * 60;
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_disabled(disabled: bool) {
+    DISABLED.store(disabled, Ordering::Relaxed);
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the response was first stored, which it is kept for the TTL after however often it is
+    /// revalidated
+    stored: u64,
+    /// When the API last confirmed the response, which is when it was stored for entries from
+    /// before this was recorded
+    #[serde(default)]
+    checked: u64,
+    pub body: String,
+}
+
+impl Entry {
+    /// The headers that ask for the response only if it changed since this one
+    pub fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in [
+            (IF_NONE_MATCH, &self.etag),
+            (IF_MODIFIED_SINCE, &self.last_modified),
+        ] {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+
+        headers
+    }
+
+    /// Whether the response was checked recently enough to be used without asking again
+    pub fn is_fresh(&self) -> bool {
+        let fresh = config::snapshot()
+            .ok()
+            .and_then(|config| config.http_cache_fresh)
+            .unwrap_or(DEFAULT_FRESH);
+        let checked = self.checked.max(self.stored);
+
+        unix_now().is_ok_and(|now| now.saturating_sub(checked) < fresh)
+    }
+
+    fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// How long responses are kept, which is `None` when the cache is turned off
+fn ttl() -> Option<u64> {
+    if DISABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    match config::snapshot() {
+        Ok(config) => Some(config.http_cache_ttl.unwrap_or(DEFAULT_TTL)).filter(|ttl| *ttl > 0),
+        Err(_) => None,
+    }
+}
+
+fn path(url: &Url) -> Option<PathBuf> {
+    let name = format!("{:x}.json", Sha256::digest(url.as_str()));
+    Some(environment::get().cache_dir()?.join("http").join(name))
+}
+
+/// Returns the kept response for a url, unless it expired or the cache is turned off
+pub fn get(url: &Url) -> Option<Entry> {
+    let ttl = ttl()?;
+    let path = path(url)?;
+    let entry: Entry = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+
+    let now = unix_now().ok()?;
+    let expired = now.saturating_sub(entry.stored) >= ttl;
+    // without validators, a response is no use once it can't be used as is
+    if entry.url != url.as_str() || expired || (!entry.has_validators() && !entry.is_fresh()) {
+        let _ = fs::remove_file(path);
+        return None;
+    }
+
+    Some(entry)
+}
+
+fn write(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Records that the API confirmed a kept response is unchanged, so that it is used as is again
+pub fn touch(url: &Url, mut entry: Entry) -> Entry {
+    if let Some(path) = path(url)
+        && let Ok(now) = unix_now()
+    {
+        entry.checked = now;
+        if let Err(err) = write(&path, &entry) {
+            verbose!("Failed to update the kept response for {url}: {err}");
+        }
+    }
+
+    entry
+}
+
+/// Keeps a response. One without validators to revalidate it with is only kept to be used as is.
+pub fn put(url: &Url, headers: &HeaderMap, body: &str) {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(String::from)
+    };
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+    if ttl().is_none() {
+        return;
+    }
+
+    let Some(path) = path(url) else {
+        return;
+    };
+
+    let result = unix_now().and_then(|stored| {
+        let entry = Entry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            stored,
+            checked: stored,
+            body: body.to_string(),
+        };
+
+        write(&path, &entry)
+    });
+
+    if let Err(err) = result {
+        verbose!("Failed to keep the response for {url}: {err}");
+    }
+}
+
+/// Removes everything kept in the cache directory, API responses and completion candidates alike,
+/// returning how many files there were
+pub fn clear() -> Result<usize> {
+    let Some(dir) = environment::get().cache_dir() else {
+        return Ok(0);
+    };
+
+    fn count(dir: &Path) -> io::Result<usize> {
+        let mut files = 0;
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            files += if entry.file_type()?.is_dir() {
+                count(&entry.path())?
+            } else {
+                1
+            };
+        }
+        Ok(files)
+    }
+
+    let files = match count(&dir) {
+        Ok(files) => files,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    fs::remove_dir_all(&dir)?;
+    Ok(files)
+}
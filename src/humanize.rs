@@ -0,0 +1,80 @@
+//! Human readable formatting, kept in one place so that output can be localized later on.
+
+const SECS_MINUTE: u64 = 60;
+const SECS_HOUR: u64 = SECS_MINUTE * 60;
+const SECS_DAY: u64 = SECS_HOUR * 24;
+const SECS_YEAR: u64 = (SECS_DAY as f64 * 365.2425) as u64;
+
+/// Formats a number of seconds as e.g. `3d 4h 5m 6s`, omitting leading zero units
+pub fn duration(secs: u64) -> String {
+    let years = secs / SECS_YEAR;
+    let years_remainder = secs % SECS_YEAR;
+
+    let days = years_remainder / SECS_DAY;
+    let days_remainder = years_remainder % SECS_DAY;
+
+    let hours = days_remainder / SECS_HOUR;
+    let hours_remainder = days_remainder % SECS_HOUR;
+
+    let minutes = hours_remainder / SECS_MINUTE;
+    let seconds = hours_remainder % SECS_MINUTE;
+
+    if years > 0 {
+        format!("{years}y {days}d {hours}h {minutes}m {seconds}s")
+    } else if days > 0 {
+        format!("{days}d {hours}h {minutes}m {seconds}s")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// Formats a number of bytes with binary units, e.g. `1.5 GiB`
+pub fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_omit_leading_zero_units() {
+        assert_eq!(duration(0), "0s");
+        assert_eq!(duration(59), "59s");
+        assert_eq!(duration(60), "1m 0s");
+        assert_eq!(duration(3 * SECS_HOUR + 4), "3h 0m 4s");
+        assert_eq!(duration(2 * SECS_DAY + 5 * SECS_MINUTE), "2d 0h 5m 0s");
+        assert_eq!(duration(SECS_YEAR + 1), "1y 0d 0h 0m 1s");
+    }
+
+    #[test]
+    fn durations_handle_the_largest_values() {
+        assert!(duration(u64::MAX).ends_with('s'));
+    }
+
+    #[test]
+    fn bytes_use_binary_units() {
+        assert_eq!(bytes(0), "0 B");
+        assert_eq!(bytes(1023), "1023 B");
+        assert_eq!(bytes(1024), "1.0 KiB");
+        assert_eq!(bytes(1536 * 1024 * 1024), "1.5 GiB");
+        assert_eq!(bytes(u64::MAX), "16777216.0 TiB");
+    }
+}
@@ -0,0 +1,270 @@
+//! `.mcserverignore` files, which list paths in a server that shouldn't be counted towards its
+//! size or copied into templates, such as caches that a mod can regenerate.
+//!
+//! The file supports a subset of gitignore syntax, with one pattern per line:
+//!
+//! - blank lines and lines starting with `#` are skipped
+//! - `*` matches anything except `/`, `?` matches one character except `/`
+//! - `**` as a whole component matches any number of directories
+//! - a pattern containing a `/` other than a trailing one is anchored to the server root, otherwise
+//!   it matches a name at any depth
+//! - a trailing `/` only matches directories
+//! - a leading `!` re-includes paths excluded by an earlier pattern, although as with git, nothing
+//!   inside an excluded directory can be re-included
+//!
+//! The last pattern matching a path decides whether it's excluded. Character classes and escapes
+//! aren't supported.
+
+use crate::{error::Result, server::get_server_dir_required};
+use std::{
+    fs, io,
+    path::{Component, Path},
+};
+
+pub const IGNORE_FILE: &str = ".mcserverignore";
+
+#[derive(Debug)]
+struct Pattern {
+    components: Vec<String>,
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+
+        let components: Vec<String> = line
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if components.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            components,
+            anchored,
+            dir_only,
+            negated,
+        })
+    }
+
+    fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            match_components(&self.components, components)
+        } else {
+            components
+                .last()
+                .is_some_and(|name| match_component(&self.components[0], name))
+        }
+    }
+}
+
+fn match_components(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(name, path)| {
+            match_component(first, name) && match_components(rest, path)
+        }),
+    }
+}
+
+/// Matches a single path component against a pattern with `*` and `?` wildcards
+fn match_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The patterns from a server's `.mcserverignore`, which is empty if the file doesn't exist
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    pub fn load(root: impl AsRef<Path>) -> Result<Self> {
+        match fs::read_to_string(root.as_ref().join(IGNORE_FILE)) {
+            Ok(contents) => Ok(Self {
+                patterns: contents.lines().filter_map(Pattern::parse).collect(),
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Whether the patterns exclude a path relative to the root, without checking its parents,
+    /// for walkers that don't descend into excluded directories
+    pub fn excludes(&self, relative: &Path, is_dir: bool) -> bool {
+        let components = path_components(relative);
+        self.excludes_components(&components, is_dir)
+    }
+
+    fn excludes_components(&self, components: &[&str], is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(components, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    /// Whether a path relative to the root is excluded, either itself or through one of its parent
+    /// directories
+    pub fn excludes_path(&self, relative: &Path, is_dir: bool) -> bool {
+        let components = path_components(relative);
+
+        (1..components.len()).any(|len| self.excludes_components(&components[..len], true))
+            || self.excludes_components(&components, is_dir)
+    }
+}
+
+fn path_components(path: &Path) -> Vec<&str> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a path within a server would be left out, treating it as a directory if it is one or
+/// is written with a trailing `/`
+pub fn is_ignored(server: &str, path: &Path) -> Result<bool> {
+    let server_dir = get_server_dir_required(server)?;
+    let relative = path.strip_prefix(&server_dir).unwrap_or(path);
+    let is_dir =
+        path.as_os_str().to_string_lossy().ends_with('/') || server_dir.join(relative).is_dir();
+
+    Ok(IgnoreRules::load(&server_dir)?.excludes_path(relative, is_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> IgnoreRules {
+        IgnoreRules {
+            patterns: contents.lines().filter_map(Pattern::parse).collect(),
+        }
+    }
+
+    #[test]
+    fn wildcards_stay_within_a_component() {
+        assert!(match_component("*.log", "latest.log"));
+        assert!(match_component("*.log", ".log"));
+        assert!(match_component("debug-?.txt", "debug-1.txt"));
+        assert!(match_component("a*b*c", "aXbYbZc"));
+        assert!(!match_component("debug-?.txt", "debug-10.txt"));
+        assert!(!match_component("*.log", "latest.log.gz"));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let rules = parse("# logs\n\n   \n/\n");
+        assert!(rules.patterns.is_empty());
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let rules = parse("*.tmp\n");
+        assert!(rules.excludes_path(Path::new("a.tmp"), false));
+        assert!(rules.excludes_path(Path::new("world/region/a.tmp"), false));
+        assert!(!rules.excludes_path(Path::new("world/a.tmpx"), false));
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_the_root() {
+        let rules = parse("/cache\nworld/*.old\n");
+        assert!(rules.excludes_path(Path::new("cache"), true));
+        assert!(!rules.excludes_path(Path::new("plugins/cache"), true));
+        assert!(rules.excludes_path(Path::new("world/level.dat.old"), false));
+        assert!(!rules.excludes_path(Path::new("backup/world/level.dat.old"), false));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_directories() {
+        let rules = parse("plugins/**/cache/\n");
+        assert!(rules.excludes(Path::new("plugins/cache"), true));
+        assert!(rules.excludes(Path::new("plugins/dynmap/web/cache"), true));
+        assert!(!rules.excludes(Path::new("cache"), true));
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_directories() {
+        let rules = parse("logs/\n");
+        assert!(rules.excludes(Path::new("logs"), true));
+        assert!(!rules.excludes(Path::new("logs"), false));
+    }
+
+    #[test]
+    fn the_last_matching_pattern_wins() {
+        let rules = parse("*.log\n!latest.log\n");
+        assert!(rules.excludes_path(Path::new("logs/debug.log"), false));
+        assert!(!rules.excludes_path(Path::new("logs/latest.log"), false));
+
+        let reversed = parse("!latest.log\n*.log\n");
+        assert!(reversed.excludes_path(Path::new("logs/latest.log"), false));
+    }
+
+    #[test]
+    fn nothing_inside_an_excluded_directory_is_re_included() {
+        let rules = parse("cache/\n!cache/keep.txt\n");
+        assert!(rules.excludes_path(Path::new("cache/keep.txt"), false));
+        assert!(!rules.excludes(Path::new("cache/keep.txt"), false));
+    }
+
+    #[test]
+    fn missing_file_excludes_nothing() {
+        let rules = IgnoreRules::load(Path::new("/nonexistent/server")).unwrap();
+        assert!(!rules.excludes_path(Path::new("anything"), false));
+    }
+}
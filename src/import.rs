@@ -0,0 +1,266 @@
+use crate::{
+    config::get_expanded_servers_dir,
+    error::{Error, Result},
+    ignore::IgnoreRules,
+    names,
+    platforms::Platform,
+    server::{
+        METADATA_DIRECTORY, copy_directory, copy_directory_excluding, find_jars,
+        read_server_jar_path, set_default_metadata,
+    },
+    space,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs, io,
+    os::unix,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    Copy,
+    Move,
+    Link,
+}
+
+/// A row of an import manifest. The platform is used to pick the server jar when a directory
+/// contains several, and the version is only shown in the summary.
+#[derive(Debug, Deserialize)]
+struct ImportEntry {
+    source: PathBuf,
+    name: String,
+    platform: Option<Platform>,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlManifest {
+    #[serde(default)]
+    servers: Vec<ImportEntry>,
+}
+
+const CSV_COLUMNS: [&str; 4] = ["source", "name", "platform", "version"];
+
+/// Parses a CSV manifest with the columns `source,name,platform,version`, where the last two may
+/// be empty. Quoting is not supported, so paths can't contain commas.
+fn parse_csv(contents: &str) -> Result<Vec<(usize, ImportEntry)>> {
+    let mut entries = vec![];
+    let mut errors = vec![];
+
+    for (index, line) in contents.lines().enumerate() {
+        let row = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split(',').map(str::trim).collect();
+
+        if row == 1 && fields.first() == Some(&CSV_COLUMNS[0]) {
+            continue;
+        }
+
+        if fields.len() < 2 || fields.len() > CSV_COLUMNS.len() {
+            errors.push(format!(
+                "row {row}: expected the columns {}",
+                CSV_COLUMNS.join(",")
+            ));
+            continue;
+        }
+
+        let field = |i: usize| {
+            fields
+                .get(i)
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_string())
+        };
+
+        let platform = match field(2) {
+            Some(platform) => match Platform::from_str(&platform, true) {
+                Ok(platform) => Some(platform),
+                Err(_) => {
+                    errors.push(format!("row {row}: unknown platform {platform}"));
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        entries.push((
+            row,
+            ImportEntry {
+                source: PathBuf::from(fields[0]),
+                name: fields[1].to_string(),
+                platform,
+                version: field(3),
+            },
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(report_invalid(errors))
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<Vec<(usize, ImportEntry)>> {
+    let contents = fs::read_to_string(path)?;
+
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        let manifest: TomlManifest = toml::from_str(&contents)?;
+        Ok((1..).zip(manifest.servers).collect())
+    } else {
+        parse_csv(&contents)
+    }
+}
+
+/// Checks every entry up front, so that a bad manifest doesn't leave a half finished import, and
+/// normalizes the server names
+fn validate(entries: &mut [(usize, ImportEntry)], servers_dir: &Path) -> Vec<String> {
+    let mut errors = vec![];
+    let mut seen = HashSet::new();
+
+    for (row, entry) in entries.iter_mut() {
+        if let Ok(name) = names::normalize(&entry.name)
+            && name != entry.name
+        {
+            eprintln!("row {row}: using server name {name} for {:?}", entry.name);
+            entry.name = name;
+        }
+    }
+
+    for (row, entry) in entries.iter() {
+        if !entry.source.is_dir() {
+            errors.push(format!(
+                "row {row}: {} is not a directory",
+                entry.source.display()
+            ));
+        }
+
+        if names::normalize(&entry.name).is_err() {
+            errors.push(format!("row {row}: invalid server name {:?}", entry.name));
+        } else if !seen.insert(entry.name.as_str()) {
+            errors.push(format!("row {row}: {} is named more than once", entry.name));
+        } else if servers_dir.join(&entry.name).exists() {
+            errors.push(format!("row {row}: server {} already exists", entry.name));
+        }
+    }
+
+    errors
+}
+
+/// Finds the server jar in a directory, preferring one named after the platform when there are
+/// several
+fn detect_jar(server_dir: &Path, platform: Option<Platform>) -> Result<String> {
+    let mut jars = find_jars(server_dir)?;
+
+    if jars.len() > 1
+        && let Some(platform) = platform
+        && let Some(jar) = jars
+            .iter()
+            .find(|jar| jar.to_lowercase().contains(&platform.to_string()))
+    {
+        return Ok(jar.clone());
+    }
+
+    if jars.len() == 1 {
+        Ok(jars.remove(0))
+    } else {
+        Err(Error::JarNotDetected {
+            dir: server_dir.to_path_buf(),
+        })
+    }
+}
+
+fn move_directory(source: &Path, destination: &Path) -> io::Result<()> {
+    // a rename fails across filesystems, in which case the directory is copied instead, in full
+    // since the source is deleted afterwards
+    if fs::rename(source, destination).is_err() {
+        copy_directory_excluding(source, destination, &IgnoreRules::default())?;
+        fs::remove_dir_all(source)?;
+    }
+
+    Ok(())
+}
+
+fn import_entry(entry: &ImportEntry, servers_dir: &Path, mode: ImportMode) -> Result<()> {
+    let destination = servers_dir.join(&entry.name);
+
+    // the jar is picked before anything is placed, so that a failure leaves nothing behind
+    let jar = match read_server_jar_path(&entry.source) {
+        Ok(_) => None,
+        Err(_) => Some(detect_jar(&entry.source, entry.platform)?),
+    };
+
+    if mode == ImportMode::Copy {
+        space::ensure_for_copy(&entry.source, servers_dir)?;
+    }
+
+    match mode {
+        ImportMode::Copy => copy_directory(&entry.source, &destination)?,
+        ImportMode::Move => move_directory(&entry.source, &destination)?,
+        ImportMode::Link => unix::fs::symlink(fs::canonicalize(&entry.source)?, &destination)?,
+    }
+
+    if let Some(jar) = jar {
+        set_default_metadata(destination.join(METADATA_DIRECTORY), jar)?;
+    }
+
+    Ok(())
+}
+
+/// Imports every server in a CSV or TOML manifest, carrying on past failures and printing a
+/// summary at the end
+pub fn bulk(manifest: impl AsRef<Path>, mode: ImportMode) -> Result<()> {
+    let servers_dir = get_expanded_servers_dir()?;
+
+    let mut entries = read_manifest(manifest.as_ref())?;
+
+    let errors = validate(&mut entries, servers_dir);
+    if !errors.is_empty() {
+        return Err(report_invalid(errors));
+    }
+
+    let mut failures = 0;
+
+    for (row, entry) in &entries {
+        let description = match (&entry.platform, &entry.version) {
+            (Some(platform), Some(version)) => format!("{} ({platform} {version})", entry.name),
+            (Some(platform), None) => format!("{} ({platform})", entry.name),
+            _ => entry.name.clone(),
+        };
+
+        match import_entry(entry, servers_dir, mode) {
+            Ok(()) => println!("[\x1b[32;1mok\x1b[0m] {description}"),
+            Err(err) => {
+                failures += 1;
+                println!("[\x1b[31;1mfail\x1b[0m] {description} (row {row}): {err}");
+            }
+        }
+    }
+
+    println!(
+        "Imported {} of {} server(s)",
+        entries.len() - failures,
+        entries.len()
+    );
+
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
+
+    Ok(())
+}
+
+fn report_invalid(errors: Vec<String>) -> Error {
+    for error in &errors {
+        eprintln!("{error}");
+    }
+
+    Error::InvalidImportManifest(errors.len())
+}
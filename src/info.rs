@@ -0,0 +1,99 @@
+use crate::{
+    error::Result,
+    lineage::{self, SourceKind},
+    provenance, server,
+    startup::{self, Outcome},
+    timeutil::{self, unix_now},
+};
+
+pub fn print(server: &str) -> Result<()> {
+    let server_dir = server::get_server_dir_required(server)?;
+    let metadata_dir = server::get_metadata_dir(server)?;
+
+    println!("Name: {server}");
+    println!("Path: {}", server_dir.display());
+
+    let provenance = provenance::read(&metadata_dir)?;
+    if let Some(provenance) = &provenance {
+        let build = provenance
+            .build
+            .as_ref()
+            .map(|build| format!(" build {build}"))
+            .unwrap_or_default();
+        let requested = provenance.requested_version.as_deref().unwrap_or("latest");
+
+        println!(
+            "Platform: {} {}{build} (requested {requested})",
+            provenance.platform, provenance.version
+        );
+    }
+
+    match server::read_start_command(&server_dir)? {
+        Some(start_command) => {
+            if provenance.is_none() {
+                println!("Platform: bedrock");
+            }
+            println!("Start command: {start_command}");
+        }
+        None => match server::get_server_jar_path(&server_dir) {
+            Ok(jar) => println!("Jar: {}", jar.display()),
+            Err(err) => println!("Jar: unknown ({err})"),
+        },
+    }
+
+    println!("Stop command: {}", server::get_stop_command(server)?);
+
+    println!(
+        "Last used: {}",
+        server::get_last_used(server)?.describe(unix_now()?)
+    );
+
+    match startup::read(server)? {
+        Some(last_start) => {
+            let outcome = match last_start.outcome {
+                Outcome::Ready => "ready",
+                Outcome::Failed => "failed",
+            };
+            let reason = last_start
+                .reason
+                .map(|reason| format!(": {reason}"))
+                .unwrap_or_default();
+            let age = timeutil::since(last_start.at)?;
+
+            println!("Last start: {outcome} {age} ago{reason}");
+            if let Some(excerpt) = startup::excerpt_file(server)? {
+                println!("Log excerpt: {}", excerpt.display());
+            }
+        }
+        None => println!("Last start: unknown"),
+    }
+
+    if server::is_template(server) {
+        match lineage::template_revision(&metadata_dir)? {
+            Some(revision) => println!("Template revision: {revision}"),
+            None => println!("Template revision: unknown"),
+        }
+    }
+
+    match lineage::read(&metadata_dir)? {
+        Some(lineage) => {
+            let kind = match lineage.kind {
+                SourceKind::Template => "template",
+                SourceKind::Server => "server",
+            };
+            let revision = lineage
+                .source_revision
+                .map(|revision| format!(" (revision {revision})"))
+                .unwrap_or_default();
+            let age = timeutil::since(lineage.created)?;
+
+            println!(
+                "Lineage: {kind} {}{revision}, created {age} ago",
+                lineage.source
+            );
+        }
+        None => println!("Lineage: unknown"),
+    }
+
+    Ok(())
+}
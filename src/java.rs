@@ -0,0 +1,246 @@
+//! Finds the Java runtimes installed on the machine and picks one for each server, so that servers
+//! needing different Java versions can run side by side without configuring each one.
+//!
+//! A server can still pin a runtime by writing its path to `.mcserver/java_path.txt`.
+
+use crate::{
+    error::{Error, Result},
+    server::{self, METADATA_DIRECTORY, get_server_dir_required},
+    subprocess,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+pub const JAVA_PATH_FILE: &str = "java_path.txt";
+
+/// Where distributions and package managers put JDKs on Linux
+const INSTALL_DIRS: [&str; 2] = ["/usr/lib/jvm", "/opt/java"];
+
+static RUNTIMES: OnceLock<Vec<Runtime>> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct Runtime {
+    pub path: PathBuf,
+    pub version: String,
+    pub major: u32,
+}
+
+fn candidates() -> Vec<PathBuf> {
+    let mut candidates = vec![];
+
+    if let Some(path) = env::var_os("PATH") {
+        candidates.extend(env::split_paths(&path).map(|dir| dir.join("java")));
+    }
+
+    if let Some(java_home) = env::var_os("JAVA_HOME") {
+        candidates.push(Path::new(&java_home).join("bin").join("java"));
+    }
+
+    for dir in INSTALL_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut jdks: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join("bin").join("java"))
+            .collect();
+        jdks.sort();
+        candidates.extend(jdks);
+    }
+
+    candidates
+}
+
+/// Parses the major version out of `java -version` output, such as 17 from `"17.0.15"` or 8 from
+/// `"1.8.0_402"`
+fn parse_version(output: &str) -> Option<(String, u32)> {
+    let version = output.split('"').nth(1)?;
+    let mut parts = version.split(['.', '_', '-', '+']);
+
+    let major = match parts.next()? {
+        "1" => parts.next()?,
+        major => major,
+    };
+
+    Some((version.to_string(), major.parse().ok()?))
+}
+
+fn probe(path: &Path) -> Option<Runtime> {
+    let output = subprocess::run_with_timeout(
+        Command::new(path)
+            .arg("-version")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+        Error::from,
+    )
+    .ok()?;
+    // java prints its version on stderr
+    let (version, major) = parse_version(&String::from_utf8_lossy(&output.stderr))?;
+
+    Some(Runtime {
+        path: path.to_path_buf(),
+        version,
+        major,
+    })
+}
+
+/// Returns every Java runtime found on the PATH, in JAVA_HOME and in the usual install
+/// directories, once each even when reachable through several symlinks. Discovery runs once per
+/// process.
+pub fn discover() -> &'static [Runtime] {
+    RUNTIMES.get_or_init(|| {
+        let mut seen = HashSet::new();
+
+        candidates()
+            .into_iter()
+            .filter(|path| path.is_file())
+            .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+            .filter_map(|path| probe(&path))
+            .collect()
+    })
+}
+
+/// The parts of the `version.json` that vanilla jars, and the Paper style jars built from them,
+/// carry
+#[derive(Deserialize)]
+pub struct VersionInfo {
+    /// The game version, such as 1.21.4 or 24w14a
+    pub name: Option<String>,
+    /// The data version of worlds the jar writes
+    pub world_version: Option<i32>,
+    pub java_version: Option<u32>,
+}
+
+/// Reads the `version.json` of a jar, if it has one
+pub fn read_version_info(jar: &Path) -> Option<VersionInfo> {
+    let mut archive = zip::ZipArchive::new(File::open(jar).ok()?).ok()?;
+    let mut contents = String::new();
+    archive
+        .by_name("version.json")
+        .ok()?
+        .read_to_string(&mut contents)
+        .ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+/// Parses a release such as 1.20.4 or 1.21 into its minor and patch numbers
+pub fn parse_game_version(version: &str) -> Option<(u32, u32)> {
+    let mut numbers = version.split('.');
+    if numbers.next()? != "1" {
+        return None;
+    }
+    let minor = numbers.next()?.parse().ok()?;
+    let patch = numbers.next().map_or(Some(0), |patch| patch.parse().ok())?;
+    Some((minor, patch))
+}
+
+/// Finds a game version such as 1.20.4 in a jar's file name
+pub fn game_version_from_name(jar: &Path) -> Option<(u32, u32)> {
+    let name = jar.file_stem()?.to_string_lossy();
+    name.split(['-', '_']).find_map(parse_game_version)
+}
+
+/// The minimum Java version each range of game versions needs
+fn java_for_game_version((minor, patch): (u32, u32)) -> u32 {
+    match (minor, patch) {
+        (..=16, _) => 8,
+        (17, _) => 16,
+        (18..=19, _) | (20, ..=4) => 17,
+        _ => 21,
+    }
+}
+
+/// The Java version a server needs, if it can be told from its jar
+pub fn required_major(server_dir: &Path) -> Option<u32> {
+    let jar = server::read_server_jar_path(server_dir).ok()?;
+    read_version_info(&jar)
+        .and_then(|info| info.java_version)
+        .or_else(|| game_version_from_name(&jar).map(java_for_game_version))
+}
+
+fn read_override(server_dir: &Path) -> Result<Option<PathBuf>> {
+    match fs::read_to_string(server_dir.join(METADATA_DIRECTORY).join(JAVA_PATH_FILE)) {
+        Ok(path) => Ok(Some(PathBuf::from(path.trim()))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Picks the runtime a server runs with: its override if it has one, otherwise the newest runtime
+/// that is at least the version it needs. Falls back to a bare `java` when no runtime was found at
+/// all, leaving the PATH to decide.
+pub fn select(server_dir: &Path) -> Result<PathBuf> {
+    if let Some(path) = read_override(server_dir)? {
+        return Ok(path);
+    }
+
+    let runtimes = discover();
+    if runtimes.is_empty() {
+        return Ok(PathBuf::from("java"));
+    }
+
+    let required = required_major(server_dir);
+    runtimes
+        .iter()
+        .filter(|runtime| required.is_none_or(|required| runtime.major >= required))
+        .max_by_key(|runtime| runtime.major)
+        .map(|runtime| runtime.path.clone())
+        .ok_or_else(|| Error::JavaNotFound {
+            required: required.unwrap_or_default(),
+        })
+}
+
+/// Prints the runtimes that were found and which one each server would use
+pub fn list() -> Result<()> {
+    let runtimes = discover();
+    if runtimes.is_empty() {
+        println!("No Java runtimes were found");
+    }
+    for runtime in runtimes {
+        println!(
+            "Java {} ({}): {}",
+            runtime.major,
+            runtime.version,
+            runtime.path.display()
+        );
+    }
+
+    let mut servers = vec![];
+    server::for_each(|server| {
+        if !server::is_template(&server) {
+            servers.push(server);
+        }
+    })?;
+    servers.sort();
+
+    if !servers.is_empty() {
+        println!();
+    }
+
+    for server in servers {
+        let server_dir = get_server_dir_required(&server)?;
+        if server::read_start_command(&server_dir)?.is_some() {
+            continue;
+        }
+
+        let needs = required_major(&server_dir)
+            .map(|major| format!("needs {major}"))
+            .unwrap_or_else(|| "needs unknown".to_string());
+
+        match select(&server_dir) {
+            Ok(path) => println!("{server} ({needs}): {}", path.display()),
+            Err(err) => println!("{server} ({needs}): {err}"),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Per-server launch scripts, for servers that need setup the java arguments can't express, such
+//! as environment variables or an `LD_PRELOAD`ed profiler.
+//!
+//! When `.mcserver/launch.sh` exists it runs in place of the usual java command, still inside the
+//! server's session. It gets the jar as its first argument followed by the java arguments, and the
+//! same values in `MCSERVER_JAR`, `MCSERVER_JAVA` and `MCSERVER_JAVA_ARGS`.
+
+use crate::{
+    error::{Error, Result},
+    server::{self, JavaLaunch, METADATA_DIRECTORY, get_server_dir_required, shell_quote},
+    textdiff,
+};
+use std::{
+    fs::{self, Permissions},
+    io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+pub const LAUNCH_SCRIPT_FILE: &str = "launch.sh";
+
+fn path(server_dir: &Path) -> PathBuf {
+    server_dir.join(METADATA_DIRECTORY).join(LAUNCH_SCRIPT_FILE)
+}
+
+/// Returns a server's launch script if it has one, failing if it exists but can't be executed
+pub fn find(server_dir: &Path) -> Result<Option<PathBuf>> {
+    let path = path(server_dir);
+
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+        return Err(Error::LaunchScriptNotExecutable { path });
+    }
+
+    Ok(Some(path))
+}
+
+/// The command that runs a launch script for a Java server
+pub fn java_command(script: &Path, launch: &JavaLaunch) -> String {
+    format!(
+        "MCSERVER_JAR={} MCSERVER_JAVA={} MCSERVER_JAVA_ARGS={} {} {} {}",
+        shell_quote(launch.jar.to_string_lossy()),
+        shell_quote(launch.java.to_string_lossy()),
+        shell_quote(&launch.java_args),
+        shell_quote(script.to_string_lossy()),
+        shell_quote(launch.jar.to_string_lossy()),
+        launch.java_args,
+    )
+}
+
+/// The command that runs a launch script for a native server, which has no jar or java arguments
+pub fn native_command(script: &Path, start_command: &str) -> String {
+    format!(
+        "MCSERVER_START_COMMAND={} {}",
+        shell_quote(start_command),
+        shell_quote(script.to_string_lossy()),
+    )
+}
+
+fn starter(server: &str) -> Result<String> {
+    let server_dir = get_server_dir_required(server)?;
+    let command = match server::read_start_command(&server_dir)? {
+        Some(start_command) => start_command,
+        None => server::get_java_launch(server)?.command_line(),
+    };
+
+    Ok(format!(
+        "#!/bin/sh\n\
+         # Runs {server} in place of the command mcserver would otherwise use, from the server's\n\
+         # directory. The jar is passed as $1 followed by the java arguments, and also as\n\
+         # $MCSERVER_JAR, $MCSERVER_JAVA and $MCSERVER_JAVA_ARGS.\n\
+         \n\
+         exec {}\n",
+        command.trim_end()
+    ))
+}
+
+/// Writes a starter launch script that runs the server the same way it runs without one
+pub fn init(server: &str, force: bool) -> Result<PathBuf> {
+    let server_dir = get_server_dir_required(server)?;
+    let path = path(&server_dir);
+    let contents = starter(server)?;
+
+    match fs::read_to_string(&path) {
+        Ok(_) if !force => return Err(Error::LaunchScriptExists { path }),
+        Ok(old) => textdiff::print(&path.display().to_string(), &old, &contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    fs::write(&path, contents)?;
+    fs::set_permissions(&path, Permissions::from_mode(0o755))?;
+
+    Ok(path)
+}
@@ -1,6 +1,9 @@
 pub mod config;
 mod config_defs;
+pub mod environment;
 pub mod error;
+#[cfg(test)]
+mod testing;
 
-pub use config_defs::{DynamicConfig, Password, RconConfig, StaticConfig};
+pub use config_defs::{DynamicConfig, HttpConfig, Password, Profile, RconConfig, StaticConfig};
 pub use error::{Error, Result};
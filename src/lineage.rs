@@ -0,0 +1,92 @@
+use crate::{error::Result, timeutil::unix_now};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const LINEAGE_FILE: &str = "lineage.toml";
+const TEMPLATE_FILE: &str = "template.toml";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Template,
+    Server,
+}
+
+/// Where a server was copied from, recorded when it is created
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Lineage {
+    pub source: String,
+    pub kind: SourceKind,
+    pub source_revision: Option<u32>,
+    pub created: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct TemplateMetadata {
+    revision: u32,
+}
+
+pub fn record(
+    metadata_dir: impl AsRef<Path>,
+    source: impl Into<String>,
+    kind: SourceKind,
+    source_revision: Option<u32>,
+) -> Result<()> {
+    let metadata_dir = metadata_dir.as_ref();
+
+    // a copy of a template is not a template itself
+    let template_file = metadata_dir.join(TEMPLATE_FILE);
+    if template_file.exists() {
+        fs::remove_file(template_file)?;
+    }
+
+    let lineage = Lineage {
+        source: source.into(),
+        kind,
+        source_revision,
+        created: unix_now()?,
+    };
+
+    fs::create_dir_all(metadata_dir)?;
+    fs::write(metadata_dir.join(LINEAGE_FILE), toml::to_string(&lineage)?)?;
+    Ok(())
+}
+
+pub fn read(metadata_dir: impl AsRef<Path>) -> Result<Option<Lineage>> {
+    let path = metadata_dir.as_ref().join(LINEAGE_FILE);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// Returns the revision of a template, or `None` for templates made before revisions were tracked
+pub fn template_revision(metadata_dir: impl AsRef<Path>) -> Result<Option<u32>> {
+    let path = metadata_dir.as_ref().join(TEMPLATE_FILE);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let metadata: TemplateMetadata = toml::from_str(&fs::read_to_string(path)?)?;
+    Ok(Some(metadata.revision))
+}
+
+/// Sets a template to its next revision, returning the new revision
+pub fn bump_template_revision(
+    metadata_dir: impl AsRef<Path>,
+    previous: Option<u32>,
+) -> Result<u32> {
+    let metadata_dir = metadata_dir.as_ref();
+    let revision = previous.map_or(1, |revision| revision + 1);
+
+    fs::create_dir_all(metadata_dir)?;
+    fs::write(
+        metadata_dir.join(TEMPLATE_FILE),
+        toml::to_string(&TemplateMetadata { revision })?,
+    )?;
+
+    Ok(revision)
+}
@@ -0,0 +1,232 @@
+//! Parses server log lines, so that they can be colored and filtered by level. The formats differ
+//! slightly between platforms:
+//!
+//! - vanilla and Paper: `[12:34:56] [Server thread/INFO]: message`
+//! - the Paper console: `[12:34:56 INFO]: message`
+//! - Fabric: `[12:34:56] [main/INFO] (Minecraft) message`
+//! - Forge: `[15Mar2024 12:34:56.789] [main/INFO] [net.minecraft.server.Main/]: message`
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    fn parse(level: &str) -> Option<Self> {
+        match level {
+            "TRACE" => Some(Self::Trace),
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARN" | "WARNING" => Some(Self::Warn),
+            "ERROR" | "SEVERE" => Some(Self::Error),
+            "FATAL" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LogLine<'a> {
+    pub time: &'a str,
+    pub thread: Option<&'a str>,
+    pub level: Level,
+    pub message: &'a str,
+}
+
+/// Takes a leading `[…]` group, returning its contents and what follows it
+fn bracketed(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix('[')?;
+    let end = rest.find(']')?;
+    Some((&rest[..end], &rest[end + 1..]))
+}
+
+/// Parses a line in any of the known formats, returning `None` for anything else, such as the
+/// lines of a stacktrace
+pub fn parse(line: &str) -> Option<LogLine<'_>> {
+    let (first, rest) = bracketed(line)?;
+
+    // the Paper console puts the level in the same brackets as the time
+    if let Some((time, level)) = first.rsplit_once(' ')
+        && let Some(level) = Level::parse(level)
+        && let Some(message) = rest.strip_prefix(": ")
+    {
+        return Some(LogLine {
+            time,
+            thread: None,
+            level,
+            message,
+        });
+    }
+
+    let (source, rest) = bracketed(rest.strip_prefix(' ')?)?;
+    let (thread, level) = source.rsplit_once('/')?;
+    let level = Level::parse(level)?;
+
+    // Forge adds the logger in brackets, which is kept as part of the message
+    let message = rest
+        .strip_prefix(": ")
+        .or_else(|| rest.strip_prefix(' '))
+        .unwrap_or(rest);
+
+    Some(LogLine {
+        time: first,
+        thread: Some(thread),
+        level,
+        message,
+    })
+}
+
+/// Whether a line continues a stacktrace started by an earlier line
+pub fn is_stacktrace(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    (line.starts_with(char::is_whitespace)
+        && (trimmed.starts_with("at ") || trimmed.starts_with("...")))
+        || trimmed.starts_with("Caused by: ")
+        || trimmed.starts_with("Suppressed: ")
+}
+
+/// Returns the ANSI color a line is shown in: its level's color, dim for stacktraces, or none
+pub fn color(line: &str) -> Option<&'static str> {
+    let Some(parsed) = parse(line) else {
+        return is_stacktrace(line).then_some("\x1b[2m");
+    };
+
+    match parsed.level {
+        Level::Warn => Some("\x1b[33m"),
+        Level::Error | Level::Fatal => Some("\x1b[31m"),
+        Level::Trace | Level::Debug | Level::Info => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line<'a>(
+        time: &'a str,
+        thread: Option<&'a str>,
+        level: Level,
+        message: &'a str,
+    ) -> LogLine<'a> {
+        LogLine {
+            time,
+            thread,
+            level,
+            message,
+        }
+    }
+
+    #[test]
+    fn parses_every_platform_format() {
+        let cases = [
+            (
+                "[12:34:56] [Server thread/INFO]: Done (3.141s)! For help, type \"help\"",
+                line(
+                    "12:34:56",
+                    Some("Server thread"),
+                    Level::Info,
+                    "Done (3.141s)! For help, type \"help\"",
+                ),
+            ),
+            (
+                "[12:34:56 WARN]: Can't keep up! Is the server overloaded?",
+                line(
+                    "12:34:56",
+                    None,
+                    Level::Warn,
+                    "Can't keep up! Is the server overloaded?",
+                ),
+            ),
+            (
+                "[12:34:56] [main/INFO] (Minecraft) Loading Minecraft 1.21.1 with Fabric Loader",
+                line(
+                    "12:34:56",
+                    Some("main"),
+                    Level::Info,
+                    "(Minecraft) Loading Minecraft 1.21.1 with Fabric Loader",
+                ),
+            ),
+            (
+                "[15Mar2024 12:34:56.789] [main/ERROR] [net.minecraft.server.Main/]: Failed",
+                line(
+                    "15Mar2024 12:34:56.789",
+                    Some("main"),
+                    Level::Error,
+                    "[net.minecraft.server.Main/]: Failed",
+                ),
+            ),
+            (
+                "[00:00:01] [Server-Worker-1/SEVERE]: Exception ticking world",
+                line(
+                    "00:00:01",
+                    Some("Server-Worker-1"),
+                    Level::Error,
+                    "Exception ticking world",
+                ),
+            ),
+            (
+                "[00:00:01] [Worker/Main/WARNING]: slashes in the thread",
+                line(
+                    "00:00:01",
+                    Some("Worker/Main"),
+                    Level::Warn,
+                    "slashes in the thread",
+                ),
+            ),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse(text), Some(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        for text in [
+            "",
+            "java.lang.NullPointerException: null",
+            "\tat net.minecraft.server.Main.main(Main.java:1)",
+            "[12:34:56] no source",
+            "[12:34:56] [Server thread/NOTICE]: unknown level",
+            "[12:34:56 INFO] missing colon",
+            "[unterminated",
+        ] {
+            assert_eq!(parse(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn detects_stacktrace_lines() {
+        assert!(is_stacktrace(
+            "\tat net.minecraft.server.Main.main(Main.java:1)"
+        ));
+        assert!(is_stacktrace("    ... 12 more"));
+        assert!(is_stacktrace("Caused by: java.io.IOException"));
+        assert!(is_stacktrace("\tSuppressed: java.io.IOException"));
+        assert!(!is_stacktrace("at the start of a line"));
+        assert!(!is_stacktrace("[12:34:56] [Server thread/INFO]: at spawn"));
+    }
+
+    #[test]
+    fn colors_by_level() {
+        assert_eq!(color("[12:34:56 INFO]: hello"), None);
+        assert_eq!(color("[12:34:56 WARN]: hello"), Some("\x1b[33m"));
+        assert_eq!(color("[12:34:56] [main/FATAL]: hello"), Some("\x1b[31m"));
+        assert_eq!(color("\tat Main.main(Main.java:1)"), Some("\x1b[2m"));
+        assert_eq!(color("plain output"), None);
+    }
+
+    #[test]
+    fn levels_are_ordered_by_severity() {
+        assert!(Level::Trace < Level::Debug);
+        assert!(Level::Info < Level::Warn);
+        assert!(Level::Error < Level::Fatal);
+    }
+}
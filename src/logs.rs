@@ -0,0 +1,168 @@
+//! Shows a server's `logs/latest.log`, colored by level and filtered by level or pattern.
+
+use crate::{
+    error::{Error, Result},
+    logformat::{self, Level},
+    server::get_server_dir_required,
+};
+use regex::Regex;
+use std::{
+    fs::{self, File},
+    io::{self, IsTerminal, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+pub const DEFAULT_LINES: usize = 20;
+
+/// How often a followed log is checked for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Decides which lines are shown and how, following which entry each line belongs to so that the
+/// lines of a stacktrace are kept or dropped along with the entry that logged it
+struct Renderer {
+    min_level: Option<Level>,
+    pattern: Option<Regex>,
+    color: bool,
+    entry_level: Option<Level>,
+}
+
+impl Renderer {
+    fn keeps(&mut self, line: &str) -> bool {
+        // unformatted lines continue the entry before them, such as its stacktrace
+        if let Some(parsed) = logformat::parse(line) {
+            self.entry_level = Some(parsed.level);
+        }
+
+        let level_kept = self
+            .min_level
+            .is_none_or(|min_level| self.entry_level.is_some_and(|level| level >= min_level));
+        let pattern_kept = self
+            .pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(line));
+
+        level_kept && pattern_kept
+    }
+
+    fn print(&self, line: &str) {
+        match logformat::color(line).filter(|_| self.color) {
+            Some(color) => println!("{color}{line}\x1b[0m"),
+            None => println!("{line}"),
+        }
+    }
+
+    /// Prints the kept lines among complete lines of text
+    fn print_kept(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.keeps(line) {
+                self.print(line);
+            }
+        }
+    }
+}
+
+/// The length of the complete lines in some bytes, leaving out a line still being written
+fn complete_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |end| end + 1)
+}
+
+/// A log being followed from some offset, holding on to a line still being written
+struct Tail {
+    file: File,
+    offset: u64,
+    pending: Vec<u8>,
+}
+
+impl Tail {
+    fn print_new(&mut self, renderer: &mut Renderer) -> Result<()> {
+        // a truncated log starts over
+        if self.file.metadata()?.len() < self.offset {
+            self.offset = 0;
+            self.pending.clear();
+        }
+
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        self.offset += self.file.read_to_end(&mut self.pending)? as u64;
+
+        let complete = complete_len(&self.pending);
+        renderer.print_kept(&String::from_utf8_lossy(&self.pending[..complete]));
+        self.pending.drain(..complete);
+        Ok(())
+    }
+}
+
+fn follow(path: &Path, mut tail: Tail, mut renderer: Renderer) -> Result<()> {
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        tail.print_new(&mut renderer)?;
+
+        // the server starts a new log by moving the old one away, which is finished first
+        if let Ok(metadata) = fs::metadata(path)
+            && metadata.ino() != tail.file.metadata()?.ino()
+        {
+            tail = Tail {
+                file: File::open(path)?,
+                offset: 0,
+                pending: vec![],
+            };
+            tail.print_new(&mut renderer)?;
+        }
+    }
+}
+
+/// Prints the last `lines` lines of a server's log that pass the filters, then keeps printing new
+/// ones as they are written if `follow` is set
+pub fn show(
+    server: &str,
+    lines: usize,
+    follow_log: bool,
+    min_level: Option<Level>,
+    grep: Option<&str>,
+) -> Result<()> {
+    let path = get_server_dir_required(server)?
+        .join("logs")
+        .join("latest.log");
+
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::MissingFile { file: path });
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = vec![];
+    file.read_to_end(&mut contents)?;
+    let complete = complete_len(&contents);
+    let text = String::from_utf8_lossy(&contents[..complete]);
+
+    let mut renderer = Renderer {
+        min_level,
+        pattern: grep.map(Regex::new).transpose()?,
+        color: io::stdout().is_terminal(),
+        entry_level: None,
+    };
+
+    // the filters apply before the count, so that the count is of matching lines
+    let kept: Vec<&str> = text.lines().filter(|line| renderer.keeps(line)).collect();
+    for line in &kept[kept.len().saturating_sub(lines)..] {
+        renderer.print(line);
+    }
+
+    if follow_log {
+        let tail = Tail {
+            file,
+            offset: complete as u64,
+            pending: vec![],
+        };
+        follow(&path, tail, renderer)?;
+    }
+
+    Ok(())
+}
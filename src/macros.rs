@@ -0,0 +1,148 @@
+use crate::{
+    config::{get_config_directory, has_rcon_config},
+    error::{Error, Result},
+    server::{get_server_dir_required, rcon_output},
+    session,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+const MACROS_FILE: &str = "macros.toml";
+const PLACEHOLDERS: [&str; 2] = ["server", "server_dir"];
+
+#[derive(Default, Deserialize, Serialize)]
+struct MacroFile {
+    #[serde(default)]
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Rcon,
+    Session,
+}
+
+fn get_macros_file() -> Result<PathBuf> {
+    Ok(get_config_directory()?.join(MACROS_FILE))
+}
+
+fn read_macros() -> Result<MacroFile> {
+    let path = get_macros_file()?;
+
+    if !path.exists() {
+        return Ok(MacroFile::default());
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_macros(macros: &MacroFile) -> Result<()> {
+    fs::create_dir_all(get_config_directory()?)?;
+    fs::write(get_macros_file()?, toml::to_string(macros)?)?;
+    Ok(())
+}
+
+/// Returns the first placeholder in a command that isn't one of [`PLACEHOLDERS`]
+fn find_unknown_placeholder(command: &str) -> Option<String> {
+    let mut rest = command;
+
+    while let Some((_, after)) = rest.split_once('{') {
+        let (placeholder, after) = after.split_once('}')?;
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Some(placeholder.to_string());
+        }
+        rest = after;
+    }
+
+    None
+}
+
+fn substitute(command: &str, server: &str, server_dir: &Path) -> String {
+    command
+        .replace("{server}", server)
+        .replace("{server_dir}", &server_dir.to_string_lossy())
+}
+
+/// Saves a macro from a file, or from stdin when no file is given, with one command per line
+pub fn save(name: &str, from_file: Option<&Path>) -> Result<()> {
+    let contents = match from_file {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut contents = String::new();
+            io::stdin().read_to_string(&mut contents)?;
+            contents
+        }
+    };
+
+    let commands: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+
+    if let Some(placeholder) = commands.iter().find_map(|c| find_unknown_placeholder(c)) {
+        return Err(Error::UnknownPlaceholder(placeholder));
+    }
+
+    let count = commands.len();
+    let mut macros = read_macros()?;
+    macros.macros.insert(name.to_string(), commands);
+    write_macros(&macros)?;
+
+    println!("Saved macro {name} ({count} command(s))");
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    for (name, commands) in read_macros()?.macros {
+        println!("{name} ({} command(s))", commands.len());
+    }
+
+    Ok(())
+}
+
+pub fn run(name: &str, server: &str, target: Option<Target>, dry_run: bool) -> Result<()> {
+    let macros = read_macros()?;
+    let commands = macros
+        .macros
+        .get(name)
+        .ok_or_else(|| Error::MacroNotFound(name.to_string()))?;
+
+    let server_dir = get_server_dir_required(server)?;
+    let target = match target {
+        Some(target) => target,
+        None if has_rcon_config(server)? => Target::Rcon,
+        None => Target::Session,
+    };
+
+    if target == Target::Session && !dry_run {
+        session::ensure_registered(server, false)?;
+    }
+
+    let session_name = session::get_name(server);
+
+    for command in commands {
+        let command = substitute(command, server, &server_dir);
+
+        if dry_run {
+            println!("{command}");
+            continue;
+        }
+
+        match target {
+            Target::Rcon => match rcon_output(server, &command) {
+                Ok(response) => println!("[\x1b[32;1mok\x1b[0m] {command}: {response}"),
+                Err(err) => println!("[\x1b[31;1mfail\x1b[0m] {command}: {err}"),
+            },
+            Target::Session => session::write_line(&session_name, &command)?,
+        }
+    }
+
+    Ok(())
+}
@@ -1,36 +1,235 @@
+mod backup;
+mod buildinfo;
+mod buildtools;
+mod checksum;
 mod cli;
+mod compat;
+mod completion;
 mod config;
 mod config_defs;
+mod containerize;
+mod copy;
+mod docs;
+mod doctor;
+mod downgrade;
+mod environment;
 mod error;
+mod fleet;
+mod groups;
+mod hooks;
+mod httpcache;
+mod humanize;
+mod ignore;
+mod import;
+mod info;
+mod java;
+mod launch_script;
+mod lineage;
+mod logformat;
+mod logging;
+mod logs;
+mod macros;
+mod maintenance;
+mod manifest;
+mod modrinth;
+mod names;
+mod nbt;
+mod open;
+mod permissions;
 mod platforms;
+mod players;
+mod plugins;
+mod pregen;
+mod profiles;
+mod progress;
+mod properties;
+mod provenance;
+mod quilt;
+mod ratelimit;
+mod rcon;
+mod registry;
+mod repair;
+mod report;
+mod safe_edit;
+mod schedule;
 mod server;
 mod session;
+mod sizes;
+mod space;
+mod startup;
+mod stats;
+mod subprocess;
+#[cfg(test)]
+mod testing;
+mod textdiff;
+mod timeutil;
+mod worlds;
 
 use clap::Parser;
 use cli::*;
-use color_eyre::eyre::{Result, WrapErr};
+use color_eyre::eyre::{Result, WrapErr, eyre};
+use compat::CompatCheck;
+use platforms::{Platform, VersionSpec};
+use report::Recorder;
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+};
+
+/// Joins console commands into one when `--one` is passed, so that multi-word commands don't
+/// need quoting
+fn join_commands(commands: Vec<String>, one: bool) -> Vec<String> {
+    if one && !commands.is_empty() {
+        vec![commands.join(" ")]
+    } else {
+        commands
+    }
+}
+
+fn edit_player_list(list: players::PlayerList, action: PlayerListCommands) -> Result<()> {
+    match action {
+        PlayerListCommands::Add {
+            player,
+            server,
+            online_uuid,
+            offline_uuid,
+        } => {
+            let mode = if online_uuid {
+                Some(players::UuidMode::Online)
+            } else if offline_uuid {
+                Some(players::UuidMode::Offline)
+            } else {
+                None
+            };
+            players::add(&unwrap_server_or_default!(server)?, list, &player, mode)?
+        }
+        PlayerListCommands::Remove { player, server } => {
+            players::remove(&unwrap_server_or_default!(server)?, list, &player)?
+        }
+    }
+
+    Ok(())
+}
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let args = Cli::parse();
+    logging::set_verbose(args.verbose);
+    space::set_ignored(args.ignore_space);
+    httpcache::set_disabled(args.no_http_cache);
+    ratelimit::set_limit(args.limit_rate);
+    subprocess::set_timeout(args.timeout);
+    let reporter = progress::reporter(args.json_lines);
+
+    if let Some(path) = args.config {
+        config::set_config_file(path).wrap_err("Failed to use configuration file")?;
+    }
+
+    if args.command.is_destructive() && config::is_restricted()? {
+        return Err(error::Error::RestrictedMode.into());
+    }
 
     match args.command {
-        Commands::Attach { server } => session::attach(unwrap_server_or_default!(server)?)
-            .wrap_err("Failed to attach to session session")?,
+        Commands::Attach { server, force } => {
+            session::attach(unwrap_server_or_default!(server)?, force)
+                .wrap_err("Failed to attach to session session")?
+        }
+        Commands::Backup { action } => match action {
+            BackupCommands::Create {
+                server,
+                incremental,
+            } => {
+                let server = server::server_or_current(server)?;
+                backup::create(&server, incremental)
+                    .wrap_err_with(|| format!("Failed to back up {server}"))?
+            }
+            BackupCommands::List { server } => {
+                let server = server::server_or_current(server)?;
+                backup::list(&server)
+                    .wrap_err_with(|| format!("Failed to list backups of {server}"))?
+            }
+            BackupCommands::Verify {
+                server,
+                backup,
+                latest: _,
+                all,
+                restore_test,
+            } => {
+                let server = server::server_or_current(server)?;
+                let selection = match backup {
+                    Some(name) => backup::Selection::Named(name),
+                    None if all => backup::Selection::All,
+                    None => backup::Selection::Latest,
+                };
+                backup::verify(&server, selection, restore_test)
+                    .wrap_err_with(|| format!("Failed to verify backups of {server}"))?
+            }
+        },
+        Commands::Cache { action } => match action {
+            CacheCommands::Clear => {
+                let files = httpcache::clear().wrap_err("Failed to clear the cache")?;
+                println!("Removed {files} cached file(s)");
+            }
+        },
+        Commands::Command { server } => {
+            let server = unwrap_server_or_default!(server)?;
+            println!("{}", server::get_printable_command(&server)?);
+        }
+        Commands::Compat {
+            server,
+            target_version,
+        } => {
+            let server = server::server_or_current(server)?;
+            let report = compat::check(&server, &target_version)
+                .wrap_err("Failed to check compatibility")?;
+            compat::print_report(&server, &target_version, &report);
+        }
+        Commands::Complete { kind, platform } => {
+            completion::print_candidates(&kind, platform.as_deref());
+            // completion runs on every keystroke, and must not fail because of the configuration
+            return Ok(());
+        }
+        Commands::Completions { shell } => print!("{}", completion::script(shell)),
         Commands::Config { config_type } => match config_type {
             ConfigType::Static => println!("{:#?}", config::get_static()),
-            ConfigType::Dynamic => println!("{:#?}", config::get()?),
+            ConfigType::Dynamic { migrations: false } => println!("{:#?}", config::snapshot()?),
+            ConfigType::Dynamic { migrations: true } => config::print_migrations()?,
         },
+        Commands::Containerize {
+            server,
+            image,
+            compose,
+            force,
+        } => containerize::generate(&unwrap_server_or_default!(server)?, &image, compose, force)
+            .wrap_err("Failed to containerize server")?,
+        Commands::Current { path } => {
+            let server = server::server_or_current(".").wrap_err("Not in a server")?;
+            if path {
+                println!("{}", server::get_server_dir_required(&server)?.display());
+            } else {
+                println!("{server}");
+            }
+        }
         Commands::Default { action } => match action {
             DefaultCommands::Get => {
-                if let Some(default_server) = &config::get()?.default_server {
+                if let Some(default_server) = &config::snapshot()?.default_server {
                     println!("\"{default_server}\"")
                 } else {
                     println!("None")
                 }
+
+                if let Some(env_server) = config::get_env_server() {
+                    eprintln!(
+                        "Note: {} is set to \"{env_server}\", which takes precedence",
+                        config::SERVER_ENV_VAR
+                    );
+                }
+            }
+            DefaultCommands::Set { server } => {
+                let server = server::server_or_current(server)?;
+                config::update(|config| config.default_server = Some(server))?
             }
-            DefaultCommands::Set { server } => config::get()?.default_server = Some(server),
         },
         Commands::DeleteAllSessions { force } => if force {
             session::delete_all()
@@ -42,20 +241,177 @@ fn main() -> Result<()> {
             session::delete_server_session(unwrap_server_or_default!(session)?, force)
                 .wrap_err("Failed to delete session")?
         }
-        Commands::Deploy { server } => {
+        Commands::Deploy {
+            server,
+            print_command,
+            skip_verify,
+            allow_downgrade,
+            wait_ready,
+            ready_timeout,
+        } => {
+            let wait_ready = wait_ready
+                .then(|| timeutil::parse_duration(&ready_timeout))
+                .transpose()?;
+            if let Some(group) = server.as_deref().and_then(groups::group_name) {
+                groups::for_each(group, groups::Order::Listed, |server| {
+                    if print_command {
+                        println!("{}", server::get_printable_command(server)?);
+                        Ok(())
+                    } else {
+                        server::deploy(server, skip_verify, allow_downgrade, wait_ready)
+                    }
+                })
+                .wrap_err_with(|| format!("Failed to deploy group {group}"))?;
+            } else {
+                let server = unwrap_server_or_default!(server)?;
+                if print_command {
+                    println!("{}", server::get_printable_command(&server)?);
+                } else {
+                    server::deploy(&server, skip_verify, allow_downgrade, wait_ready)
+                        .wrap_err_with(|| format!("Failed to deploy {server}"))?;
+                }
+            }
+        }
+        Commands::Doctor { fix, dry_run } => {
+            let problems = doctor::run(fix, dry_run).wrap_err("Failed to run checks")?;
+            if problems > 0 && fix && !dry_run {
+                return Err(eyre!("{problems} problem(s) left unfixed"));
+            } else if problems > 0 {
+                return Err(eyre!("{problems} problem(s) found"));
+            }
+        }
+        Commands::Execute {
+            server,
+            one,
+            at,
+            within,
+            commands,
+        } => {
+            let commands = join_commands(commands, one);
+            let delay = schedule::delay(at.as_deref(), within.as_deref())?;
+            if let Some((delay, target)) = &delay {
+                schedule::wait(*delay, target);
+            }
+
+            let execute = |server: &str| -> error::Result<()> {
+                if delay.is_some() {
+                    schedule::ensure_session_alive(server)?;
+                }
+                session::ensure_registered(server, false)?;
+                let session_name = session::get_name(server);
+                for command in &commands {
+                    session::write_line(&session_name, command)?;
+                }
+                Ok(())
+            };
+
+            if let Some(group) = server.as_deref().and_then(groups::group_name) {
+                groups::for_each(group, groups::Order::Listed, execute)
+                    .wrap_err_with(|| format!("Failed to execute on group {group}"))?;
+            } else {
+                execute(&unwrap_server_or_default!(server)?)?;
+            }
+        }
+        Commands::Fleet { action } => match action {
+            FleetCommands::Check { json, skip, report } => {
+                let recorder = Recorder::new(reporter.as_ref())?;
+                let result = fleet::check(&skip, json, &recorder);
+                if let Some(path) = report {
+                    recorder
+                        .write(&path, "fleet check", &result)
+                        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+                }
+                result.wrap_err("The fleet is not ready")?
+            }
+        },
+        Commands::FixPermissions { server } => {
             let server = unwrap_server_or_default!(server)?;
-            session::new_server(&server, Some(server::get_command(&server)?))?;
+            permissions::fix(&server)
+                .wrap_err_with(|| format!("Failed to fix the permissions of {server}"))?
         }
-        Commands::Execute { server, commands } => {
-            let session_name = session::get_name(unwrap_server_or_default!(server)?);
-            for command in commands {
-                session::write_line(&session_name, command)?;
+        Commands::Group { action } => match action {
+            GroupCommands::List => groups::list().wrap_err("Failed to list groups")?,
+            GroupCommands::Add { group, servers } => groups::add(&group, servers)
+                .wrap_err_with(|| format!("Failed to add to group {group}"))?,
+            GroupCommands::Remove { group, servers } => groups::remove(&group, servers)
+                .wrap_err_with(|| format!("Failed to remove from group {group}"))?,
+        },
+        Commands::Ignore { action } => match action {
+            IgnoreCommands::Edit { server } => {
+                let server = unwrap_server_or_default!(server)?;
+                open::open(
+                    &server,
+                    open::Tool::Editor,
+                    Some(Path::new(ignore::IGNORE_FILE)),
+                )
+                .wrap_err_with(|| format!("Failed to edit the ignore file of {server}"))?
+            }
+            IgnoreCommands::Test { server, path } => {
+                let server = server::server_or_current(server)?;
+                if ignore::is_ignored(&server, &path)? {
+                    println!("{} is ignored", path.display());
+                } else {
+                    println!("{} is not ignored", path.display());
+                }
+            }
+        },
+        Commands::Import { action } => match action {
+            ImportCommands::Bulk {
+                manifest,
+                r#move,
+                link,
+            } => {
+                let mode = if r#move {
+                    import::ImportMode::Move
+                } else if link {
+                    import::ImportMode::Link
+                } else {
+                    import::ImportMode::Copy
+                };
+                import::bulk(&manifest, mode).wrap_err("Failed to import servers")?
+            }
+        },
+        Commands::Java { action } => match action {
+            JavaCommands::List => java::list().wrap_err("Failed to list Java runtimes")?,
+        },
+        Commands::LaunchScript { action } => match action {
+            LaunchScriptCommands::Init { server, force } => {
+                let server = unwrap_server_or_default!(server)?;
+                let path = launch_script::init(&server, force)
+                    .wrap_err_with(|| format!("Failed to write a launch script for {server}"))?;
+                println!("Wrote {}", path.display());
+            }
+        },
+        Commands::Internal { action } => {
+            let written = match action {
+                InternalCommands::GenMan { dir } => docs::generate_man(&dir),
+                InternalCommands::GenMarkdown { dir } => docs::generate_markdown(&dir),
+            }
+            .wrap_err("Failed to generate documentation")?;
+
+            for path in written {
+                println!("{}", path.display());
             }
         }
+        Commands::Info { server } => {
+            info::print(&unwrap_server_or_default!(server)?).wrap_err("Failed to get info")?
+        }
+        Commands::Logs {
+            server,
+            lines,
+            follow,
+            level,
+            grep,
+        } => {
+            let server = unwrap_server_or_default!(server)?;
+            logs::show(&server, lines, follow, level, grep.as_deref())
+                .wrap_err_with(|| format!("Failed to show the log of {server}"))?
+        }
         Commands::List {
             active,
             inactive,
             dead,
+            names_only,
         } => {
             let mut servers = vec![];
             server::for_each(|s| servers.push(server::ServerObject::new(s)))
@@ -76,38 +432,361 @@ fn main() -> Result<()> {
                 server::fully_tag_servers(&mut servers).wrap_err("Failed to tag active servers")?;
             }
 
+            if !names_only {
+                server::tag_bedrock(&mut servers).wrap_err("Failed to tag Bedrock servers")?;
+                server::tag_failing(&mut servers);
+            }
+
             for server in servers {
-                println!("{server}");
+                if names_only {
+                    println!("{}", server.name);
+                } else {
+                    println!("{server}");
+                }
             }
         }
-        Commands::Rcon { server, commands } => {
-            server::rcon(unwrap_server_or_default!(server)?, commands)
-                .wrap_err("Failed to run rcon command")?
+        Commands::Macro { action } => match action {
+            MacroCommands::Save { name, from_file } => macros::save(&name, from_file.as_deref())
+                .wrap_err_with(|| format!("Failed to save macro {name}"))?,
+            MacroCommands::List => macros::list().wrap_err("Failed to list macros")?,
+            MacroCommands::Run {
+                name,
+                server,
+                rcon,
+                session,
+                dry_run,
+            } => {
+                let server = unwrap_server_or_default!(server)?;
+                let target = if rcon {
+                    Some(macros::Target::Rcon)
+                } else if session {
+                    Some(macros::Target::Session)
+                } else {
+                    None
+                };
+                macros::run(&name, &server, target, dry_run)
+                    .wrap_err_with(|| format!("Failed to run macro {name}"))?
+            }
+        },
+        Commands::Maintenance { action } => match action {
+            MaintenanceCommands::On { server, motd } => {
+                let server = unwrap_server_or_default!(server)?;
+                maintenance::enable(&server, motd)
+                    .wrap_err_with(|| format!("Failed to enable maintenance mode for {server}"))?
+            }
+            MaintenanceCommands::Off { server } => {
+                let server = unwrap_server_or_default!(server)?;
+                maintenance::disable(&server)
+                    .wrap_err_with(|| format!("Failed to disable maintenance mode for {server}"))?
+            }
+            MaintenanceCommands::Status { server } => {
+                let server = unwrap_server_or_default!(server)?;
+                maintenance::status(&server).wrap_err("Failed to get maintenance status")?
+            }
+        },
+        Commands::Op { action } => edit_player_list(players::PlayerList::Ops, action)
+            .wrap_err("Failed to edit the operators")?,
+        Commands::Open {
+            server,
+            editor,
+            path,
+            ..
+        } => {
+            let server = unwrap_server_or_default!(server)?;
+            let tool = if editor {
+                open::Tool::Editor
+            } else {
+                open::Tool::Files
+            };
+            open::open(&server, tool, path.as_deref())
+                .wrap_err_with(|| format!("Failed to open {server}"))?
+        }
+        Commands::Plugins { action } => match action {
+            PluginsCommands::Sync {
+                source,
+                target,
+                dry_run,
+                prune,
+                rolling_restart,
+                restart_timeout,
+            } => {
+                let restart_timeout = rolling_restart
+                    .then(|| timeutil::parse_duration(&restart_timeout))
+                    .transpose()?;
+                plugins::sync(&source, &target, dry_run, prune, restart_timeout).wrap_err_with(
+                    || format!("Failed to sync the plugins of {source} to {target}"),
+                )?
+            }
+        },
+        Commands::Pregen {
+            action,
+            server,
+            radius,
+            world,
+            detach,
+        } => match action {
+            Some(PregenCommands::Status { server }) => {
+                pregen::status(&unwrap_server_or_default!(server)?)
+                    .wrap_err("Failed to get pre-generation status")?
+            }
+            Some(PregenCommands::Cancel { server }) => {
+                pregen::cancel(&unwrap_server_or_default!(server)?)
+                    .wrap_err("Failed to cancel pre-generation")?
+            }
+            None => {
+                let radius = radius.expect("Clap ensures a radius is provided");
+                pregen::start(
+                    &unwrap_server_or_default!(server)?,
+                    radius,
+                    world.as_deref(),
+                    detach,
+                    reporter.as_ref(),
+                )
+                .wrap_err("Failed to pre-generate world")?
+            }
+        },
+        Commands::Rcon {
+            server,
+            one,
+            at,
+            within,
+            host,
+            port,
+            password,
+            password_env,
+            commands,
+        } => {
+            let endpoint = rcon::endpoint_from_flags(host, port, password, password_env)?;
+
+            // an endpoint given outright belongs to no server, so the first argument is a command
+            let (server, commands) = match endpoint {
+                Some(_) => (None, server.into_iter().chain(commands).collect()),
+                None => (server, commands),
+            };
+
+            let commands = join_commands(commands, one);
+            let delay = schedule::delay(at.as_deref(), within.as_deref())?;
+            if let Some((delay, target)) = &delay {
+                schedule::wait(*delay, target);
+            }
+
+            if let Some(endpoint) = endpoint {
+                if delay.is_some() {
+                    let (address, port) = rcon::address(&endpoint);
+                    schedule::ensure_rcon_reachable(&format!("{address}:{port}"), &endpoint)?;
+                }
+                server::rcon_to(&endpoint, &commands).wrap_err("Failed to run rcon command")?;
+                return Ok(());
+            }
+
+            let send = |server: &str| -> error::Result<()> {
+                let endpoint = rcon::resolve(server)?;
+                if delay.is_some() {
+                    schedule::ensure_rcon_reachable(server, &endpoint)?;
+                }
+                server::rcon_to(&endpoint, &commands)
+            };
+
+            if let Some(group) = server.as_deref().and_then(groups::group_name) {
+                groups::for_each(group, groups::Order::Listed, send)
+                    .wrap_err_with(|| format!("Failed to run rcon command on group {group}"))?
+            } else {
+                send(&unwrap_server_or_default!(server)?).wrap_err("Failed to run rcon command")?
+            }
+        }
+        Commands::RconTest { servers, all, json } => {
+            rcon::test(servers, all, json).wrap_err("Some rcon endpoints failed")?
         }
         Commands::New {
             platform,
+            jar,
+            url,
             version,
+            build,
             name,
-        } => server::create_new(platform, version, name)
-            .wrap_err(format!("Failed to create {platform} server"))?,
-        Commands::Remove { servers, force } => if force {
-            server::remove_servers(servers)
+            no_clobber_session,
+            quiet,
+            json,
+            profile,
+            keep_build_dir,
+            changelog,
+        } => {
+            // an unknown profile is reported before anything is downloaded
+            if let Some(profile) = &profile {
+                profiles::get(profile)?;
+            }
+
+            let created = match (platform, jar, url) {
+                (Some(platform), _, _) => server::create_new(
+                    platform,
+                    VersionSpec::new(version, build),
+                    name,
+                    no_clobber_session,
+                    keep_build_dir,
+                    changelog,
+                    reporter.as_ref(),
+                ),
+                (None, Some(path), _) => server::create_custom(
+                    server::CustomJar::Path(path),
+                    name,
+                    no_clobber_session,
+                    reporter.as_ref(),
+                ),
+                (None, None, Some(url)) => server::create_custom(
+                    server::CustomJar::Url(url),
+                    name,
+                    no_clobber_session,
+                    reporter.as_ref(),
+                ),
+                (None, None, None) => {
+                    unreachable!("Clap ensures a platform, jar or url is provided")
+                }
+            }
+            .wrap_err(format!(
+                "Failed to create {} server",
+                platform.unwrap_or(Platform::Custom)
+            ))?;
+
+            if !reporter.is_human() {
+                // the done event already carries the result
+            } else if json {
+                println!("{}", serde_json::to_string_pretty(&created)?);
+            } else if quiet || !io::stdout().is_terminal() {
+                println!("{}", created.name);
+            } else {
+                println!(
+                    "Created {} ({} {})",
+                    created.name, created.platform, created.version
+                );
+                println!("  Path: {}", created.path.display());
+                println!("  Jar: {}", created.jar);
+            }
+
+            if let Some(profile) = profile {
+                profiles::apply(&created.name, &profile, platform)
+                    .wrap_err_with(|| format!("Failed to apply profile {profile}"))?;
+            }
+        }
+        Commands::Remove {
+            servers,
+            force,
+            allow_current,
+        } => if force {
+            server::remove_servers(servers, allow_current)
         } else {
-            server::remove_servers_with_confirmation(servers)
+            server::remove_servers_with_confirmation(servers, allow_current)
         }
         .wrap_err("Failed to remove server")?,
-        Commands::Restart => server::restart().wrap_err("Failed to restart server")?,
-        Commands::Stop { server } => {
+        Commands::Repair { server, jar, force } => {
             let server = unwrap_server_or_default!(server)?;
-            server::rcon(&server, vec!["stop"])
-                .wrap_err_with(|| format!("Failed to stop server {}", &server))?;
+            let jar = repair::repair(&server, jar, force)
+                .wrap_err_with(|| format!("Failed to repair {server}"))?;
+            println!("Rebuilt the metadata of {server} with {jar}");
+        }
+        Commands::Restart {
+            print_command,
+            skip_verify,
+        } => {
+            if print_command {
+                let (_, server) = server::get_session_server()?;
+                println!("{}", server::get_printable_command(&server)?);
+            } else {
+                server::restart(skip_verify).wrap_err("Failed to restart server")?
+            }
+        }
+        Commands::Sessions { repair, orphans } => if repair {
+            session::repair_registry()
+        } else if orphans {
+            session::list_namespace_orphans()
+        } else {
+            session::list_registry()
+        }
+        .wrap_err("Failed to read the session registry")?,
+        Commands::Size {
+            server,
+            depth,
+            json,
+        } => sizes::print(&unwrap_server_or_default!(server)?, depth, json)
+            .wrap_err("Failed to measure server")?,
+        Commands::Stats { json } => stats::print(json).wrap_err("Failed to collect stats")?,
+        Commands::Stop {
+            server,
+            wait,
+            wait_timeout,
+        } => {
+            let wait = wait
+                .then(|| timeutil::parse_duration(&wait_timeout))
+                .transpose()?;
+            if let Some(group) = server.as_deref().and_then(groups::group_name) {
+                groups::for_each(group, groups::Order::Reversed, |server| {
+                    server::stop(server, wait)
+                })
+                .wrap_err_with(|| format!("Failed to stop group {group}"))?;
+            } else {
+                let server = unwrap_server_or_default!(server)?;
+                server::stop(&server, wait)
+                    .wrap_err_with(|| format!("Failed to stop server {server}"))?;
+            }
         }
         Commands::Template { action } => match action {
-            TemplateCommands::New { server } => server::new_template(&server)
-                .wrap_err_with(|| format!("Failed to create template with server {server}"))?,
-            TemplateCommands::From { template, server } => {
-                server::from_template(&template, server.as_deref())
-                    .wrap_err_with(|| format!("Failed to use template {template}"))?
+            TemplateCommands::New { server } => {
+                let server = server::server_or_current(server)?;
+                let template = server::new_template(&server)
+                    .wrap_err_with(|| format!("Failed to create template with server {server}"))?;
+                println!("{template}");
+            }
+            TemplateCommands::Update { server } => {
+                let server = server::server_or_current(server)?;
+                let (template, revision) = server::update_template(&server)
+                    .wrap_err_with(|| format!("Failed to update template of {server}"))?;
+                println!("Updated {template} to revision {revision}");
+            }
+            TemplateCommands::Children { template } => server::template_children(&template)
+                .wrap_err_with(|| format!("Failed to list children of {template}"))?,
+            TemplateCommands::From {
+                template,
+                server,
+                profile,
+            } => {
+                if let Some(profile) = &profile {
+                    profiles::get(profile)?;
+                }
+
+                let server = server::from_template(&template, server.as_deref())
+                    .wrap_err_with(|| format!("Failed to use template {template}"))?;
+                println!("{server}");
+
+                if let Some(profile) = profile {
+                    profiles::apply(&server, &profile, None)
+                        .wrap_err_with(|| format!("Failed to apply profile {profile}"))?;
+                }
+            }
+        },
+        Commands::Properties { action } => match action {
+            PropertiesCommands::Get { server, key } => {
+                let server = server::server_or_current(server)?;
+                properties::print(&server, &key)
+                    .wrap_err_with(|| format!("Failed to get {key} of {server}"))?
+            }
+            PropertiesCommands::Set {
+                server,
+                key,
+                value,
+                migrate,
+            } => {
+                let server = server::server_or_current(server)?;
+                properties::set(&server, &key, &value, migrate)
+                    .wrap_err_with(|| format!("Failed to set {key} of {server}"))?
+            }
+        },
+        Commands::Profile { action } => match action {
+            ProfileCommands::List => profiles::list().wrap_err("Failed to list profiles")?,
+            ProfileCommands::Export { name, output } => profiles::export(&name, output.as_deref())
+                .wrap_err_with(|| format!("Failed to export profile {name}"))?,
+            ProfileCommands::Import { path, name, force } => {
+                let name = profiles::import(&path, name, force)
+                    .wrap_err_with(|| format!("Failed to import {}", path.display()))?;
+                println!("Imported profile {name}");
             }
         },
         Commands::Reinstall {
@@ -132,11 +811,91 @@ fn main() -> Result<()> {
             server,
             platform,
             version,
-        } => server::update_existing(server, platform, version)
-            .wrap_err("Failed to update server")?,
+            build,
+            all,
+            all_version,
+            manifest,
+            report,
+            check_compat,
+            ignore_compat,
+            changelog,
+            allow_current,
+            force,
+        } => {
+            if let Some(manifest) = manifest {
+                server::apply_manifest(&manifest, allow_current, force, reporter.as_ref())
+                    .wrap_err_with(|| format!("Failed to apply {}", manifest.display()))?
+            } else if let Some(only) = all {
+                let recorder = Recorder::new(reporter.as_ref())?;
+                let result = server::update_all(
+                    only,
+                    VersionSpec::new(all_version, build),
+                    allow_current,
+                    &recorder,
+                );
+                if let Some(path) = report {
+                    recorder
+                        .write(&path, "update --all", &result)
+                        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+                }
+                result.wrap_err("Failed to update servers")?
+            } else if let Some(server) = server {
+                let version = version.or(all_version);
+                let compat_check = match (check_compat, ignore_compat) {
+                    (false, _) => CompatCheck::Skip,
+                    (true, false) => CompatCheck::Enforce,
+                    (true, true) => CompatCheck::Ignore,
+                };
+
+                if let Some(group) = groups::group_name(&server) {
+                    groups::for_each(group, groups::Order::Listed, |server| {
+                        server::update_existing(
+                            server,
+                            platform,
+                            force,
+                            VersionSpec::new(version.clone(), build.clone()),
+                            compat_check,
+                            changelog,
+                            allow_current,
+                            reporter.as_ref(),
+                        )
+                    })
+                    .wrap_err_with(|| format!("Failed to update group {group}"))?
+                } else {
+                    let server = server::server_or_current(server)?;
+                    server::update_existing(
+                        server,
+                        platform,
+                        force,
+                        VersionSpec::new(version, build),
+                        compat_check,
+                        changelog,
+                        allow_current,
+                        reporter.as_ref(),
+                    )
+                    .wrap_err("Failed to update server")?
+                }
+            } else {
+                unreachable!("Clap ensures a server, --all or --manifest is provided")
+            }
+        }
+        Commands::Version { json } => {
+            buildinfo::print(args.verbose, json).wrap_err("Failed to print the version")?
+        }
+        Commands::Versions { platform, limit } => {
+            let versions =
+                platforms::list_versions(platform).wrap_err("Failed to list versions")?;
+            for version in versions.iter().take(limit.unwrap_or(usize::MAX)) {
+                println!("{version}");
+            }
+        }
+        Commands::Whitelist { action } => edit_player_list(players::PlayerList::Whitelist, action)
+            .wrap_err("Failed to edit the whitelist")?,
     };
 
-    config::CONFIG.write()?;
+    config::CONFIG
+        .write()
+        .wrap_err("Failed to save the configuration")?;
 
     Ok(())
 }
@@ -0,0 +1,141 @@
+use crate::{
+    error::{Error, Result},
+    properties::{self, Properties},
+    server::{get_metadata_dir, get_server_dir_required, rcon_output},
+    session::get_alive_server_sessions,
+    textdiff,
+    timeutil::{self, unix_now},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs};
+
+const STATE_FILE: &str = "maintenance.toml";
+pub const DEFAULT_MOTD: &str = "Server is under maintenance";
+
+/// The values that were replaced when maintenance mode was enabled, so that they can be restored
+#[derive(Deserialize, Serialize)]
+struct MaintenanceState {
+    since: u64,
+    previous_motd: Option<String>,
+    previous_whitelist: Option<String>,
+}
+
+/// Parses the names from the output of `list` or `whitelist list`, e.g.
+/// `There are 2 of a max of 20 players online: Alice, Bob`
+fn parse_players(output: &str) -> Vec<String> {
+    output
+        .split_once(':')
+        .map(|(_, players)| {
+            players
+                .split(',')
+                .map(str::trim)
+                .filter(|player| !player.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_online(server: &str) -> Result<bool> {
+    Ok(get_alive_server_sessions()?.contains(server))
+}
+
+fn restore(properties: &mut Properties, key: &str, previous: Option<&String>) {
+    match previous {
+        Some(value) => properties.set(key, value),
+        None => properties.remove(key),
+    }
+}
+
+pub fn enable(server: &str, motd: Option<String>) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let metadata_dir = get_metadata_dir(server)?;
+    let state_path = metadata_dir.join(STATE_FILE);
+
+    if state_path.exists() {
+        return Err(Error::MaintenanceAlreadyActive(server.to_string()));
+    }
+
+    let motd = motd.unwrap_or_else(|| DEFAULT_MOTD.to_string());
+    let mut properties = Properties::read(&server_dir)?;
+
+    let state = MaintenanceState {
+        since: unix_now()?,
+        previous_motd: properties.get("motd").map(String::from),
+        previous_whitelist: properties.get("white-list").map(String::from),
+    };
+
+    fs::create_dir_all(&metadata_dir)?;
+    fs::write(&state_path, toml::to_string(&state)?)?;
+
+    if is_online(server)? {
+        rcon_output(server, "whitelist on")?;
+
+        let whitelisted: HashSet<_> = parse_players(&rcon_output(server, "whitelist list")?)
+            .into_iter()
+            .map(|player| player.to_lowercase())
+            .collect();
+
+        for player in parse_players(&rcon_output(server, "list")?) {
+            if !whitelisted.contains(&player.to_lowercase()) {
+                rcon_output(server, format!("kick {player} {motd}"))?;
+                println!("Kicked {player}");
+            }
+        }
+    }
+
+    properties.set("white-list", "true");
+    properties.set("motd", &motd);
+    properties.write(&server_dir)?;
+
+    println!("Maintenance mode enabled for {server}");
+    Ok(())
+}
+
+pub fn disable(server: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let state_path = get_metadata_dir(server)?.join(STATE_FILE);
+
+    if !state_path.exists() {
+        return Err(Error::MaintenanceNotActive(server.to_string()));
+    }
+
+    let state: MaintenanceState = toml::from_str(&fs::read_to_string(&state_path)?)?;
+    let mut properties = Properties::read(&server_dir)?;
+
+    restore(&mut properties, "motd", state.previous_motd.as_ref());
+    restore(
+        &mut properties,
+        "white-list",
+        state.previous_whitelist.as_ref(),
+    );
+
+    if is_online(server)? && state.previous_whitelist.as_deref() != Some("true") {
+        rcon_output(server, "whitelist off")?;
+    }
+
+    let current = fs::read_to_string(server_dir.join(properties::FILE_NAME)).unwrap_or_default();
+    textdiff::print(properties::FILE_NAME, &current, &properties.contents());
+    properties.write(&server_dir)?;
+    fs::remove_file(state_path)?;
+
+    println!("Maintenance mode disabled for {server}");
+    Ok(())
+}
+
+pub fn status(server: &str) -> Result<()> {
+    let state_path = get_metadata_dir(server)?.join(STATE_FILE);
+
+    if !state_path.exists() {
+        println!("Maintenance mode is not active for {server}");
+        return Ok(());
+    }
+
+    let state: MaintenanceState = toml::from_str(&fs::read_to_string(&state_path)?)?;
+    println!(
+        "Maintenance mode is active for {server} (since {} ago)",
+        timeutil::since(state.since)?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::{
+    error::{Error, Result},
+    platforms::ResolvedArtifact,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// A record of the artifact each server was updated to during a bulk operation, which can be
+/// applied again elsewhere to reproduce exactly the same rollout.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RunManifest {
+    pub version: u32,
+    #[serde(default)]
+    pub servers: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub server: String,
+    pub artifact: ResolvedArtifact,
+}
+
+impl RunManifest {
+    pub fn new() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            servers: vec![],
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let manifest: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        if manifest.version != MANIFEST_VERSION {
+            return Err(Error::UnsupportedManifestVersion(manifest.version));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
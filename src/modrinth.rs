@@ -0,0 +1,101 @@
+use crate::{
+    error::{Error, Result},
+    platforms::get_client,
+};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+const MODRINTH_BASE_API_URL: &str = "https://api.modrinth.com/v2";
+
+pub const PLUGIN_LOADERS: [&str; 5] = ["paper", "purpur", "folia", "spigot", "bukkit"];
+pub const MOD_LOADERS: [&str; 4] = ["fabric", "quilt", "forge", "neoforge"];
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectVersion {
+    pub project_id: String,
+    pub version_number: String,
+    pub game_versions: Vec<String>,
+    pub loaders: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<VersionFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VersionFile {
+    pub url: String,
+    pub filename: String,
+    pub primary: bool,
+}
+
+impl ProjectVersion {
+    fn primary_file(&self) -> Option<&VersionFile> {
+        self.files
+            .iter()
+            .find(|file| file.primary)
+            .or_else(|| self.files.first())
+    }
+}
+
+pub fn sha1_file(path: impl AsRef<Path>) -> Result<String> {
+    Ok(format!("{:x}", Sha1::digest(fs::read(path)?)))
+}
+
+/// Looks up the Modrinth version a jar belongs to by its sha1 hash, returning `None` for jars that
+/// Modrinth doesn't know about
+pub fn version_from_file(path: impl AsRef<Path>) -> Result<Option<ProjectVersion>> {
+    get_client()?.get_json_optional(format!(
+        "{MODRINTH_BASE_API_URL}/version_file/{}?algorithm=sha1",
+        sha1_file(path)?
+    ))
+}
+
+/// Lists the versions of a project that support any of the loaders (and the game version, when
+/// given), newest first
+pub fn project_versions_for(
+    project_id: &str,
+    game_version: Option<&str>,
+    loaders: &[impl AsRef<str>],
+) -> Result<Vec<ProjectVersion>> {
+    let loaders: Vec<_> = loaders.iter().map(AsRef::as_ref).collect();
+
+    let mut url = url::Url::parse(&format!(
+        "{MODRINTH_BASE_API_URL}/project/{project_id}/version"
+    ))?;
+    url.query_pairs_mut()
+        .append_pair("loaders", &serde_json::to_string(&loaders)?);
+
+    if let Some(game_version) = game_version {
+        url.query_pairs_mut()
+            .append_pair("game_versions", &serde_json::to_string(&[game_version])?);
+    }
+
+    Ok(get_client()?.get_json_optional(url)?.unwrap_or_default())
+}
+
+/// Downloads the newest version of a project for any of the loaders into a directory, returning
+/// the path of the downloaded file
+pub fn install_latest(
+    project_id: &str,
+    loaders: &[impl AsRef<str>],
+    directory: impl AsRef<Path>,
+) -> Result<PathBuf> {
+    let versions = project_versions_for(project_id, None, loaders)?;
+    let file = versions
+        .first()
+        .and_then(ProjectVersion::primary_file)
+        .ok_or_else(|| Error::PlatformsNotFound(format!("a release of {project_id}")))?;
+
+    fs::create_dir_all(&directory)?;
+    let path = directory.as_ref().join(&file.filename);
+
+    println!("Downloading {}...", file.filename);
+    let mut response = get_client()?.get_raw(&file.url)?;
+    io::copy(&mut response, &mut File::create(&path)?)?;
+
+    Ok(path)
+}
@@ -0,0 +1,142 @@
+//! Normalizes the names servers are created under. A server's name becomes both a directory and
+//! part of its zellij session name, so it has to be safe as each of them.
+
+use crate::{
+    error::{Error, Result},
+    session,
+};
+
+/// The longest a server name can be in bytes. Zellij keeps a socket per session, named after it,
+/// and socket paths are limited to 108 bytes, so this leaves room for the session suffix and
+/// zellij's socket directory as well as a `-N` suffix added to tell apart servers of one name.
+pub fn max_len() -> usize {
+    64 - session::suffix().len()
+}
+
+/// Characters that are replaced with a dash: path separators, characters that some filesystems
+/// don't allow, and whitespace, which zellij doesn't allow in session names
+fn is_disallowed(c: char) -> bool {
+    matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_whitespace()
+}
+
+/// Normalizes a server name: surrounding whitespace is trimmed, disallowed characters are
+/// replaced with dashes, runs of dashes are collapsed, control characters are dropped, leading
+/// dots and dashes are stripped so that the directory isn't hidden, and the name is cut to
+/// [`max_len`] bytes. Fails if nothing is left.
+pub fn normalize(name: &str) -> Result<String> {
+    normalize_to(name, max_len())
+}
+
+fn normalize_to(name: &str, max_len: usize) -> Result<String> {
+    let mut normalized = String::with_capacity(name.len());
+
+    for c in name.trim().chars() {
+        if c.is_control() {
+            continue;
+        }
+
+        let c = if is_disallowed(c) { '-' } else { c };
+        if c == '-' && normalized.ends_with('-') {
+            continue;
+        }
+
+        if normalized.len() + c.len_utf8() > max_len {
+            break;
+        }
+        normalized.push(c);
+    }
+
+    let normalized = normalized
+        .trim_start_matches(['.', '-'])
+        .trim_end_matches('-');
+
+    if normalized.is_empty() {
+        return Err(Error::InvalidServerName(name.to_string()));
+    }
+
+    Ok(normalized.to_string())
+}
+
+/// Normalizes a name typed by the user, telling them when it was changed
+pub fn normalize_typed(name: &str) -> Result<String> {
+    let normalized = normalize(name)?;
+
+    if normalized != name {
+        eprintln!("Using server name {normalized} for {name:?}");
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The length limit under the default session namespace
+    const MAX_LEN: usize = 64 - ".mcserver".len();
+
+    fn normalize(name: &str) -> Result<String> {
+        normalize_to(name, MAX_LEN)
+    }
+
+    #[test]
+    fn valid_names_are_unchanged() {
+        for name in ["survival", "Creative_2", "mc.1.21", "世界", "🎮-hub"] {
+            assert_eq!(normalize(name).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn disallowed_characters_become_single_dashes() {
+        let cases = [
+            ("my server", "my-server"),
+            ("a/b\\c", "a-b-c"),
+            ("what?*", "what"),
+            ("a : b", "a-b"),
+            ("wide\u{3000}space", "wide-space"),
+            ("a--b", "a-b"),
+            ("<|>x", "x"),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(normalize(name).unwrap(), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn control_characters_are_dropped() {
+        assert_eq!(normalize("sur\x1b[31mvival\n").unwrap(), "sur[31mvival");
+        assert_eq!(normalize("a\u{7f}b").unwrap(), "ab");
+    }
+
+    #[test]
+    fn names_cannot_be_hidden() {
+        assert_eq!(normalize(".hidden").unwrap(), "hidden");
+        assert_eq!(normalize("-.-name").unwrap(), "name");
+        assert_eq!(normalize("  ../etc  ").unwrap(), "etc");
+    }
+
+    #[test]
+    fn long_names_are_cut_at_a_character_boundary() {
+        assert_eq!(normalize(&"a".repeat(100)).unwrap().len(), MAX_LEN);
+
+        // 3 byte characters don't fit exactly
+        let cut = normalize(&"世".repeat(100)).unwrap();
+        assert_eq!(cut.len(), MAX_LEN / 3 * 3);
+        assert!(cut.chars().all(|c| c == '世'));
+
+        // a dash left at the cut is trimmed
+        let name = format!("{}-b", "a".repeat(MAX_LEN - 1));
+        assert_eq!(normalize(&name).unwrap(), "a".repeat(MAX_LEN - 1));
+    }
+
+    #[test]
+    fn empty_names_are_rejected() {
+        for name in ["", "   ", "...", "/", "\u{1}", "-.-"] {
+            assert!(
+                matches!(normalize(name), Err(Error::InvalidServerName(_))),
+                "{name:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! A reader for the NBT format Minecraft saves worlds in, enough to look values up in files such
+//! as `level.dat`. Names and strings are Java's modified UTF-8, which only differs from UTF-8 for
+//! characters no key or version name uses, so they are read as UTF-8.
+
+use flate2::read::GzDecoder;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+/// How deeply lists and compounds may nest, as Minecraft itself refuses deeper files
+const MAX_DEPTH: usize = 512;
+
+/// A tag, keeping the values of only the kinds that are looked up. Others, lists included, are
+/// read past.
+#[derive(Debug)]
+pub enum Tag {
+    Int(i32),
+    String(String),
+    Compound(HashMap<String, Tag>),
+    Other,
+}
+
+impl Tag {
+    /// Looks up a tag through nested compounds, such as `["Data", "Version", "Name"]`
+    pub fn get(&self, path: &[&str]) -> Option<&Tag> {
+        path.iter().try_fold(self, |tag, key| match tag {
+            Tag::Compound(entries) => entries.get(*key),
+            _ => None,
+        })
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            Tag::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn length(&mut self) -> io::Result<usize> {
+        usize::try_from(i32::from_be_bytes(self.bytes()?)).map_err(|_| invalid("negative length"))
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<Tag> {
+        if io::copy(&mut self.inner.by_ref().take(len), &mut io::sink())? < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(Tag::Other)
+    }
+
+    fn skip_array(&mut self, item_size: u64) -> io::Result<Tag> {
+        let len = self.length()? as u64;
+        self.skip(len * item_size)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = u16::from_be_bytes(self.bytes()?);
+        let mut bytes = vec![0; usize::from(len)];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> io::Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("nested too deeply"));
+        }
+
+        Ok(match id {
+            1 => self.skip(1)?,
+            2 => self.skip(2)?,
+            3 => Tag::Int(i32::from_be_bytes(self.bytes()?)),
+            4 | 6 => self.skip(8)?,
+            5 => self.skip(4)?,
+            7 => self.skip_array(1)?,
+            8 => Tag::String(self.string()?),
+            9 => {
+                let [item_id] = self.bytes()?;
+                // items can't be skipped by length alone, as compounds have none
+                for _ in 0..self.length()? {
+                    self.payload(item_id, depth + 1)?;
+                }
+                Tag::Other
+            }
+            10 => {
+                let mut entries = HashMap::new();
+                loop {
+                    let [id] = self.bytes()?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.insert(name, self.payload(id, depth + 1)?);
+                }
+                Tag::Compound(entries)
+            }
+            11 => self.skip_array(4)?,
+            12 => self.skip_array(8)?,
+            _ => return Err(invalid("unknown tag type")),
+        })
+    }
+}
+
+/// Reads an NBT file compressed with gzip, as `level.dat` is, returning its root tag
+pub fn read_gzip(path: &Path) -> io::Result<Tag> {
+    let mut reader = Reader {
+        inner: GzDecoder::new(BufReader::new(File::open(path)?)),
+    };
+
+    let [id] = reader.bytes()?;
+    if id != 10 {
+        return Err(invalid("the root tag is not a compound"));
+    }
+    reader.string()?;
+    reader.payload(id, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, write::GzEncoder};
+    use std::{fs, io::Write, path::PathBuf};
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// Reads uncompressed NBT, compressing it first as `level.dat` is
+    fn read(nbt: &[u8]) -> io::Result<Tag> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut encoder = GzEncoder::new(file.as_file(), Compression::default());
+        encoder.write_all(nbt).unwrap();
+        encoder.finish().unwrap();
+
+        read_gzip(file.path())
+    }
+
+    #[test]
+    fn level_dat_values_are_found() {
+        let root = read_gzip(&fixture("level-1.21.4.dat")).unwrap();
+
+        assert_eq!(
+            root.get(&["Data", "Version", "Name"]).and_then(Tag::as_str),
+            Some("1.21.4")
+        );
+        assert_eq!(
+            root.get(&["Data", "DataVersion"]).and_then(Tag::as_int),
+            Some(4189)
+        );
+        // bytes, longs, doubles, lists and arrays are read past
+        for skipped in ["Players", "L", "D"] {
+            assert!(matches!(root.get(&["Data", skipped]), Some(Tag::Other)));
+        }
+        assert!(matches!(
+            root.get(&["Data", "Version", "Snapshot"]),
+            Some(Tag::Other)
+        ));
+    }
+
+    #[test]
+    fn lookups_stop_at_missing_keys_and_other_kinds() {
+        let root = read_gzip(&fixture("level-1.20.4.dat")).unwrap();
+
+        assert!(root.get(&["Data", "Missing"]).is_none());
+        assert!(root.get(&["Data", "DataVersion", "Deeper"]).is_none());
+        assert!(
+            root.get(&["Data", "Version", "Name"])
+                .unwrap()
+                .as_int()
+                .is_none()
+        );
+        assert!(
+            root.get(&["Data", "DataVersion"])
+                .unwrap()
+                .as_str()
+                .is_none()
+        );
+        assert!(matches!(root.get(&[]), Some(Tag::Compound(_))));
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let mut nbt = vec![];
+        GzDecoder::new(File::open(fixture("level-1.21.4.dat")).unwrap())
+            .read_to_end(&mut nbt)
+            .unwrap();
+
+        for len in [0, 1, nbt.len() / 2, nbt.len() - 1] {
+            let err = read(&nbt[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "cut at {len}");
+        }
+        assert!(read(&nbt).is_ok());
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let cases: [(&str, &[u8]); 3] = [
+            ("a string at the root", &[8, 0, 0, 0, 1, b'x']),
+            ("an unknown tag type", &[10, 0, 0, 13, 0, 1, b'x', 0]),
+            (
+                "a negative length",
+                &[10, 0, 0, 9, 0, 1, b'l', 1, 0xff, 0xff, 0xff, 0xff, 0],
+            ),
+        ];
+
+        for (case, nbt) in cases {
+            let err = read(nbt).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{case}");
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        // a list holding a list, and so on further than Minecraft allows
+        let mut nbt = vec![10, 0, 0, 9, 0, 1, b'l'];
+        for _ in 0..=MAX_DEPTH {
+            nbt.extend([9, 0, 0, 0, 1]);
+        }
+        nbt.extend([0, 0, 0, 0, 0, 0]);
+
+        let err = read(&nbt).unwrap_err();
+        assert_eq!(err.to_string(), "nested too deeply");
+    }
+
+    #[test]
+    fn files_that_arent_gzip_are_rejected() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), [10, 0, 0, 0]).unwrap();
+        assert!(read_gzip(file.path()).is_err());
+    }
+}
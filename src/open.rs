@@ -0,0 +1,86 @@
+use crate::{
+    config,
+    error::{Error, Result},
+    server::get_server_dir_required,
+};
+use std::{
+    env, io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+const DEFAULT_OPEN_COMMAND: &str = "xdg-open";
+const FALLBACK_EDITOR: &str = "vi";
+
+#[derive(Clone, Copy)]
+pub enum Tool {
+    Files,
+    Editor,
+}
+
+fn get_command_line(tool: Tool) -> Result<(String, &'static str)> {
+    let config = config::snapshot()?;
+
+    Ok(match tool {
+        Tool::Files => (
+            config
+                .open_command
+                .clone()
+                .unwrap_or_else(|| DEFAULT_OPEN_COMMAND.to_string()),
+            "open_command",
+        ),
+        Tool::Editor => (
+            config
+                .editor_command
+                .clone()
+                .or_else(|| env::var("EDITOR").ok())
+                .unwrap_or_else(|| FALLBACK_EDITOR.to_string()),
+            "editor_command",
+        ),
+    })
+}
+
+/// Opens a server directory, or a path inside it, with the configured file manager or editor.
+/// File managers are detached so that the CLI returns immediately, while editors stay in the
+/// foreground since they are usually terminal programs.
+pub fn open(server: &str, tool: Tool, path: Option<&Path>) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let target = match path {
+        Some(path) => server_dir.join(path),
+        None => server_dir,
+    };
+
+    let (command_line, config_key) = get_command_line(tool)?;
+    let mut parts = command_line.split_whitespace();
+    let program = parts.next().ok_or_else(|| Error::ToolNotFound {
+        tool: command_line.clone(),
+        config_key,
+    })?;
+
+    let mut command = Command::new(program);
+    command.args(parts).arg(&target);
+
+    let result = match tool {
+        Tool::Files => command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map(|_| ()),
+        Tool::Editor => command.status().and_then(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(io::Error::other(format!("{program} exited with {status}")))
+            }
+        }),
+    };
+
+    result.map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::ToolNotFound {
+            tool: program.to_string(),
+            config_key,
+        },
+        _ => Error::Io(err),
+    })
+}
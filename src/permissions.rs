@@ -0,0 +1,150 @@
+//! Repairs ownership and modes of server trees, which end up wrong after restoring files as root
+//! or copying servers between users.
+
+use crate::{
+    error::{Error, Result},
+    server::{JAR_FILE_TXT_NAME, METADATA_DIRECTORY, get_server_dir_required},
+    verbose,
+};
+use std::{
+    ffi::CString,
+    fs::{self, Permissions},
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+/// jar_file.txt is kept read only so that it isn't edited by accident
+const JAR_FILE_TXT_MODE: u32 = 0o444;
+
+/// Wraps a permission error from reading a server's files with a hint to fix it
+pub fn with_hint(err: io::Error, path: &Path, server_dir: &Path) -> Error {
+    if err.kind() != io::ErrorKind::PermissionDenied {
+        return err.into();
+    }
+
+    Error::PermissionDenied {
+        path: path.to_path_buf(),
+        server: server_dir
+            .file_name()
+            .unwrap_or(server_dir.as_os_str())
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+struct Owner {
+    uid: u32,
+    gid: u32,
+}
+
+fn lchown(path: &Path, owner: &Owner) -> io::Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+
+    // SAFETY: c_path is a valid nul terminated string which outlives the call
+    if unsafe { libc::lchown(c_path.as_ptr(), owner.uid, owner.gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// The mode an entry should have, which adds the owner's access to whatever it already has
+fn sane_mode(path: &Path, mode: u32, is_dir: bool) -> u32 {
+    let is_jar_file_txt = path
+        .file_name()
+        .is_some_and(|name| name == JAR_FILE_TXT_NAME)
+        && path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|parent| parent == METADATA_DIRECTORY);
+
+    if is_jar_file_txt {
+        JAR_FILE_TXT_MODE
+    } else if is_dir {
+        mode & 0o7777 | 0o700
+    } else {
+        mode & 0o7777 | 0o600
+    }
+}
+
+fn fix_entry(path: &Path, owner: &Owner) -> io::Result<bool> {
+    let metadata = fs::symlink_metadata(path)?;
+
+    if metadata.uid() != owner.uid || metadata.gid() != owner.gid {
+        verbose!("Changing the owner of {}", path.display());
+        lchown(path, owner)?;
+    }
+
+    if metadata.file_type().is_symlink() {
+        return Ok(false);
+    }
+
+    let mode = sane_mode(path, metadata.mode(), metadata.is_dir());
+    if mode != metadata.mode() & 0o7777 {
+        verbose!("Changing the mode of {} to {mode:o}", path.display());
+        fs::set_permissions(path, Permissions::from_mode(mode))?;
+    }
+
+    Ok(metadata.is_dir())
+}
+
+fn fix_tree(path: &Path, owner: &Owner, problems: &mut Vec<(PathBuf, io::Error)>) {
+    let is_dir = match fix_entry(path, owner) {
+        Ok(is_dir) => is_dir,
+        Err(err) => {
+            problems.push((path.to_path_buf(), err));
+            // the directory may still be readable even if it couldn't be changed
+            path.is_dir() && !path.is_symlink()
+        }
+    };
+
+    if !is_dir {
+        return;
+    }
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            problems.push((path.to_path_buf(), err));
+            return;
+        }
+    };
+
+    for entry in entries {
+        match entry {
+            Ok(entry) => fix_tree(&entry.path(), owner, problems),
+            Err(err) => problems.push((path.to_path_buf(), err)),
+        }
+    }
+}
+
+/// Gives the current user ownership of every file in a server with sane modes, reporting the
+/// paths that couldn't be fixed, such as those owned by another user when chown isn't permitted
+pub fn fix(server: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+
+    // SAFETY: these calls have no preconditions and can't fail
+    let owner = unsafe {
+        Owner {
+            uid: libc::geteuid(),
+            gid: libc::getegid(),
+        }
+    };
+
+    let mut problems = Vec::new();
+    fix_tree(&server_dir, &owner, &mut problems);
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    for (path, err) in &problems {
+        println!("[\x1b[31;1mfail\x1b[0m] {}: {err}", path.display());
+    }
+
+    Err(Error::PermissionsNotFixed(problems.len()))
+}
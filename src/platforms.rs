@@ -1,30 +1,290 @@
 use crate::{
-    config::STATIC_CONFIG,
+    config::{self, STATIC_CONFIG},
+    environment,
     error::{Error, Result},
+    httpcache::{self, Entry},
+    verbose,
 };
 use clap::ValueEnum;
 use reqwest::{
-    self,
-    blocking::{self, Client},
-    header::{HeaderMap, HeaderValue, USER_AGENT},
+    self, StatusCode,
+    blocking::{Client, Response},
+    header::{HeaderMap, HeaderValue, RETRY_AFTER, USER_AGENT},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::{
+    collections::HashMap,
     fmt::{self, Display, Formatter},
-    sync::OnceLock,
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use url::Url;
 
-static CLIENT: OnceLock<Client> = OnceLock::new();
+static CLIENT: OnceLock<ApiClient> = OnceLock::new();
+
+const DEFAULT_ATTEMPTS: u32 = 5;
+
+static SINGLE_ATTEMPT: AtomicBool = AtomicBool::new(false);
+
+/// Makes requests fail on the first failure instead of retrying, for callers that must answer
+/// quickly
+pub fn set_single_attempt(single: bool) {
+    SINGLE_ATTEMPT.store(single, Ordering::Relaxed);
+}
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How many of a platform's newest versions are suggested in place of one it doesn't have
+const SUGGESTED_VERSIONS: usize = 5;
+
+const BEDROCK_LINKS_API_URL: &str =
+    "https://net-secondary.web.minecraft-services.net/api/v1.0/download/links";
+const BEDROCK_BASE_DOWNLOAD_URL: &str =
+    "https://www.minecraft.net/bedrockdedicatedserver/bin-linux";
+const BEDROCK_LINUX_DOWNLOAD_TYPE: &str = "serverBedrockLinux";
 
 const FABRIC_BASE_API_URL: &str = "https://meta.fabricmc.net/v2/versions";
 
-const PAPER_BASE_API_URL: &str = "https://api.papermc.io/v2/projects/paper";
-const PAPER_BASE_DOWNLOAD_URL: &str = "https://fill-data.papermc.io/v1/objects";
+const QUILT_BASE_API_URL: &str = "https://meta.quiltmc.org/v3/versions";
+
+const PAPERMC_BASE_API_URL: &str = "https://api.papermc.io/v2/projects";
+const PAPERMC_BASE_DOWNLOAD_URL: &str = "https://fill-data.papermc.io/v1/objects";
 
 const PURPUR_BASE_API_URL: &str = "https://api.purpurmc.org/v2/purpur";
 
-fn get_client() -> Result<&'static Client> {
+const VANILLA_VERSION_MANIFEST_URL: &str =
+    "https://launchermeta.mojang.com/mc/game/version_manifest_v2.json";
+
+const BUILD_TOOLS_URL: &str = "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
+
+pub struct ApiClient {
+    client: Client,
+    host_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    responses: Mutex<HashMap<String, String>>,
+}
+
+impl ApiClient {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            host_locks: Mutex::new(HashMap::new()),
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn host_lock(&self, host: &str) -> Arc<Mutex<()>> {
+        self.host_locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(host.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Sends a GET request, retrying it with exponential backoff while it fails in a way that may
+    /// pass, which is a timeout, a failed connection, or the server being busy or unreachable
+    /// behind its proxy
+    fn send_with_retries(&self, url: &Url, headers: HeaderMap) -> Result<Response> {
+        let (attempts, base_delay) = retry_settings();
+        self.send_attempts(
+            &environment::get().redirect(url),
+            headers,
+            attempts,
+            base_delay,
+        )
+    }
+
+    fn send_attempts(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+        attempts: u32,
+        base_delay: Duration,
+    ) -> Result<Response> {
+        let host = url.host_str().unwrap_or_default();
+        let lock = self.host_lock(host);
+        let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut rate_limited = false;
+        let mut reason = String::new();
+
+        for attempt in 1..=attempts {
+            verbose!("GET {url} (attempt {attempt}/{attempts})");
+            let delay = match self.client.get(url.clone()).headers(headers.clone()).send() {
+                Ok(response) if is_transient_status(response.status()) => {
+                    rate_limited = response.status() == StatusCode::TOO_MANY_REQUESTS;
+                    reason = response.status().to_string();
+                    retry_after(response.headers())
+                }
+                Ok(response) => return Ok(response.error_for_status()?),
+                Err(err) if is_transient_error(&err) => {
+                    rate_limited = false;
+                    reason = err.to_string();
+                    None
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            if attempt < attempts {
+                let delay = delay.unwrap_or_else(|| backoff(base_delay, attempt));
+                verbose!("Request to {host} failed ({reason}), retrying in {delay:?}");
+                thread::sleep(delay);
+            }
+        }
+
+        if rate_limited {
+            return Err(Error::RateLimited {
+                host: host.to_string(),
+                attempts,
+            });
+        }
+
+        Err(Error::RequestFailed {
+            url: url.to_string(),
+            attempts,
+            reason,
+        })
+    }
+
+    fn get_text(&self, url: impl AsRef<str>) -> Result<String> {
+        let url = Url::parse(url.as_ref())?;
+
+        if let Some(body) = self
+            .responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(url.as_str())
+        {
+            verbose!("Using cached response for {url}");
+            return Ok(body.clone());
+        }
+
+        let body = self.fetch_text(&url)?;
+
+        self.responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(url.to_string(), body.clone());
+
+        Ok(body)
+    }
+
+    /// Fetches a response body, using the one kept on disk from an earlier run if it was checked
+    /// recently and revalidating it otherwise
+    fn fetch_text(&self, url: &Url) -> Result<String> {
+        let kept = httpcache::get(url);
+        if let Some(kept) = &kept
+            && kept.is_fresh()
+        {
+            verbose!("Using the response kept for {url}");
+            return Ok(kept.body.clone());
+        }
+
+        let headers = kept
+            .as_ref()
+            .map(Entry::conditional_headers)
+            .unwrap_or_default();
+
+        let response = self.send_with_retries(url, headers)?;
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(kept) = kept
+        {
+            verbose!("{url} is unchanged, using the kept response");
+            return Ok(httpcache::touch(url, kept).body);
+        }
+
+        let headers = response.headers().clone();
+        let body = response.text()?;
+        httpcache::put(url, &headers, &body);
+        Ok(body)
+    }
+
+    /// Sends an uncached request, for downloads
+    pub fn get_raw(&self, url: impl AsRef<str>) -> Result<Response> {
+        self.send_with_retries(&Url::parse(url.as_ref())?, HeaderMap::new())
+    }
+
+    pub fn get_json<T: DeserializeOwned>(&self, url: impl AsRef<str>) -> Result<T> {
+        Ok(serde_json::from_str(&self.get_text(url)?)?)
+    }
+
+    /// Like [`ApiClient::get_json`], but treats a 404 response as `None`
+    pub fn get_json_optional<T: DeserializeOwned>(
+        &self,
+        url: impl AsRef<str>,
+    ) -> Result<Option<T>> {
+        match self.get_json(url) {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::Reqwest(err)) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// How many times requests are attempted and the delay before the first retry, from the
+/// configuration
+fn retry_settings() -> (u32, Duration) {
+    let config = config::snapshot().ok();
+    let attempts = config
+        .as_ref()
+        .and_then(|config| config.http_attempts)
+        .unwrap_or(DEFAULT_ATTEMPTS)
+        .max(1);
+    let attempts = if SINGLE_ATTEMPT.load(Ordering::Relaxed) {
+        1
+    } else {
+        attempts
+    };
+    let base_delay = config
+        .and_then(|config| config.http_retry_delay_ms)
+        .map_or(DEFAULT_RETRY_DELAY, Duration::from_millis);
+
+    (attempts, base_delay)
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect() || err.is_request()
+}
+
+/// The delay a busy server asks for with `Retry-After`, in seconds
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_DELAY))
+}
+
+/// The delay before retrying after a failed attempt: the base delay doubled for each attempt before
+/// it, plus up to a quarter more at random so that clients failing together don't retry together
+fn backoff(base_delay: Duration, attempt: u32) -> Duration {
+    let delay = base_delay
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_RETRY_DELAY);
+
+    // the clock's nanoseconds are random enough to spread retries out
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos())
+        .unwrap_or_default();
+    delay + (delay / 4).mul_f64(f64::from(nanos % 1000) / 1000.0)
+}
+
+pub fn get_client() -> Result<&'static ApiClient> {
     if let Some(client) = CLIENT.get() {
         return Ok(client);
     }
@@ -40,31 +300,116 @@ fn get_client() -> Result<&'static Client> {
         ))?,
     );
 
-    let client = Client::builder().default_headers(headers).build()?;
+    let http = config::snapshot()?.http.unwrap_or_default();
+    let mut builder = Client::builder().default_headers(headers);
+
+    if let Some(secs) = http.request_timeout_secs {
+        builder = builder.timeout((secs > 0).then(|| Duration::from_secs(secs)));
+    }
+    if let Some(secs) = http.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if let Some(proxy) = config::configured_proxy(&http)? {
+        builder = builder.proxy(proxy);
+    }
+
+    let client = builder.build()?;
 
-    Ok(CLIENT.get_or_init(|| client))
+    Ok(CLIENT.get_or_init(|| ApiClient::new(client)))
 }
 
 #[derive(Debug, Deserialize)]
-struct FabricEntry {
-    version: String,
-    stable: bool,
+struct BedrockLinks {
+    result: BedrockLinksResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct BedrockLinksResult {
+    links: Vec<BedrockLink>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BedrockLink {
+    download_type: String,
+    download_url: String,
+}
+
+/// Takes the version out of a download url such as `.../bedrock-server-1.21.50.07.zip`
+fn bedrock_version_from_url(url: &str) -> Option<String> {
+    url.rsplit('/')
+        .next()?
+        .strip_prefix("bedrock-server-")?
+        .strip_suffix(".zip")
+        .map(String::from)
+}
+
+fn get_bedrock(version: Option<String>) -> Result<ResolvedArtifact> {
+    let (version, url) = match version {
+        Some(version) => {
+            let url = format!("{BEDROCK_BASE_DOWNLOAD_URL}/bedrock-server-{version}.zip");
+            (version, url)
+        }
+        None => {
+            let links: BedrockLinks = get_client()?.get_json(BEDROCK_LINKS_API_URL)?;
+            let url = links
+                .result
+                .links
+                .into_iter()
+                .find(|link| link.download_type == BEDROCK_LINUX_DOWNLOAD_TYPE)
+                .map(|link| link.download_url)
+                .ok_or_else(|| Error::PlatformsNotFound("bedrock linux server".to_string()))?;
+            let version = bedrock_version_from_url(&url)
+                .ok_or_else(|| Error::PlatformsNotFound(format!("a version in {url}")))?;
+            (version, url)
+        }
+    };
+
+    Ok(ResolvedArtifact {
+        platform: Platform::Bedrock,
+        version,
+        build: None,
+        url,
+        sha256: None,
+        published_digest: None,
+        commits: vec![],
+    })
 }
 
+/// An entry of the Fabric or Quilt meta API, which list game versions, loaders and installers alike
 #[derive(Debug, Deserialize)]
-struct FabricVersions {
-    game: Vec<FabricEntry>,
-    loader: Vec<FabricEntry>,
-    installer: Vec<FabricEntry>,
+struct MetaEntry {
+    version: String,
+    /// Quilt only flags game versions, so loaders and installers are told apart by their version
+    stable: Option<bool>,
+    /// Where Quilt's installers are downloaded from
+    url: Option<String>,
 }
 
-fn first_stable(entries: Vec<FabricEntry>) -> Option<FabricEntry> {
-    entries.into_iter().find(|entry| entry.stable)
+impl MetaEntry {
+    fn is_stable(&self) -> bool {
+        // prereleases are versioned like 0.26.0-beta.1
+        self.stable.unwrap_or_else(|| !self.version.contains('-'))
+    }
 }
 
-fn get_fabric(game_version: Option<String>) -> Result<String> {
-    let versions: FabricVersions = blocking::get(FABRIC_BASE_API_URL)?.json()?;
+#[derive(Debug, Deserialize)]
+struct MetaVersions {
+    game: Vec<MetaEntry>,
+    loader: Vec<MetaEntry>,
+    installer: Vec<MetaEntry>,
+}
 
+fn first_stable(entries: Vec<MetaEntry>) -> Option<MetaEntry> {
+    entries.into_iter().find(MetaEntry::is_stable)
+}
+
+/// Picks the game version, or the latest stable one, along with the latest stable loader and
+/// installer
+fn resolve_meta(
+    versions: MetaVersions,
+    game_version: Option<String>,
+) -> Result<(String, MetaEntry, MetaEntry)> {
     let game_version = game_version.map_or_else(
         || {
             first_stable(versions.game)
@@ -73,16 +418,52 @@ fn get_fabric(game_version: Option<String>) -> Result<String> {
         },
         Ok,
     )?;
-    let loader_version = first_stable(versions.loader)
-        .ok_or_else(|| Error::PlatformsNotFound("stable loader".to_string()))?
-        .version;
-    let installer_version = first_stable(versions.installer)
-        .ok_or_else(|| Error::PlatformsNotFound("stable installer".to_string()))?
-        .version;
-
-    Ok(format!(
-        "{FABRIC_BASE_API_URL}/loader/{game_version}/{loader_version}/{installer_version}/server/jar",
-    ))
+    let loader = first_stable(versions.loader)
+        .ok_or_else(|| Error::PlatformsNotFound("stable loader".to_string()))?;
+    let installer = first_stable(versions.installer)
+        .ok_or_else(|| Error::PlatformsNotFound("stable installer".to_string()))?;
+
+    Ok((game_version, loader, installer))
+}
+
+fn get_fabric(game_version: Option<String>) -> Result<ResolvedArtifact> {
+    let versions: MetaVersions = get_client()?.get_json(FABRIC_BASE_API_URL)?;
+
+    let (game_version, loader, installer) = resolve_meta(versions, game_version)?;
+    let (loader_version, installer_version) = (loader.version, installer.version);
+
+    Ok(ResolvedArtifact {
+        platform: Platform::Fabric,
+        url: format!(
+            "{FABRIC_BASE_API_URL}/loader/{game_version}/{loader_version}/{installer_version}/server/jar",
+        ),
+        version: game_version,
+        build: Some(format!("{loader_version}/{installer_version}")),
+        sha256: None,
+        published_digest: None,
+        commits: vec![],
+    })
+}
+
+/// Quilt has no server jar to download, so its artifact is the installer, which sets up the server
+/// with the loader named in the build
+fn get_quilt(game_version: Option<String>) -> Result<ResolvedArtifact> {
+    let versions: MetaVersions = get_client()?.get_json(QUILT_BASE_API_URL)?;
+    let (game_version, loader, installer) = resolve_meta(versions, game_version)?;
+
+    let url = installer
+        .url
+        .ok_or_else(|| Error::PlatformsNotFound("a quilt installer download".to_string()))?;
+
+    Ok(ResolvedArtifact {
+        platform: Platform::Quilt,
+        url,
+        version: game_version,
+        build: Some(format!("{}/{}", loader.version, installer.version)),
+        sha256: None,
+        published_digest: None,
+        commits: vec![],
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,6 +478,7 @@ struct PaperBuildsInfo {
 
 #[derive(Debug, Deserialize)]
 struct Build {
+    build: u32,
     downloads: PaperDownloads,
 }
 
@@ -111,36 +493,125 @@ struct PaperApplication {
     sha256: String,
 }
 
-fn get_paper(version: Option<String>) -> Result<String> {
-    let client = get_client()?;
+/// Lists the versions of a PaperMC project, oldest first
+fn get_papermc_versions(platform: Platform) -> Result<Vec<String>> {
+    let project_info: PaperProjectInfo =
+        get_client()?.get_json(format!("{PAPERMC_BASE_API_URL}/{platform}"))?;
+    Ok(project_info.versions)
+}
 
-    let version = version.map_or_else(
-        || {
-            let project_info: PaperProjectInfo = client.get(PAPER_BASE_API_URL).send()?.json()?;
-            let mut versions = project_info.versions;
-            Ok::<_, Error>(versions.pop().unwrap())
-        },
-        Ok,
-    )?;
+/// The error for a build number a version doesn't have, listing the ones it does
+fn unknown_build(platform: Platform, version: &str, build: &str, valid: &[String]) -> Error {
+    if valid.is_empty() {
+        return Error::PlatformsNotFound(format!(
+            "{platform} {version} build {build} ({version} has no builds yet)"
+        ));
+    }
+
+    Error::PlatformsNotFound(format!(
+        "{platform} {version} build {build} (valid builds: {})",
+        valid.join(", ")
+    ))
+}
+
+/// The error for a version a platform doesn't have, suggesting the newest of `valid`, which is
+/// oldest first
+fn unknown_version(platform: Platform, version: &str, valid: &[String]) -> Error {
+    Error::VersionNotFound {
+        platform: platform.to_string(),
+        version: version.to_string(),
+        recent: valid
+            .iter()
+            .rev()
+            .take(SUGGESTED_VERSIONS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Whether an API answered that what was asked for doesn't exist. Malformed versions get a 400
+/// rather than a 404 from some APIs.
+fn is_not_found(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Reqwest(err)
+            if matches!(err.status(), Some(StatusCode::NOT_FOUND | StatusCode::BAD_REQUEST))
+    )
+}
 
-    let builds: Vec<Build> = client
-        .get(format!("{PAPER_BASE_API_URL}/versions/{version}/builds"))
-        .send()?
-        .json::<PaperBuildsInfo>()?
-        .builds;
-    let application = &builds[builds.len() - 1].downloads.application;
+/// Lists the builds of a version of a PaperMC project, oldest first
+fn get_papermc_builds(platform: Platform, version: &str) -> Result<Vec<Build>> {
+    let builds_url = format!("{PAPERMC_BASE_API_URL}/{platform}/versions/{version}/builds");
+
+    match get_client()?.get_json::<PaperBuildsInfo>(builds_url) {
+        Ok(info) => Ok(info.builds),
+        Err(err) if is_not_found(&err) => Err(unknown_version(
+            platform,
+            version,
+            &get_papermc_versions(platform)?,
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+/// Resolves a project served by the PaperMC API, which Paper, Folia and Velocity all are, each
+/// under the platform's name
+fn get_papermc_project(
+    platform: Platform,
+    version: Option<String>,
+    build: Option<String>,
+) -> Result<ResolvedArtifact> {
+    let version = match version {
+        Some(version) => version,
+        None => get_papermc_versions(platform)?
+            .pop()
+            .ok_or_else(|| Error::PlatformsNotFound(format!("any {platform} version")))?,
+    };
+
+    let builds_url = format!("{PAPERMC_BASE_API_URL}/{platform}/versions/{version}/builds");
+    let build = match build {
+        Some(build) => {
+            match get_client()?.get_json_optional::<Build>(format!("{builds_url}/{build}"))? {
+                Some(build) => build,
+                None => {
+                    let builds = get_papermc_builds(platform, &version)?;
+                    let valid: Vec<String> = builds.iter().map(|b| b.build.to_string()).collect();
+                    return Err(unknown_build(platform, &version, &build, &valid));
+                }
+            }
+        }
+        None => get_papermc_builds(platform, &version)?
+            .pop()
+            .ok_or_else(|| {
+                Error::PlatformsNotFound(format!(
+                    "a build of {platform} {version}, which has none yet"
+                ))
+            })?,
+    };
+    let application = &build.downloads.application;
 
     let download_url = format!(
-        "{PAPER_BASE_DOWNLOAD_URL}/{}/{}",
+        "{PAPERMC_BASE_DOWNLOAD_URL}/{}/{}",
         application.sha256, application.name
     );
 
-    Ok(download_url)
+    Ok(ResolvedArtifact {
+        platform,
+        version,
+        build: Some(build.build.to_string()),
+        url: download_url,
+        sha256: Some(application.sha256.clone()),
+        published_digest: None,
+        commits: vec![],
+    })
 }
 
 #[derive(Debug, Deserialize)]
 struct PurpurProjectInfo {
     metadata: PurpurMetadata,
+    /// Every version Purpur was built for, oldest first
+    versions: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -155,59 +626,738 @@ struct PurpurVersionInfo {
 
 #[derive(Debug, Deserialize)]
 struct PurpurBuilds {
+    all: Vec<String>,
     latest: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct PurpurBuildInfo {
+    commits: Vec<PurpurCommit>,
+    md5: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PurpurCommit {
+    hash: String,
+    description: String,
+}
+
+impl PurpurCommit {
+    /// The short hash and first line of the commit, such as `1a2b3c4 Update upstream`
+    fn summary(&self) -> String {
+        let hash = self.hash.get(..7).unwrap_or(&self.hash);
+        let title = self.description.lines().next().unwrap_or_default();
+        format!("{hash} {title}")
+    }
+}
+
+fn get_purpur_project() -> Result<PurpurProjectInfo> {
+    get_client()?.get_json(PURPUR_BASE_API_URL)
+}
+
 fn get_current_purpur_version() -> Result<String> {
-    let project_info: PurpurProjectInfo = blocking::get(PURPUR_BASE_API_URL)?.json()?;
-    Ok(project_info.metadata.current)
+    Ok(get_purpur_project()?.metadata.current)
+}
+
+fn get_purpur_build(version: &str, build: &str) -> Result<Option<PurpurBuildInfo>> {
+    get_client()?.get_json_optional(format!("{PURPUR_BASE_API_URL}/{version}/{build}"))
 }
 
-fn get_purpur(version: Option<String>) -> Result<String> {
+fn get_purpur(version: Option<String>, build: Option<String>) -> Result<ResolvedArtifact> {
     let version = version.map_or_else(get_current_purpur_version, Ok)?;
 
     let version_url = format!("{PURPUR_BASE_API_URL}/{version}");
-    let version_info: PurpurVersionInfo = blocking::get(&version_url)?.json()?;
+    let builds = match get_client()?.get_json::<PurpurVersionInfo>(&version_url) {
+        Ok(info) => info.builds,
+        Err(err) if is_not_found(&err) => {
+            let valid = get_purpur_project()?.versions;
+            return Err(unknown_version(Platform::Purpur, &version, &valid));
+        }
+        Err(err) => return Err(err),
+    };
+
+    if let Some(build) = &build
+        && !builds.all.contains(build)
+    {
+        return Err(unknown_build(
+            Platform::Purpur,
+            &version,
+            build,
+            &builds.all,
+        ));
+    }
 
-    let latest = version_info.builds.latest;
-    println!("Creating purpur server (v{version}, build {latest})");
+    let pinned = build.is_some();
+    let mut build = build.unwrap_or(builds.latest);
+    let info = match get_purpur_build(&version, &build)? {
+        Some(info) => info,
+        None if pinned => {
+            return Err(Error::PlatformsNotFound(format!(
+                "purpur {version} build {build}"
+            )));
+        }
+        None => {
+            // the latest build is sometimes listed before its details are published
+            let previous = builds
+                .all
+                .iter()
+                .take_while(|listed| **listed != build)
+                .last()
+                .ok_or_else(|| Error::PlatformsNotFound(format!("purpur {version} build {build}")))?
+                .clone();
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m purpur {version} build {build} has no details yet, \
+                 using build {previous}"
+            );
+            let info = get_purpur_build(&version, &previous)?.ok_or_else(|| {
+                Error::PlatformsNotFound(format!("purpur {version} build {previous}"))
+            })?;
+            build = previous;
+            info
+        }
+    };
+    verbose!("Resolved purpur {version} to build {build}");
 
-    let download_url = format!("{version_url}/{latest}/download");
-    Ok(download_url)
+    let download_url = format!("{version_url}/{build}/download");
+    Ok(ResolvedArtifact {
+        platform: Platform::Purpur,
+        version,
+        build: Some(build),
+        url: download_url,
+        sha256: None,
+        published_digest: Some(PublishedDigest::Md5(info.md5)),
+        commits: info.commits.iter().map(PurpurCommit::summary).collect(),
+    })
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+/// The most builds a changelog looks back over, since each one takes a request
+const MAX_CHANGELOG_BUILDS: usize = 50;
+
+/// Lists the commits of the builds after `installed_build` up to and including the resolved one,
+/// newest first, each line prefixed with its build. Without an installed build of the same
+/// version, only the resolved build's commits are listed. Returns `None` for platforms that don't
+/// publish commits.
+pub fn changelog(
+    artifact: &ResolvedArtifact,
+    installed: Option<(&str, &str)>,
+) -> Result<Option<Vec<String>>> {
+    if artifact.platform != Platform::Purpur {
+        return Ok(None);
+    }
+
+    let Some(target) = artifact.build.as_deref() else {
+        return Ok(None);
+    };
+
+    let installed_build = installed
+        .filter(|(version, _)| *version == artifact.version)
+        .map(|(_, build)| build);
+
+    let builds = match installed_build {
+        Some(installed_build) => {
+            let all = get_client()?
+                .get_json::<PurpurVersionInfo>(format!(
+                    "{PURPUR_BASE_API_URL}/{}",
+                    artifact.version
+                ))?
+                .builds
+                .all;
+            let from = all.iter().position(|build| build == installed_build);
+            let to = all.iter().position(|build| build == target);
+            match (from, to) {
+                (Some(from), Some(to)) if from < to => all[from + 1..=to].to_vec(),
+                _ => vec![],
+            }
+        }
+        None => vec![target.to_string()],
+    };
+
+    let mut lines = vec![];
+    for build in builds.iter().rev().take(MAX_CHANGELOG_BUILDS) {
+        let commits = if build == target {
+            artifact.commits.clone()
+        } else {
+            get_purpur_build(&artifact.version, build)?
+                .map(|info| info.commits.iter().map(PurpurCommit::summary).collect())
+                .unwrap_or_default()
+        };
+        lines.extend(
+            commits
+                .into_iter()
+                .map(|commit| format!("{build} {commit}")),
+        );
+    }
+
+    if builds.len() > MAX_CHANGELOG_BUILDS {
+        lines.push(format!(
+            "... and {} earlier build(s)",
+            builds.len() - MAX_CHANGELOG_BUILDS
+        ));
+    }
+
+    Ok(Some(lines))
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaManifest {
+    latest: VanillaLatest,
+    versions: Vec<VanillaManifestVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaLatest {
+    release: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaManifestVersion {
+    id: String,
+    /// `release`, `snapshot`, `old_beta` or `old_alpha`
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaVersionInfo {
+    downloads: VanillaDownloads,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaDownloads {
+    server: Option<VanillaDownload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaDownload {
+    sha1: String,
+    url: String,
+}
+
+fn get_vanilla(version: Option<String>) -> Result<ResolvedArtifact> {
+    let client = get_client()?;
+    let manifest: VanillaManifest = client.get_json(VANILLA_VERSION_MANIFEST_URL)?;
+
+    let version = version.unwrap_or(manifest.latest.release);
+    let entry = manifest
+        .versions
+        .into_iter()
+        .find(|entry| entry.id == version)
+        .ok_or_else(|| Error::PlatformsNotFound(format!("vanilla {version}")))?;
+
+    // the oldest versions have no server download
+    let server = client
+        .get_json::<VanillaVersionInfo>(&entry.url)?
+        .downloads
+        .server
+        .ok_or_else(|| {
+            Error::PlatformsNotFound(format!("a server download for vanilla {version}"))
+        })?;
+
+    Ok(ResolvedArtifact {
+        platform: Platform::Vanilla,
+        version,
+        build: None,
+        url: server.url,
+        sha256: None,
+        published_digest: Some(PublishedDigest::Sha1(server.sha1)),
+        commits: vec![],
+    })
+}
+
+/// Spigot can't be downloaded, so its artifact is BuildTools, which builds the requested revision
+fn get_spigot(version: Option<String>) -> Result<ResolvedArtifact> {
+    Ok(ResolvedArtifact {
+        platform: Platform::Spigot,
+        version: version.unwrap_or_else(|| "latest".to_string()),
+        build: None,
+        url: BUILD_TOOLS_URL.to_string(),
+        sha256: None,
+        published_digest: None,
+        commits: vec![],
+    })
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Platform {
+    Bedrock,
+    /// A jar brought by the user, which no platform resolves
+    #[value(skip)]
+    Custom,
     Fabric,
+    Folia,
     Forge,
     Neoforge,
     Paper,
     Purpur,
+    Quilt,
+    Spigot,
+    Vanilla,
+    Velocity,
 }
 
 impl Display for Platform {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
+            Self::Bedrock => write!(f, "bedrock"),
+            Self::Custom => write!(f, "custom"),
             Self::Fabric => write!(f, "fabric"),
+            Self::Folia => write!(f, "folia"),
             Self::Forge => write!(f, "forge"),
             Self::Neoforge => write!(f, "neoforge"),
             Self::Paper => write!(f, "paper"),
             Self::Purpur => write!(f, "purpur"),
+            Self::Quilt => write!(f, "quilt"),
+            Self::Spigot => write!(f, "spigot"),
+            Self::Vanilla => write!(f, "vanilla"),
+            Self::Velocity => write!(f, "velocity"),
+        }
+    }
+}
+
+impl Platform {
+    /// Guesses the platform of an installed jar from its name, such as `paper-1.21.4-100.jar`
+    pub fn from_jar_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+
+        // neoforge jars also contain "forge", so the longest matching name wins
+        Self::value_variants()
+            .iter()
+            .copied()
+            .filter(|platform| name.contains(&platform.to_string()))
+            .max_by_key(|platform| platform.to_string().len())
+    }
+
+    /// The console command that shuts a server of this platform down. Proxies use `end` rather
+    /// than `stop`, so each platform states its own.
+    pub fn stop_command(self) -> &'static str {
+        match self {
+            Self::Bedrock
+            | Self::Custom
+            | Self::Fabric
+            | Self::Folia
+            | Self::Forge
+            | Self::Neoforge
+            | Self::Paper
+            | Self::Purpur
+            | Self::Quilt
+            | Self::Spigot
+            | Self::Vanilla => "stop",
+            Self::Velocity => "end",
+        }
+    }
+
+    /// Whether this platform is a proxy rather than a game server. Proxies don't take the `nogui`
+    /// argument, and fail to start when given it.
+    pub fn is_proxy(self) -> bool {
+        matches!(self, Self::Velocity)
+    }
+
+    /// Whether a build of a version can be picked, for the platforms that number their builds
+    pub fn has_selectable_builds(self) -> bool {
+        matches!(
+            self,
+            Self::Folia | Self::Paper | Self::Purpur | Self::Velocity
+        )
+    }
+}
+
+/// A digest a platform publishes for its downloads in an algorithm other than sha256, which the
+/// download is checked against as well
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublishedDigest {
+    Md5(String),
+    Sha1(String),
+}
+
+/// The exact build a platform and version resolved to, so that it can be recorded and reapplied
+/// without resolving "latest" again.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResolvedArtifact {
+    pub platform: Platform,
+    pub version: String,
+    pub build: Option<String>,
+    pub url: String,
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_digest: Option<PublishedDigest>,
+    /// Summaries of the commits that went into the build, for platforms that publish them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<String>,
+}
+
+impl ResolvedArtifact {
+    pub fn download_url(&self) -> Result<Url> {
+        Ok(Url::parse(&self.url)?)
+    }
+
+    /// The loader version of a Fabric or Quilt build, which is recorded as `<loader>/<installer>`
+    pub fn loader_version(&self) -> Option<&str> {
+        self.build.as_deref()?.split('/').next()
+    }
+}
+
+/// What to resolve a platform to: a version, or the latest one when it is `None` or "latest", and
+/// optionally a build of it for the platforms that number their builds
+#[derive(Clone, Debug, Default)]
+pub struct VersionSpec {
+    pub version: Option<String>,
+    pub build: Option<String>,
+}
+
+impl VersionSpec {
+    pub fn new(version: Option<String>, build: Option<String>) -> Self {
+        Self { version, build }
+    }
+}
+
+/// Lists the versions a platform can be installed at, newest first. Fabric, Quilt and vanilla only
+/// list releases, leaving out snapshots. Fails for platforms whose versions can't be listed.
+pub fn list_versions(platform: Platform) -> Result<Vec<String>> {
+    match platform {
+        // the meta APIs and the vanilla manifest list the newest first, the others the oldest
+        Platform::Fabric | Platform::Quilt => {
+            let url = if platform == Platform::Fabric {
+                FABRIC_BASE_API_URL
+            } else {
+                QUILT_BASE_API_URL
+            };
+            let versions: MetaVersions = get_client()?.get_json(url)?;
+            Ok(versions
+                .game
+                .into_iter()
+                .filter(MetaEntry::is_stable)
+                .map(|entry| entry.version)
+                .collect())
+        }
+        Platform::Folia | Platform::Paper | Platform::Velocity => {
+            Ok(get_papermc_versions(platform)?.into_iter().rev().collect())
         }
+        Platform::Purpur => Ok(get_purpur_project()?.versions.into_iter().rev().collect()),
+        Platform::Vanilla => {
+            let manifest: VanillaManifest = get_client()?.get_json(VANILLA_VERSION_MANIFEST_URL)?;
+            Ok(manifest
+                .versions
+                .into_iter()
+                .filter(|entry| entry.kind == "release")
+                .map(|entry| entry.id)
+                .collect())
+        }
+        Platform::Bedrock
+        | Platform::Custom
+        | Platform::Forge
+        | Platform::Neoforge
+        | Platform::Spigot => Err(Error::VersionsNotListed(platform.to_string())),
     }
 }
 
-pub fn get(platform: Platform, version: Option<String>) -> Result<Url> {
+pub fn resolve(platform: Platform, spec: VersionSpec) -> Result<ResolvedArtifact> {
     // set version to none if the it is "latest" so that it defaults to the latest one
-    let version = version.filter(|v| v != "latest");
+    let version = spec.version.filter(|v| v != "latest");
+    let build = spec.build;
+
+    if build.is_some() {
+        if !platform.has_selectable_builds() {
+            return Err(Error::BuildsNotSelectable(platform.to_string()));
+        }
+        // build numbers are per version, so one of the latest version would stop meaning the same
+        // build once another version comes out
+        if version.is_none() {
+            return Err(Error::BuildWithoutVersion);
+        }
+    }
 
-    let download_url = match platform {
-        Platform::Fabric => get_fabric(version)?,
+    match platform {
+        Platform::Bedrock => get_bedrock(version),
+        Platform::Custom => Err(Error::CustomPlatformResolved),
+        Platform::Fabric => get_fabric(version),
+        Platform::Folia => get_papermc_project(platform, version, build),
         Platform::Forge => todo!(),
         Platform::Neoforge => todo!(),
-        Platform::Paper => get_paper(version)?,
-        Platform::Purpur => get_purpur(version)?,
+        Platform::Paper => get_papermc_project(platform, version, build),
+        Platform::Purpur => get_purpur(version, build),
+        Platform::Quilt => get_quilt(version),
+        Platform::Spigot => get_spigot(version),
+        Platform::Vanilla => get_vanilla(version),
+        Platform::Velocity => get_papermc_project(platform, version, build),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
     };
 
-    Ok(Url::parse(&download_url)?)
+    /// Serves each canned response to one connection in turn, returning the url to request
+    fn serve(responses: Vec<&'static str>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        url
+    }
+
+    const TOO_MANY_REQUESTS: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\n\
+                                     Content-Length: 0\r\nConnection: close\r\n\r\n";
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_reads_seconds() {
+        assert_eq!(retry_after(&headers("3")), Some(Duration::from_secs(3)));
+        assert_eq!(retry_after(&headers(" 0 ")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        assert_eq!(retry_after(&headers("86400")), Some(MAX_RETRY_DELAY));
+    }
+
+    #[test]
+    fn retry_after_ignores_dates_and_garbage() {
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), None);
+        assert_eq!(retry_after(&headers("-1")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn backoff_doubles_with_up_to_a_quarter_of_jitter() {
+        let base = Duration::from_millis(100);
+
+        for attempt in 1..=4 {
+            let expected = base * (1 << (attempt - 1));
+            let delay = backoff(base, attempt);
+            assert!(delay >= expected, "{delay:?} < {expected:?}");
+            assert!(delay <= expected + expected / 4, "{delay:?} too long");
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let delay = backoff(Duration::from_secs(1), 40);
+        assert!(delay >= MAX_RETRY_DELAY);
+        assert!(delay <= MAX_RETRY_DELAY + MAX_RETRY_DELAY / 4);
+    }
+
+    #[test]
+    fn rate_limited_request_is_retried() {
+        let url = serve(vec![TOO_MANY_REQUESTS, OK]);
+        let client = ApiClient::new(Client::new());
+
+        let response = client
+            .send_attempts(&url, HeaderMap::new(), 3, Duration::from_millis(1))
+            .unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+    }
+
+    #[test]
+    fn rate_limited_request_gives_up() {
+        let url = serve(vec![TOO_MANY_REQUESTS, TOO_MANY_REQUESTS]);
+        let client = ApiClient::new(Client::new());
+
+        let err = client
+            .send_attempts(&url, HeaderMap::new(), 2, Duration::from_millis(1))
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::RateLimited { attempts: 2, .. }),
+            "{err:?}"
+        );
+    }
+
+    /// Canned API responses by path, which starts with the original host as requests go through
+    /// the API override
+    const FIXTURES: &[(&str, &str)] = &[
+        (
+            "/api.papermc.io/v2/projects/paper",
+            r#"{"versions": ["1.20.4", "1.21.1", "1.21.3"]}"#,
+        ),
+        (
+            "/api.papermc.io/v2/projects/paper/versions/1.21.1/builds",
+            r#"{"builds": []}"#,
+        ),
+        (
+            "/api.papermc.io/v2/projects/paper/versions/1.21.3/builds",
+            r#"{"builds": [
+                {"build": 6, "downloads": {"application": {"name": "paper-6.jar", "sha256": "aa"}}},
+                {"build": 7, "downloads": {"application": {"name": "paper-7.jar", "sha256": "bb"}}}
+            ]}"#,
+        ),
+        (
+            "/api.papermc.io/v2/projects/paper/versions/1.21.3/builds/6",
+            r#"{"build": 6, "downloads": {"application": {"name": "paper-6.jar", "sha256": "aa"}}}"#,
+        ),
+        ("/api.papermc.io/v2/projects/folia", r#"{"versions": []}"#),
+        (
+            "/api.purpurmc.org/v2/purpur",
+            r#"{"metadata": {"current": "1.21.3"}, "versions": ["1.21.1", "1.21.3"]}"#,
+        ),
+        (
+            "/api.purpurmc.org/v2/purpur/1.21.1",
+            r#"{"builds": {"all": [], "latest": ""}}"#,
+        ),
+        (
+            "/api.purpurmc.org/v2/purpur/1.21.3",
+            r#"{"builds": {"all": ["2300", "2301"], "latest": "2301"}}"#,
+        ),
+        (
+            "/api.purpurmc.org/v2/purpur/1.21.3/2300",
+            r#"{"commits": [{"hash": "1a2b3c4d5e6f", "description": "Update upstream\n\nDetails"}],
+                "md5": "d41d8cd98f00b204e9800998ecf8427e"}"#,
+        ),
+    ];
+
+    fn fake_api() {
+        testing::sandbox(FIXTURES);
+    }
+
+    fn paper(version: Option<&str>, build: Option<&str>) -> Result<ResolvedArtifact> {
+        fake_api();
+        get_papermc_project(
+            Platform::Paper,
+            version.map(String::from),
+            build.map(String::from),
+        )
+    }
+
+    fn purpur(version: Option<&str>, build: Option<&str>) -> Result<ResolvedArtifact> {
+        fake_api();
+        get_purpur(version.map(String::from), build.map(String::from))
+    }
+
+    #[test]
+    fn papermc_resolves_the_latest_build_of_the_latest_version() {
+        let artifact = paper(None, None).unwrap();
+        assert_eq!(artifact.version, "1.21.3");
+        assert_eq!(artifact.build.as_deref(), Some("7"));
+        assert_eq!(artifact.sha256.as_deref(), Some("bb"));
+        assert_eq!(
+            artifact.url,
+            format!("{PAPERMC_BASE_DOWNLOAD_URL}/bb/paper-7.jar")
+        );
+
+        let pinned = paper(Some("1.21.3"), Some("6")).unwrap();
+        assert_eq!(pinned.build.as_deref(), Some("6"));
+    }
+
+    #[test]
+    fn papermc_project_without_versions() {
+        fake_api();
+        let err = get_papermc_project(Platform::Folia, None, None).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what == "any folia version"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn papermc_version_without_builds() {
+        let err = paper(Some("1.21.1"), None).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what.contains("has none yet")),
+            "{err:?}"
+        );
+
+        let err = paper(Some("1.21.1"), Some("1")).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what.contains("has no builds yet")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn papermc_unknown_version_suggests_recent_ones() {
+        for build in [None, Some("7")] {
+            let err = paper(Some("9.9"), build).unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    Error::VersionNotFound { platform, version, recent }
+                        if platform == "paper" && version == "9.9" && recent == "1.21.3, 1.21.1, 1.20.4"
+                ),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn papermc_unknown_build_lists_valid_ones() {
+        let err = paper(Some("1.21.3"), Some("99")).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what.ends_with("(valid builds: 6, 7)")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn purpur_falls_back_to_a_build_with_details() {
+        // the latest build, 2301, has no details yet
+        let artifact = purpur(None, None).unwrap();
+        assert_eq!(artifact.version, "1.21.3");
+        assert_eq!(artifact.build.as_deref(), Some("2300"));
+        assert_eq!(artifact.commits, ["1a2b3c4 Update upstream"]);
+        assert!(matches!(
+            artifact.published_digest,
+            Some(PublishedDigest::Md5(ref md5)) if md5 == "d41d8cd98f00b204e9800998ecf8427e"
+        ));
+
+        // a pinned build isn't swapped for another
+        let err = purpur(Some("1.21.3"), Some("2301")).unwrap_err();
+        assert!(matches!(err, Error::PlatformsNotFound(_)), "{err:?}");
+    }
+
+    #[test]
+    fn purpur_version_without_builds() {
+        let err = purpur(Some("1.21.1"), Some("1")).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what.contains("has no builds yet")),
+            "{err:?}"
+        );
+
+        let err = purpur(Some("1.21.1"), None).unwrap_err();
+        assert!(matches!(err, Error::PlatformsNotFound(_)), "{err:?}");
+    }
+
+    #[test]
+    fn purpur_unknown_version_suggests_recent_ones() {
+        let err = purpur(Some("9.9"), None).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Error::VersionNotFound { version, recent, .. }
+                    if version == "9.9" && recent == "1.21.3, 1.21.1"
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn purpur_unknown_build_lists_valid_ones() {
+        let err = purpur(Some("1.21.3"), Some("1")).unwrap_err();
+        assert!(
+            matches!(&err, Error::PlatformsNotFound(what) if what.ends_with("(valid builds: 2300, 2301)")),
+            "{err:?}"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+use crate::{
+    error::{Error, Result},
+    platforms::get_client,
+    properties::Properties,
+    safe_edit,
+    server::get_server_dir_required,
+};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+};
+
+const MOJANG_PROFILE_API_URL: &str = "https://api.mojang.com/users/profiles/minecraft";
+
+/// The operator permission level used when `op-permission-level` isn't set
+const DEFAULT_OP_LEVEL: u8 = 4;
+
+#[derive(Clone, Copy)]
+pub enum PlayerList {
+    Whitelist,
+    Ops,
+}
+
+impl PlayerList {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Whitelist => "whitelist.json",
+            Self::Ops => "ops.json",
+        }
+    }
+}
+
+impl Display for PlayerList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Whitelist => write!(f, "whitelist"),
+            Self::Ops => write!(f, "operators"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UuidMode {
+    Online,
+    Offline,
+}
+
+impl Display for UuidMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Online => write!(f, "online"),
+            Self::Offline => write!(f, "offline"),
+        }
+    }
+}
+
+fn hyphenate(hex: &str) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Derives the UUID an offline mode server gives a player, which is a version 3 UUID of
+/// `OfflinePlayer:<name>`
+pub fn offline_uuid(player: &str) -> String {
+    let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{player}")).into();
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    hyphenate(&hex)
+}
+
+#[derive(Deserialize)]
+struct MojangProfile {
+    id: String,
+    name: String,
+}
+
+/// Looks up a player's UUID with the Mojang API, returning the correctly capitalized name as well
+fn online_profile(player: &str) -> Result<(String, String)> {
+    let profile: MojangProfile = get_client()?
+        .get_json_optional(format!("{MOJANG_PROFILE_API_URL}/{player}"))?
+        .ok_or_else(|| Error::PlayerNotFound(player.to_string()))?;
+
+    Ok((profile.name, hyphenate(&profile.id)))
+}
+
+/// Uses the mode that was asked for, or otherwise the server's `online-mode`
+fn resolve_mode(properties: &Properties, mode: Option<UuidMode>) -> UuidMode {
+    mode.unwrap_or_else(|| match properties.get("online-mode") {
+        Some("false") => UuidMode::Offline,
+        _ => UuidMode::Online,
+    })
+}
+
+fn read_list(server_dir: &Path, list: PlayerList) -> Result<Vec<Value>> {
+    let path = server_dir.join(list.file_name());
+
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_list(server_dir: &Path, list: PlayerList, entries: &[Value]) -> Result<()> {
+    safe_edit::write(
+        server_dir.join(list.file_name()),
+        &serde_json::to_string_pretty(entries)?,
+        |contents| {
+            serde_json::from_str::<Vec<Value>>(contents)?;
+            Ok(())
+        },
+    )
+}
+
+fn entry_name(entry: &Value) -> Option<&str> {
+    entry.get("name").and_then(Value::as_str)
+}
+
+pub fn add(server: &str, list: PlayerList, player: &str, mode: Option<UuidMode>) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let properties = Properties::read(&server_dir)?;
+    let mode = resolve_mode(&properties, mode);
+
+    let (name, uuid) = match mode {
+        UuidMode::Online => online_profile(player)?,
+        UuidMode::Offline => (player.to_string(), offline_uuid(player)),
+    };
+
+    let mut entries = read_list(&server_dir, list)?;
+    entries.retain(|entry| entry_name(entry).is_none_or(|n| !n.eq_ignore_ascii_case(&name)));
+
+    let mut entry = Map::new();
+    entry.insert("uuid".to_string(), Value::from(uuid.clone()));
+    entry.insert("name".to_string(), Value::from(name.clone()));
+
+    if let PlayerList::Ops = list {
+        let level = properties
+            .get("op-permission-level")
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(DEFAULT_OP_LEVEL);
+        entry.insert("level".to_string(), Value::from(level));
+        entry.insert("bypassesPlayerLimit".to_string(), Value::from(false));
+    }
+
+    entries.push(Value::Object(entry));
+    write_list(&server_dir, list, &entries)?;
+
+    println!("Added {name} ({uuid}) to the {list} of {server} using {mode} mode UUIDs");
+    Ok(())
+}
+
+pub fn remove(server: &str, list: PlayerList, player: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let mut entries = read_list(&server_dir, list)?;
+    let count = entries.len();
+
+    entries.retain(|entry| entry_name(entry).is_none_or(|n| !n.eq_ignore_ascii_case(player)));
+
+    if entries.len() == count {
+        return Err(Error::PlayerNotFound(player.to_string()));
+    }
+
+    write_list(&server_dir, list, &entries)?;
+    println!("Removed {player} from the {list} of {server}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuids_match_known_values() {
+        for (player, uuid) in [
+            ("Notch", "b50ad385-829d-3141-a216-7e7d7539ba7f"),
+            ("jeb_", "a762f560-4fce-3236-812a-b80efff0b62b"),
+            ("Steve", "5627dd98-e6be-3c21-b8a8-e92344183641"),
+            ("a", "52428a0e-1e30-3cb1-976c-e728b2614047"),
+        ] {
+            assert_eq!(offline_uuid(player), uuid, "{player}");
+        }
+    }
+
+    #[test]
+    fn offline_uuids_are_case_sensitive() {
+        assert_ne!(offline_uuid("Notch"), offline_uuid("notch"));
+    }
+
+    #[test]
+    fn offline_uuids_are_version_3() {
+        let uuid = offline_uuid("Alex");
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "3");
+        assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Keeping the plugins of several servers identical to one server's, such as the backends behind a
+//! proxy. Only the jars at the top of `plugins/` are compared, by name and SHA-256, so that the
+//! directories next to them, where each server keeps its own plugin configuration, are left alone.
+
+use crate::{
+    checksum,
+    error::{Error, Result},
+    groups,
+    server::{self, get_server_dir_required},
+    session::get_alive_server_sessions,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const PLUGINS_DIRECTORY: &str = "plugins";
+
+enum Change {
+    /// A jar the target doesn't have
+    Add(String),
+    /// A jar the target has with other contents
+    Replace(String),
+    /// A jar only the target has, removed with `--prune`
+    Remove(String),
+    /// A jar only the target has, kept without `--prune`
+    Extra(String),
+}
+
+impl Change {
+    fn is_applied(&self) -> bool {
+        !matches!(self, Self::Extra(_))
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add(jar) => write!(f, "\x1b[32;1m+\x1b[0m {jar}"),
+            Self::Replace(jar) => write!(f, "\x1b[33;1m~\x1b[0m {jar}"),
+            Self::Remove(jar) => write!(f, "\x1b[31;1m-\x1b[0m {jar}"),
+            Self::Extra(jar) => write!(f, "  {jar} (only on this server, kept without --prune)"),
+        }
+    }
+}
+
+struct TargetDiff {
+    server: String,
+    dir: PathBuf,
+    changes: Vec<Change>,
+    running: bool,
+}
+
+impl TargetDiff {
+    fn is_changed(&self) -> bool {
+        self.changes.iter().any(Change::is_applied)
+    }
+}
+
+/// The jars at the top of a plugins directory by name, along with their digests
+fn read_jars(plugins_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut jars = BTreeMap::new();
+
+    if !plugins_dir.is_dir() {
+        return Ok(jars);
+    }
+
+    for entry in fs::read_dir(plugins_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "jar") {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            jars.insert(name.to_string(), checksum::sha256_file(&path)?);
+        }
+    }
+
+    Ok(jars)
+}
+
+fn diff(source: &BTreeMap<String, String>, target_dir: &Path, prune: bool) -> Result<Vec<Change>> {
+    let target = read_jars(&target_dir.join(PLUGINS_DIRECTORY))?;
+    let mut changes = vec![];
+
+    for (jar, digest) in source {
+        match target.get(jar) {
+            None => changes.push(Change::Add(jar.clone())),
+            Some(target_digest) if target_digest != digest => {
+                changes.push(Change::Replace(jar.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for jar in target.keys().filter(|jar| !source.contains_key(*jar)) {
+        changes.push(if prune {
+            Change::Remove(jar.clone())
+        } else {
+            Change::Extra(jar.clone())
+        });
+    }
+
+    Ok(changes)
+}
+
+/// The servers a sync goes to, which are the members of a group other than the source, or a single
+/// server
+fn targets(source: &str, target: &str) -> Result<Vec<String>> {
+    let targets = match groups::group_name(target) {
+        Some(group) => groups::members(group, groups::Order::Listed)?,
+        None => vec![target.to_string()],
+    };
+
+    Ok(targets
+        .into_iter()
+        .filter(|target| target != source)
+        .collect())
+}
+
+/// Copies a jar next to the one it replaces before renaming it over it, so that a running server
+/// keeps reading the jar it loaded rather than one being written
+fn copy_jar(src: &Path, dst_dir: &Path, jar: &str) -> Result<()> {
+    let temp = dst_dir.join(format!(".{jar}.tmp"));
+    fs::copy(src, &temp)?;
+    fs::rename(&temp, dst_dir.join(jar))?;
+    Ok(())
+}
+
+fn apply(source_plugins: &Path, target: &TargetDiff) -> Result<()> {
+    let plugins = target.dir.join(PLUGINS_DIRECTORY);
+    fs::create_dir_all(&plugins)?;
+
+    for change in &target.changes {
+        match change {
+            Change::Add(jar) | Change::Replace(jar) => {
+                copy_jar(&source_plugins.join(jar), &plugins, jar)?
+            }
+            Change::Remove(jar) => fs::remove_file(plugins.join(jar))?,
+            Change::Extra(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn print_report(diffs: &[TargetDiff]) {
+    for target in diffs {
+        let running = if target.running && target.is_changed() {
+            " \x1b[33;1m[running, needs a restart]\x1b[0m"
+        } else {
+            ""
+        };
+
+        if target.changes.is_empty() {
+            println!("{}: in sync", target.server);
+            continue;
+        }
+
+        println!("{}:{running}", target.server);
+        for change in &target.changes {
+            println!("  {change}");
+        }
+    }
+}
+
+/// Restarts servers one at a time, waiting for each to be ready before stopping the next, so that
+/// only one is ever down. The first that fails to come back up stops the roll.
+fn rolling_restart(servers: &[&str], timeout: Duration) -> Result<()> {
+    for server in servers {
+        println!("Restarting {server}...");
+        server::stop(server, Some(timeout))?;
+        server::deploy(server, false, false, Some(timeout))?;
+    }
+
+    Ok(())
+}
+
+/// Makes the plugin jars of `target`, a server or a group, match those of `source`, only printing
+/// the differences with `dry_run`. Jars only the targets have are removed with `prune`. Running
+/// targets that changed are restarted one by one with `restart_timeout`, and otherwise left to be
+/// restarted by hand.
+pub fn sync(
+    source: &str,
+    target: &str,
+    dry_run: bool,
+    prune: bool,
+    restart_timeout: Option<Duration>,
+) -> Result<()> {
+    let source_plugins = get_server_dir_required(source)?.join(PLUGINS_DIRECTORY);
+    if !source_plugins.is_dir() {
+        return Err(Error::NoPluginsDirectory(source.to_string()));
+    }
+    let source_jars = read_jars(&source_plugins)?;
+
+    let alive: HashSet<String> = get_alive_server_sessions()?;
+    let diffs = targets(source, target)?
+        .into_iter()
+        .map(|server| {
+            let dir = get_server_dir_required(&server)?;
+            Ok(TargetDiff {
+                changes: diff(&source_jars, &dir, prune)?,
+                running: alive.contains(&server),
+                dir,
+                server,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    print_report(&diffs);
+
+    if dry_run {
+        println!("Dry run, nothing was changed");
+        return Ok(());
+    }
+
+    for target in diffs.iter().filter(|target| target.is_changed()) {
+        apply(&source_plugins, target)?;
+    }
+
+    let to_restart: Vec<&str> = diffs
+        .iter()
+        .filter(|target| target.running && target.is_changed())
+        .map(|target| target.server.as_str())
+        .collect();
+
+    if to_restart.is_empty() {
+        return Ok(());
+    }
+
+    match restart_timeout {
+        Some(timeout) => rolling_restart(&to_restart, timeout),
+        None => {
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m running servers keep their old plugins until they are \
+                 restarted, which --rolling-restart does one at a time: {}",
+                to_restart.join(", ")
+            );
+            Ok(())
+        }
+    }
+}
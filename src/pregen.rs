@@ -0,0 +1,151 @@
+use crate::{
+    config::has_rcon_config,
+    error::{Error, Result},
+    modrinth::{self, MOD_LOADERS, PLUGIN_LOADERS},
+    progress::{Event, Reporter},
+    server::{get_server_dir_required, rcon_output},
+};
+use std::{fs, path::Path, thread, time::Duration};
+
+const CHUNKY_PROJECT: &str = "chunky";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct Progress {
+    pub percent: f64,
+    pub eta: Option<String>,
+}
+
+fn has_chunky(directory: &Path) -> Result<bool> {
+    if !directory.is_dir() {
+        return Ok(false);
+    }
+
+    for entry in fs::read_dir(directory)? {
+        let name = entry?.file_name().to_string_lossy().to_lowercase();
+        if name.starts_with(CHUNKY_PROJECT) && name.ends_with(".jar") {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Makes sure Chunky is present in the server's plugins or mods directory, installing it from
+/// Modrinth if it isn't
+fn ensure_chunky(server: &str) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+    let plugins_dir = server_dir.join("plugins");
+    let mods_dir = server_dir.join("mods");
+
+    if has_chunky(&plugins_dir)? || has_chunky(&mods_dir)? {
+        return Ok(());
+    }
+
+    let installed = if mods_dir.is_dir() {
+        modrinth::install_latest(CHUNKY_PROJECT, &MOD_LOADERS, mods_dir)?
+    } else {
+        modrinth::install_latest(CHUNKY_PROJECT, &PLUGIN_LOADERS, plugins_dir)?
+    };
+
+    Err(Error::RestartRequired(format!(
+        "Chunky was installed to {}",
+        installed.display()
+    )))
+}
+
+/// Parses the output of `chunky progress`, e.g.
+/// `Task running for world. Processed: 1234 chunks (12.34%), ETA: 0:12:34, Rate: 123.4 cps`
+fn parse_progress(output: &str) -> Option<Progress> {
+    let (_, after) = output.split_once('(')?;
+    let (percent, _) = after.split_once('%')?;
+
+    let eta = output
+        .split_once("ETA: ")
+        .and_then(|(_, after)| after.split(',').next())
+        .map(|eta| eta.trim().to_string());
+
+    Some(Progress {
+        percent: percent.trim().parse().ok()?,
+        eta,
+    })
+}
+
+fn require_rcon(server: &str) -> Result<()> {
+    if has_rcon_config(server)? {
+        Ok(())
+    } else {
+        Err(Error::MissingRconConfig(server.to_string()))
+    }
+}
+
+pub fn start(
+    server: &str,
+    radius: u32,
+    world: Option<&str>,
+    detach: bool,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    require_rcon(server)?;
+    ensure_chunky(server)?;
+
+    if let Some(world) = world {
+        rcon_output(server, format!("chunky world {world}"))?;
+    }
+
+    rcon_output(server, format!("chunky radius {radius}"))?;
+    println!("{}", rcon_output(server, "chunky start")?);
+
+    if detach {
+        println!("Pre-generation started, check on it with `mcserver pregen status {server}`");
+        return Ok(());
+    }
+
+    watch(server, reporter)
+}
+
+fn watch(server: &str, reporter: &dyn Reporter) -> Result<()> {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let progress = parse_progress(&rcon_output(server, "chunky progress")?);
+
+        if let Some(progress) = &progress {
+            reporter.report(Event::PregenProgress {
+                percent: progress.percent,
+                eta: progress.eta.as_deref(),
+            });
+        }
+
+        if progress.is_none_or(|progress| progress.percent >= 100.0) {
+            if reporter.is_human() {
+                eprintln!();
+            }
+            reporter.message("Pre-generation finished");
+            reporter.report(Event::Done {
+                path: &get_server_dir_required(server)?,
+            });
+            return Ok(());
+        }
+    }
+}
+
+pub fn status(server: &str) -> Result<()> {
+    require_rcon(server)?;
+
+    match parse_progress(&rcon_output(server, "chunky progress")?) {
+        Some(progress) => println!(
+            "{:.2}% (ETA {})",
+            progress.percent,
+            progress.eta.as_deref().unwrap_or("unknown")
+        ),
+        None => println!("No pre-generation is running on {server}"),
+    }
+
+    Ok(())
+}
+
+pub fn cancel(server: &str) -> Result<()> {
+    require_rcon(server)?;
+    println!("{}", rcon_output(server, "chunky cancel")?);
+    Ok(())
+}
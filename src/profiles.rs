@@ -0,0 +1,222 @@
+//! Named bundles of properties, gamerules, plugins and java arguments from the configuration,
+//! applied to servers as they are created.
+
+use crate::{
+    config,
+    config_defs::Profile,
+    error::{Error, Result},
+    modrinth::{self, PLUGIN_LOADERS},
+    platforms::Platform,
+    properties::{self, Properties},
+    server::{JAVA_ARGS_FILE, METADATA_DIRECTORY, get_server_dir_required},
+    session, textdiff,
+};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+/// Console commands run once the server is first deployed, one per line
+const FIRST_START_FILE: &str = "first-start.txt";
+
+pub fn get(name: &str) -> Result<Profile> {
+    let mut profiles = config::snapshot()?.profiles.unwrap_or_default();
+
+    profiles.remove(name).ok_or_else(|| {
+        let mut available: Vec<_> = profiles.into_keys().collect();
+        available.sort();
+        Error::ProfileNotFound {
+            name: name.to_string(),
+            available,
+        }
+    })
+}
+
+pub fn list() -> Result<()> {
+    let mut names: Vec<_> = config::snapshot()?
+        .profiles
+        .unwrap_or_default()
+        .into_keys()
+        .collect();
+    names.sort();
+
+    for name in names {
+        println!("{name}");
+    }
+
+    Ok(())
+}
+
+fn report(kind: &str, item: &str, result: Result<()>) -> bool {
+    match result {
+        Ok(()) => {
+            println!("[\x1b[32;1mok\x1b[0m] {kind} {item}");
+            true
+        }
+        Err(err) => {
+            println!("[\x1b[31;1mfail\x1b[0m] {kind} {item}: {err}");
+            false
+        }
+    }
+}
+
+fn queue_first_start(metadata_dir: &Path, command: &str) -> Result<()> {
+    fs::create_dir_all(metadata_dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(metadata_dir.join(FIRST_START_FILE))?;
+    writeln!(file, "{command}")?;
+    Ok(())
+}
+
+/// Downloads a plugin or mod, into `mods` for mod loaders and `plugins` otherwise. Without a known
+/// platform, an existing `mods` directory decides.
+fn install_plugin(server_dir: &Path, platform: Option<Platform>, slug: &str) -> Result<()> {
+    let mods_dir = server_dir.join("mods");
+
+    match platform {
+        Some(platform @ (Platform::Fabric | Platform::Forge | Platform::Neoforge)) => {
+            modrinth::install_latest(slug, &[platform.to_string()], mods_dir)?
+        }
+        // Quilt also loads Fabric mods
+        Some(Platform::Quilt) => modrinth::install_latest(slug, &["quilt", "fabric"], mods_dir)?,
+        // proxy plugins are built for the proxy alone
+        Some(Platform::Velocity) => {
+            modrinth::install_latest(slug, &["velocity"], server_dir.join("plugins"))?
+        }
+        None if mods_dir.is_dir() => {
+            modrinth::install_latest(slug, &modrinth::MOD_LOADERS, mods_dir)?
+        }
+        _ => modrinth::install_latest(slug, &PLUGIN_LOADERS, server_dir.join("plugins"))?,
+    };
+
+    Ok(())
+}
+
+/// Applies a profile to a server, carrying on past items that fail and reporting each one.
+/// Gamerules can only be set on a running server, so they are queued for its first deploy.
+pub fn apply(server: &str, name: &str, platform: Option<Platform>) -> Result<()> {
+    let profile = get(name)?;
+    let server_dir = get_server_dir_required(server)?;
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    let mut failures = 0;
+
+    if let Some(values) = &profile.properties {
+        let mut entries: Vec<_> = values.iter().collect();
+        entries.sort();
+
+        let mut properties = Properties::read(&server_dir)?;
+        for (key, value) in &entries {
+            properties.set(key, value);
+        }
+
+        match properties.write(&server_dir) {
+            Ok(()) => {
+                for (key, value) in entries {
+                    report("property", &format!("{key}={value}"), Ok(()));
+                }
+            }
+            Err(err) => {
+                failures += 1;
+                report("properties", properties::FILE_NAME, Err(err));
+            }
+        }
+    }
+
+    for gamerule in profile.gamerules.iter().flatten() {
+        let result = queue_first_start(&metadata_dir, &format!("gamerule {gamerule}"));
+        failures += usize::from(!report("gamerule (queued)", gamerule, result));
+    }
+
+    for slug in profile.plugins.iter().flatten() {
+        let result = install_plugin(&server_dir, platform, slug);
+        failures += usize::from(!report("plugin", slug, result));
+    }
+
+    if let Some(java_args) = &profile.java_args {
+        let result = fs::create_dir_all(&metadata_dir)
+            .and_then(|()| fs::write(metadata_dir.join(JAVA_ARGS_FILE), java_args))
+            .map_err(Error::from);
+        failures += usize::from(!report("java args", java_args, result));
+    }
+
+    if failures > 0 {
+        return Err(Error::ProfileIncomplete(failures));
+    }
+
+    Ok(())
+}
+
+/// Sends the commands queued for a server's first start to its session, then forgets them
+pub fn run_first_start(server: &str) -> Result<()> {
+    let path = get_server_dir_required(server)?
+        .join(METADATA_DIRECTORY)
+        .join(FIRST_START_FILE);
+
+    let commands = match fs::read_to_string(&path) {
+        Ok(commands) => commands,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let session_name = session::get_name(server);
+    for command in commands.lines().filter(|line| !line.trim().is_empty()) {
+        session::write_line(&session_name, command)?;
+    }
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Writes a profile as standalone TOML, to a file or stdout, for sharing
+pub fn export(name: &str, path: Option<&Path>) -> Result<()> {
+    let contents = toml::to_string(&get(name)?)?;
+
+    match path {
+        Some(path) => fs::write(path, contents)?,
+        None => print!("{contents}"),
+    }
+
+    Ok(())
+}
+
+/// Adds a profile from a standalone TOML file, named after the file unless a name is given, and
+/// returns the name it was saved under
+pub fn import(path: &Path, name: Option<String>, force: bool) -> Result<String> {
+    let profile: Profile = toml::from_str(&fs::read_to_string(path)?)?;
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(|| Error::MissingFile {
+                file: path.to_path_buf(),
+            })?,
+    };
+
+    // going through a table sorts the keys, so that the diff only shows real changes
+    let sorted = |profile: &Profile| -> Result<String> {
+        Ok(toml::to_string(&toml::Table::try_from(profile)?)?)
+    };
+    let new = sorted(&profile)?;
+    let replaced = config::update(|cfg| {
+        let profiles = cfg.profiles.get_or_insert_default();
+        if !force && profiles.contains_key(&name) {
+            return Err(Error::ProfileAlreadyExists(name.clone()));
+        }
+
+        Ok(profiles.insert(name.clone(), profile))
+    })??;
+
+    if let Some(replaced) = replaced {
+        textdiff::print(
+            &format!("profile {name}"),
+            &toml::to_string(&replaced)?,
+            &new,
+        );
+    }
+
+    Ok(name)
+}
@@ -0,0 +1,203 @@
+//! Progress reporting for long operations, either for people or, with `--json-lines`, as one JSON
+//! object per line on stdout for other programs to consume.
+//!
+//! The JSON events are a stable interface. Each object has an `event` field naming it, along
+//! with the fields listed here:
+//!
+//! - `resolved`: `platform`, `version` and `build` (or null), once a version has been picked
+//! - `download_started`: `url` and `total`, the size in bytes (or null when unknown)
+//! - `download_progress`: `bytes` written so far, `total`, the average `bytes_per_second` and
+//!   `eta_seconds` (or null when the total is unknown), at most once per MiB
+//! - `download_finished`: `bytes` and `sha256`, once the jar has been verified
+//! - `build_output`: `line`, for each line BuildTools prints while building a Spigot jar
+//! - `pregen_progress`: `percent` and `eta` (or null)
+//! - `server_finished`: `server`, `duration_ms` and `error` (or null), for each server a bulk
+//!   operation is done with
+//! - `done`: `path` of the server the operation finished with
+//!
+//! New events and fields may be added, so consumers should ignore what they don't recognize.
+
+use crate::{humanize, platforms::Platform};
+use serde::Serialize;
+use std::{
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+const BUILD_LINE_WIDTH: usize = 100;
+const MIB: f64 = 1024.0 * 1024.0;
+const BAR_WIDTH: usize = 30;
+/// Shown instead of a bar when the size of a download isn't known
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// A bar such as `[=========>          ]  45%`
+pub fn progress_bar(bytes: u64, total: u64) -> String {
+    let fraction = (bytes as f64 / total as f64).clamp(0.0, 1.0);
+    let filled = (fraction * BAR_WIDTH as f64) as usize;
+    let head = if filled < BAR_WIDTH { ">" } else { "" };
+
+    format!(
+        "[{}{head}{}] {:3.0}%",
+        "=".repeat(filled),
+        " ".repeat(BAR_WIDTH.saturating_sub(filled + head.len())),
+        fraction * 100.0
+    )
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Resolved {
+        platform: Platform,
+        version: &'a str,
+        build: Option<&'a str>,
+    },
+    DownloadStarted {
+        url: &'a str,
+        total: Option<u64>,
+    },
+    DownloadProgress {
+        bytes: u64,
+        total: Option<u64>,
+        bytes_per_second: u64,
+        eta_seconds: Option<u64>,
+    },
+    DownloadFinished {
+        bytes: u64,
+        sha256: &'a str,
+    },
+    BuildOutput {
+        line: &'a str,
+    },
+    PregenProgress {
+        percent: f64,
+        eta: Option<&'a str>,
+    },
+    ServerFinished {
+        server: &'a str,
+        duration_ms: u64,
+        error: Option<&'a str>,
+    },
+    Done {
+        path: &'a Path,
+    },
+}
+
+pub trait Reporter {
+    fn report(&self, event: Event);
+
+    /// Prints output meant for people, which must not be mixed into machine readable output
+    fn message(&self, text: &str);
+
+    /// Whether the caller should print its own human readable results
+    fn is_human(&self) -> bool;
+}
+
+/// Prints progress for people, on stderr so that stdout stays free for results
+pub struct Human;
+
+impl Reporter for Human {
+    fn report(&self, event: Event) {
+        match event {
+            Event::Resolved {
+                platform,
+                version,
+                build: Some(build),
+            } => eprintln!("Resolved {platform} {version} (build {build})"),
+            Event::Resolved {
+                platform, version, ..
+            } => eprintln!("Resolved {platform} {version}"),
+            Event::DownloadStarted { url, .. } => eprintln!("Downloading from {url}..."),
+            Event::DownloadProgress {
+                bytes,
+                total,
+                bytes_per_second,
+                eta_seconds,
+            } => {
+                // a line redrawn in place would pile up in a log file
+                if !io::stderr().is_terminal() {
+                    return;
+                }
+
+                let rate = bytes_per_second as f64 / MIB;
+                let line = match total.filter(|total| *total > 0) {
+                    Some(total) => {
+                        let eta = eta_seconds
+                            .map(|eta| format!(", ETA {}", humanize::duration(eta)))
+                            .unwrap_or_default();
+                        format!(
+                            "{} {:.1} / {:.1} MiB at {rate:.1} MiB/s{eta}",
+                            progress_bar(bytes, total),
+                            bytes as f64 / MIB,
+                            total as f64 / MIB
+                        )
+                    }
+                    None => {
+                        let spinner = SPINNER[(bytes / MIB as u64) as usize % SPINNER.len()];
+                        format!("{spinner} {:.1} MiB at {rate:.1} MiB/s", bytes as f64 / MIB)
+                    }
+                };
+                eprint!("\r\x1b[K{line}");
+                let _ = io::stderr().flush();
+            }
+            Event::DownloadFinished { bytes, .. } => {
+                if io::stderr().is_terminal() {
+                    // clear the progress line
+                    eprint!("\r\x1b[K");
+                    let _ = io::stderr().flush();
+                } else {
+                    eprintln!("Downloaded {}", humanize::bytes(bytes));
+                }
+            }
+            Event::BuildOutput { line } => {
+                // one line that keeps being replaced, since the full output is thousands of lines
+                let line: String = line.chars().take(BUILD_LINE_WIDTH).collect();
+                eprint!("\r\x1b[K{line}");
+                let _ = io::stderr().flush();
+            }
+            Event::PregenProgress { percent, eta } => {
+                eprint!("\r{percent:6.2}% (ETA {})", eta.unwrap_or("unknown"));
+                let _ = io::stderr().flush();
+            }
+            // bulk operations print their own results
+            Event::ServerFinished { .. } | Event::Done { .. } => {}
+        }
+    }
+
+    fn message(&self, text: &str) {
+        println!("{text}");
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
+
+/// Prints each event as a line of JSON on stdout, moving human output to stderr
+pub struct JsonLines;
+
+impl Reporter for JsonLines {
+    fn report(&self, event: Event) {
+        if let Ok(line) = serde_json::to_string(&event) {
+            let mut stdout = io::stdout().lock();
+            let _ = writeln!(stdout, "{line}");
+            let _ = stdout.flush();
+        }
+    }
+
+    fn message(&self, text: &str) {
+        eprintln!("{text}");
+    }
+
+    fn is_human(&self) -> bool {
+        false
+    }
+}
+
+pub fn reporter(json_lines: bool) -> Box<dyn Reporter> {
+    if json_lines {
+        Box::new(JsonLines)
+    } else {
+        Box::new(Human)
+    }
+}
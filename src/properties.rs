@@ -0,0 +1,119 @@
+use crate::{
+    error::{Error, Result},
+    safe_edit,
+    server::get_server_dir_required,
+    worlds,
+};
+use std::{fs, path::Path};
+
+pub const FILE_NAME: &str = "server.properties";
+
+/// A `server.properties` file which keeps comments, ordering and unknown keys intact when written
+/// back
+pub struct Properties {
+    lines: Vec<String>,
+}
+
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+        return None;
+    }
+
+    let (key, value) = trimmed.split_once('=')?;
+    Some((key.trim(), value.trim_start()))
+}
+
+impl Properties {
+    /// Reads the properties of a server, treating a missing file as empty
+    pub fn read(server_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = server_dir.as_ref().join(FILE_NAME);
+
+        let lines = if path.exists() {
+            fs::read_to_string(path)?
+                .lines()
+                .map(String::from)
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(Self { lines })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| parse_line(line))
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn set(&mut self, key: &str, value: impl AsRef<str>) {
+        let new_line = format!("{key}={}", value.as_ref());
+
+        match self
+            .lines
+            .iter_mut()
+            .find(|line| parse_line(line).is_some_and(|(k, _)| k == key))
+        {
+            Some(line) => *line = new_line,
+            None => self.lines.push(new_line),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.lines
+            .retain(|line| parse_line(line).is_none_or(|(k, _)| k != key));
+    }
+
+    /// Returns the file contents as they would be written
+    pub fn contents(&self) -> String {
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+        contents
+    }
+
+    /// Writes the file back through [`safe_edit`], refusing values that would not read back the
+    /// same, such as ones containing line breaks
+    pub fn write(&self, server_dir: impl AsRef<Path>) -> Result<()> {
+        safe_edit::write(
+            server_dir.as_ref().join(FILE_NAME),
+            &self.contents(),
+            |contents| {
+                if contents.lines().eq(self.lines.iter().map(String::as_str)) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidEdit(FILE_NAME.to_string()))
+                }
+            },
+        )
+    }
+}
+
+/// Prints the value of a server property
+pub fn print(server: &str, key: &str) -> Result<()> {
+    let properties = Properties::read(get_server_dir_required(server)?)?;
+
+    match properties.get(key) {
+        Some(value) => println!("{value}"),
+        None => return Err(Error::PropertyNotSet(key.to_string())),
+    }
+
+    Ok(())
+}
+
+/// Sets a server property, renaming the world directories along with the level-name when migrating
+pub fn set(server: &str, key: &str, value: &str, migrate: bool) -> Result<()> {
+    if migrate {
+        if key != worlds::LEVEL_NAME_KEY {
+            return Err(Error::MigrationUnsupported(key.to_string()));
+        }
+        return worlds::rename_level(server, value);
+    }
+
+    let server_dir = get_server_dir_required(server)?;
+    let mut properties = Properties::read(&server_dir)?;
+    properties.set(key, value);
+    properties.write(&server_dir)
+}
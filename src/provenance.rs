@@ -0,0 +1,55 @@
+use crate::{
+    error::Result,
+    platforms::{Platform, ResolvedArtifact},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const PLATFORM_FILE: &str = "platform.toml";
+
+/// Where a server's jar came from, recorded when it is created or updated so that `update` can
+/// keep it on the same platform
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Provenance {
+    pub platform: Platform,
+    /// The version that was asked for, which is missing when the latest was
+    pub requested_version: Option<String>,
+    pub version: String,
+    pub build: Option<String>,
+    pub url: String,
+}
+
+/// Records the artifact a server was installed from. `version` is the version actually installed,
+/// which for Spigot is only known once BuildTools has picked it.
+pub fn record(
+    metadata_dir: impl AsRef<Path>,
+    artifact: &ResolvedArtifact,
+    requested_version: Option<&str>,
+    version: &str,
+) -> Result<()> {
+    let provenance = Provenance {
+        platform: artifact.platform,
+        requested_version: requested_version.map(String::from),
+        version: version.to_string(),
+        build: artifact.build.clone(),
+        url: artifact.url.clone(),
+    };
+
+    let metadata_dir = metadata_dir.as_ref();
+    fs::create_dir_all(metadata_dir)?;
+    fs::write(
+        metadata_dir.join(PLATFORM_FILE),
+        toml::to_string(&provenance)?,
+    )?;
+    Ok(())
+}
+
+pub fn read(metadata_dir: impl AsRef<Path>) -> Result<Option<Provenance>> {
+    let path = metadata_dir.as_ref().join(PLATFORM_FILE);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
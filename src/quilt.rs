@@ -0,0 +1,64 @@
+//! Sets up Quilt servers with the Quilt installer, since Quilt publishes no server jar to download.
+//! The installer fetches the loader's libraries and the vanilla server, and writes a launch jar
+//! that starts them.
+
+use crate::error::{Error, Result};
+use std::{
+    ffi::OsString,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+pub const INSTALLER_JAR: &str = "quilt-installer.jar";
+/// The jar the installer writes, which servers are launched with
+pub const LAUNCH_JAR: &str = "quilt-server-launch.jar";
+
+const JAVA_INSTALL_HINT: &str = "install Java, which the Quilt installer runs on";
+
+/// Runs the installer to set up a server for a game and loader version in a directory. It can take
+/// a while to download everything, so it runs without a time limit.
+pub fn run(
+    installer: &Path,
+    game_version: &str,
+    loader_version: &str,
+    server_dir: &Path,
+) -> Result<()> {
+    let mut install_dir = OsString::from("--install-dir=");
+    install_dir.push(server_dir);
+
+    let mut command = Command::new("java");
+    command
+        .arg("-jar")
+        .arg(installer)
+        .args([
+            "install",
+            "server",
+            game_version,
+            loader_version,
+            "--download-server",
+        ])
+        .arg(install_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+
+    let output = command
+        .output()
+        .map_err(|err| Error::from_spawn(err, "java", JAVA_INSTALL_HINT))?;
+
+    if !output.status.success() {
+        return Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        ));
+    }
+
+    if !server_dir.join(LAUNCH_JAR).is_file() {
+        return Err(Error::MissingFile {
+            file: server_dir.join(LAUNCH_JAR),
+        });
+    }
+
+    Ok(())
+}
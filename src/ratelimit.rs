@@ -0,0 +1,190 @@
+//! A download bandwidth limit, shared by every download in the process so that bulk operations
+//! running several downloads at once stay under it together.
+
+use crate::{
+    config,
+    error::{Error, Result},
+};
+use std::{
+    sync::{Mutex, OnceLock, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+static LIMIT_FLAG: OnceLock<u64> = OnceLock::new();
+static BUCKET: OnceLock<Option<Mutex<TokenBucket>>> = OnceLock::new();
+
+/// Refills at the rate limit and holds at most a second's worth of bytes, going into debt when a
+/// read takes more than it holds so that the debt is slept off
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: now,
+        }
+    }
+
+    /// Takes bytes from the bucket, returning how long to wait before taking more
+    fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate) - bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Parses a rate in bytes per second such as `500K`, `2M` or `1.5MB/s`, where the units are
+/// powers of 1024
+pub fn parse_rate(input: &str) -> Result<u64> {
+    let invalid = || Error::InvalidRate(input.to_string());
+
+    let trimmed = input.trim();
+    let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+    let trimmed = trimmed
+        .strip_suffix(['B', 'b'])
+        .unwrap_or(trimmed)
+        .to_ascii_uppercase();
+
+    let (number, multiplier) = match trimmed.chars().last() {
+        Some('K') => (&trimmed[..trimmed.len() - 1], 1024.0),
+        Some('M') => (&trimmed[..trimmed.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&trimmed[..trimmed.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (trimmed.as_str(), 1.0),
+    };
+
+    let rate = number.trim().parse::<f64>().map_err(|_| invalid())? * multiplier;
+    if !rate.is_finite() || rate < 1.0 {
+        return Err(invalid());
+    }
+
+    Ok(rate as u64)
+}
+
+/// Sets the limit given on the command line, which takes precedence over the configuration
+pub fn set_limit(rate: Option<u64>) {
+    if let Some(rate) = rate {
+        let _ = LIMIT_FLAG.set(rate);
+    }
+}
+
+fn bucket() -> Result<Option<&'static Mutex<TokenBucket>>> {
+    if let Some(bucket) = BUCKET.get() {
+        return Ok(bucket.as_ref());
+    }
+
+    let rate = match LIMIT_FLAG.get() {
+        Some(&rate) => Some(rate),
+        None => config::snapshot()?
+            .download_rate_limit
+            .as_deref()
+            .map(parse_rate)
+            .transpose()?,
+    };
+
+    Ok(BUCKET
+        .get_or_init(|| rate.map(|rate| Mutex::new(TokenBucket::new(rate, Instant::now()))))
+        .as_ref())
+}
+
+/// Waits as long as needed to keep downloads under the limit after reading some bytes, returning
+/// straight away when there is no limit
+pub fn throttle(bytes: usize) -> Result<()> {
+    let Some(bucket) = bucket()? else {
+        return Ok(());
+    };
+
+    let wait = bucket
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take(bytes, Instant::now());
+
+    if !wait.is_zero() {
+        thread::sleep(wait);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIB: u64 = 1024;
+
+    #[test]
+    fn rates_parse_with_binary_units() {
+        assert_eq!(parse_rate("500").unwrap(), 500);
+        assert_eq!(parse_rate("500K").unwrap(), 500 * KIB);
+        assert_eq!(parse_rate("2m").unwrap(), 2 * KIB * KIB);
+        assert_eq!(parse_rate("1.5MB/s").unwrap(), 1536 * KIB);
+        assert_eq!(parse_rate(" 1 G ").unwrap(), KIB * KIB * KIB);
+        assert_eq!(parse_rate("10kb").unwrap(), 10 * KIB);
+    }
+
+    #[test]
+    fn rates_reject_bad_input() {
+        for input in ["", "K", "fast", "0", "0.5", "-1M", "1T", "inf", "NaN"] {
+            assert!(parse_rate(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn full_bucket_allows_a_second_of_bytes_at_once() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+
+        assert_eq!(bucket.take(600, start), Duration::ZERO);
+        assert_eq!(bucket.take(400, start), Duration::ZERO);
+        assert_eq!(bucket.take(250, start), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn debt_is_paid_off_by_waiting() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+
+        assert_eq!(bucket.take(3000, start), Duration::from_secs(2));
+        // after sleeping off the debt, the bucket is empty rather than full
+        let later = start + Duration::from_secs(2);
+        assert_eq!(bucket.take(500, later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn refill_is_capped_at_a_second_of_bytes() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+        bucket.take(1000, start);
+
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(1000, later), Duration::ZERO);
+        assert_eq!(bucket.take(100, later), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn sustained_reads_average_out_at_the_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(64 * KIB, start);
+        let mut now = start;
+
+        // 4 MiB in 16 KiB reads, sleeping whenever told to
+        for _ in 0..256 {
+            now += bucket.take(16 * 1024, now);
+        }
+
+        // the first second's worth comes out of the full bucket
+        let elapsed = now.duration_since(start).as_secs_f64();
+        assert!((elapsed - 63.0).abs() < 0.01, "{elapsed}");
+    }
+}
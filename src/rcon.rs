@@ -0,0 +1,285 @@
+//! Finds the rcon endpoint of a server, and a minimal native RCON client for checking that
+//! endpoints answer without running any game command through `mcrcon`.
+
+use crate::{
+    config,
+    config_defs::{Password, RconConfig},
+    error::{Error, Result},
+    properties::Properties,
+    server::get_server_dir_required,
+};
+use serde::Serialize;
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The defaults `mcrcon` uses for endpoints that leave them out
+const DEFAULT_ADDRESS: &str = "localhost";
+const DEFAULT_PORT: u16 = 25575;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The `rcon` entry used by servers that have no entry of their own and don't enable rcon in their
+/// `server.properties`
+pub const DEFAULT_ENTRY: &str = "*";
+
+const LOGIN_PACKET: i32 = 3;
+const REQUEST_ID: i32 = 1;
+/// The request id a server answers a login with when the password is wrong
+const AUTH_FAILED_ID: i32 = -1;
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PingOutcome {
+    Reachable { latency_ms: u128 },
+    AuthFailed { latency_ms: u128 },
+    Unreachable { reason: String },
+}
+
+fn write_packet(stream: &mut TcpStream, kind: i32, body: &str) -> io::Result<()> {
+    let mut packet = vec![];
+    // the length covers the id, the type, the body and its two trailing nulls
+    packet.extend_from_slice(&(body.len() as i32 + 10).to_le_bytes());
+    packet.extend_from_slice(&REQUEST_ID.to_le_bytes());
+    packet.extend_from_slice(&kind.to_le_bytes());
+    packet.extend_from_slice(body.as_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    stream.write_all(&packet)
+}
+
+/// Reads a packet, returning its request id
+fn read_packet_id(stream: &mut TcpStream) -> io::Result<i32> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = i32::from_le_bytes(len);
+    if !(10..=4110).contains(&len) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid packet length {len}"),
+        ));
+    }
+
+    let mut rest = vec![0; len as usize];
+    stream.read_exact(&mut rest)?;
+    Ok(i32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]))
+}
+
+fn connect(address: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "the address did not resolve");
+
+    for addr in (address, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+
+    Err(last_err)
+}
+
+/// The endpoint rcon is enabled on in a server's `server.properties`, if it is, which is on this
+/// machine since the server's files are
+fn from_properties(server: &str) -> Result<Option<RconConfig>> {
+    let Ok(server_dir) = get_server_dir_required(server) else {
+        return Ok(None);
+    };
+
+    let properties = Properties::read(server_dir)?;
+    if properties.get("enable-rcon") != Some("true") {
+        return Ok(None);
+    }
+
+    Ok(Some(RconConfig {
+        server_address: None,
+        port: properties
+            .get("rcon.port")
+            .and_then(|port| port.trim().parse().ok()),
+        password: properties
+            .get("rcon.password")
+            .filter(|password| !password.is_empty())
+            .map(|password| Password(password.to_string())),
+    }))
+}
+
+/// Finds the rcon endpoint of a server, taking the first of:
+///
+/// 1. the server's own entry in the `rcon` configuration
+/// 2. its `server.properties`, when that enables rcon
+/// 3. the [`DEFAULT_ENTRY`] in the `rcon` configuration
+///
+/// Fields an endpoint leaves out fall back to `localhost` and port 25575. Endpoints given on the
+/// command line take precedence over all of these, see [`endpoint_from_flags`].
+pub fn resolve(server: &str) -> Result<RconConfig> {
+    let config = config::snapshot()?;
+    let entry = |name: &str| {
+        config
+            .rcon
+            .as_ref()
+            .and_then(|rcon| rcon.get(name).cloned())
+    };
+
+    if let Some(endpoint) = entry(server) {
+        return Ok(endpoint);
+    }
+
+    if let Some(endpoint) = from_properties(server)? {
+        return Ok(endpoint);
+    }
+
+    entry(DEFAULT_ENTRY).ok_or_else(|| Error::MissingRconConfig(server.to_string()))
+}
+
+/// Builds an endpoint from `--host`, `--port`, `--password` and `--password-env`, or returns
+/// `None` when none of them were given
+pub fn endpoint_from_flags(
+    host: Option<String>,
+    port: Option<u16>,
+    password: Option<String>,
+    password_env: Option<String>,
+) -> Result<Option<RconConfig>> {
+    let password = match password_env {
+        Some(var) => Some(env::var(&var).map_err(|_| Error::MissingEnvVar(var))?),
+        None => password,
+    };
+
+    if host.is_none() && port.is_none() && password.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(RconConfig {
+        server_address: host,
+        port,
+        password: password.map(Password),
+    }))
+}
+
+/// The address and port of an endpoint, with the defaults filled in
+pub fn address(endpoint: &RconConfig) -> (&str, u16) {
+    (
+        endpoint
+            .server_address
+            .as_deref()
+            .unwrap_or(DEFAULT_ADDRESS),
+        endpoint.port.unwrap_or(DEFAULT_PORT),
+    )
+}
+
+/// Connects to an endpoint and logs in, then closes the connection straight away
+pub fn ping(endpoint: &RconConfig) -> PingOutcome {
+    let (address, port) = address(endpoint);
+    let password = endpoint
+        .password
+        .as_ref()
+        .map(|password| password.0.as_str())
+        .unwrap_or_default();
+
+    let started = Instant::now();
+    let result = connect(address, port).and_then(|mut stream| {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write_packet(&mut stream, LOGIN_PACKET, password)?;
+        let id = read_packet_id(&mut stream)?;
+        let _ = stream.shutdown(Shutdown::Both);
+        Ok(id)
+    });
+    let latency_ms = started.elapsed().as_millis();
+
+    match result {
+        Ok(AUTH_FAILED_ID) => PingOutcome::AuthFailed { latency_ms },
+        Ok(_) => PingOutcome::Reachable { latency_ms },
+        Err(err) => PingOutcome::Unreachable {
+            reason: err.to_string(),
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct PingReport {
+    server: String,
+    #[serde(flatten)]
+    outcome: PingOutcome,
+}
+
+/// Pings the rcon endpoints of several servers at once, or of every configured one, failing if any
+/// of them can't be logged into
+pub fn test(servers: Vec<String>, all: bool, json: bool) -> Result<()> {
+    let config = config::snapshot()?;
+    let endpoints = config.rcon.unwrap_or_default();
+
+    let mut servers = if all {
+        endpoints.keys().cloned().collect()
+    } else {
+        servers
+    };
+    servers.sort();
+    servers.dedup();
+
+    let reports: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = servers
+            .into_iter()
+            .map(|server| {
+                let endpoint = endpoints.get(&server);
+                scope.spawn(move || {
+                    let outcome = match endpoint {
+                        Some(endpoint) => ping(endpoint),
+                        None => PingOutcome::Unreachable {
+                            reason: Error::MissingRconConfig(server.clone()).to_string(),
+                        },
+                    };
+                    PingReport { server, outcome }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("rcon pings don't panic"))
+            .collect()
+    });
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else if reports.is_empty() {
+        eprintln!("No rcon endpoints are configured");
+    }
+
+    let mut failures = 0;
+    for report in &reports {
+        let line = match &report.outcome {
+            PingOutcome::Reachable { latency_ms } => {
+                format!(
+                    "[\x1b[32;1mok\x1b[0m] {}: reachable ({latency_ms} ms)",
+                    report.server
+                )
+            }
+            PingOutcome::AuthFailed { latency_ms } => {
+                failures += 1;
+                format!(
+                    "[\x1b[31;1mfail\x1b[0m] {}: authentication failed ({latency_ms} ms)",
+                    report.server
+                )
+            }
+            PingOutcome::Unreachable { reason } => {
+                failures += 1;
+                format!(
+                    "[\x1b[31;1mfail\x1b[0m] {}: unreachable ({reason})",
+                    report.server
+                )
+            }
+        };
+
+        if !json {
+            println!("{line}");
+        }
+    }
+
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
+
+    Ok(())
+}
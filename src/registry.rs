@@ -0,0 +1,73 @@
+use crate::{
+    config::{get_config_directory, get_expanded_servers_dir},
+    error::Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const REGISTRY_FILE: &str = "sessions.toml";
+
+/// The sessions this tool has created, mapped to the server directory each one was created for,
+/// so that personal zellij sessions which happen to share the suffix are never mistaken for servers
+#[derive(Default, Deserialize, Serialize)]
+pub struct Registry {
+    #[serde(default)]
+    pub sessions: BTreeMap<String, PathBuf>,
+}
+
+fn get_registry_file() -> Result<PathBuf> {
+    Ok(get_config_directory()?.join(REGISTRY_FILE))
+}
+
+pub fn read() -> Result<Registry> {
+    let path = get_registry_file()?;
+
+    if !path.exists() {
+        return Ok(Registry::default());
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn write(registry: &Registry) -> Result<()> {
+    fs::create_dir_all(get_config_directory()?)?;
+    fs::write(get_registry_file()?, toml::to_string(registry)?)?;
+    Ok(())
+}
+
+fn get_server_dir(server: impl AsRef<Path>) -> Result<PathBuf> {
+    Ok(get_expanded_servers_dir()?.join(server))
+}
+
+impl Registry {
+    pub fn contains(&self, session_name: &str, server: &str) -> bool {
+        self.sessions
+            .get(session_name)
+            .is_some_and(|dir| get_server_dir(server).is_ok_and(|server_dir| *dir == server_dir))
+    }
+
+    pub fn insert(&mut self, session_name: String, server: &str) -> Result<()> {
+        self.sessions.insert(session_name, get_server_dir(server)?);
+        Ok(())
+    }
+}
+
+pub fn register(session_name: impl Into<String>, server: &str) -> Result<()> {
+    let mut registry = read()?;
+    registry.insert(session_name.into(), server)?;
+    write(&registry)
+}
+
+pub fn unregister(session_name: &str) -> Result<()> {
+    let mut registry = read()?;
+
+    if registry.sessions.remove(session_name).is_some() {
+        write(&registry)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Rebuilds a server's `.mcserver` metadata from the jars in its directory, for servers restored
+//! from backups that didn't include it.
+
+use crate::{
+    error::{Error, Result},
+    server::{
+        JAR_FILE_TXT_NAME, METADATA_DIRECTORY, find_jars, get_server_dir_required,
+        read_server_jar_path, set_default_metadata,
+    },
+};
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Asks which of several jars is the server's, returning `None` if the answer is empty
+fn prompt_for_jar(jars: &[String]) -> Result<Option<String>> {
+    for (i, jar) in jars.iter().enumerate() {
+        eprintln!("  {}) {jar}", i + 1);
+    }
+
+    loop {
+        eprint!(
+            "Pick the server jar (1-{}) or nothing to cancel: ",
+            jars.len()
+        );
+        io::stderr().flush()?;
+
+        let mut response = String::new();
+        io::stdin().read_line(&mut response)?;
+
+        if response.trim().is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(jar) = response
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|choice| jars.get(choice.checked_sub(1)?))
+        {
+            return Ok(Some(jar.clone()));
+        }
+    }
+}
+
+fn pick_jar(server_dir: &Path, jar: Option<String>) -> Result<String> {
+    let mut jars = find_jars(server_dir)?;
+
+    if let Some(jar) = jar {
+        return if jars.contains(&jar) {
+            Ok(jar)
+        } else {
+            Err(Error::MissingFile {
+                file: server_dir.join(jar),
+            })
+        };
+    }
+
+    match jars.len() {
+        0 => Err(Error::NoJarFound {
+            dir: server_dir.to_path_buf(),
+        }),
+        1 => Ok(jars.remove(0)),
+        _ => {
+            if is_interactive()
+                && let Some(jar) = prompt_for_jar(&jars)?
+            {
+                return Ok(jar);
+            }
+
+            Err(Error::MultipleJars {
+                dir: server_dir.to_path_buf(),
+                jars,
+            })
+        }
+    }
+}
+
+/// Recreates a server's metadata, picking its jar automatically when there's only one, and
+/// returns the jar it now uses. Intact metadata is only replaced with `force`.
+pub fn repair(server: &str, jar: Option<String>, force: bool) -> Result<String> {
+    let server_dir = get_server_dir_required(server)?;
+
+    if !force && read_server_jar_path(&server_dir).is_ok() {
+        return Err(Error::MetadataIntact(server.to_string()));
+    }
+
+    let jar = pick_jar(&server_dir, jar)?;
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+
+    // jar_file.txt is read only, so it has to be removed before it can be recreated
+    match fs::remove_file(metadata_dir.join(JAR_FILE_TXT_NAME)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+
+    set_default_metadata(metadata_dir, &jar)?;
+
+    Ok(jar)
+}
+
+/// Offers to repair a server whose metadata is missing when running interactively, returning
+/// whether it was repaired
+pub fn offer(server_dir: &Path) -> Result<bool> {
+    if !is_interactive() {
+        return Ok(false);
+    }
+
+    let server = server_dir
+        .file_name()
+        .unwrap_or(server_dir.as_os_str())
+        .to_string_lossy()
+        .to_string();
+
+    eprint!("The metadata of {server} is missing, rebuild it now? [y/N] ");
+    io::stderr().flush()?;
+
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+
+    if !response.trim().eq_ignore_ascii_case("y") {
+        return Ok(false);
+    }
+
+    let jar = repair(&server, None, false)?;
+    eprintln!("Rebuilt the metadata of {server} with {jar}");
+
+    Ok(true)
+}
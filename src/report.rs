@@ -0,0 +1,133 @@
+//! Reports of bulk operations written with `--report`, so that CI pipelines can tell what
+//! happened to each server without parsing the console output.
+//!
+//! The report is a JSON object with these fields, whose meaning only changes along with
+//! `schema_version`:
+//!
+//! - `schema_version`: 1
+//! - `command`: the operation, such as `update --all`
+//! - `started`: when it started, in seconds since the unix epoch
+//! - `duration_ms`: how long it took
+//! - `status`: `ok` or `failed`
+//! - `error`: why it failed (or null)
+//! - `servers`: an object for each server it got to, with `server`, `status`, `duration_ms`,
+//!   `bytes_downloaded` and `error` (or null)
+//!
+//! New fields may be added without bumping the schema version.
+
+use crate::{
+    error::{Error, Result},
+    progress::{Event, Reporter},
+    timeutil::unix_now,
+};
+use serde::Serialize;
+use std::{cell::RefCell, fs, path::Path, time::Instant};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Failed,
+}
+
+impl Status {
+    fn of(error: Option<&str>) -> Self {
+        match error {
+            Some(_) => Self::Failed,
+            None => Self::Ok,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ServerOutcome {
+    server: String,
+    status: Status,
+    duration_ms: u64,
+    bytes_downloaded: u64,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    schema_version: u32,
+    command: &'a str,
+    started: u64,
+    duration_ms: u64,
+    status: Status,
+    error: Option<String>,
+    servers: Vec<ServerOutcome>,
+}
+
+/// Passes events on to another reporter while recording the outcome of each server, to be written
+/// as a report once the operation is over
+pub struct Recorder<'a> {
+    inner: &'a dyn Reporter,
+    started: u64,
+    clock: Instant,
+    servers: RefCell<Vec<ServerOutcome>>,
+    /// Bytes downloaded since the last server finished, which belong to the next one to finish
+    downloaded: RefCell<u64>,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(inner: &'a dyn Reporter) -> Result<Self> {
+        Ok(Self {
+            inner,
+            started: unix_now()?,
+            clock: Instant::now(),
+            servers: RefCell::new(vec![]),
+            downloaded: RefCell::new(0),
+        })
+    }
+
+    /// Writes the report of an operation that ended with `result`, which may well be a failure
+    /// partway through
+    pub fn write<T>(self, path: &Path, command: &str, result: &Result<T>) -> Result<()> {
+        let error = result.as_ref().err().map(Error::to_string);
+        let report = Report {
+            schema_version: SCHEMA_VERSION,
+            command,
+            started: self.started,
+            duration_ms: self.clock.elapsed().as_millis() as u64,
+            status: Status::of(error.as_deref()),
+            error,
+            servers: self.servers.into_inner(),
+        };
+
+        fs::write(path, serde_json::to_string_pretty(&report)? + "\n")?;
+        Ok(())
+    }
+}
+
+impl Reporter for Recorder<'_> {
+    fn report(&self, event: Event) {
+        match &event {
+            Event::DownloadFinished { bytes, .. } => *self.downloaded.borrow_mut() += bytes,
+            Event::ServerFinished {
+                server,
+                duration_ms,
+                error,
+            } => self.servers.borrow_mut().push(ServerOutcome {
+                server: server.to_string(),
+                status: Status::of(*error),
+                duration_ms: *duration_ms,
+                bytes_downloaded: self.downloaded.replace(0),
+                error: error.map(String::from),
+            }),
+            _ => {}
+        }
+
+        self.inner.report(event);
+    }
+
+    fn message(&self, text: &str) {
+        self.inner.message(text);
+    }
+
+    fn is_human(&self) -> bool {
+        self.inner.is_human()
+    }
+}
@@ -0,0 +1,152 @@
+//! Crash safe writes for files the server also writes itself, such as `server.properties` and the
+//! player lists. A crash halfway through a plain write leaves a truncated file, which the server
+//! then replaces with its defaults, so edits are written to a temporary file next to the original
+//! and renamed over it once they are complete.
+
+use crate::error::Result;
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn write_temp(temp: &Path, original: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(temp)?;
+    file.write_all(contents.as_bytes())?;
+
+    // the edit keeps the permissions of the file it replaces
+    if let Ok(metadata) = fs::metadata(original) {
+        file.set_permissions(metadata.permissions())?;
+    }
+
+    file.sync_all()
+}
+
+/// Replaces a file with new contents after checking that they parse, keeping the previous contents
+/// as a single `.bak` file. The original is left untouched if validation or writing fails.
+pub fn write(
+    path: impl AsRef<Path>,
+    contents: &str,
+    validate: impl FnOnce(&str) -> Result<()>,
+) -> Result<()> {
+    let path = path.as_ref();
+    validate(contents)?;
+
+    let temp = sibling(path, &format!(".{}.tmp", process::id()));
+    if let Err(err) = write_temp(&temp, path, contents) {
+        let _ = fs::remove_file(&temp);
+        return Err(err.into());
+    }
+
+    if path.exists() {
+        fs::copy(path, sibling(path, ".bak"))?;
+    }
+
+    if let Err(err) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(err.into());
+    }
+
+    // the rename itself only survives a crash once the directory is synced
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn setup(contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ops.json");
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    fn read(path: impl AsRef<Path>) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn valid_json(contents: &str) -> Result<()> {
+        serde_json::from_str::<serde_json::Value>(contents)?;
+        Ok(())
+    }
+
+    #[test]
+    fn edit_keeps_the_previous_contents_as_a_backup() {
+        let (dir, path) = setup("[]");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write(&path, "[1]", valid_json).unwrap();
+        assert_eq!(read(&path), "[1]");
+        assert_eq!(read(sibling(&path, ".bak")), "[]");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        // only the last version is kept
+        write(&path, "[2]", valid_json).unwrap();
+        assert_eq!(read(sibling(&path, ".bak")), "[1]");
+        assert_eq!(entries(dir.path()), ["ops.json", "ops.json.bak"]);
+    }
+
+    #[test]
+    fn new_file_has_no_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ops.json");
+
+        write(&path, "[]", valid_json).unwrap();
+        assert_eq!(entries(dir.path()), ["ops.json"]);
+    }
+
+    #[test]
+    fn failed_validation_leaves_everything_untouched() {
+        let (dir, path) = setup("[1]");
+        write(&path, "[2]", valid_json).unwrap();
+
+        let err = write(&path, "[3", valid_json).unwrap_err();
+        assert!(matches!(err, Error::SerdeJson(_)), "{err:?}");
+        assert_eq!(read(&path), "[2]");
+        assert_eq!(read(sibling(&path, ".bak")), "[1]");
+        assert_eq!(entries(dir.path()), ["ops.json", "ops.json.bak"]);
+    }
+
+    #[test]
+    fn failed_write_leaves_everything_untouched() {
+        let (dir, path) = setup("[1]");
+        write(&path, "[2]", valid_json).unwrap();
+
+        // a directory where the temporary file goes makes creating it fail
+        let temp = sibling(&path, &format!(".{}.tmp", process::id()));
+        fs::create_dir(&temp).unwrap();
+
+        assert!(write(&path, "[3]", valid_json).is_err());
+        assert_eq!(read(&path), "[2]");
+        assert_eq!(read(sibling(&path, ".bak")), "[1]");
+
+        fs::remove_dir(&temp).unwrap();
+        assert_eq!(entries(dir.path()), ["ops.json", "ops.json.bak"]);
+    }
+}
@@ -0,0 +1,105 @@
+//! One-shot scheduling for console and rcon commands. The process itself waits until the moment
+//! comes and then sends them, so cancelling with Ctrl-C leaves nothing behind.
+
+use crate::{
+    config_defs::RconConfig,
+    error::{Error, Result},
+    humanize, rcon,
+    rcon::PingOutcome,
+    session, timeutil,
+};
+use jiff::Zoned;
+use std::{
+    io::{self, IsTerminal},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the countdown is redrawn on a terminal
+const TERMINAL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the countdown is printed when stderr is redirected, such as to a log file
+const LOG_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait before sending and the moment that is, from a time of day given with `--at`
+/// or a duration given with `--in`. Returns `None` when neither is given.
+pub fn delay(at: Option<&str>, within: Option<&str>) -> Result<Option<(Duration, Zoned)>> {
+    let now = Zoned::now().with_time_zone(timeutil::time_zone()?);
+
+    let target = match (at, within) {
+        (Some(at), _) => timeutil::next_occurrence(timeutil::parse_time_of_day(at)?)?,
+        (None, Some(within)) => {
+            let delay = timeutil::parse_duration(within)?;
+            now.checked_add(delay)
+                .map_err(|_| Error::InvalidDuration(within.to_string()))?
+        }
+        (None, None) => return Ok(None),
+    };
+
+    let delay = now
+        .duration_until(&target)
+        .try_into()
+        .unwrap_or(Duration::ZERO);
+
+    Ok(Some((delay, target)))
+}
+
+/// Sleeps for a delay while counting it down on stderr
+pub fn wait(delay: Duration, target: &Zoned) {
+    let deadline = Instant::now() + delay;
+    let terminal = io::stderr().is_terminal();
+    let interval = if terminal {
+        TERMINAL_INTERVAL
+    } else {
+        LOG_INTERVAL
+    };
+    let target = target.strftime("%Y-%m-%d %H:%M:%S %Z");
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+
+        // rounded up, so that the countdown never shows 0s before it is over
+        let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+        let line = format!(
+            "Sending in {} (at {target}), press Ctrl-C to cancel",
+            humanize::duration(secs)
+        );
+        if terminal {
+            eprint!("\r\x1b[K{line}");
+        } else {
+            eprintln!("{line}");
+        }
+
+        thread::sleep(remaining.min(interval));
+    }
+
+    if terminal {
+        eprint!("\r\x1b[K");
+    }
+}
+
+/// Fails unless a server's session is still running, checked just before sending to it
+pub fn ensure_session_alive(server: &str) -> Result<()> {
+    if session::get_alive_server_sessions()?.contains(server) {
+        Ok(())
+    } else {
+        Err(Error::SessionNotRunning(server.to_string()))
+    }
+}
+
+/// Fails unless an rcon endpoint accepts a login, checked just before sending to it. The server
+/// is only used to name the endpoint in the error.
+pub fn ensure_rcon_reachable(server: &str, endpoint: &RconConfig) -> Result<()> {
+    let reason = match rcon::ping(endpoint) {
+        PingOutcome::Reachable { .. } => return Ok(()),
+        PingOutcome::AuthFailed { .. } => "authentication failed".to_string(),
+        PingOutcome::Unreachable { reason } => reason,
+    };
+
+    Err(Error::RconUnreachable {
+        server: server.to_string(),
+        reason,
+    })
+}
@@ -1,34 +1,86 @@
 use crate::{
-    config::{self, get_expanded_servers_dir, server_or_current},
+    buildtools, checksum,
+    compat::{self, CompatCheck},
+    config::{self, get_expanded_servers_dir},
+    config_defs::RconConfig,
+    copy, downgrade,
     error::{Error, Result},
-    platforms::{self, Platform},
+    hooks::{self, Hook},
+    humanize,
+    ignore::IgnoreRules,
+    java, launch_script,
+    lineage::{self, SourceKind},
+    manifest::{ManifestEntry, RunManifest},
+    names, permissions,
+    platforms::{self, Platform, PublishedDigest, ResolvedArtifact, VersionSpec},
+    profiles,
+    progress::{Event, Reporter},
+    provenance, quilt, ratelimit, rcon, registry, repair,
     session::{
         self, get_alive_server_sessions, get_dead_server_sessions, get_server_sessions_to_living,
     },
+    space, startup, subprocess,
+    timeutil::unix_now,
+    verbose,
 };
-use reqwest::{
-    blocking::{self, Response},
-    header,
-};
+use reqwest::{blocking::Response, header};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     env,
     ffi::OsStr,
     fmt::{self, Display, Formatter},
     fs::{self, File},
     io::{self, Write},
+    os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
-    process::Command,
-    time::{SystemTime, UNIX_EPOCH},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 use url::Url;
 
 const REPO_URL: &str = env!("CARGO_PKG_REPOSITORY");
 const TEMPLATE_SUFFIX: &str = ".template";
 
-const METADATA_DIRECTORY: &str = ".mcserver";
-const JAR_FILE_TXT_NAME: &str = "jar_file.txt";
+/// How often download progress is reported, in bytes
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+const DEFAULT_TAB_TITLE: &str = "{server}";
+const LOCAL_ADDRESSES: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+const STOP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const METADATA_DIRECTORY: &str = ".mcserver";
+pub const JAR_FILE_TXT_NAME: &str = "jar_file.txt";
 const LAST_USED_FILE: &str = "last_used.timestamp";
+/// Java arguments for one server, used instead of `default_java_args`
+pub const JAVA_ARGS_FILE: &str = "java_args.txt";
+/// The command a native server such as Bedrock starts with, instead of a jar
+const START_COMMAND_FILE: &str = "start_command.txt";
+/// What kind of server it is, which is `proxy` for proxies and missing for game servers
+const KIND_FILE: &str = "kind.txt";
+const PROXY_KIND: &str = "proxy";
+/// The kind of a server created from a jar that no platform resolves
+const CUSTOM_KIND: &str = "custom";
+/// Where jars are downloaded to in the metadata directory before being moved into place
+const DOWNLOAD_FILE: &str = "download.tmp";
+/// The console command that shuts one server down, used instead of its platform's
+pub const STOP_COMMAND_FILE: &str = "stop_command.txt";
+const DEFAULT_STOP_COMMAND: &str = "stop";
+
+const BUILD_TOOLS_JAR: &str = "BuildTools.jar";
+/// Roughly how much room BuildTools needs for the sources and build output
+const BUILD_TOOLS_SPACE: u64 = 3 * 1024 * 1024 * 1024;
+
+const BEDROCK_START_COMMAND: &str = "./bedrock_server";
+/// Files in the Bedrock zip that hold the server's own configuration, which updates keep
+const BEDROCK_CONFIG_FILES: [&str; 3] = ["server.properties", "allowlist.json", "permissions.json"];
+
+pub const RCON_COMMAND: &str = "mcrcon";
+pub const RCON_INSTALL_HINT: &str =
+    "install it from https://github.com/Tiiffi/mcrcon or your package manager";
 
 pub struct ServerObject {
     pub name: String,
@@ -51,20 +103,23 @@ impl Display for ServerObject {
     }
 }
 
-pub fn copy_directory(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
-    fs::create_dir_all(&dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-
-        if file_type.is_dir() {
-            copy_directory(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        }
-    }
+/// Copies a directory recursively, leaving out transient lock files and skipping sockets, FIFOs
+/// and devices with a warning since they can't be copied. Paths excluded by the directory's
+/// `.mcserverignore` are left out too.
+pub fn copy_directory(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    let src = src.as_ref();
+    let rules = IgnoreRules::load(src)?;
+    Ok(copy_directory_excluding(src, dst, &rules)?)
+}
 
-    Ok(())
+/// Copies a directory like [`copy_directory`], but with the given rules rather than its own
+/// `.mcserverignore`
+pub fn copy_directory_excluding(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    rules: &IgnoreRules,
+) -> io::Result<()> {
+    copy::copy_tree(src.as_ref(), dst.as_ref(), rules)
 }
 
 pub fn remove_dir_with_retries(dir: impl AsRef<Path>) -> Result<()> {
@@ -83,58 +138,153 @@ pub fn remove_dir_with_retries(dir: impl AsRef<Path>) -> Result<()> {
     unreachable!("Code returns before the for loop ends")
 }
 
+/// Clears configuration that references a removed server
+fn forget_server(server: &str) -> Result<()> {
+    let (cleared_default, removed_rcon) = config::update(|config| {
+        let cleared_default = config.default_server.as_deref() == Some(server);
+        if cleared_default {
+            config.default_server = None;
+        }
+
+        let removed_rcon = config
+            .rcon
+            .as_mut()
+            .is_some_and(|rcon| rcon.remove(server).is_some());
+
+        (cleared_default, removed_rcon)
+    })?;
+
+    if cleared_default {
+        println!("Cleared the default server, which was {server}");
+    }
+
+    if removed_rcon {
+        println!("Removed the rcon configuration for {server}");
+    }
+
+    Ok(())
+}
+
 fn remove_server(server: String) -> Result<()> {
-    remove_dir_with_retries(get_expanded_servers_dir()?.join(server))?;
+    let servers_dir = get_expanded_servers_dir()?;
+
+    // a process can't reliably delete the directory it runs in, so it steps out of it first
+    if config::get_current_server_directory().is_ok_and(|current| current == server) {
+        env::set_current_dir(servers_dir)?;
+        eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m the current directory is inside {server}, which is being \
+             removed, so change your shell's directory afterwards"
+        );
+    }
+
+    remove_dir_with_retries(servers_dir.join(&server))?;
+
+    if let Err(err) = forget_server(&server) {
+        eprintln!("Server {server} was removed, but its configuration could not be cleared: {err}");
+    }
+
     Ok(())
 }
 
-pub fn remove_servers(servers: Vec<String>) -> Result<()> {
+enum RemoveOutcome {
+    Removed,
+    Failed(Error),
+    Skipped,
+}
+
+/// Resolves "." and drops repeated names, failing before anything is removed if a server is
+/// missing or is the current server without `allow_current`
+fn resolve_servers_to_remove(servers: Vec<String>, allow_current: bool) -> Result<Vec<String>> {
     let all_servers = get_all_hashed()?;
+    let mut resolved = vec![];
 
     for server in servers {
         let server = server_or_current(server)?;
 
-        if all_servers.get(&server).as_ref().is_none() {
+        if !all_servers.contains(&server) {
             return Err(Error::ServerNotFound(server));
         }
 
-        remove_server(server)?;
+        ensure_not_current(&server, "remove", allow_current)?;
+
+        if !resolved.contains(&server) {
+            resolved.push(server);
+        }
     }
 
-    Ok(())
+    Ok(resolved)
 }
 
-pub fn remove_servers_with_confirmation(servers: Vec<String>) -> Result<()> {
-    let all_servers = get_all_hashed()?;
+fn confirm_removal(server: &str) -> Result<bool> {
+    loop {
+        print!("Enter `{server}` to delete the server or nothing to cancel operation: ");
+        io::stdout().flush()?;
 
-    for server in servers {
-        if all_servers.get(&server).as_ref().is_none() {
-            return Err(Error::ServerNotFound(server));
-        }
+        let mut response = String::new();
+        io::stdin().read_line(&mut response)?;
 
-        if loop {
-            print!("Enter `{server}` to delete the server or nothing to cancel operation: ");
-            io::stdout().flush()?;
+        if server == response.trim_end() {
+            return Ok(true);
+        } else if response.trim().is_empty() {
+            return Ok(false);
+        }
+    }
+}
 
-            let mut response = String::new();
-            io::stdin().read_line(&mut response)?;
+fn print_removal_summary(outcomes: Vec<(String, RemoveOutcome)>) -> Result<()> {
+    let mut failures = 0;
 
-            if server == response.trim_end() {
-                break true;
-            } else if response.is_empty() {
-                break false;
+    for (server, outcome) in outcomes {
+        match outcome {
+            RemoveOutcome::Removed => println!("[\x1b[32;1mremoved\x1b[0m] {server}"),
+            RemoveOutcome::Failed(err) => {
+                failures += 1;
+                println!("[\x1b[31;1mfailed\x1b[0m] {server}: {err}");
             }
-        } {
-            remove_server(server)?;
-            println!("Server successfully removed");
-        } else {
-            println!("Operation canceled");
+            RemoveOutcome::Skipped => println!("[\x1b[33;1mskipped\x1b[0m] {server}"),
         }
     }
 
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
+
     Ok(())
 }
 
+/// Removes each server with `remove`, carrying on past failures and summarizing them at the end
+fn remove_each(
+    servers: Vec<String>,
+    confirm: bool,
+    allow_current: bool,
+    remove: fn(String) -> Result<()>,
+) -> Result<()> {
+    let mut outcomes = vec![];
+
+    for server in resolve_servers_to_remove(servers, allow_current)? {
+        let outcome = if confirm && !confirm_removal(&server)? {
+            RemoveOutcome::Skipped
+        } else {
+            match remove(server.clone()) {
+                Ok(()) => RemoveOutcome::Removed,
+                Err(err) => RemoveOutcome::Failed(err),
+            }
+        };
+
+        outcomes.push((server, outcome));
+    }
+
+    print_removal_summary(outcomes)
+}
+
+pub fn remove_servers(servers: Vec<String>, allow_current: bool) -> Result<()> {
+    remove_each(servers, false, allow_current, remove_server)
+}
+
+pub fn remove_servers_with_confirmation(servers: Vec<String>, allow_current: bool) -> Result<()> {
+    remove_each(servers, true, allow_current, remove_server)
+}
+
 fn set_last_used_metadata(metadata_dir: impl AsRef<Path>, timestamp: u64) -> Result<()> {
     let mut file = File::create(metadata_dir.as_ref().join(LAST_USED_FILE))?;
     file.write_all(&timestamp.to_le_bytes())?;
@@ -142,14 +292,31 @@ fn set_last_used_metadata(metadata_dir: impl AsRef<Path>, timestamp: u64) -> Res
     Ok(())
 }
 
-pub fn set_jar_file_metadata<M, J>(metadata_dir: M, jar_file_name: J) -> Result<File>
+/// Records the jar a server runs. The file is kept read only so that it isn't edited by accident,
+/// so it is replaced rather than written to.
+pub fn set_jar_file_metadata<M, J>(metadata_dir: M, jar_file_name: J) -> Result<()>
 where
     M: AsRef<Path>,
     J: Display,
 {
-    let mut jar_file_txt = File::create(metadata_dir.as_ref().join(JAR_FILE_TXT_NAME))?;
+    // the recorded digest belonged to the previous jar
+    checksum::forget(&metadata_dir)?;
+
+    let path = metadata_dir.as_ref().join(JAR_FILE_TXT_NAME);
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    let mut jar_file_txt = File::create(&path)?;
     writeln!(jar_file_txt, "{jar_file_name}")?;
-    Ok(jar_file_txt)
+
+    let mut perms = jar_file_txt.metadata()?.permissions();
+    perms.set_readonly(true);
+    jar_file_txt.set_permissions(perms)?;
+
+    Ok(())
 }
 
 pub fn set_default_metadata<M, J>(metadata_dir: M, jar_file_name: J) -> Result<()>
@@ -158,35 +325,286 @@ where
     J: Display,
 {
     fs::create_dir_all(&metadata_dir)?;
+    set_jar_file_metadata(&metadata_dir, jar_file_name)?;
+    set_last_used_metadata(&metadata_dir, u64::MAX)?;
 
-    let jar_file_txt = set_jar_file_metadata(&metadata_dir, jar_file_name)?;
+    Ok(())
+}
 
-    let mut perms = jar_file_txt.metadata()?.permissions();
-    perms.set_readonly(true);
-    jar_file_txt.set_permissions(perms)?;
+fn set_start_command_metadata(metadata_dir: impl AsRef<Path>, command: &str) -> Result<()> {
+    fs::create_dir_all(&metadata_dir)?;
+    fs::write(
+        metadata_dir.as_ref().join(START_COMMAND_FILE),
+        format!("{command}\n"),
+    )?;
+    Ok(())
+}
 
-    set_last_used_metadata(&metadata_dir, u64::MAX)?;
+/// Reads the command a native server starts with, which is `None` for servers that run a jar
+pub fn read_start_command(server_dir: impl AsRef<Path>) -> Result<Option<String>> {
+    let server_dir = server_dir.as_ref();
+    let path = server_dir.join(METADATA_DIRECTORY).join(START_COMMAND_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(command) => Ok(Some(command.trim().to_string())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(permissions::with_hint(err, &path, server_dir)),
+    }
+}
+
+/// Records whether a server is a proxy, for the platform it now runs
+fn set_kind_metadata(metadata_dir: impl AsRef<Path>, platform: Platform) -> Result<()> {
+    let path = metadata_dir.as_ref().join(KIND_FILE);
+
+    if platform.is_proxy() {
+        fs::write(path, format!("{PROXY_KIND}\n"))?;
+    } else if platform == Platform::Custom {
+        fs::write(path, format!("{CUSTOM_KIND}\n"))?;
+    } else if let Err(err) = fs::remove_file(path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        return Err(err.into());
+    }
 
     Ok(())
 }
 
-fn copy_jar<S, J, F>(server_dir: S, mut jar: J, file_name: F) -> Result<()>
+/// Whether a server is a proxy, going by its recorded kind, or by the name of its jar for servers
+/// created before the kind was recorded
+pub fn is_proxy(server_dir: impl AsRef<Path>) -> Result<bool> {
+    let server_dir = server_dir.as_ref();
+    let path = server_dir.join(METADATA_DIRECTORY).join(KIND_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(kind) => Ok(kind.trim() == PROXY_KIND),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Ok(get_platform(server_dir)?.is_some_and(Platform::is_proxy))
+        }
+        Err(err) => Err(permissions::with_hint(err, &path, server_dir)),
+    }
+}
+
+/// Returns a server's platform as recorded when it was created or last updated. Servers from
+/// before it was recorded have it guessed: Bedrock for servers with a start command, custom for
+/// servers created from a jar of their own, otherwise from the name of its jar.
+pub fn get_platform(server_dir: impl AsRef<Path>) -> Result<Option<Platform>> {
+    let server_dir = server_dir.as_ref();
+    if let Some(provenance) = provenance::read(server_dir.join(METADATA_DIRECTORY))? {
+        return Ok(Some(provenance.platform));
+    }
+
+    if read_start_command(server_dir)?.is_some() {
+        return Ok(Some(Platform::Bedrock));
+    }
+
+    // a custom jar may well be named after the platform it was built from
+    let kind = fs::read_to_string(server_dir.join(METADATA_DIRECTORY).join(KIND_FILE));
+    if kind.is_ok_and(|kind| kind.trim() == CUSTOM_KIND) {
+        return Ok(Some(Platform::Custom));
+    }
+
+    let jar = read_server_jar_path(server_dir)?;
+    Ok(jar
+        .file_name()
+        .and_then(|name| Platform::from_jar_name(&name.to_string_lossy())))
+}
+
+/// Returns the console command that shuts a server down: its override if it has one, otherwise
+/// its platform's, falling back to `stop` when the platform can't be told
+pub fn get_stop_command(server: &str) -> Result<String> {
+    let server_dir = get_server_dir_required(server)?;
+    let path = server_dir.join(METADATA_DIRECTORY).join(STOP_COMMAND_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(command) if !command.trim().is_empty() => return Ok(command.trim().to_string()),
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(permissions::with_hint(err, &path, &server_dir)),
+    }
+
+    Ok(match get_platform(&server_dir) {
+        Ok(Some(platform)) => platform.stop_command().to_string(),
+        Ok(None) | Err(_) => DEFAULT_STOP_COMMAND.to_string(),
+    })
+}
+
+/// Returns where a jar is written while it downloads inside a server, out of the way of the jars
+/// the server is started with. A download cut short by a crash is replaced by the next one.
+fn get_local_partial_path(server_dir: &Path) -> Result<PathBuf> {
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    fs::create_dir_all(&metadata_dir)?;
+    Ok(metadata_dir.join(DOWNLOAD_FILE))
+}
+
+/// Returns where a jar is written while it downloads, which is inside the server unless a download
+/// directory is configured
+fn get_partial_path(server_dir: &Path) -> Result<PathBuf> {
+    let download_directory = config::snapshot()?.download_directory;
+
+    match download_directory {
+        Some(dir) => {
+            let dir = PathBuf::from(shellexpand::tilde(&dir).as_ref());
+            fs::create_dir_all(&dir)?;
+
+            // servers share the download directory, so the name is prefixed to avoid clashes
+            let server_name = server_dir.file_name().unwrap_or_default().to_string_lossy();
+            Ok(dir.join(format!("{server_name}-{DOWNLOAD_FILE}")))
+        }
+        None => get_local_partial_path(server_dir),
+    }
+}
+
+/// Fails unless as many bytes were written as a download's Content-Length announced, when it
+/// announced one
+fn check_content_length(expected: Option<u64>, written: u64) -> Result<()> {
+    match expected {
+        Some(expected) if expected != written => Err(Error::IncompleteDownload {
+            expected,
+            actual: written,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Writes and syncs a partial download, returning how many bytes were written and their sha256
+/// digest
+fn write_partial(
+    path: &Path,
+    mut jar: impl io::Read,
+    expected_len: Option<u64>,
+    expected_sha256: Option<&str>,
+    reporter: &dyn Reporter,
+) -> Result<(u64, String)> {
+    let mut jar_file = File::create(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    let mut len = 0;
+    let started = Instant::now();
+
+    loop {
+        let read = jar.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..read]);
+        jar_file.write_all(&buf[..read])?;
+        ratelimit::throttle(read)?;
+
+        let previous_len = len;
+        len += read as u64;
+
+        if previous_len / PROGRESS_INTERVAL != len / PROGRESS_INTERVAL {
+            let elapsed = started.elapsed().as_secs_f64();
+            let bytes_per_second = if elapsed > 0.0 {
+                (len as f64 / elapsed) as u64
+            } else {
+                0
+            };
+            let eta_seconds = expected_len
+                .filter(|_| bytes_per_second > 0)
+                .map(|total| total.saturating_sub(len) / bytes_per_second);
+
+            reporter.report(Event::DownloadProgress {
+                bytes: len,
+                total: expected_len,
+                bytes_per_second,
+                eta_seconds,
+            });
+        }
+    }
+
+    jar_file.sync_all()?;
+    check_content_length(expected_len, len)?;
+
+    let actual = format!("{:x}", hasher.finalize());
+
+    if let Some(expected) = expected_sha256
+        && !expected.eq_ignore_ascii_case(&actual)
+    {
+        return Err(Error::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+
+    reporter.report(Event::DownloadFinished {
+        bytes: len,
+        sha256: &actual,
+    });
+
+    Ok((len, actual))
+}
+
+/// Copies the jar into the server directory, returning how many bytes were written and their
+/// sha256 digest.
+/// The jar is written to a temporary file first and only renamed into place once it is complete
+/// and verified, so an interrupted download never leaves a truncated jar under its final name, and
+/// the metadata is only pointed at it afterwards.
+fn copy_jar<S, J, F>(
+    server_dir: S,
+    jar: J,
+    file_name: F,
+    expected_len: Option<u64>,
+    expected_sha256: Option<&str>,
+    published_digest: Option<&PublishedDigest>,
+    reporter: &dyn Reporter,
+) -> Result<(u64, String)>
 where
     S: AsRef<Path>,
     J: io::Read,
     F: AsRef<Path>,
 {
-    env::set_current_dir(server_dir)?;
+    let server_dir = server_dir.as_ref();
+    let file_name = file_name.as_ref();
+    let final_path = server_dir.join(file_name);
+    let partial_path = get_partial_path(server_dir)?;
+
+    let written = write_partial(&partial_path, jar, expected_len, expected_sha256, reporter)
+        .and_then(|written| {
+            if let Some(published_digest) = published_digest {
+                checksum::verify_published(&partial_path, published_digest)?;
+            }
+            Ok(written)
+        });
+
+    let written = match written {
+        Ok(written) => written,
+        Err(err) => {
+            let _ = fs::remove_file(&partial_path);
+            return Err(err);
+        }
+    };
 
-    let mut jar_file = File::create(file_name)?;
-    io::copy(&mut jar, &mut jar_file)?;
+    if fs::rename(&partial_path, &final_path).is_err() {
+        // the download directory is on another filesystem, so the jar is copied into the server
+        // first to keep the last step atomic
+        let local_partial_path = get_local_partial_path(server_dir)?;
+        let copied = fs::copy(&partial_path, &local_partial_path)
+            .and_then(|_| File::open(&local_partial_path)?.sync_all());
+        let _ = fs::remove_file(&partial_path);
+        if let Err(err) = copied.and_then(|_| fs::rename(&local_partial_path, &final_path)) {
+            let _ = fs::remove_file(&local_partial_path);
+            return Err(err.into());
+        }
+    }
 
-    Ok(())
+    // the rename only lasts through a crash once the directory holding it is synced
+    File::open(server_dir)?.sync_all()?;
+
+    Ok(written)
 }
 
-pub fn get_jar(download_url: Url, platform: Platform) -> Result<(Response, String)> {
-    println!("Downloading from {download_url}...");
-    let response = blocking::get(download_url)?;
+pub fn get_jar(
+    download_url: Url,
+    platform: Platform,
+    reporter: &dyn Reporter,
+) -> Result<(Response, String)> {
+    let response = platforms::get_client()?.get_raw(download_url.as_str())?;
+    reporter.report(Event::DownloadStarted {
+        url: download_url.as_str(),
+        total: response.content_length(),
+    });
 
     let file_name = response
         .headers()
@@ -201,73 +619,919 @@ pub fn get_jar(download_url: Url, platform: Platform) -> Result<(Response, Strin
     Ok((response, file_name))
 }
 
-pub fn create_new<N>(platform: Platform, version: Option<String>, name: Option<N>) -> Result<()>
-where
-    N: Display,
-{
-    let download_url = platforms::get(platform, version)?;
+/// Unpacks a Bedrock zip into a server directory, keeping any configuration files already there
+fn extract_bedrock(zip_path: &Path, server_dir: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(File::open(zip_path)?)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        // entries with paths leading outside of the server are skipped
+        let Some(relative) = entry.enclosed_name() else {
+            continue;
+        };
+        let path = server_dir.join(&relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
 
-    let server_dir = match name {
-        Some(name) => get_first_server_path(name)?,
-        None => get_first_server_path(format!("{platform}-server"))?,
+        if path.exists()
+            && relative
+                .to_str()
+                .is_some_and(|name| BEDROCK_CONFIG_FILES.contains(&name))
+        {
+            verbose!("Keeping the existing {}", relative.display());
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&path)?)?;
+
+        if let Some(mode) = entry.unix_mode() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+    }
+
+    // the zip doesn't always carry the executable bit
+    let binary = server_dir.join(BEDROCK_START_COMMAND);
+    fs::set_permissions(&binary, fs::Permissions::from_mode(0o755))?;
+
+    Ok(())
+}
+
+/// Downloads a Bedrock server zip and unpacks it into a server directory, recording its start
+/// command, and returns the sha256 digest of the zip
+fn install_bedrock(
+    server_dir: &Path,
+    artifact: &ResolvedArtifact,
+    expected_sha256: Option<&str>,
+    reporter: &dyn Reporter,
+) -> Result<String> {
+    let url = artifact.download_url()?;
+    let zip_name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("bedrock-server.zip")
+        .to_string();
+
+    let (zip, _) = get_jar(url, artifact.platform, reporter)?;
+    let len = zip.content_length();
+    // the zip sits next to its unpacked contents, which are about twice its size
+    space::ensure(server_dir, len.unwrap_or_default() * 3)?;
+    fs::create_dir_all(server_dir)?;
+
+    let (written, sha256) = copy_jar(
+        server_dir,
+        zip,
+        &zip_name,
+        len,
+        expected_sha256,
+        None,
+        reporter,
+    )?;
+    check_content_length(len, written)?;
+    let zip_path = server_dir.join(&zip_name);
+    let result = extract_bedrock(&zip_path, server_dir);
+    fs::remove_file(&zip_path)?;
+    result?;
+
+    set_start_command_metadata(server_dir.join(METADATA_DIRECTORY), BEDROCK_START_COMMAND)?;
+    Ok(sha256)
+}
+
+/// Finds the Spigot jar BuildTools wrote into a server directory, which is the newest one when an
+/// update leaves older jars next to it
+fn find_built_spigot_jar(server_dir: &Path) -> Result<String> {
+    let mut newest = None;
+
+    for entry in fs::read_dir(server_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !(name.starts_with("spigot-") && name.ends_with(".jar")) {
+            continue;
+        }
+
+        let modified = entry.metadata()?.modified()?;
+        if newest.as_ref().is_none_or(|(newest, _)| modified > *newest) {
+            newest = Some((modified, name));
+        }
+    }
+
+    newest.map(|(_, name)| name).ok_or(Error::NoJarFound {
+        dir: server_dir.to_path_buf(),
+    })
+}
+
+fn build_spigot(
+    server_dir: &Path,
+    build_dir: &Path,
+    artifact: &ResolvedArtifact,
+    expected_sha256: Option<&str>,
+    reporter: &dyn Reporter,
+) -> Result<(String, String)> {
+    space::ensure(build_dir, BUILD_TOOLS_SPACE)?;
+
+    let (build_tools, _) = get_jar(artifact.download_url()?, artifact.platform, reporter)?;
+    let len = build_tools.content_length();
+    let (written, _) = copy_jar(
+        build_dir,
+        build_tools,
+        BUILD_TOOLS_JAR,
+        len,
+        None,
+        None,
+        reporter,
+    )?;
+    check_content_length(len, written)?;
+
+    reporter.message(&format!(
+        "Building spigot {} with BuildTools, which takes several minutes...",
+        artifact.version
+    ));
+    fs::create_dir_all(server_dir)?;
+    let result = buildtools::run(
+        &build_dir.join(BUILD_TOOLS_JAR),
+        &artifact.version,
+        server_dir,
+        reporter,
+    );
+    if reporter.is_human() {
+        // finish the line the build output was written on
+        eprintln!();
+    }
+    result?;
+
+    let jar_file_name = find_built_spigot_jar(server_dir)?;
+    let sha256 = checksum::sha256_file(server_dir.join(&jar_file_name))?;
+
+    if let Some(expected) = expected_sha256
+        && !expected.eq_ignore_ascii_case(&sha256)
+    {
+        return Err(Error::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: sha256,
+        });
+    }
+
+    Ok((jar_file_name, sha256))
+}
+
+/// Sets up a Quilt server with its installer, returning the launch jar's name and sha256 digest
+fn install_quilt(
+    server_dir: &Path,
+    artifact: &ResolvedArtifact,
+    reporter: &dyn Reporter,
+) -> Result<(String, String)> {
+    let loader_version = artifact
+        .loader_version()
+        .ok_or_else(|| Error::PlatformsNotFound("a quilt loader version".to_string()))?;
+
+    let work_dir = env::temp_dir().join(format!("mcserver-quilt-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)?;
+    fs::create_dir_all(server_dir)?;
+
+    let result = (|| {
+        let (installer, _) = get_jar(artifact.download_url()?, artifact.platform, reporter)?;
+        let len = installer.content_length();
+        let (written, _) = copy_jar(
+            &work_dir,
+            installer,
+            quilt::INSTALLER_JAR,
+            len,
+            None,
+            None,
+            reporter,
+        )?;
+        check_content_length(len, written)?;
+
+        reporter.message(&format!(
+            "Installing quilt {} for {}...",
+            loader_version, artifact.version
+        ));
+        quilt::run(
+            &work_dir.join(quilt::INSTALLER_JAR),
+            &artifact.version,
+            loader_version,
+            server_dir,
+        )?;
+
+        let sha256 = checksum::sha256_file(server_dir.join(quilt::LAUNCH_JAR))?;
+        Ok((quilt::LAUNCH_JAR.to_string(), sha256))
+    })();
+
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+/// Builds a Spigot jar into a server directory with BuildTools, returning the jar's name and its
+/// sha256 digest. The build directory is removed afterwards, even after a failure, unless it is
+/// kept.
+fn install_spigot(
+    server_dir: &Path,
+    artifact: &ResolvedArtifact,
+    expected_sha256: Option<&str>,
+    keep_build_dir: bool,
+    reporter: &dyn Reporter,
+) -> Result<(String, String)> {
+    buildtools::preflight()?;
+
+    let build_dir = buildtools::build_dir();
+    fs::create_dir_all(&build_dir)?;
+
+    let result = build_spigot(server_dir, &build_dir, artifact, expected_sha256, reporter);
+
+    if keep_build_dir {
+        eprintln!("Kept the build directory at {}", build_dir.display());
+    } else if let Err(err) = fs::remove_dir_all(&build_dir) {
+        eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m failed to remove the build directory {}: {err}",
+            build_dir.display()
+        );
+    }
+
+    result
+}
+
+/// A server made by [`create_new`]
+#[derive(Debug, Serialize)]
+pub struct CreatedServer {
+    pub name: String,
+    pub path: PathBuf,
+    pub platform: Platform,
+    pub version: String,
+    pub jar: String,
+}
+
+fn report_resolved(artifact: &ResolvedArtifact, reporter: &dyn Reporter) {
+    reporter.report(Event::Resolved {
+        platform: artifact.platform,
+        version: &artifact.version,
+        build: artifact.build.as_deref(),
+    });
+}
+
+/// Warns that a session already exists under the name of a server being created, which deploying
+/// the server would run into
+fn warn_leftover_session(server: &str, alive: bool) -> Result<()> {
+    let state = if alive { "running" } else { "dead" };
+    let force = if registry::read()?.contains(&session::get_name(server), server) {
+        ""
+    } else {
+        " --force"
     };
 
-    fs::create_dir_all(&server_dir)?;
-    let (jar, jar_file_name) = get_jar(download_url, platform)?;
-    copy_jar(&server_dir, jar, &jar_file_name)?;
-    set_default_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+    eprintln!(
+        "\x1b[33;1mWarning:\x1b[0m a {state} session named {} already exists, left from an \
+         earlier server (delete it with `mcserver delete-session{force} {server}`, or create with \
+         --no-clobber-session to pick another name)",
+        session::get_name(server)
+    );
     Ok(())
 }
 
-pub fn update_existing<S>(server: S, platform: Platform, version: Option<String>) -> Result<()>
+/// The version a server was installed at. BuildTools picks the version when asked for the latest
+/// one, so for Spigot it is taken from the jar, which is named after it.
+fn installed_version(artifact: &ResolvedArtifact, jar_file_name: &str) -> String {
+    match jar_file_name
+        .strip_prefix("spigot-")
+        .and_then(|name| name.strip_suffix(".jar"))
+    {
+        Some(version) if artifact.platform == Platform::Spigot => version.to_string(),
+        _ => artifact.version.clone(),
+    }
+}
+
+/// Picks the directory of a new server, named as given or after its platform, and numbered past
+/// any server already there
+fn get_new_server_path<N: Display>(
+    name: Option<N>,
+    platform: Platform,
+    no_clobber_session: bool,
+) -> Result<PathBuf> {
+    // a session left behind by a removed server would otherwise be picked up by this one
+    let sessions = session::get_any_server_sessions();
+    let taken = if no_clobber_session {
+        sessions.keys().cloned().collect()
+    } else {
+        HashSet::new()
+    };
+
+    let server_dir = match name {
+        Some(name) => get_first_server_path(names::normalize_typed(&name.to_string())?, &taken)?,
+        None => get_first_server_path(format!("{platform}-server"), &taken)?,
+    };
+
+    let server = server_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if let Some(&alive) = sessions.get(&server) {
+        warn_leftover_session(&server, alive)?;
+    }
+
+    Ok(server_dir)
+}
+
+pub fn create_new<N>(
+    platform: Platform,
+    spec: VersionSpec,
+    name: Option<N>,
+    no_clobber_session: bool,
+    keep_build_dir: bool,
+    changelog: bool,
+    reporter: &dyn Reporter,
+) -> Result<CreatedServer>
 where
-    S: AsRef<Path>,
+    N: Display,
 {
-    let download_url = platforms::get(platform, version)?;
-    let server_dir = get_expanded_servers_dir()?.join(&server);
+    let requested_version = spec.version.clone();
+    let artifact = platforms::resolve(platform, spec)?;
+    report_resolved(&artifact, reporter);
+
+    if changelog {
+        print_changelog(&artifact, None, reporter)?;
+    }
+
+    let server_dir = get_new_server_path(name, platform, no_clobber_session)?;
+
+    let jar_file_name = if platform == Platform::Bedrock {
+        install_bedrock(&server_dir, &artifact, None, reporter)?;
+        set_last_used_metadata(server_dir.join(METADATA_DIRECTORY), u64::MAX)?;
+        BEDROCK_START_COMMAND.to_string()
+    } else if platform == Platform::Spigot {
+        let (jar_file_name, sha256) =
+            install_spigot(&server_dir, &artifact, None, keep_build_dir, reporter)?;
+        set_default_metadata(server_dir.join(METADATA_DIRECTORY), &jar_file_name)?;
+        checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+        jar_file_name
+    } else if platform == Platform::Quilt {
+        let (jar_file_name, sha256) = install_quilt(&server_dir, &artifact, reporter)?;
+        set_default_metadata(server_dir.join(METADATA_DIRECTORY), &jar_file_name)?;
+        checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+        jar_file_name
+    } else {
+        let (jar, jar_file_name) = get_jar(artifact.download_url()?, platform, reporter)?;
+        space::ensure(&server_dir, jar.content_length().unwrap_or_default())?;
+        fs::create_dir_all(&server_dir)?;
+        let len = jar.content_length();
+        let (written, sha256) = copy_jar(
+            &server_dir,
+            jar,
+            &jar_file_name,
+            len,
+            artifact.sha256.as_deref(),
+            artifact.published_digest.as_ref(),
+            reporter,
+        )?;
+        check_content_length(len, written)?;
+        set_default_metadata(server_dir.join(METADATA_DIRECTORY), &jar_file_name)?;
+        set_kind_metadata(server_dir.join(METADATA_DIRECTORY), platform)?;
+        checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+        jar_file_name
+    };
+
+    let version = installed_version(&artifact, &jar_file_name);
+    provenance::record(
+        server_dir.join(METADATA_DIRECTORY),
+        &artifact,
+        requested_version.as_deref(),
+        &version,
+    )?;
+    reporter.report(Event::Done { path: &server_dir });
+
+    Ok(CreatedServer {
+        name: server_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: server_dir,
+        platform,
+        version,
+        jar: jar_file_name,
+    })
+}
+
+/// Where the jar of a custom server comes from
+pub enum CustomJar {
+    Path(PathBuf),
+    Url(Url),
+}
+
+/// Creates a server around a jar that no platform resolves, such as a patched fork or the jar of a
+/// modpack's server pack. Its kind is recorded as custom, so that it isn't taken for the platform
+/// its jar happens to be named after.
+pub fn create_custom<N: Display>(
+    jar: CustomJar,
+    name: Option<N>,
+    no_clobber_session: bool,
+    reporter: &dyn Reporter,
+) -> Result<CreatedServer> {
+    let platform = Platform::Custom;
+    let server_dir = get_new_server_path(name, platform, no_clobber_session)?;
+
+    let (jar_file_name, sha256) = match jar {
+        CustomJar::Path(path) => {
+            let len = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    return Err(Error::MissingFile { file: path });
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let jar_file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| Error::MissingFile { file: path.clone() })?;
+
+            space::ensure(&server_dir, len)?;
+            fs::create_dir_all(&server_dir)?;
+            let final_path = server_dir.join(&jar_file_name);
+            fs::copy(&path, &final_path)?;
+            (jar_file_name, checksum::sha256_file(&final_path)?)
+        }
+        CustomJar::Url(url) => {
+            let (jar, jar_file_name) = get_jar(url.clone(), platform, reporter)?;
+            // without a name from the server, the url names the jar better than the platform does
+            let jar_file_name = match url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+            {
+                Some(segment)
+                    if jar_file_name == format!("{platform}.jar") && segment.ends_with(".jar") =>
+                {
+                    segment.to_string()
+                }
+                _ => jar_file_name,
+            };
+
+            space::ensure(&server_dir, jar.content_length().unwrap_or_default())?;
+            fs::create_dir_all(&server_dir)?;
+            let len = jar.content_length();
+            let (written, sha256) =
+                copy_jar(&server_dir, jar, &jar_file_name, len, None, None, reporter)?;
+            check_content_length(len, written)?;
+            (jar_file_name, sha256)
+        }
+    };
+
+    set_default_metadata(server_dir.join(METADATA_DIRECTORY), &jar_file_name)?;
+    set_kind_metadata(server_dir.join(METADATA_DIRECTORY), platform)?;
+    checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+    reporter.report(Event::Done { path: &server_dir });
+
+    Ok(CreatedServer {
+        name: server_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: server_dir,
+        platform,
+        version: "unknown".to_string(),
+        jar: jar_file_name,
+    })
+}
+
+/// Installs an already resolved artifact into an existing server, returning the sha256 digest of
+/// the installed jar. The artifact is recorded as the server's provenance, along with the
+/// version that was asked for. Callers check that the server isn't the current one first.
+fn update_with_artifact(
+    server: impl AsRef<str>,
+    artifact: &ResolvedArtifact,
+    requested_version: Option<&str>,
+    expected_sha256: Option<&str>,
+    reporter: &dyn Reporter,
+) -> Result<String> {
+    let server_dir = get_server_dir_required(server.as_ref())?;
+    let previous_jar = read_server_jar_path(&server_dir).ok();
+
+    if artifact.platform == Platform::Bedrock {
+        let sha256 = install_bedrock(&server_dir, artifact, expected_sha256, reporter)?;
+        // the digest is of the downloaded zip, not of anything that is launched
+        checksum::forget(server_dir.join(METADATA_DIRECTORY))?;
+        provenance::record(
+            server_dir.join(METADATA_DIRECTORY),
+            artifact,
+            requested_version,
+            &artifact.version,
+        )?;
+        reporter.report(Event::Done { path: &server_dir });
+        return Ok(sha256);
+    }
+
+    if artifact.platform == Platform::Quilt {
+        let (jar_file_name, sha256) = install_quilt(&server_dir, artifact, reporter)?;
+        let version = installed_version(artifact, &jar_file_name);
+        set_jar_file_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+        set_kind_metadata(server_dir.join(METADATA_DIRECTORY), artifact.platform)?;
+        checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+        provenance::record(
+            server_dir.join(METADATA_DIRECTORY),
+            artifact,
+            requested_version,
+            &version,
+        )?;
+        remove_replaced_jar(&server_dir, previous_jar);
+        reporter.report(Event::Done { path: &server_dir });
+        return Ok(sha256);
+    }
+
+    if artifact.platform == Platform::Spigot {
+        let (jar_file_name, sha256) =
+            install_spigot(&server_dir, artifact, expected_sha256, false, reporter)?;
+        let version = installed_version(artifact, &jar_file_name);
+        set_jar_file_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+        set_kind_metadata(server_dir.join(METADATA_DIRECTORY), artifact.platform)?;
+        checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+        provenance::record(
+            server_dir.join(METADATA_DIRECTORY),
+            artifact,
+            requested_version,
+            &version,
+        )?;
+        remove_replaced_jar(&server_dir, previous_jar);
+        reporter.report(Event::Done { path: &server_dir });
+        return Ok(sha256);
+    }
 
-    let (jar, jar_file_name) = get_jar(download_url, platform)?;
-    copy_jar(&server, jar, &jar_file_name)?;
+    let (jar, jar_file_name) = get_jar(artifact.download_url()?, artifact.platform, reporter)?;
+    space::ensure(&server_dir, jar.content_length().unwrap_or_default())?;
+    let len = jar.content_length();
+    let (written, sha256) = copy_jar(
+        &server_dir,
+        jar,
+        &jar_file_name,
+        len,
+        // a digest pinned by a run manifest is checked instead of the published one
+        expected_sha256.or(artifact.sha256.as_deref()),
+        artifact.published_digest.as_ref(),
+        reporter,
+    )?;
+    check_content_length(len, written)?;
     set_jar_file_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+    set_kind_metadata(server_dir.join(METADATA_DIRECTORY), artifact.platform)?;
+    checksum::record(server_dir.join(METADATA_DIRECTORY), &sha256)?;
+    provenance::record(
+        server_dir.join(METADATA_DIRECTORY),
+        artifact,
+        requested_version,
+        &artifact.version,
+    )?;
+    remove_replaced_jar(&server_dir, previous_jar);
+    reporter.report(Event::Done { path: &server_dir });
+
+    Ok(sha256)
+}
+
+/// Removes the jar a server ran before an update, once the metadata points at the new one, so
+/// that old builds don't pile up. Jars outside the server itself are left alone, as they may be
+/// shared, and failing to remove one only warrants a warning since the update itself succeeded.
+fn remove_replaced_jar(server_dir: &Path, previous_jar: Option<PathBuf>) {
+    let Some(previous_jar) = previous_jar else {
+        return;
+    };
+
+    let replaced = previous_jar.parent() == Some(server_dir)
+        && read_server_jar_path(server_dir).is_ok_and(|current| current != previous_jar);
+    if !replaced {
+        return;
+    }
+
+    match fs::remove_file(&previous_jar) {
+        Ok(()) => verbose!("Removed the replaced jar {}", previous_jar.display()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m failed to remove the replaced jar {}: {err}",
+            previous_jar.display()
+        ),
+    }
+}
+
+/// Takes the version and build out of a jar named like `purpur-1.21.4-2000.jar`
+fn version_and_build(jar_name: &str) -> Option<(&str, &str)> {
+    jar_name
+        .strip_suffix(".jar")?
+        .split_once('-')?
+        .1
+        .rsplit_once('-')
+}
+
+/// Prints the changes between a server's installed build and the one it is updated to, or that it
+/// is created with when there is no installed build
+pub fn print_changelog(
+    artifact: &ResolvedArtifact,
+    installed_jar: Option<&str>,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let installed = installed_jar.and_then(version_and_build);
+
+    match platforms::changelog(artifact, installed)? {
+        None => reporter.message(&format!("{} doesn't publish changelogs", artifact.platform)),
+        Some(lines) if lines.is_empty() => reporter.message("No changes"),
+        Some(lines) => {
+            match installed {
+                Some((version, build)) if version == artifact.version => {
+                    reporter.message(&format!("Changes since build {build}:"))
+                }
+                _ => reporter.message("Changes:"),
+            }
+
+            for line in lines {
+                reporter.message(&format!("  {line}"));
+            }
+        }
+    }
 
     Ok(())
 }
 
-pub fn save_last_used_now(server: impl AsRef<Path>) -> Result<()> {
-    let now = SystemTime::now();
-
-    let timestamp = now
-        .duration_since(UNIX_EPOCH)
-        .map_err(|_| Error::TimeWentBackwards)?
-        .as_secs();
-
-    set_last_used_metadata(
-        get_expanded_servers_dir()?
-            .join(server)
-            .join(METADATA_DIRECTORY),
-        timestamp,
+/// Picks the platform a server is updated on: the one it already runs when none is given, and
+/// otherwise the one given, which may only differ from the one it runs with `force`
+fn update_platform(server: &str, requested: Option<Platform>, force: bool) -> Result<Platform> {
+    let current = get_platform(get_server_dir_required(server)?)?;
+
+    match (requested, current) {
+        (None, Some(current)) => Ok(current),
+        (None, None) => Err(Error::PlatformUnknown(server.to_string())),
+        (Some(requested), Some(current)) if requested != current => {
+            if !force {
+                return Err(Error::PlatformMismatch {
+                    server: server.to_string(),
+                    current: current.to_string(),
+                    requested: requested.to_string(),
+                });
+            }
+
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m switching {server} from {current} to {requested}, \
+                 whose worlds, plugins and mods may not carry over"
+            );
+            Ok(requested)
+        }
+        (Some(requested), _) => Ok(requested),
+    }
+}
+
+/// Updates a server, on the platform it already runs unless another is given with `force`
+#[allow(clippy::too_many_arguments)]
+pub fn update_existing(
+    server: impl AsRef<str>,
+    platform: Option<Platform>,
+    force: bool,
+    spec: VersionSpec,
+    compat_check: CompatCheck,
+    changelog: bool,
+    allow_current: bool,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let server = server.as_ref();
+    // checked before anything is resolved, so that a refusal comes straight away
+    ensure_not_current(server, "update", allow_current)?;
+    let platform = update_platform(server, platform, force)?;
+
+    let requested_version = spec.version.clone();
+    let artifact = platforms::resolve(platform, spec)?;
+    report_resolved(&artifact, reporter);
+
+    if changelog {
+        let installed_jar = get_server_jar_path(get_server_dir_required(server)?)?;
+        let installed_jar = installed_jar.file_name().map(|name| name.to_string_lossy());
+        print_changelog(&artifact, installed_jar.as_deref(), reporter)?;
+    }
+
+    compat::check_before_update(server, &artifact.version, compat_check)?;
+    update_with_artifact(
+        server,
+        &artifact,
+        requested_version.as_deref(),
+        None,
+        reporter,
     )?;
+    Ok(())
+}
+
+/// Updates every non-template server on the platform it runs, or only those on `only`, then prints
+/// the run manifest so the rollout can be reproduced with `apply_manifest`. Each platform is
+/// resolved once for the whole run. Servers whose platform isn't known are counted as failures
+/// rather than guessed at, and custom ones are skipped as they have nothing to update to.
+pub fn update_all(
+    only: Option<Platform>,
+    spec: VersionSpec,
+    allow_current: bool,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let requested_version = spec.version.clone();
+    let mut manifest = RunManifest::new();
+    let mut failures = 0;
+
+    let mut servers = vec![];
+    for_each(|server| {
+        if !is_template(&server) {
+            servers.push(server);
+        }
+    })?;
+    servers.sort();
+
+    let mut by_platform: BTreeMap<Platform, Vec<String>> = BTreeMap::new();
+    for server in servers {
+        match get_server_dir_required(&server).and_then(get_platform) {
+            Ok(Some(Platform::Custom)) => verbose!("Skipping {server}, which runs a custom jar"),
+            Ok(Some(platform)) if only.is_none_or(|only| only == platform) => {
+                by_platform.entry(platform).or_default().push(server)
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                let err = Error::PlatformUnknown(server.clone());
+                report_failure(&server, &err, reporter);
+                failures += 1;
+            }
+            Err(err) => {
+                report_failure(&server, &err, reporter);
+                failures += 1;
+            }
+        }
+    }
+
+    for (platform, servers) in by_platform {
+        let artifact = match platforms::resolve(platform, spec.clone()) {
+            Ok(artifact) => artifact,
+            Err(err) => {
+                for server in &servers {
+                    report_failure(server, &err, reporter);
+                }
+                failures += servers.len();
+                continue;
+            }
+        };
+        report_resolved(&artifact, reporter);
+
+        for server in servers {
+            let started = Instant::now();
+            let result = ensure_not_current(&server, "update", allow_current).and_then(|()| {
+                update_with_artifact(
+                    &server,
+                    &artifact,
+                    requested_version.as_deref(),
+                    None,
+                    reporter,
+                )
+            });
+            let error = result.as_ref().err().map(Error::to_string);
+            reporter.report(Event::ServerFinished {
+                server: &server,
+                duration_ms: started.elapsed().as_millis() as u64,
+                error: error.as_deref(),
+            });
+
+            match result {
+                Ok(sha256) => manifest.servers.push(ManifestEntry {
+                    server,
+                    artifact: ResolvedArtifact {
+                        sha256: Some(sha256),
+                        ..artifact.clone()
+                    },
+                }),
+                Err(err) => {
+                    eprintln!("Failed to update {server}: {err}");
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    reporter.message(manifest.to_toml()?.trim_end());
+
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
 
     Ok(())
 }
 
+/// Reports a server of a bulk update that failed before anything was installed
+fn report_failure(server: &str, err: &Error, reporter: &dyn Reporter) {
+    eprintln!("Failed to update {server}: {err}");
+    reporter.report(Event::ServerFinished {
+        server,
+        duration_ms: 0,
+        error: Some(&err.to_string()),
+    });
+}
+
+/// Applies a run manifest, installing each server's pinned artifact. A server the manifest would
+/// move to another platform than the one it runs fails unless `force` is set.
+pub fn apply_manifest(
+    path: impl AsRef<Path>,
+    allow_current: bool,
+    force: bool,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let manifest = RunManifest::read(path)?;
+    let mut failures = 0;
+
+    for entry in &manifest.servers {
+        let Some(expected) = entry.artifact.sha256.as_deref() else {
+            eprintln!("Skipping {}: no checksum was recorded", entry.server);
+            failures += 1;
+            continue;
+        };
+
+        if let Err(err) = ensure_not_current(&entry.server, "update", allow_current)
+            .and_then(|()| update_platform(&entry.server, Some(entry.artifact.platform), force))
+        {
+            eprintln!("Failed to update {}: {err}", entry.server);
+            failures += 1;
+            continue;
+        }
+
+        // a manifest pins the version, which is what was asked for
+        if let Err(err) = update_with_artifact(
+            &entry.server,
+            &entry.artifact,
+            Some(&entry.artifact.version),
+            Some(expected),
+            reporter,
+        ) {
+            eprintln!("Failed to update {}: {err}", entry.server);
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        return Err(Error::BulkOperationFailed(failures));
+    }
+
+    Ok(())
+}
+
+/// Records that a server was used just now. The metadata directory is created if it is missing,
+/// so that servers brought in from elsewhere start being tracked on their first use.
+pub fn save_last_used_now(server: impl AsRef<Path>) -> Result<()> {
+    let timestamp = unix_now()?;
+    let metadata_dir = get_expanded_servers_dir()?
+        .join(server)
+        .join(METADATA_DIRECTORY);
+
+    fs::create_dir_all(&metadata_dir)?;
+    set_last_used_metadata(metadata_dir, timestamp)?;
+
+    Ok(())
+}
+
+/// What a server's last used record says
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LastUsed {
+    /// Created by mcserver but never deployed
     Never,
+    /// Not recorded, as for servers from before it was tracked or brought in from elsewhere
     Unknown,
-    Time(String),
+    /// Recorded, but the record can't be made sense of
+    Corrupt,
+    /// Last used at a unix timestamp
+    At(u64),
 }
 
+impl LastUsed {
+    /// Describes the record for people, such as `never` or `2h 3m 4s ago`, as of `now`
+    pub fn describe(self, now: u64) -> String {
+        match self {
+            Self::Never => "never".to_string(),
+            Self::Unknown => "unknown".to_string(),
+            Self::Corrupt => "corrupt".to_string(),
+            Self::At(timestamp) => {
+                format!("{} ago", humanize::duration(now.saturating_sub(timestamp)))
+            }
+        }
+    }
+}
+
+/// Reads what a server's last used record says. A corrupt record is reported as such rather than
+/// as an error, so that one bad file doesn't stop whatever is looking at every server.
 pub fn get_last_used(server: impl AsRef<Path>) -> Result<LastUsed> {
-    let timestamp_path = get_expanded_servers_dir()?
-        .join(&server)
-        .join(METADATA_DIRECTORY)
-        .join(LAST_USED_FILE);
+    match read_last_used_raw(server) {
+        Ok(None) => Ok(LastUsed::Unknown),
+        Ok(Some(u64::MAX)) => Ok(LastUsed::Never),
+        Ok(Some(timestamp)) => Ok(LastUsed::At(timestamp)),
+        Err(Error::InvalidTimestampFile(_)) => Ok(LastUsed::Corrupt),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the unix timestamp a server was last used at as stored, which is `u64::MAX` if it never
+/// was, or `None` if it isn't recorded
+pub fn read_last_used_raw(server: impl AsRef<Path>) -> Result<Option<u64>> {
+    let server_dir = get_expanded_servers_dir()?.join(&server);
+    let timestamp_path = server_dir.join(METADATA_DIRECTORY).join(LAST_USED_FILE);
 
     if !timestamp_path.exists() {
-        return Ok(LastUsed::Unknown);
+        return Ok(None);
     }
 
-    let data = fs::read(timestamp_path)?;
+    let data = fs::read(&timestamp_path)
+        .map_err(|err| permissions::with_hint(err, &timestamp_path, &server_dir))?;
 
     if data.len() != 8 {
         return Err(Error::InvalidTimestampFile(
@@ -279,62 +1543,62 @@ pub fn get_last_used(server: impl AsRef<Path>) -> Result<LastUsed> {
         .try_into()
         .map_err(|_| Error::InvalidTimestampFile(server.as_ref().to_string_lossy().to_string()))?;
 
-    let timestamp = u64::from_le_bytes(bytes);
-
-    if timestamp == u64::MAX {
-        return Ok(LastUsed::Never);
-    }
-
-    let now_ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|_| Error::TimeWentBackwards)?
-        .as_secs();
-
-    let difference = now_ts.saturating_sub(timestamp);
-
-    const SECS_MINUTE: u64 = 60;
-    const SECS_HOUR: u64 = SECS_MINUTE * 60;
-    const SECS_DAY: u64 = SECS_HOUR * 24;
-    const SECS_YEAR: u64 = (SECS_DAY as f64 * 365.2425) as u64;
-
-    let years = difference / SECS_YEAR;
-    let years_remainder = difference % SECS_YEAR;
-
-    let days = years_remainder / SECS_DAY;
-    let days_remainder = years_remainder % SECS_DAY;
+    Ok(Some(u64::from_le_bytes(bytes)))
+}
 
-    let hours = days_remainder / SECS_HOUR;
-    let hours_remainder = days_remainder % SECS_HOUR;
+/// Whether an error looks like the filesystem went away, as a stale or disconnected network mount
+/// does, rather than like something wrong with the directory itself
+fn is_unavailable(err: &io::Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::ESTALE | libc::EIO | libc::ENOTCONN | libc::EHOSTDOWN | libc::ETIMEDOUT)
+    )
+}
 
-    let minutes = hours_remainder / SECS_MINUTE;
-    let seconds = hours_remainder % SECS_MINUTE;
+fn read_server_names(servers_dir: &Path) -> Result<Vec<String>> {
+    let names: io::Result<Vec<String>> = fs::read_dir(servers_dir).and_then(|entries| {
+        entries
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+            .collect()
+    });
 
-    Ok(LastUsed::Time(if years > 0 {
-        format!("{years}y {days}d {hours}h {minutes}m {seconds}s")
-    } else if days > 0 {
-        format!("{days}d {hours}h {minutes}m {seconds}s")
-    } else if hours > 0 {
-        format!("{hours}h {minutes}m {seconds}s")
-    } else if minutes > 0 {
-        format!("{minutes}m {seconds}s")
-    } else {
-        format!("{seconds}s")
-    }))
+    names.map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => Error::MissingDirectory {
+            dir: servers_dir.to_path_buf(),
+        },
+        _ if is_unavailable(&err) => Error::ServersDirectoryUnavailable {
+            dir: servers_dir.to_path_buf(),
+            reason: err.to_string(),
+        },
+        _ => err.into(),
+    })
 }
 
+/// Calls `f` with the name of every entry in the servers directory. The directory is read in full
+/// first, so that a failure doesn't leave a partial listing, and on another thread, so that a hung
+/// network mount is given up on once the time limit passes.
 pub fn for_each(mut f: impl FnMut(String)) -> Result<()> {
     let servers_dir = get_expanded_servers_dir()?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(read_server_names(servers_dir)));
+
+    let names = match subprocess::timeout()? {
+        None => receiver
+            .recv()
+            .expect("reading the servers directory doesn't panic")?,
+        Some(secs) => match receiver.recv_timeout(Duration::from_secs(secs)) {
+            Ok(names) => names?,
+            Err(_) => {
+                return Err(Error::ServersDirectoryUnavailable {
+                    dir: servers_dir.to_path_buf(),
+                    reason: format!("listing it took longer than {secs}s"),
+                });
+            }
+        },
+    };
 
-    if !servers_dir.exists() || !servers_dir.is_dir() {
-        return Err(Error::MissingDirectory {
-            dir: servers_dir.to_path_buf(),
-        });
-    }
-
-    for entry in fs::read_dir(servers_dir)? {
-        let entry = entry?;
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        f(file_name);
+    for name in names {
+        f(name);
     }
 
     Ok(())
@@ -358,7 +1622,12 @@ pub fn get_server_dir_required(server: impl AsRef<Path>) -> Result<PathBuf> {
     Ok(server_dir)
 }
 
-fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
+pub fn get_metadata_dir(server: impl AsRef<Path>) -> Result<PathBuf> {
+    Ok(get_server_dir_required(server)?.join(METADATA_DIRECTORY))
+}
+
+/// Reads the jar a server runs from its metadata, without offering to repair it
+pub fn read_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
     let server_dir = server_dir.as_ref();
     let jar_file_txt = server_dir.join(METADATA_DIRECTORY).join(JAR_FILE_TXT_NAME);
 
@@ -366,7 +1635,9 @@ fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
         return Err(Error::MissingFile { file: jar_file_txt });
     }
 
-    let jar_file_path = server_dir.join(fs::read_to_string(jar_file_txt)?.trim_end());
+    let jar_file_name = fs::read_to_string(&jar_file_txt)
+        .map_err(|err| permissions::with_hint(err, &jar_file_txt, server_dir))?;
+    let jar_file_path = server_dir.join(jar_file_name.trim_end());
 
     if !jar_file_path.is_file() {
         return Err(Error::MissingFile {
@@ -377,6 +1648,106 @@ fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
     Ok(jar_file_path)
 }
 
+/// Reads the jar a server runs from its metadata, offering to rebuild the metadata when it's
+/// missing and running interactively
+pub fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let server_dir = server_dir.as_ref();
+
+    match read_server_jar_path(server_dir) {
+        Err(Error::MissingFile { file })
+            if file.ends_with(Path::new(METADATA_DIRECTORY).join(JAR_FILE_TXT_NAME)) =>
+        {
+            if repair::offer(server_dir)? {
+                read_server_jar_path(server_dir)
+            } else {
+                Err(Error::MissingFile { file })
+            }
+        }
+        result => result,
+    }
+}
+
+/// Lists the jars at the top of a server directory, sorted by name
+pub fn find_jars(server_dir: impl AsRef<Path>) -> Result<Vec<String>> {
+    let mut jars = vec![];
+
+    for entry in fs::read_dir(server_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".jar") && entry.file_type()?.is_file() {
+            jars.push(name);
+        }
+    }
+
+    jars.sort();
+    Ok(jars)
+}
+
+/// Quotes a value for the shell inside a session
+pub fn shell_quote(value: impl AsRef<str>) -> String {
+    format!("'{}'", value.as_ref().replace('\'', r"'\''"))
+}
+
+/// Renders the configured tab title for a server
+pub fn get_tab_title(server: &str) -> Result<String> {
+    Ok(config::snapshot()?
+        .tab_title
+        .as_deref()
+        .unwrap_or(DEFAULT_TAB_TITLE)
+        .replace("{server}", server))
+}
+
+/// Sets a session's tab title to show what is happening to a server, without failing the
+/// operation when the title can't be changed
+fn set_status_title(server: &str, status: &str) {
+    let result = get_tab_title(server).and_then(|title| {
+        session::set_tab_title(session::get_name(server), format!("{title} — {status}"))
+    });
+
+    if let Err(err) = result {
+        verbose!("Failed to set the tab title of {server}: {err}");
+    }
+}
+
+/// Returns the shell command that runs a server from its directory, which is what its session
+/// runs and what containers are started with. A portable invocation uses the `java` on the PATH
+/// and paths relative to the server, so that it works wherever the server is copied to.
+pub fn get_launch_invocation(server: &str, portable: bool) -> Result<String> {
+    let server_dir = get_server_dir_required(server)?;
+    let relative = |path: PathBuf| {
+        if portable {
+            path.strip_prefix(&server_dir)
+                .map(Path::to_path_buf)
+                .unwrap_or(path)
+        } else {
+            path
+        }
+    };
+    let java_launch = || {
+        get_java_launch(server).map(|mut launch| {
+            if portable {
+                launch.java = PathBuf::from("java");
+                launch.jar = relative(launch.jar);
+            }
+            launch
+        })
+    };
+
+    Ok(
+        match (
+            launch_script::find(&server_dir)?.map(relative),
+            read_start_command(&server_dir)?,
+        ) {
+            (Some(script), Some(start_command)) => {
+                launch_script::native_command(&script, &start_command)
+            }
+            (Some(script), None) => launch_script::java_command(&script, &java_launch()?),
+            (None, Some(start_command)) => start_command,
+            (None, None) => java_launch()?.command_line(),
+        },
+    )
+}
+
 pub fn get_command(server: impl AsRef<str>) -> Result<String> {
     let server = server.as_ref();
     if is_template(server) {
@@ -384,42 +1755,253 @@ pub fn get_command(server: impl AsRef<str>) -> Result<String> {
     }
 
     let server_dir = get_server_dir_required(server)?;
-    let config = &config::get()?;
+    let launch = get_launch_invocation(server, false)?;
+
     Ok(format!(
-        "{} action rename-tab Server && cd {} && java -jar {} {} {} && {} kill-session $ZELLIJ_SESSION_NAME",
+        "{} action rename-tab {} && cd {} && {} && {} kill-session $ZELLIJ_SESSION_NAME",
         session::BASE_COMMAND,
-        server_dir.to_string_lossy(),
-        config.default_java_args,
-        get_server_jar_path(&server_dir)?.to_string_lossy(),
-        if config.nogui { "nogui" } else { "" },
+        shell_quote(get_tab_title(server)?),
+        shell_quote(server_dir.to_string_lossy()),
+        launch,
         session::BASE_COMMAND
     ))
 }
 
-pub fn restart() -> Result<()> {
-    let session_name = env::var_os("ZELLIJ_SESSION_NAME")
-        .ok_or(Error::NoSessionName)?
-        .to_string_lossy()
-        .to_string();
+/// What a Java server is run with, before it is put together into a command
+pub struct JavaLaunch {
+    pub java: PathBuf,
+    pub java_args: String,
+    pub jar: PathBuf,
+    pub nogui: bool,
+}
+
+impl JavaLaunch {
+    /// The java command line, with the arguments left unquoted so that they split into words
+    pub fn command_line(&self) -> String {
+        format!(
+            "{} -jar {} {} {}",
+            shell_quote(self.java.to_string_lossy()),
+            self.java_args,
+            shell_quote(self.jar.to_string_lossy()),
+            if self.nogui { "nogui" } else { "" },
+        )
+    }
+}
 
-    let Some(server) = session_name.strip_suffix(session::SUFFIX) else {
-        return Err(Error::InvalidServerSession(session_name));
+/// Resolves the runtime, arguments and jar a Java server runs with
+pub fn get_java_launch(server: &str) -> Result<JavaLaunch> {
+    let server_dir = get_server_dir_required(server)?;
+    let config = config::snapshot()?;
+
+    let java_args =
+        match fs::read_to_string(server_dir.join(METADATA_DIRECTORY).join(JAVA_ARGS_FILE)) {
+            Ok(java_args) => java_args.trim().to_string(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => config.default_java_args,
+            Err(err) => return Err(err.into()),
+        };
+
+    let java = java::select(&server_dir)?;
+    verbose!("Using {} to run {server}", java.display());
+
+    Ok(JavaLaunch {
+        java,
+        java_args,
+        jar: get_server_jar_path(&server_dir)?,
+        nogui: config.nogui && !is_proxy(&server_dir)?,
+    })
+}
+
+/// Returns the launch command for printing, refusing to show it if it would leak the server's
+/// rcon password
+pub fn get_printable_command(server: impl AsRef<str>) -> Result<String> {
+    let server = server.as_ref();
+    let command = get_command(server)?;
+
+    let config = config::snapshot()?;
+    let password = config
+        .rcon
+        .as_ref()
+        .and_then(|rcon| rcon.get(server))
+        .and_then(|rcon| rcon.password.as_ref());
+
+    if let Some(password) = password
+        && !password.0.is_empty()
+        && command.contains(&password.0)
+    {
+        return Err(Error::SecretInCommand(server.to_string()));
+    }
+
+    Ok(command)
+}
+
+/// Returns the name of the current zellij session and the server it belongs to
+pub fn get_session_server() -> Result<(String, String)> {
+    let session_name = session::current_session_name().ok_or(Error::NoSessionName)?;
+    let server = session::current_server()
+        .ok_or_else(|| Error::InvalidServerSession(session_name.clone()))?;
+
+    Ok((session_name, server))
+}
+
+/// Resolves `.` to the current server, preferring the server session this process runs in over
+/// the server containing the current directory, since a session is unambiguous
+pub fn server_or_current<S>(server: S) -> Result<String>
+where
+    S: Into<String> + for<'a> PartialEq<&'a str>,
+{
+    if server == "." {
+        match session::current_server() {
+            Some(server) => Ok(server),
+            None => config::get_current_server_directory(),
+        }
+    } else {
+        Ok(server.into())
+    }
+}
+
+/// Whether a server is the one this process runs inside of, by its session or its directory
+pub fn is_current_server(server: &str) -> bool {
+    session::current_server().is_some_and(|current| current == server)
+        || config::get_current_server_directory().is_ok_and(|current| current == server)
+}
+
+/// Fails if a server is the one this process runs inside of, unless that is allowed. Removing or
+/// updating it would pull the directory or the jar out from under the shell and the server.
+pub fn ensure_not_current(server: &str, action: &'static str, allow_current: bool) -> Result<()> {
+    if !allow_current && is_current_server(server) {
+        return Err(Error::CurrentServerTargeted {
+            server: server.to_string(),
+            action,
+        });
+    }
+
+    Ok(())
+}
+
+/// Deploys a server in a new session, running its deploy hooks around it. The jar is checked
+/// against its recorded digest first unless `skip_verify` is set, and against the version of its
+/// world unless `allow_downgrade` is set. With `wait_ready`, it then waits up to that long for the
+/// server to finish starting and records how that went.
+pub fn deploy(
+    server: &str,
+    skip_verify: bool,
+    allow_downgrade: bool,
+    wait_ready: Option<Duration>,
+) -> Result<()> {
+    let command = get_command(server)?;
+    if !skip_verify {
+        checksum::verify_jar(server)?;
+    }
+    downgrade::check(server, allow_downgrade)?;
+    hooks::run_pre(server, Hook::PreDeploy)?;
+    let deployed = SystemTime::now();
+    session::new_server(server, Some(command))?;
+    profiles::run_first_start(server)?;
+    hooks::run_post(server, Hook::PostDeploy);
+
+    if let Some(timeout) = wait_ready {
+        startup::wait_ready(server, deployed, timeout)?;
+        println!("{server} is ready");
+    }
+
+    Ok(())
+}
+
+pub fn restart(skip_verify: bool) -> Result<()> {
+    let (session_name, server) = get_session_server()?;
+    let command = get_command(&server)?;
+    if !skip_verify {
+        checksum::verify_jar(&server)?;
+    }
+
+    hooks::run_pre(&server, Hook::PreDeploy)?;
+    save_last_used_now(&server)?;
+    // the launch command sets the title back once the server starts
+    set_status_title(&server, "restarting…");
+    session::write_line(&session_name, command)?;
+    hooks::run_post(&server, Hook::PostDeploy);
+    Ok(())
+}
+
+/// Whether a server's rcon configuration points at this machine, or is missing entirely, in which
+/// case its session can be typed into instead
+fn is_local_server(server: &str) -> Result<bool> {
+    let config = config::snapshot()?;
+    let address = config
+        .rcon
+        .as_ref()
+        .and_then(|rcon| rcon.get(server))
+        .and_then(|rcon| rcon.server_address.as_deref());
+
+    Ok(address.is_none_or(|address| LOCAL_ADDRESSES.contains(&address)))
+}
+
+/// Waits for a server's session to exit, or to be removed by the launch command
+fn wait_for_exit(server: &str, timeout: Duration) -> Result<()> {
+    let start = Instant::now();
+
+    while start.elapsed() < timeout {
+        if get_server_sessions_to_living()?.get(server) != Some(&true) {
+            return Ok(());
+        }
+
+        thread::sleep(STOP_POLL_INTERVAL);
+    }
+
+    Err(Error::StopTimedOut(server.to_string()))
+}
+
+/// Stops a server over rcon, falling back to typing `stop` into its session when rcon isn't
+/// configured or can't connect, and running its stop hooks around it
+pub fn stop(server: &str, wait: Option<Duration>) -> Result<()> {
+    hooks::run_pre(server, Hook::PreStop)?;
+
+    let alive = get_alive_server_sessions()?.contains(server);
+    if alive {
+        set_status_title(server, "stopping…");
+    }
+
+    let stop_command = get_stop_command(server)?;
+
+    // native servers such as Bedrock have no rcon, so their console is the only way to stop them
+    let native = read_start_command(get_server_dir_required(server)?)?.is_some();
+    let result = if native {
+        Err(Error::RconUnsupported(server.to_string()))
+    } else {
+        rcon(server, vec![stop_command.as_str()])
     };
 
-    save_last_used_now(server)?;
-    session::write_line(&session_name, get_command(server)?)
+    match result {
+        Ok(()) => println!("Stopped {server} over rcon"),
+        Err(err) if alive && (native || is_local_server(server)?) => {
+            verbose!("Falling back to the session console: {err}");
+            session::ensure_registered(server, false)?;
+            session::write_line(session::get_name(server), &stop_command)?;
+            println!("Stopped {server} through its session console");
+        }
+        Err(err) => return Err(err),
+    }
+
+    if let Some(timeout) = wait {
+        wait_for_exit(server, timeout)?;
+        println!("{server} has exited");
+    }
+
+    hooks::run_post(server, Hook::PostStop);
+    Ok(())
 }
 
 pub fn is_template(server: impl AsRef<str>) -> bool {
     server.as_ref().ends_with(TEMPLATE_SUFFIX)
 }
 
-pub fn new_template(server: impl AsRef<str>) -> Result<()> {
+/// Creates a template from a server, returning the template's name
+pub fn new_template(server: impl AsRef<str>) -> Result<String> {
     let server = server.as_ref();
     if is_template(server) {
         return Err(Error::TemplateUsedForTemplate);
     }
-    println!("Creating template using server {server}...");
+    eprintln!("Creating template using server {server}...");
 
     let servers_dir = get_expanded_servers_dir()?;
 
@@ -428,48 +2010,128 @@ pub fn new_template(server: impl AsRef<str>) -> Result<()> {
         return Err(Error::ServerNotFound(server.to_string()));
     }
 
-    let template_path = servers_dir.join(format!("{server}{TEMPLATE_SUFFIX}"));
+    let template_name = format!("{server}{TEMPLATE_SUFFIX}");
+    let template_path = servers_dir.join(&template_name);
     if template_path.exists() {
         return Err(Error::TemplateAlreadyExists(server.to_string()));
     }
 
-    copy_directory(server_path, template_path)?;
+    space::ensure_for_copy(&server_path, &template_path)?;
+    copy_directory(server_path, &template_path)?;
+    lineage::bump_template_revision(template_path.join(METADATA_DIRECTORY), None)?;
+
+    Ok(template_name)
+}
+
+/// Replaces a template's contents with the current state of the server it was made from, bumping
+/// its revision, which is returned along with the template's name
+pub fn update_template(server: impl AsRef<str>) -> Result<(String, u32)> {
+    let server = server.as_ref();
+    if is_template(server) {
+        return Err(Error::TemplateUsedForTemplate);
+    }
+
+    let servers_dir = get_expanded_servers_dir()?;
+
+    let server_path = servers_dir.join(server);
+    if !server_path.exists() {
+        return Err(Error::ServerNotFound(server.to_string()));
+    }
+
+    let template_name = format!("{server}{TEMPLATE_SUFFIX}");
+    let template_path = servers_dir.join(&template_name);
+    if !template_path.exists() {
+        return Err(Error::TemplateNotFound(template_name));
+    }
+
+    let previous = lineage::template_revision(template_path.join(METADATA_DIRECTORY))?;
+
+    space::ensure_for_copy(&server_path, &template_path)?;
+    remove_dir_with_retries(&template_path)?;
+    copy_directory(server_path, &template_path)?;
+    let revision =
+        lineage::bump_template_revision(template_path.join(METADATA_DIRECTORY), previous)?;
+
+    Ok((template_name, revision))
+}
+
+/// Lists the servers created from a template, and whether they predate its latest revision
+pub fn template_children(template: impl AsRef<str>) -> Result<()> {
+    let template_name = get_template_name(template.as_ref());
+    let servers_dir = get_expanded_servers_dir()?;
+
+    let template_path = servers_dir.join(&template_name);
+    if !template_path.exists() {
+        return Err(Error::TemplateNotFound(template_name));
+    }
+
+    let revision = lineage::template_revision(template_path.join(METADATA_DIRECTORY))?;
+
+    let mut servers = vec![];
+    for_each(|server| servers.push(server))?;
+    servers.sort();
+
+    for server in servers {
+        let Some(lineage) = lineage::read(servers_dir.join(&server).join(METADATA_DIRECTORY))?
+        else {
+            continue;
+        };
+
+        if lineage.kind != SourceKind::Template || lineage.source != template_name {
+            continue;
+        }
+
+        match (lineage.source_revision, revision) {
+            (Some(child), Some(latest)) if child < latest => {
+                println!("{server} (revision {child}, \x1b[33;1moutdated\x1b[0m)")
+            }
+            (Some(child), _) => println!("{server} (revision {child})"),
+            (None, _) => println!("{server} (revision unknown)"),
+        }
+    }
 
     Ok(())
 }
 
-fn get_first_server_path(name: impl Display) -> Result<PathBuf> {
+fn get_template_name(template: &str) -> String {
+    if template.ends_with(TEMPLATE_SUFFIX) {
+        template.to_string()
+    } else {
+        format!("{template}{TEMPLATE_SUFFIX}")
+    }
+}
+
+/// Returns the path of the first free server name, which is the name itself or the name with the
+/// first free number appended. Names in `taken` count as used even without a directory.
+fn get_first_server_path(name: impl Display, taken: &HashSet<String>) -> Result<PathBuf> {
     let servers_dir = get_expanded_servers_dir()?;
-    let path = servers_dir.join(format!("{name}"));
+    let name = name.to_string();
+    let is_free = |name: &str| !servers_dir.join(name).exists() && !taken.contains(name);
 
-    if !path.exists() {
-        return Ok(path);
+    if is_free(&name) {
+        return Ok(servers_dir.join(name));
     }
 
     let mut number = 2;
 
     Ok(loop {
-        let path = servers_dir.join(format!("{name}-{number}"));
-        if !path.exists() {
-            break path;
+        let numbered = format!("{name}-{number}");
+        if is_free(&numbered) {
+            break servers_dir.join(numbered);
         }
 
         number += 1;
     })
 }
 
-pub fn from_template(template: impl AsRef<str>, server: Option<impl AsRef<str>>) -> Result<()> {
+/// Creates a server from a template, returning the new server's name
+pub fn from_template(template: impl AsRef<str>, server: Option<impl AsRef<str>>) -> Result<String> {
     let template = template.as_ref();
     let servers_dir = get_expanded_servers_dir()?;
 
-    let template_path = if template.ends_with(TEMPLATE_SUFFIX) {
-        println!("Creating server from {template}");
-        servers_dir.join(template)
-    } else {
-        let template_name = format!("{}{TEMPLATE_SUFFIX}", template);
-        println!("Creating server from {template_name}");
-        servers_dir.join(template_name)
-    };
+    let template_name = get_template_name(template);
+    eprintln!("Creating server from {template_name}");
+    let template_path = servers_dir.join(&template_name);
 
     if !template_path.exists() {
         return Err(Error::TemplateNotFound(template.to_string()));
@@ -477,19 +2139,35 @@ pub fn from_template(template: impl AsRef<str>, server: Option<impl AsRef<str>>)
 
     let server_path = match server {
         Some(server) => {
-            let server = server.as_ref();
-            let path = get_expanded_servers_dir()?.join(server);
+            let server = names::normalize_typed(server.as_ref())?;
+            let path = get_expanded_servers_dir()?.join(&server);
             if path.exists() {
-                return Err(Error::ServerAlreadyExists(server.to_string()));
+                return Err(Error::ServerAlreadyExists(server));
             }
             path
         }
-        None => get_first_server_path(template)?,
+        None => get_first_server_path(
+            template_name
+                .strip_suffix(TEMPLATE_SUFFIX)
+                .unwrap_or(&template_name),
+            &HashSet::new(),
+        )?,
     };
 
-    copy_directory(template_path, server_path)?;
+    let revision = lineage::template_revision(template_path.join(METADATA_DIRECTORY))?;
+    space::ensure_for_copy(&template_path, &server_path)?;
+    copy_directory(template_path, &server_path)?;
+    lineage::record(
+        server_path.join(METADATA_DIRECTORY),
+        template_name,
+        SourceKind::Template,
+        revision,
+    )?;
 
-    Ok(())
+    Ok(server_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default())
 }
 
 pub fn reinstall_with_git(commit: Option<String>) -> io::Result<()> {
@@ -530,16 +2208,46 @@ pub fn reinstall_with_crate() -> io::Result<()> {
     Ok(())
 }
 
+pub fn tag_bedrock(servers: &mut [ServerObject]) -> Result<()> {
+    let servers_dir = get_expanded_servers_dir()?;
+
+    for server in servers {
+        if read_start_command(servers_dir.join(&server.name))?.is_some() {
+            server.tags.push("(bedrock)".to_string());
+        }
+    }
+
+    Ok(())
+}
+
 fn add_last_used_tag(server: &mut ServerObject) {
-    let last_used = get_last_used(&server.name);
-
-    server
-        .tags
-        .push(match last_used.unwrap_or(LastUsed::Unknown) {
-            LastUsed::Never => "(Last used \x1b[35;1mnever\x1b[0m)".to_string(),
-            LastUsed::Unknown => "(Last used unknown)".to_string(),
-            LastUsed::Time(time) => format!("(Last used \x1b[35;1m{time}\x1b[0m ago)"),
-        });
+    let last_used = get_last_used(&server.name).unwrap_or(LastUsed::Unknown);
+    let description = last_used.describe(unix_now().unwrap_or_default());
+
+    server.tags.push(match last_used {
+        LastUsed::Unknown => format!("(Last used {description})"),
+        LastUsed::Corrupt => format!("(Last used \x1b[31;1m{description}\x1b[0m)"),
+        LastUsed::Never | LastUsed::At(_) => {
+            let (time, ago) = description
+                .strip_suffix(" ago")
+                .map_or((description.as_str(), ""), |time| (time, " ago"));
+            format!("(Last used \x1b[35;1m{time}\x1b[0m{ago})")
+        }
+    });
+}
+
+/// Tags the servers whose last start failed with why, such as `[failing: port in use]`
+pub fn tag_failing(servers: &mut [ServerObject]) {
+    for server in servers {
+        if let Ok(Some(last_start)) = startup::read(&server.name)
+            && last_start.failed()
+        {
+            let reason = last_start.reason.as_deref().unwrap_or("unknown");
+            server
+                .tags
+                .push(format!("\x1b[31;1m[failing: {reason}]\x1b[0m"));
+        }
+    }
 }
 
 fn tag_as_active(server: &mut ServerObject) {
@@ -598,47 +2306,266 @@ pub fn fully_tag_servers(servers: &mut [ServerObject]) -> Result<()> {
     Ok(())
 }
 
-pub fn rcon<C, T>(server: impl AsRef<str>, commands: C) -> Result<()>
-where
-    C: AsRef<[T]>,
-    T: AsRef<OsStr>,
-{
-    let config = config::get()?;
-    let rcon_config = config.rcon.as_ref().ok_or(Error::NoRconConfig)?;
-
-    let server_rcon_config = rcon_config
-        .get(server.as_ref())
-        .ok_or_else(|| Error::MissingRconConfig(server.as_ref().to_string()))?;
+fn rcon_command(endpoint: &RconConfig) -> Command {
+    let mut command = Command::new(RCON_COMMAND);
 
-    let mut command = Command::new("mcrcon");
-
-    if let Some(server_address) = &server_rcon_config.server_address {
+    if let Some(server_address) = &endpoint.server_address {
         command.arg("-H");
         command.arg(server_address);
     }
 
-    if let Some(port) = &server_rcon_config.port {
+    if let Some(port) = &endpoint.port {
         command.arg("-P");
         command.arg(port.to_string());
     }
 
-    if let Some(password) = &server_rcon_config.password {
+    if let Some(password) = &endpoint.password {
         command.arg("-p");
         command.arg(password);
     }
 
+    command
+}
+
+pub fn rcon<C, T>(server: impl AsRef<str>, commands: C) -> Result<()>
+where
+    C: AsRef<[T]>,
+    T: AsRef<OsStr>,
+{
+    rcon_to(&rcon::resolve(server.as_ref())?, commands)
+}
+
+/// Sends commands to an rcon endpoint, whether or not it belongs to a server
+pub fn rcon_to<C, T>(endpoint: &RconConfig, commands: C) -> Result<()>
+where
+    C: AsRef<[T]>,
+    T: AsRef<OsStr>,
+{
+    let mut command = rcon_command(endpoint);
+
     for arg in commands.as_ref() {
         command.arg(arg);
     }
 
-    let status = command.status()?;
+    command.stdout(Stdio::inherit()).stderr(Stdio::piped());
+    let output = subprocess::run_with_timeout(&mut command, |err| {
+        Error::from_spawn(err, RCON_COMMAND, RCON_INSTALL_HINT)
+    })?;
 
-    if status.success() {
+    if output.status.success() {
         Ok(())
     } else {
-        Err(Error::CommandFailure {
-            code: status.code(),
-            stderr: None,
-        })
+        Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        ))
+    }
+}
+
+/// Runs a single rcon command, returning the server's response without colors
+pub fn rcon_output(
+    server: impl AsRef<str>,
+    rcon_command_line: impl AsRef<OsStr>,
+) -> Result<String> {
+    let mut command = rcon_command(&rcon::resolve(server.as_ref())?);
+    command
+        .arg("-c")
+        .arg(rcon_command_line)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let output = subprocess::run_with_timeout(&mut command, |err| {
+        Error::from_spawn(err, RCON_COMMAND, RCON_INSTALL_HINT)
+    })?;
+
+    if !output.status.success() {
+        return Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{progress::Human, testing};
+
+    /// Reads `data` and then fails, like a connection dropped partway through a download
+    struct Interrupted<'a>(&'a [u8]);
+
+    impl io::Read for Interrupted<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "connection reset",
+                ));
+            }
+
+            let read = self.0.len().min(buf.len());
+            buf[..read].copy_from_slice(&self.0[..read]);
+            self.0 = &self.0[read..];
+            Ok(read)
+        }
+    }
+
+    const JAR: &[u8] = b"a complete jar";
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    fn copy(server_dir: &Path, jar: impl io::Read, len: Option<u64>) -> Result<(u64, String)> {
+        testing::sandbox(&[]);
+        copy_jar(server_dir, jar, "server.jar", len, None, None, &Human)
+    }
+
+    fn no_partial_download(server_dir: &Path) -> bool {
+        !server_dir
+            .join(METADATA_DIRECTORY)
+            .join(DOWNLOAD_FILE)
+            .exists()
+    }
+
+    #[test]
+    fn copied_jar_reports_its_length_and_digest() {
+        let server_dir = tempfile::tempdir().unwrap();
+
+        let written = copy(server_dir.path(), JAR, Some(JAR.len() as u64)).unwrap();
+        assert_eq!(written, (JAR.len() as u64, sha256(JAR)));
+        assert_eq!(fs::read(server_dir.path().join("server.jar")).unwrap(), JAR);
+        assert!(no_partial_download(server_dir.path()));
+    }
+
+    #[test]
+    fn interrupted_download_leaves_no_jar() {
+        let server_dir = tempfile::tempdir().unwrap();
+
+        let err = copy(server_dir.path(), Interrupted(&JAR[..4]), None).unwrap_err();
+        assert!(matches!(err, Error::Io(_)), "{err:?}");
+        assert!(!server_dir.path().join("server.jar").exists());
+        assert!(no_partial_download(server_dir.path()));
+    }
+
+    #[test]
+    fn interrupted_download_keeps_the_previous_jar() {
+        let server_dir = tempfile::tempdir().unwrap();
+        fs::write(server_dir.path().join("server.jar"), b"the previous jar").unwrap();
+
+        assert!(copy(server_dir.path(), Interrupted(JAR), None).is_err());
+        assert_eq!(
+            fs::read(server_dir.path().join("server.jar")).unwrap(),
+            b"the previous jar"
+        );
+    }
+
+    #[test]
+    fn short_download_leaves_no_jar() {
+        let server_dir = tempfile::tempdir().unwrap();
+
+        let err = copy(server_dir.path(), JAR, Some(JAR.len() as u64 + 1)).unwrap_err();
+        assert!(
+            matches!(err, Error::IncompleteDownload { expected, actual }
+                if expected == JAR.len() as u64 + 1 && actual == JAR.len() as u64),
+            "{err:?}"
+        );
+        assert!(!server_dir.path().join("server.jar").exists());
+        assert!(no_partial_download(server_dir.path()));
+    }
+
+    #[test]
+    fn mismatched_digest_leaves_no_jar() {
+        testing::sandbox(&[]);
+        let server_dir = tempfile::tempdir().unwrap();
+
+        let err = copy_jar(
+            server_dir.path(),
+            JAR,
+            "server.jar",
+            None,
+            Some(&sha256(b"another jar")),
+            None,
+            &Human,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }), "{err:?}");
+        assert!(!server_dir.path().join("server.jar").exists());
+    }
+
+    #[test]
+    fn content_length_is_only_checked_when_known() {
+        assert!(check_content_length(None, 10).is_ok());
+        assert!(check_content_length(Some(10), 10).is_ok());
+        assert!(check_content_length(Some(10), 9).is_err());
+        assert!(check_content_length(Some(9), 10).is_err());
+    }
+
+    /// Creates servers with the given names in the sandbox, where only the files that make a
+    /// directory a server are needed
+    fn create_servers(names: &[&str]) {
+        let servers_dir = testing::sandbox(&[]);
+        for name in names {
+            fs::create_dir_all(servers_dir.join(name).join(METADATA_DIRECTORY)).unwrap();
+        }
+    }
+
+    /// Removes servers unless their name says the removal fails
+    fn remove_unless_failing(server: String) -> Result<()> {
+        if server.ends_with("-failing") {
+            return Err(Error::Io(io::Error::other("the disk is on fire")));
+        }
+        remove_server(server)
+    }
+
+    #[test]
+    fn bulk_removal_carries_on_past_failures() {
+        let names = ["bulk-first", "bulk-second-failing", "bulk-third"];
+        create_servers(&names);
+
+        let servers = names.iter().map(|name| name.to_string()).collect();
+        let result = remove_each(servers, false, false, remove_unless_failing);
+
+        assert!(
+            matches!(result, Err(Error::BulkOperationFailed(1))),
+            "{result:?}"
+        );
+        let servers_dir = testing::sandbox(&[]);
+        assert!(!servers_dir.join("bulk-first").exists());
+        assert!(servers_dir.join("bulk-second-failing").exists());
+        assert!(!servers_dir.join("bulk-third").exists());
+    }
+
+    #[test]
+    fn bulk_removal_checks_every_name_first() {
+        create_servers(&["bulk-kept"]);
+
+        let servers = vec!["bulk-kept".to_string(), "bulk-missing".to_string()];
+        let result = remove_each(servers, false, false, remove_server);
+
+        assert!(
+            matches!(&result, Err(Error::ServerNotFound(server)) if server == "bulk-missing"),
+            "{result:?}"
+        );
+        assert!(testing::sandbox(&[]).join("bulk-kept").exists());
+    }
+
+    #[test]
+    fn servers_to_remove_are_deduplicated_in_order() {
+        create_servers(&["dedupe-a", "dedupe-b"]);
+
+        let servers = ["dedupe-b", "dedupe-a", "dedupe-b", "dedupe-a"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            resolve_servers_to_remove(servers, false).unwrap(),
+            ["dedupe-b", "dedupe-a"]
+        );
     }
 }
@@ -1,144 +1,548 @@
 use crate::{
+    config::{self, get_expanded_servers_dir},
     error::{Error, Result},
-    server::save_last_used_now,
-    session,
+    registry::{self, Registry},
+    server::{get_server_dir_required, save_last_used_now},
+    subprocess, verbose,
 };
 use std::{
     collections::{HashMap, HashSet},
+    env,
     ffi::OsStr,
     fmt::Display,
     io::{self, Read, Write},
     path::Path,
     process::{Command, Stdio},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
     thread,
     time::Duration,
 };
 
 pub const BASE_COMMAND: &str = "zellij";
-pub const SUFFIX: &str = ".mcserver";
+
+/// The namespace of server sessions unless another one is configured
+pub const DEFAULT_NAMESPACE: &str = "mcserver";
+
+/// The longest a namespace can be in bytes, which leaves most of a session name to the server
+const MAX_NAMESPACE_LEN: usize = 16;
+
+static SUFFIX: OnceLock<String> = OnceLock::new();
+
+const INSTALL_HINT: &str = "install it with `cargo install --locked zellij` (see https://zellij.dev/documentation/installation)";
+
+/// The oldest zellij release whose `list-sessions` output and `action write-chars` behavior this
+/// crate is known to work with
+pub const MIN_VERSION: Version = (0, 39, 0);
+
+pub type Version = (u32, u32, u32);
+
+static VERSION: OnceLock<Option<Version>> = OnceLock::new();
+
+fn parse_version(output: &str) -> Option<Version> {
+    let mut parts = output
+        .split_whitespace()
+        .last()?
+        .split('.')
+        .map(|part| part.parse().ok());
+
+    Some((
+        parts.next()??,
+        parts.next()??,
+        parts.next().flatten().unwrap_or(0),
+    ))
+}
+
+fn detect_version() -> Result<Option<Version>> {
+    let output = subprocess::run_with_timeout(
+        Command::new(BASE_COMMAND)
+            .arg("--version")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+        |err| Error::from_spawn(err, BASE_COMMAND, INSTALL_HINT),
+    )?;
+
+    Ok(parse_version(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Returns the installed zellij version, detected once per process
+pub fn version() -> Result<Option<Version>> {
+    if let Some(version) = VERSION.get() {
+        return Ok(*version);
+    }
+
+    let version = detect_version()?;
+    Ok(*VERSION.get_or_init(|| version))
+}
+
+pub fn format_version((major, minor, patch): Version) -> String {
+    format!("{major}.{minor}.{patch}")
+}
+
+fn command() -> Result<Command> {
+    if VERSION.get().is_none()
+        && let Some(version) = version()?.filter(|version| *version < MIN_VERSION)
+    {
+        eprintln!(
+            "Warning: {BASE_COMMAND} {} is older than {} and may not work as expected",
+            format_version(version),
+            format_version(MIN_VERSION)
+        );
+    }
+
+    Ok(Command::new(BASE_COMMAND))
+}
+
+/// Makes a configured namespace safe to put in session names, keeping letters, digits, dashes and
+/// underscores and replacing anything else with a dash. A blank one means the default.
+fn normalize_namespace(namespace: &str) -> String {
+    let normalized: String = namespace
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(MAX_NAMESPACE_LEN)
+        .collect();
+
+    if normalized.is_empty() {
+        DEFAULT_NAMESPACE.to_string()
+    } else {
+        normalized
+    }
+}
+
+/// The suffix that marks a zellij session as a server session, `.` followed by the namespace
+pub fn suffix() -> &'static str {
+    SUFFIX.get_or_init(|| {
+        let namespace = config::snapshot()
+            .ok()
+            .and_then(|config| config.session_namespace)
+            .unwrap_or_default();
+        format!(".{}", normalize_namespace(&namespace))
+    })
+}
+
+/// The environment variable zellij sets to the name of the session a process runs in
+pub const SESSION_NAME_VAR: &str = "ZELLIJ_SESSION_NAME";
+
+/// Returns the name of the zellij session this process runs in, if any
+pub fn current_session_name() -> Option<String> {
+    env::var_os(SESSION_NAME_VAR)
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the server whose session this process runs in, which is `None` outside of zellij and
+/// in sessions that mcserver didn't create for a server
+pub fn current_server() -> Option<String> {
+    current_session_name()?
+        .strip_suffix(suffix())
+        .filter(|server| !server.is_empty())
+        .map(String::from)
+}
 
 pub fn get_name(server: impl Display) -> String {
-    format!("{server}{SUFFIX}")
+    format!("{server}{}", suffix())
 }
 
 fn get_server_sessions_raw_string() -> Result<Option<String>> {
-    let output = Command::new(BASE_COMMAND).arg("list-sessions").output()?;
+    let mut command = command()?;
+    command
+        .arg("list-sessions")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = subprocess::run_with_timeout(&mut command, Error::from)?;
 
     match output.status.code() {
         Some(0) => Ok(Some(String::from_utf8_lossy(&output.stdout).to_string())),
         Some(1) => Ok(None), // no sessions
-        _ => Err(Error::CommandFailure {
-            code: output.status.code(),
-            stderr: Some(output.stderr),
-        }),
+        _ => Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        )),
     }
 }
 
-fn session_has_exited(session_line: impl AsRef<str>) -> bool {
-    let session_line = session_line.as_ref();
-    let bracket_pos = match session_line.rfind('(') {
-        Some(pos) => pos,
-        None => return false,
-    };
+/// Removes ANSI escape sequences, which zellij uses to color its session list
+fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip until the final byte of the sequence, which is always a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
 
-    session_line[bracket_pos..].contains("EXITED") // if there is no "EXITED", still alive
+    stripped
 }
 
-fn session_is_alive(session_line: impl AsRef<str>) -> bool {
-    !session_has_exited(session_line)
+/// A session from `zellij list-sessions`
+#[derive(Debug, PartialEq)]
+pub struct SessionInfo {
+    pub name: String,
+    /// The server the session belongs to, when its name has the server suffix
+    pub server: Option<String>,
+    pub alive: bool,
+    /// How long ago the session was created, e.g. `2h 3m`
+    pub created: Option<String>,
 }
 
-fn session_line_to_server(session_line: impl AsRef<str>) -> Option<String> {
-    let session_line = session_line.as_ref();
-    let session_name = match session_line.rfind("[Created") {
-        Some(pos) => &session_line[7..=pos - 5],
-        None => return None, // unexpected error
-    };
+/// Parses a line of `zellij list-sessions` output such as
+/// `survival.mcserver [Created 2h 3m ago] (EXITED - attach to resurrect)`, ignoring colors, the
+/// `(current)` marker of the attached session and trailing whitespace
+pub fn parse_session_line(line: &str) -> Option<SessionInfo> {
+    let line = strip_ansi(line);
+    let line = line.trim();
+
+    let name_end = line.find([' ', '\t']).unwrap_or(line.len());
+    let (name, rest) = line.split_at(name_end);
+    if name.is_empty() {
+        return None;
+    }
 
-    session_name.strip_suffix(session::SUFFIX).map(String::from)
+    let created = rest
+        .split_once("[Created ")
+        .and_then(|(_, created)| created.split_once(']'))
+        .map(|(created, _)| created.trim().trim_end_matches(" ago").to_string());
+
+    // annotations follow the creation time, so a session name containing them doesn't count
+    let annotations = rest
+        .rsplit_once(']')
+        .map_or(rest, |(_, annotations)| annotations);
+    let alive = !annotations.contains("EXITED") && !annotations.contains("RESURRECTABLE");
+
+    Some(SessionInfo {
+        name: name.to_string(),
+        server: name.strip_suffix(suffix()).map(String::from),
+        alive,
+        created,
+    })
 }
 
-pub fn get_alive_server_sessions() -> Result<HashSet<String>> {
+/// Returns every zellij session, server session or not
+fn list_sessions() -> Result<Vec<SessionInfo>> {
     Ok(get_server_sessions_raw_string()?
-        .map(|server_sessions| {
-            server_sessions
-                .lines()
-                .filter(|sl| session_is_alive(sl))
-                .filter_map(session_line_to_server)
-                .collect()
-        })
+        .map(|sessions| sessions.lines().filter_map(parse_session_line).collect())
         .unwrap_or_default())
 }
 
-pub fn get_dead_server_sessions() -> Result<HashSet<String>> {
-    Ok(get_server_sessions_raw_string()?
-        .map(|server_sessions| {
-            server_sessions
-                .lines()
-                .filter(|sl| session_has_exited(sl))
-                .filter_map(session_line_to_server)
-                .collect()
+/// Keeps the sessions with the server suffix, along with the server each belongs to
+fn with_servers(sessions: Vec<SessionInfo>) -> Vec<(String, SessionInfo)> {
+    sessions
+        .into_iter()
+        .filter_map(|session| Some((session.server.clone()?, session)))
+        .collect()
+}
+
+/// Returns every session with the server suffix, whether or not this tool created it, along with
+/// the server it belongs to
+fn get_all_server_sessions() -> Result<Vec<(String, SessionInfo)>> {
+    Ok(with_servers(list_sessions()?))
+}
+
+/// Keeps the sessions this tool created for servers in this servers directory under another
+/// namespace, which are left behind when the namespace is changed. Installs with their own servers
+/// directory keep their own sessions, so those of another install are never among them.
+fn namespace_orphans<'a>(
+    registry: &Registry,
+    sessions: &'a [SessionInfo],
+) -> Result<Vec<&'a SessionInfo>> {
+    let servers_dir = get_expanded_servers_dir()?;
+
+    Ok(sessions
+        .iter()
+        .filter(|session| !session.name.ends_with(suffix()))
+        .filter(|session| {
+            registry
+                .sessions
+                .get(&session.name)
+                .is_some_and(|dir| dir.starts_with(servers_dir))
         })
-        .unwrap_or_default())
+        .collect())
+}
+
+static WARNED_ORPHANS: AtomicBool = AtomicBool::new(false);
+
+fn warn_namespace_orphans(registry: &Registry, sessions: &[SessionInfo]) -> Result<()> {
+    let orphans = namespace_orphans(registry, sessions)?;
+
+    if !orphans.is_empty() && !WARNED_ORPHANS.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "\x1b[33;1mWarning:\x1b[0m {} session(s) were created under another session namespace and are no longer managed (run `mcserver sessions --orphans` to list them)",
+            orphans.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns whether each server with a session has a living one, including sessions this tool
+/// didn't create. Zellij being unavailable is no reason to fail, so it counts as no sessions.
+pub fn get_any_server_sessions() -> HashMap<String, bool> {
+    match get_all_server_sessions() {
+        Ok(sessions) => sessions
+            .into_iter()
+            .map(|(server, session)| (server, session.alive))
+            .collect(),
+        Err(err) => {
+            verbose!("Not checking for existing sessions: {err}");
+            HashMap::new()
+        }
+    }
+}
+
+static WARNED_UNREGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Returns the server sessions recorded in the registry, warning once about any others
+fn get_server_sessions() -> Result<Vec<(String, SessionInfo)>> {
+    let registry = registry::read()?;
+    let sessions = list_sessions()?;
+    warn_namespace_orphans(&registry, &sessions)?;
+
+    let (registered, unregistered): (Vec<_>, Vec<_>) = with_servers(sessions)
+        .into_iter()
+        .partition(|(server, session)| registry.contains(&session.name, server));
+
+    if !unregistered.is_empty() && !WARNED_UNREGISTERED.swap(true, Ordering::Relaxed) {
+        for (_, session) in &unregistered {
+            eprintln!(
+                "\x1b[33;1mWarning:\x1b[0m ignoring session {}, which was not created by mcserver (run `mcserver sessions --repair` to adopt it)",
+                session.name
+            );
+        }
+    }
+
+    Ok(registered)
+}
+
+pub fn get_alive_server_sessions() -> Result<HashSet<String>> {
+    Ok(get_server_sessions()?
+        .into_iter()
+        .filter(|(_, session)| session.alive)
+        .map(|(server, _)| server)
+        .collect())
+}
+
+pub fn get_dead_server_sessions() -> Result<HashSet<String>> {
+    Ok(get_server_sessions()?
+        .into_iter()
+        .filter(|(_, session)| !session.alive)
+        .map(|(server, _)| server)
+        .collect())
 }
 
 pub fn get_server_sessions_to_living() -> Result<HashMap<String, bool>> {
-    Ok(get_server_sessions_raw_string()?
-        .map(|ss| {
-            ss.lines()
-                .map(|s| (s, session_is_alive(s)))
-                .filter_map(|(session, living)| {
-                    session_line_to_server(session).map(|server| (server, living))
-                })
-                .collect()
-        })
-        .unwrap_or_default())
+    Ok(get_server_sessions()?
+        .into_iter()
+        .map(|(server, session)| (server, session.alive))
+        .collect())
+}
+
+/// Fails if a session for the server exists but was not created by this tool, unless forced
+pub fn ensure_registered(server: &str, force: bool) -> Result<()> {
+    let session_name = get_name(server);
+
+    if force || registry::read()?.contains(&session_name, server) {
+        return Ok(());
+    }
+
+    if get_all_server_sessions()?
+        .iter()
+        .any(|(session_server, _)| session_server == server)
+    {
+        return Err(Error::UnregisteredSession(session_name));
+    }
+
+    Ok(())
+}
+
+/// Lists every server session along with whether it is registered
+pub fn list_registry() -> Result<()> {
+    let registry = registry::read()?;
+    let sessions = get_all_server_sessions()?;
+
+    if sessions.is_empty() {
+        println!("There are no server sessions");
+        return Ok(());
+    }
+
+    for (server, session) in sessions {
+        let name = session.name;
+        let state = if session.alive {
+            "(\x1b[32;1malive\x1b[0m)"
+        } else {
+            "(\x1b[31;1mdead\x1b[0m)"
+        };
+
+        if registry.contains(&name, &server) {
+            println!("{name} {state}");
+        } else {
+            println!("{name} {state} (\x1b[33;1munregistered\x1b[0m)");
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the sessions left behind under another namespace, which this tool no longer manages
+pub fn list_namespace_orphans() -> Result<()> {
+    let registry = registry::read()?;
+    let sessions = list_sessions()?;
+    let mut orphans = namespace_orphans(&registry, &sessions)?;
+
+    if orphans.is_empty() {
+        println!("There are no sessions left under another namespace");
+        return Ok(());
+    }
+
+    orphans.sort_by(|a, b| a.name.cmp(&b.name));
+    for session in orphans {
+        let state = if session.alive {
+            "(\x1b[32;1malive\x1b[0m)"
+        } else {
+            "(\x1b[31;1mdead\x1b[0m)"
+        };
+        println!("{} {state}", session.name);
+    }
+
+    Ok(())
 }
 
-pub fn attach(server: impl AsRef<str>) -> Result<()> {
+/// Returns the sessions left behind by servers that no longer exist, as each server along with
+/// whether its session is alive, sorted by server. Fails when the servers directory itself is
+/// missing, since every server would look deleted.
+pub fn get_orphaned_sessions() -> Result<Vec<(String, bool)>> {
+    let servers_dir = get_expanded_servers_dir()?;
+    if !servers_dir.is_dir() {
+        return Err(Error::MissingDirectory {
+            dir: servers_dir.to_path_buf(),
+        });
+    }
+
+    let mut sessions: Vec<_> = get_all_server_sessions()?
+        .into_iter()
+        .filter(|(server, _)| get_server_dir_required(server).is_err())
+        .map(|(server, session)| (server, session.alive))
+        .collect();
+
+    sessions.sort();
+    Ok(sessions)
+}
+
+/// Rebuilds the registry from the sessions that currently exist for existing servers
+pub fn repair_registry() -> Result<()> {
+    let old = registry::read()?;
+    let mut new = Registry::default();
+
+    // the sessions of other namespaces are theirs to repair
+    for (name, dir) in &old.sessions {
+        if !name.ends_with(suffix()) {
+            new.sessions.insert(name.clone(), dir.clone());
+        }
+    }
+
+    for (server, session) in get_all_server_sessions()? {
+        if get_server_dir_required(&server).is_ok() {
+            new.insert(session.name, &server)?;
+        }
+    }
+
+    for name in old.sessions.keys() {
+        if !new.sessions.contains_key(name) {
+            println!("Forgot {name}");
+        }
+    }
+
+    for name in new.sessions.keys() {
+        if !old.sessions.contains_key(name) {
+            println!("Registered {name}");
+        }
+    }
+
+    registry::write(&new)
+}
+
+/// Attaches to a server's session, recording the server as used if the session is one this tool
+/// created
+pub fn attach(server: impl AsRef<str>, force: bool) -> Result<()> {
     let server = server.as_ref();
-    let mut child = Command::new(BASE_COMMAND)
+    ensure_registered(server, force)?;
+
+    let session_name = get_name(server);
+    let mut command = command()?;
+    command
         .arg("attach")
-        .arg(get_name(server))
-        .stderr(Stdio::piped())
-        .spawn()?;
+        .arg(&session_name)
+        .stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+
+    // stderr is drained while zellij runs, so that it can't fill the pipe and none of it is lost
+    let stderr = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf);
+            buf
+        })
+    });
 
     let status = child.wait()?;
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
 
-    if status.success() {
-        save_last_used_now(server)
-    } else {
-        let mut buf = Vec::new();
-        child
-            .stderr
-            .take()
-            .ok_or(io::Error::new(
-                io::ErrorKind::BrokenPipe,
-                "Failed to take stderr pipe",
-            ))?
-            .read_to_end(&mut buf)?;
-
-        Err(Error::CommandFailure {
-            code: status.code(),
-            stderr: Some(buf),
-        })
+    if !status.success() {
+        return Err(Error::command_failure(
+            &command,
+            status.code(),
+            Some(stderr),
+        ));
     }
+
+    if registry::read()?.contains(&session_name, server) {
+        save_last_used_now(server)?;
+    }
+
+    Ok(())
 }
 
-pub fn new_session<S, I>(session: S, initial_command: Option<I>) -> Result<()>
+/// Starts a session, whose new panes open in `cwd` when it is given
+pub fn new_session<S, I>(session: S, cwd: Option<&Path>, initial_command: Option<I>) -> Result<()>
 where
     S: AsRef<OsStr>,
     I: AsRef<OsStr>,
 {
-    Command::new(BASE_COMMAND)
-        .arg("delete-session")
-        .arg(&session)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()?;
-
-    let mut command = Command::new(BASE_COMMAND);
+    subprocess::run_with_timeout(
+        command()?
+            .arg("delete-session")
+            .arg(&session)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+        Error::from,
+    )?;
+
+    let mut command = command()?;
     command.arg("--session").arg(&session);
+
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+
     let mut child = command.spawn()?;
 
     thread::sleep(Duration::from_millis(300));
@@ -153,87 +557,317 @@ where
 }
 
 pub fn new_server(
-    server: impl Display + AsRef<Path>,
+    server: impl AsRef<str>,
     initial_command: Option<impl AsRef<OsStr>>,
 ) -> Result<()> {
-    save_last_used_now(&server)?;
-    let session_name = get_name(&server);
-    new_session(session_name, initial_command)?;
-    save_last_used_now(&server)
+    let server = server.as_ref();
+    ensure_registered(server, false)?;
+    save_last_used_now(server)?;
+    let session_name = get_name(server);
+    let server_dir = get_server_dir_required(server)?;
+    registry::register(&session_name, server)?;
+    new_session(session_name, Some(&server_dir), initial_command)?;
+    save_last_used_now(server)
 }
 
-pub fn delete_server_session(server: impl Display, force: bool) -> Result<()> {
-    let mut command = Command::new(BASE_COMMAND);
+pub fn delete_server_session(server: impl AsRef<str>, force: bool) -> Result<()> {
+    let server = server.as_ref();
+    ensure_registered(server, force)?;
+
+    let session_name = get_name(server);
+    let mut command = command()?;
     command.arg("delete-session");
-    command.arg(format!("{server}{SUFFIX}"));
+    command.arg(&session_name);
 
     if force {
         command.arg("--force");
     }
 
-    command.status()?;
-    Ok(())
+    subprocess::run_with_timeout(&mut command, Error::from)?;
+    registry::unregister(&session_name)
+}
+
+/// Returns each dead server session along with how long ago it was created, sorted by name
+fn get_dead_server_sessions_with_age() -> Result<Vec<(String, Option<String>)>> {
+    let mut sessions: Vec<_> = get_server_sessions()?
+        .into_iter()
+        .filter(|(_, session)| !session.alive)
+        .map(|(server, session)| (server, session.created))
+        .collect();
+
+    sessions.sort();
+    Ok(sessions)
+}
+
+fn print_sessions(sessions: &[(String, Option<String>)]) {
+    for (server, created) in sessions {
+        match created {
+            Some(created) => println!("  {server} (created {created} ago)"),
+            None => println!("  {server}"),
+        }
+    }
 }
 
 pub fn delete_all() -> Result<()> {
-    for session in get_dead_server_sessions()? {
-        delete_server_session(session, false)?;
+    let sessions = get_dead_server_sessions_with_age()?;
+
+    if sessions.is_empty() {
+        println!("There are no dead sessions");
+        return Ok(());
+    }
+
+    for (server, _) in &sessions {
+        delete_server_session(server, false)?;
     }
 
+    println!("Deleted {} dead session(s):", sessions.len());
+    print_sessions(&sessions);
+
     Ok(())
 }
 
 pub fn delete_all_confirmed() -> Result<()> {
-    loop {
-        print!("Delete all sessions? (y/n): ");
-        io::stdout().flush()?;
-
-        let mut confirmation = String::new();
-        io::stdin().read_line(&mut confirmation)?;
-
-        match confirmation.trim_end().to_lowercase().as_str() {
-            "y" | "yes" => break delete_all()?,
-            "n" | "no" => {
-                println!("Operation canceled");
-                break;
+    let sessions = get_dead_server_sessions_with_age()?;
+
+    if sessions.is_empty() {
+        println!("There are no dead sessions");
+        return Ok(());
+    }
+
+    println!("Dead sessions:");
+    print_sessions(&sessions);
+
+    print!("Delete these {} dead sessions? [y/N]: ", sessions.len());
+    io::stdout().flush()?;
+
+    let mut confirmation = String::new();
+    io::stdin().read_line(&mut confirmation)?;
+
+    match confirmation.trim().to_lowercase().as_str() {
+        "y" | "yes" => {
+            for (server, _) in &sessions {
+                delete_server_session(server, false)?;
             }
-            _ => {}
-        };
+            println!("Deleted {} dead session(s)", sessions.len());
+        }
+        _ => println!("Operation canceled"),
     }
 
     Ok(())
 }
 
-fn session_write(
-    session: impl AsRef<OsStr>,
-    mode: &'static str,
-    chars: impl AsRef<OsStr>,
-) -> Result<()> {
-    let status = Command::new(BASE_COMMAND)
+/// Runs `zellij action` with some arguments in a session
+fn session_action(session: impl AsRef<OsStr>, args: &[&OsStr]) -> Result<()> {
+    let mut command = command()?;
+    command
         .arg("--session")
         .arg(session)
         .arg("action")
-        .arg(mode)
-        .arg(chars)
-        .spawn()?
-        .wait()?;
-
-    if !status.success() {
-        return Err(Error::CommandFailure {
-            code: status.code(),
-            stderr: None,
-        });
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = subprocess::run_with_timeout(&mut command, Error::from)?;
+
+    if !output.status.success() {
+        return Err(Error::command_failure(
+            &command,
+            output.status.code(),
+            Some(output.stderr),
+        ));
     }
 
     Ok(())
 }
 
+fn session_write(
+    session: impl AsRef<OsStr>,
+    mode: &'static str,
+    chars: impl AsRef<OsStr>,
+) -> Result<()> {
+    session_action(session, &[OsStr::new(mode), chars.as_ref()])
+}
+
+pub fn set_tab_title(session: impl AsRef<OsStr>, title: impl AsRef<OsStr>) -> Result<()> {
+    session_write(session, "rename-tab", title)
+}
+
 pub fn write_chars(session: impl AsRef<OsStr>, chars: impl AsRef<OsStr>) -> Result<()> {
     session_write(session, "write-chars", chars)
 }
 
+/// A key that can be pressed in a session's focused pane
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Enter,
+    #[allow(dead_code)] // for interactive console features
+    CtrlC,
+    #[allow(dead_code)] // for interactive console features
+    Up,
+    #[allow(dead_code)] // for interactive console features
+    Down,
+    #[allow(dead_code)] // for interactive console features
+    Tab,
+}
+
+impl Key {
+    /// The bytes a terminal sends for the key
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            Self::Enter => b"\r",
+            Self::CtrlC => b"\x03",
+            Self::Up => b"\x1b[A",
+            Self::Down => b"\x1b[B",
+            Self::Tab => b"\t",
+        }
+    }
+}
+
+/// The oldest zellij release whose `action write` takes each byte of a key as its own argument.
+/// Older releases get the key's characters through `action write-chars` instead.
+const WRITE_BYTES_VERSION: Version = (0, 32, 0);
+
+/// The `zellij action` arguments that press a key, for a zellij version. An unknown version is
+/// assumed to be recent.
+fn key_action(key: Key, version: Option<Version>) -> Vec<String> {
+    if version.is_some_and(|version| version < WRITE_BYTES_VERSION) {
+        let chars = String::from_utf8_lossy(key.bytes()).to_string();
+        return vec!["write-chars".to_string(), chars];
+    }
+
+    let mut args = vec!["write".to_string()];
+    args.extend(key.bytes().iter().map(|byte| byte.to_string()));
+    args
+}
+
+/// Presses a key in a session, in the way the installed zellij version expects
+pub fn send_key(session: impl AsRef<OsStr>, key: Key) -> Result<()> {
+    let args = key_action(key, version()?);
+    let args: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+    session_action(session, &args)
+}
+
 pub fn write_line(session: impl AsRef<OsStr>, chars: impl AsRef<OsStr>) -> Result<()> {
     write_chars(&session, chars)?;
-    session_write(&session, "write", "13")?; // 13 is for carriage return
-    Ok(())
+    send_key(&session, Key::Enter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses with the default namespace, which the configuration would otherwise be read for
+    fn parse(line: &str) -> Option<SessionInfo> {
+        SUFFIX.get_or_init(|| format!(".{DEFAULT_NAMESPACE}"));
+        parse_session_line(line)
+    }
+
+    #[test]
+    fn parses_captured_zellij_lines() {
+        // (line, server, alive, created)
+        let cases = [
+            (
+                "\x1b[32;1msurvival.mcserver\x1b[m [Created \x1b[35;1m2h 3m 4s\x1b[m ago] ",
+                Some("survival"),
+                true,
+                Some("2h 3m 4s"),
+            ),
+            (
+                "\x1b[32;1mcreative.mcserver\x1b[m [Created \x1b[35;1m12s\x1b[m ago] \
+                 (\x1b[31;1mEXITED\x1b[m - attach to resurrect)",
+                Some("creative"),
+                false,
+                Some("12s"),
+            ),
+            (
+                "\x1b[32;1mlobby.mcserver\x1b[m [Created \x1b[35;1m5days 1h\x1b[m ago] \
+                 (\x1b[32;1mcurrent\x1b[m)",
+                Some("lobby"),
+                true,
+                Some("5days 1h"),
+            ),
+            (
+                "survival.mcserver [Created 2h 3m 4s ago] (current)",
+                Some("survival"),
+                true,
+                Some("2h 3m 4s"),
+            ),
+            (
+                "creative.mcserver [Created 1m ago] (EXITED - attach to resurrect)",
+                Some("creative"),
+                false,
+                Some("1m"),
+            ),
+            // older zellij versions list only names
+            ("survival.mcserver (current)", Some("survival"), true, None),
+            ("hub.mcserver (RESURRECTABLE)", Some("hub"), false, None),
+            ("hub.mcserver  \t ", Some("hub"), true, None),
+            ("dev [Created 40s ago] ", None, true, Some("40s")),
+            (
+                "notes-EXITED.mcserver [Created 1s ago] ",
+                Some("notes-EXITED"),
+                true,
+                Some("1s"),
+            ),
+        ];
+
+        for (line, server, alive, created) in cases {
+            let session = parse(line).unwrap();
+            assert_eq!(session.server.as_deref(), server, "{line:?}");
+            assert_eq!(session.alive, alive, "{line:?}");
+            assert_eq!(session.created.as_deref(), created, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn parses_non_ascii_server_names() {
+        for server in ["世界", "サーバー", "서버", "🎮", "👨‍👩‍👧", "mc-🌍-01"]
+        {
+            let line = format!("{server}.mcserver [Created 3m 12s ago] ");
+            let session = parse(&line).unwrap();
+            assert_eq!(session.server.as_deref(), Some(server));
+            assert_eq!(session.created.as_deref(), Some("3m 12s"));
+            assert!(session.alive);
+        }
+    }
+
+    #[test]
+    fn parses_colored_non_ascii_lines() {
+        let line = "\x1b[32;1m世界🎮.mcserver\x1b[m [Created \x1b[35;1m1h\x1b[m ago] \
+                    (\x1b[31;1mEXITED\x1b[m - attach to resurrect)";
+        let session = parse(line).unwrap();
+        assert_eq!(session.name, "世界🎮.mcserver");
+        assert_eq!(session.server.as_deref(), Some("世界🎮"));
+        assert!(!session.alive);
+    }
+
+    #[test]
+    fn survives_malformed_non_ascii_lines() {
+        for line in [
+            "世界",
+            "🎮.mcserver [Created",
+            "世界.mcserver [Created 世界 ago",
+            "[Created 1s ago] 🎮",
+            "\x1b[32;1m世",
+            "\x1b",
+            "　世界　",
+        ] {
+            let _ = parse(line);
+        }
+        assert_eq!(parse("  \x1b[0m  "), None);
+    }
+
+    #[test]
+    fn strips_ansi_around_non_ascii() {
+        assert_eq!(strip_ansi("\x1b[1m世\x1b[0m界\x1b[33m🎮"), "世界🎮");
+    }
+
+    #[test]
+    fn namespaces_replace_non_ascii() {
+        assert_eq!(normalize_namespace("世界"), "--");
+        assert_eq!(normalize_namespace("prod 🎮"), "prod--");
+        assert_eq!(
+            normalize_namespace(&"世".repeat(40)).len(),
+            MAX_NAMESPACE_LEN
+        );
+    }
 }
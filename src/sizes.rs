@@ -0,0 +1,133 @@
+//! Shows what takes up a server's disk space, broken down by directory. Worlds break down further
+//! by dimension, since the nether and the end keep their regions in `DIM-1` and `DIM1`.
+
+use crate::{error::Result, humanize, server::get_server_dir_required, space};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+#[derive(Serialize)]
+struct Entry {
+    path: String,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    server: &'a str,
+    total: u64,
+    complete: bool,
+    entries: Vec<Entry>,
+}
+
+/// What a directory is, for the ones whose name doesn't say
+fn describe(path: &Path) -> Option<&'static str> {
+    match path.file_name()?.to_str()? {
+        "DIM-1" => Some("the nether"),
+        "DIM1" => Some("the end"),
+        _ => None,
+    }
+}
+
+/// Orders paths as a tree, with the largest entries of each directory first
+fn tree_order(by_path: &BTreeMap<PathBuf, u64>) -> Vec<(&Path, u64)> {
+    let mut children: HashMap<&Path, Vec<(&Path, u64)>> = HashMap::new();
+    for (path, &size) in by_path {
+        children
+            .entry(path.parent().unwrap_or(Path::new("")))
+            .or_default()
+            .push((path, size));
+    }
+    for entries in children.values_mut() {
+        entries.sort_by(|(a_path, a_size), (b_path, b_size)| {
+            b_size.cmp(a_size).then(a_path.cmp(b_path))
+        });
+    }
+
+    let mut ordered = Vec::with_capacity(by_path.len());
+    let mut stack: Vec<(&Path, u64)> = children
+        .get(Path::new(""))
+        .map(|entries| entries.iter().rev().copied().collect())
+        .unwrap_or_default();
+
+    while let Some((path, size)) = stack.pop() {
+        ordered.push((path, size));
+        if let Some(entries) = children.get(path) {
+            stack.extend(entries.iter().rev().copied());
+        }
+    }
+
+    ordered
+}
+
+/// Prints how much space each path in a server takes up, down to `depth` components. Ctrl-C stops
+/// the walk and prints what was counted so far.
+pub fn print(server: &str, depth: usize, json: bool) -> Result<()> {
+    let server_dir = get_server_dir_required(server)?;
+
+    CANCELLED.store(false, Ordering::SeqCst);
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+
+    let usage = space::usage(&server_dir, depth, &CANCELLED);
+
+    unsafe { libc::signal(libc::SIGINT, previous) };
+    let usage = usage?;
+
+    let ordered = tree_order(&usage.by_path);
+
+    if json {
+        let report = Report {
+            server,
+            total: usage.total,
+            complete: usage.complete,
+            entries: ordered
+                .iter()
+                .map(|(path, size)| Entry {
+                    path: path.to_string_lossy().to_string(),
+                    bytes: *size,
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    for (path, size) in ordered {
+        let percent = if usage.total == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / usage.total as f64
+        };
+        let indent = "  ".repeat(path.components().count() - 1);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let description = describe(path)
+            .map(|description| format!(" ({description})"))
+            .unwrap_or_default();
+
+        println!(
+            "{:>10} {percent:>5.1}%  {indent}{name}{description}",
+            humanize::bytes(size)
+        );
+    }
+    println!(
+        "{:>10} {:>5.1}%  total",
+        humanize::bytes(usage.total),
+        100.0
+    );
+
+    if !usage.complete {
+        eprintln!("\x1b[33;1mWarning:\x1b[0m interrupted, so the sizes are partial");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Free space checks before operations that write a lot of data, so that running out of disk
+//! doesn't leave half copied servers behind.
+
+use crate::{
+    error::{Error, Result},
+    ignore::IgnoreRules,
+    verbose,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::CString,
+    fs, io,
+    mem::MaybeUninit,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+/// Extra room on top of the estimate, for metadata and anything else written alongside
+const SAFETY_MARGIN: u64 = 64 * 1024 * 1024;
+
+static IGNORED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ignored(ignored: bool) {
+    IGNORED.store(ignored, Ordering::Relaxed);
+}
+
+/// Returns the space available to unprivileged users on the filesystem holding a path, which
+/// doesn't need to exist yet
+pub fn available(path: impl AsRef<Path>) -> Result<u64> {
+    let path = path
+        .as_ref()
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("/"));
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: c_path is a valid nul terminated string and stat is only read after statvfs
+    // succeeds and has initialized it
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        stat.assume_init()
+    };
+
+    #[allow(clippy::unnecessary_cast)] // the field types differ between platforms
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Adds up the size of every file in a directory, without following symlinks or counting paths
+/// excluded by its `.mcserverignore`
+pub fn dir_size(dir: impl AsRef<Path>) -> Result<u64> {
+    Ok(usage(dir, 0, &AtomicBool::new(false))?.total)
+}
+
+/// How the space under a directory is used
+#[derive(Debug, Default)]
+pub struct Usage {
+    pub total: u64,
+    /// The size under each path relative to the directory, down to the depth asked for
+    pub by_path: BTreeMap<PathBuf, u64>,
+    /// Whether every file was counted, which it isn't when the walk was cancelled
+    pub complete: bool,
+}
+
+/// Walks a directory, skipping what its `.mcserverignore` excludes
+struct Walker<'a> {
+    rules: &'a IgnoreRules,
+    depth: usize,
+    cancelled: &'a AtomicBool,
+    /// The files with several hard links that were already counted, by device and inode
+    linked: &'a Mutex<HashSet<(u64, u64)>>,
+}
+
+impl Walker<'_> {
+    fn walk(&self, dir: &Path, relative: &Path, usage: &mut Usage) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let relative = relative.join(entry.file_name());
+
+            if self.rules.excludes(&relative, file_type.is_dir()) {
+                continue;
+            }
+
+            if file_type.is_dir() {
+                self.walk(&entry.path(), &relative, usage)?;
+            } else if file_type.is_file() {
+                let metadata = entry.metadata()?;
+                // incremental backups hard link unchanged files, which only take up space once
+                if metadata.nlink() > 1
+                    && !self
+                        .linked
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .insert((metadata.dev(), metadata.ino()))
+                {
+                    continue;
+                }
+
+                self.count(&relative, metadata.len(), usage);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a file's size to the total and to each of its parents down to the depth
+    fn count(&self, relative: &Path, size: u64, usage: &mut Usage) {
+        usage.total += size;
+
+        let mut prefix = PathBuf::new();
+        for component in relative.components().take(self.depth) {
+            prefix.push(component);
+            *usage.by_path.entry(prefix.clone()).or_default() += size;
+        }
+    }
+}
+
+/// Adds up the size of every file in a directory like [`dir_size`], also keeping the size under
+/// each path down to `depth` components. Hard linked files are only counted once. Each top level
+/// directory is walked on its own thread, and the walk stops early with what was counted so far
+/// once `cancelled` is set.
+pub fn usage(dir: impl AsRef<Path>, depth: usize, cancelled: &AtomicBool) -> Result<Usage> {
+    let dir = dir.as_ref();
+    let rules = IgnoreRules::load(dir)?;
+    let linked = Mutex::new(HashSet::new());
+    let walker = Walker {
+        rules: &rules,
+        depth,
+        cancelled,
+        linked: &linked,
+    };
+
+    let mut usage = Usage::default();
+    let mut subdirs = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let relative = PathBuf::from(entry.file_name());
+
+        if rules.excludes(&relative, file_type.is_dir()) {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            subdirs.push((entry.path(), relative));
+        } else if file_type.is_file() {
+            walker.count(&relative, entry.metadata()?.len(), &mut usage);
+        }
+    }
+
+    let results: Vec<io::Result<Usage>> = thread::scope(|scope| {
+        let handles: Vec<_> = subdirs
+            .iter()
+            .map(|(path, relative)| {
+                let walker = &walker;
+                scope.spawn(move || {
+                    let mut usage = Usage::default();
+                    walker.walk(path, relative, &mut usage).map(|()| usage)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    for result in results {
+        let subdir = result?;
+        usage.total += subdir.total;
+        usage.by_path.extend(subdir.by_path);
+    }
+
+    usage.complete = !cancelled.load(Ordering::Relaxed);
+    Ok(usage)
+}
+
+/// Fails if writing `needed` bytes under a path would leave less than the safety margin free,
+/// unless checks were turned off with `--ignore-space`
+pub fn ensure(path: impl AsRef<Path>, needed: u64) -> Result<()> {
+    if IGNORED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let path = path.as_ref();
+    let available = available(path)?;
+    let needed = needed + SAFETY_MARGIN;
+    verbose!(
+        "{needed} bytes needed in {}, {available} available",
+        path.display()
+    );
+
+    if needed > available {
+        return Err(Error::InsufficientSpace {
+            needed,
+            available,
+            path: path.to_path_buf(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that there is room to copy a directory to a destination
+pub fn ensure_for_copy(source: impl AsRef<Path>, destination: impl AsRef<Path>) -> Result<()> {
+    if IGNORED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    ensure(destination, dir_size(source)?)
+}
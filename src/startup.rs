@@ -0,0 +1,245 @@
+//! Whether a server came up after it was deployed. The outcome of the last start is kept in the
+//! metadata directory, along with the lines of the log that led up to a failure, so that `list`
+//! and `info` can still tell why a server isn't running long after it happened.
+
+use crate::{
+    error::{Error, Result},
+    humanize, server,
+    session::get_server_sessions_to_living,
+    timeutil::unix_now,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+const LAST_START_FILE: &str = "last_start.toml";
+const EXCERPT_FILE: &str = "last_start.log";
+
+/// How many lines of the log are kept up to the one that gave the failure away
+pub const EXCERPT_LINES: usize = 20;
+
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What Java servers and proxies log once they have finished starting, as in
+/// `Done (3.021s)! For help, type "help"`
+const READY_MARKER: &str = "Done (";
+
+/// Lines that mean a server won't come up, with the reason they are reported as
+const FATAL_MARKERS: &[(&str, &str)] = &[
+    ("FAILED TO BIND TO PORT", "port in use"),
+    ("Address already in use", "port in use"),
+    ("java.lang.OutOfMemoryError", "OutOfMemoryError"),
+    ("UnsupportedClassVersionError", "Java too old for the jar"),
+    ("You need to agree to the EULA", "EULA not accepted"),
+    (
+        "Failed to start the minecraft server",
+        "crashed while starting",
+    ),
+    (
+        "Encountered an unexpected exception",
+        "crashed while starting",
+    ),
+];
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ready,
+    Failed,
+}
+
+/// How a server's last start went, recorded by `deploy --wait-ready`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastStart {
+    pub outcome: Outcome,
+    pub at: u64,
+    pub reason: Option<String>,
+}
+
+impl LastStart {
+    pub fn failed(&self) -> bool {
+        self.outcome == Outcome::Failed
+    }
+}
+
+/// The reason a log line gives for a server failing to start, if it gives one
+pub fn fatal_reason(line: &str) -> Option<&'static str> {
+    FATAL_MARKERS
+        .iter()
+        .find(|(marker, _)| line.contains(marker))
+        .map(|(_, reason)| *reason)
+}
+
+/// The last `count` lines up to and including the one at `end`, which is usually where a fatal
+/// marker was found
+pub fn excerpt<'a>(lines: &'a [&'a str], end: usize, count: usize) -> &'a [&'a str] {
+    let end = (end + 1).min(lines.len());
+    &lines[end.saturating_sub(count)..end]
+}
+
+fn excerpt_path(metadata_dir: &Path) -> PathBuf {
+    metadata_dir.join(EXCERPT_FILE)
+}
+
+fn write(metadata_dir: &Path, last_start: &LastStart) -> Result<()> {
+    fs::create_dir_all(metadata_dir)?;
+    fs::write(
+        metadata_dir.join(LAST_START_FILE),
+        toml::to_string(last_start)?,
+    )?;
+    Ok(())
+}
+
+/// Records that a server started, which clears any failure recorded before
+pub fn record_ready(server: &str) -> Result<()> {
+    let metadata_dir = server::get_metadata_dir(server)?;
+    let excerpt = excerpt_path(&metadata_dir);
+
+    if excerpt.exists() {
+        fs::remove_file(excerpt)?;
+    }
+
+    write(
+        &metadata_dir,
+        &LastStart {
+            outcome: Outcome::Ready,
+            at: unix_now()?,
+            reason: None,
+        },
+    )
+}
+
+/// Records that a server failed to start, along with the log lines that show why
+pub fn record_failure(server: &str, reason: &str, excerpt: &[&str]) -> Result<()> {
+    let metadata_dir = server::get_metadata_dir(server)?;
+
+    write(
+        &metadata_dir,
+        &LastStart {
+            outcome: Outcome::Failed,
+            at: unix_now()?,
+            reason: Some(reason.to_string()),
+        },
+    )?;
+
+    let path = excerpt_path(&metadata_dir);
+    if excerpt.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    } else {
+        fs::write(path, excerpt.join("\n") + "\n")?;
+    }
+
+    Ok(())
+}
+
+/// Reads how a server's last start went, which is `None` if it was never waited on
+pub fn read(server: &str) -> Result<Option<LastStart>> {
+    let path = server::get_metadata_dir(server)?.join(LAST_START_FILE);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// The saved log excerpt of a server's last failed start, if there is one
+pub fn excerpt_file(server: &str) -> Result<Option<PathBuf>> {
+    let path = excerpt_path(&server::get_metadata_dir(server)?);
+    Ok(path.exists().then_some(path))
+}
+
+enum Readiness {
+    Ready,
+    Failed(&'static str, Vec<String>),
+    Starting,
+}
+
+/// Looks through a log for the line saying the server is ready or the first one saying it won't be
+fn check_log(text: &str) -> Readiness {
+    let lines: Vec<&str> = text.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(reason) = fatal_reason(line) {
+            let excerpt = excerpt(&lines, index, EXCERPT_LINES);
+            return Readiness::Failed(
+                reason,
+                excerpt.iter().map(|line| line.to_string()).collect(),
+            );
+        }
+
+        if line.contains(READY_MARKER) {
+            return Readiness::Ready;
+        }
+    }
+
+    Readiness::Starting
+}
+
+/// Reads a server's log if it was started after `since`, as the server only replaces the log of
+/// its last run once it gets going
+fn read_log_since(path: &Path, since: SystemTime) -> Option<String> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    if modified < since {
+        return None;
+    }
+
+    fs::read(path)
+        .ok()
+        .map(|contents| String::from_utf8_lossy(&contents).into_owned())
+}
+
+/// Waits for a server deployed at `since` to finish starting, recording how it went. The server
+/// has failed if its log shows a fatal error, if its session goes away first or if it isn't ready
+/// within `timeout`.
+pub fn wait_ready(server: &str, since: SystemTime, timeout: Duration) -> Result<()> {
+    let log = server::get_server_dir_required(server)?
+        .join("logs")
+        .join("latest.log");
+    let start = Instant::now();
+
+    let failure = loop {
+        // checked before the log, so that a server that logged why it exited isn't missed
+        let alive = get_server_sessions_to_living()?.get(server) == Some(&true);
+        let readiness = read_log_since(&log, since)
+            .map(|text| check_log(&text))
+            .unwrap_or(Readiness::Starting);
+
+        match readiness {
+            Readiness::Ready => {
+                record_ready(server)?;
+                return Ok(());
+            }
+            Readiness::Failed(reason, excerpt) => break (reason.to_string(), excerpt),
+            Readiness::Starting => {}
+        }
+
+        if !alive {
+            break ("exited before it was ready".to_string(), vec![]);
+        }
+
+        if start.elapsed() >= timeout {
+            let reason = format!("not ready after {}", humanize::duration(timeout.as_secs()));
+            break (reason, vec![]);
+        }
+
+        thread::sleep(READY_POLL_INTERVAL);
+    };
+
+    let (reason, excerpt) = failure;
+    let excerpt: Vec<&str> = excerpt.iter().map(String::as_str).collect();
+    record_failure(server, &reason, &excerpt)?;
+
+    Err(Error::StartFailed {
+        server: server.to_string(),
+        reason,
+    })
+}
@@ -0,0 +1,291 @@
+//! A summary of the whole fleet on one screen. Every server is looked at independently, so a
+//! server that can't be read only leaves gaps in its own numbers.
+
+use crate::{
+    config,
+    error::Result,
+    humanize,
+    platforms::Platform,
+    server::{self, get_server_dir_required, rcon_output, read_last_used_raw},
+    session, space, timeutil,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// The most servers looked at together, which bounds how many directory walks run at once
+const MAX_WORKERS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SessionState {
+    Running,
+    Dead,
+    Stopped,
+}
+
+#[derive(Serialize)]
+struct ServerStats {
+    server: String,
+    platform: Option<Platform>,
+    session: SessionState,
+    disk_bytes: Option<u64>,
+    /// A unix timestamp, or null when the server was never used or it isn't recorded
+    last_used: Option<u64>,
+    players_online: Option<u32>,
+    errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct LeastRecentlyUsed {
+    server: String,
+    last_used: u64,
+}
+
+#[derive(Serialize)]
+struct FleetStats {
+    servers: usize,
+    platforms: BTreeMap<String, usize>,
+    running: usize,
+    dead: usize,
+    stopped: usize,
+    disk_bytes: u64,
+    players_online: u32,
+    least_recently_used: Option<LeastRecentlyUsed>,
+    per_server: Vec<ServerStats>,
+}
+
+/// Parses the player count out of the reply to `list`, which is either `There are 3 of a max of
+/// 20 players online: …` or, on older versions, `There are 3/20 players online: …`
+fn parse_player_count(reply: &str) -> Option<u32> {
+    let count = reply.split("There are ").nth(1)?;
+    let digits = count
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(count.len());
+    count[..digits].parse().ok()
+}
+
+/// Keeps a stat that couldn't be read as a gap, noting why
+fn or_note<T>(errors: &mut Vec<String>, result: Result<Option<T>>) -> Option<T> {
+    result.unwrap_or_else(|err| {
+        errors.push(err.to_string());
+        None
+    })
+}
+
+fn server_stats(
+    server: &str,
+    sessions: &HashMap<String, bool>,
+    rcon_servers: &HashSet<String>,
+) -> ServerStats {
+    let mut errors = vec![];
+
+    let session = match sessions.get(server) {
+        Some(true) => SessionState::Running,
+        Some(false) => SessionState::Dead,
+        None => SessionState::Stopped,
+    };
+
+    let platform = or_note(
+        &mut errors,
+        get_server_dir_required(server).and_then(server::get_platform),
+    );
+    let disk_bytes = or_note(
+        &mut errors,
+        get_server_dir_required(server)
+            .and_then(space::dir_size)
+            .map(Some),
+    );
+    let last_used =
+        or_note(&mut errors, read_last_used_raw(server)).filter(|&timestamp| timestamp != u64::MAX);
+
+    let players_online = if session == SessionState::Running
+        && platform != Some(Platform::Bedrock)
+        && rcon_servers.contains(server)
+    {
+        or_note(
+            &mut errors,
+            rcon_output(server, "list").map(|reply| parse_player_count(&reply)),
+        )
+    } else {
+        None
+    };
+
+    ServerStats {
+        server: server.to_string(),
+        platform,
+        session,
+        disk_bytes,
+        last_used,
+        players_online,
+        errors,
+    }
+}
+
+/// Collects the stats of every server on a small pool of threads, keeping the servers' order
+fn collect(servers: &[String], sessions: &HashMap<String, bool>) -> Result<Vec<ServerStats>> {
+    let rcon_servers: HashSet<String> = config::snapshot()?
+        .rcon
+        .map(|rcon| rcon.into_keys().collect())
+        .unwrap_or_default();
+
+    let workers = thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(MAX_WORKERS)
+        .min(servers.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+
+    let mut stats: Vec<(usize, ServerStats)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut collected = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(server) = servers.get(index) else {
+                            break collected;
+                        };
+                        collected.push((index, server_stats(server, sessions, &rcon_servers)));
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("collecting stats doesn't panic"))
+            .collect()
+    });
+
+    stats.sort_by_key(|(index, _)| *index);
+    Ok(stats.into_iter().map(|(_, stats)| stats).collect())
+}
+
+fn summarize(per_server: Vec<ServerStats>) -> FleetStats {
+    let mut platforms = BTreeMap::new();
+    for stats in &per_server {
+        let platform = stats
+            .platform
+            .map_or_else(|| "unknown".to_string(), |platform| platform.to_string());
+        *platforms.entry(platform).or_default() += 1;
+    }
+
+    let count = |state| {
+        per_server
+            .iter()
+            .filter(|stats| stats.session == state)
+            .count()
+    };
+
+    let least_recently_used = per_server
+        .iter()
+        .filter_map(|stats| Some((stats, stats.last_used?)))
+        .min_by_key(|(_, last_used)| *last_used)
+        .map(|(stats, last_used)| LeastRecentlyUsed {
+            server: stats.server.clone(),
+            last_used,
+        });
+
+    FleetStats {
+        servers: per_server.len(),
+        platforms,
+        running: count(SessionState::Running),
+        dead: count(SessionState::Dead),
+        stopped: count(SessionState::Stopped),
+        disk_bytes: per_server.iter().filter_map(|stats| stats.disk_bytes).sum(),
+        players_online: per_server
+            .iter()
+            .filter_map(|stats| stats.players_online)
+            .sum(),
+        least_recently_used,
+        per_server,
+    }
+}
+
+fn print_summary(stats: &FleetStats) -> Result<()> {
+    let platforms = stats
+        .platforms
+        .iter()
+        .map(|(platform, count)| format!("{platform} {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("Servers:        {} ({platforms})", stats.servers);
+    println!(
+        "Sessions:       {} running, {} dead, {} stopped",
+        stats.running, stats.dead, stats.stopped
+    );
+
+    println!("Disk used:      {}", humanize::bytes(stats.disk_bytes));
+    if let Some(largest) = stats
+        .per_server
+        .iter()
+        .filter_map(|server| Some((server, server.disk_bytes?)))
+        .max_by_key(|(_, bytes)| *bytes)
+    {
+        println!(
+            "Largest:        {} ({})",
+            largest.0.server,
+            humanize::bytes(largest.1)
+        );
+    }
+
+    let reporting = stats
+        .per_server
+        .iter()
+        .filter(|server| server.players_online.is_some())
+        .count();
+    println!(
+        "Players online: {} across {reporting} reporting server(s)",
+        stats.players_online
+    );
+
+    if let Some(least_recent) = &stats.least_recently_used {
+        println!(
+            "Least recent:   {}, last used {} ago",
+            least_recent.server,
+            timeutil::since(least_recent.last_used)?
+        );
+    }
+
+    let incomplete: Vec<_> = stats
+        .per_server
+        .iter()
+        .filter(|server| !server.errors.is_empty())
+        .map(|server| server.server.as_str())
+        .collect();
+    if !incomplete.is_empty() {
+        println!(
+            "\x1b[33;1mWarning:\x1b[0m some stats of {} could not be read (see --json)",
+            incomplete.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints an overview of every server, or the same as JSON
+pub fn print(json: bool) -> Result<()> {
+    let mut servers = vec![];
+    server::for_each(|server| {
+        if !server::is_template(&server) && get_server_dir_required(&server).is_ok() {
+            servers.push(server);
+        }
+    })?;
+    servers.sort();
+
+    // a single listing of the sessions serves every server
+    let sessions = session::get_server_sessions_to_living()?;
+    let stats = summarize(collect(&servers, &sessions)?);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_summary(&stats)?;
+    }
+
+    Ok(())
+}
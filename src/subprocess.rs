@@ -0,0 +1,95 @@
+//! Runs short lived external programs under a time limit, so that a hung zellij or mcrcon can't
+//! block the CLI forever. Programs meant to run for as long as the user wants, such as attached
+//! sessions, editors and BuildTools, are spawned directly instead.
+
+use crate::{
+    config,
+    error::{Error, Result},
+};
+use std::{
+    io::{self, Read},
+    process::{Command, Output},
+    sync::OnceLock,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How often a running program is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+static TIMEOUT_FLAG: OnceLock<u64> = OnceLock::new();
+
+/// Sets the limit given on the command line, which takes precedence over the configuration
+pub fn set_timeout(secs: Option<u64>) {
+    if let Some(secs) = secs {
+        let _ = TIMEOUT_FLAG.set(secs);
+    }
+}
+
+/// The time limit in seconds, if there is one
+pub fn timeout() -> Result<Option<u64>> {
+    match TIMEOUT_FLAG.get() {
+        Some(&secs) => Ok(Some(secs)),
+        None => Ok(config::snapshot()?.command_timeout),
+    }
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Option<JoinHandle<Vec<u8>>> {
+    pipe.map(|mut pipe| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = pipe.read_to_end(&mut buf);
+            buf
+        })
+    })
+}
+
+fn collect(reader: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    reader
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default()
+}
+
+/// Runs a command to completion like [`Command::output`], killing it once the time limit passes.
+/// Only the streams the command pipes are captured, the rest are inherited as with
+/// [`Command::spawn`]. Errors from spawning it are converted with `on_spawn_error`.
+pub fn run_with_timeout(
+    command: &mut Command,
+    on_spawn_error: impl FnOnce(io::Error) -> Error,
+) -> Result<Output> {
+    let timeout = timeout()?;
+    let mut child = command.spawn().map_err(on_spawn_error)?;
+
+    // the pipes are drained while the program runs, so that it can't block on a full pipe
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(secs) => {
+            let deadline = Instant::now() + Duration::from_secs(secs);
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Error::CommandTimeout {
+                        program: command.get_program().to_string_lossy().to_string(),
+                        secs,
+                    });
+                }
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
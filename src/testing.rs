@@ -0,0 +1,100 @@
+//! A sandbox for unit tests that read the configuration, the servers directory or an API: a
+//! temporary configuration, cache and servers directory, and a fake API answering every request
+//! through the API override, set up once per test process in place of the real ones.
+
+use crate::environment::{self, Environment};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    thread,
+};
+use url::Url;
+
+/// Responses of the fake API by path, which starts with the original host
+static ROUTES: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+fn serve(listener: TcpListener) {
+    for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = String::new();
+        reader.read_line(&mut request).unwrap();
+
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+
+        let path = request.split(' ').nth(1).unwrap_or_default();
+        let body = ROUTES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(|routes| routes.get(path).cloned());
+
+        let response = match body {
+            Some(body) => format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            ),
+            None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        };
+        let _ = stream.write_all(response.as_bytes());
+    }
+}
+
+/// Sets the sandbox up the first time it is called, and adds `routes`, pairs of a path and the
+/// body it is answered with, to the fake API, where any other path gets a 404. Returns the
+/// servers directory, which every test in the process shares.
+pub fn sandbox(routes: &[(&str, &str)]) -> &'static Path {
+    static SERVERS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+    ROUTES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_default()
+        .extend(
+            routes
+                .iter()
+                .map(|(path, body)| (path.to_string(), body.to_string())),
+        );
+
+    SERVERS_DIR.get_or_init(|| {
+        // kept for the rest of the process, since any test may still be using it
+        let dir = tempfile::tempdir().unwrap().keep();
+        let servers_dir = dir.join("Servers");
+        fs::create_dir_all(&servers_dir).unwrap();
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(
+            dir.join("config/config.toml"),
+            format!(
+                "config_version = 1\ndefault_java_args = \"\"\nnogui = true\n\
+                 servers_directory = {:?}\n",
+                servers_dir
+            ),
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        thread::spawn(move || serve(listener));
+
+        let environment = environment::init(Environment {
+            config_dir: Some(dir.join("config")),
+            cache_dir: Some(dir.join("cache")),
+            api_url: Some(api_url.clone()),
+        });
+        assert_eq!(
+            environment.api_url.as_ref(),
+            Some(&api_url),
+            "the environment was read before the sandbox was set up"
+        );
+
+        servers_dir
+    })
+}
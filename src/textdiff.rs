@@ -0,0 +1,183 @@
+//! A small line based diff, for showing what is about to change in a file the user may have edited
+//! by hand before it is overwritten.
+
+use std::{
+    fmt::Write as _,
+    io::{self, IsTerminal},
+};
+
+/// How many changed lines are shown before the rest are summarized
+pub const DEFAULT_MAX_LINES: usize = 40;
+
+/// Beyond this many line pairs, files are treated as entirely replaced instead of being compared
+/// line by line, which keeps the comparison cheap for huge files
+const MAX_COMPARISONS: usize = 4_000_000;
+
+#[derive(Debug, PartialEq)]
+enum Change<'a> {
+    Removed(usize, &'a str),
+    Added(usize, &'a str),
+}
+
+/// Lists the removed and added lines between two texts, with the line numbers they have in the
+/// old and new text respectively
+fn changes<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    if old.len().saturating_mul(new.len()) > MAX_COMPARISONS {
+        return old
+            .iter()
+            .enumerate()
+            .map(|(i, line)| Change::Removed(i + 1, line))
+            .chain(
+                new.iter()
+                    .enumerate()
+                    .map(|(i, line)| Change::Added(i + 1, line)),
+            )
+            .collect();
+    }
+
+    // lengths of the longest common subsequences of every pair of suffixes
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push(Change::Removed(i + 1, old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(j + 1, new[j]));
+            j += 1;
+        }
+    }
+
+    changes
+}
+
+/// Renders the changed lines between two texts with aligned line numbers, colored when `color`
+/// is set, showing at most `max_lines` of them. Returns an empty string when nothing changed.
+pub fn render(old: &str, new: &str, max_lines: usize, color: bool) -> String {
+    let changes = changes(old, new);
+    let width = changes
+        .iter()
+        .map(|change| match change {
+            Change::Removed(line, _) | Change::Added(line, _) => line.to_string().len(),
+        })
+        .max()
+        .unwrap_or_default();
+
+    let (red, green, reset) = if color {
+        ("\x1b[31m", "\x1b[32m", "\x1b[0m")
+    } else {
+        ("", "", "")
+    };
+
+    let mut output = String::new();
+    for change in changes.iter().take(max_lines) {
+        let _ = match change {
+            Change::Removed(line, text) => writeln!(output, "{red}{line:>width$} - {text}{reset}"),
+            Change::Added(line, text) => writeln!(output, "{green}{line:>width$} + {text}{reset}"),
+        };
+    }
+
+    if changes.len() > max_lines {
+        let _ = writeln!(output, "… {} more lines", changes.len() - max_lines);
+    }
+
+    output
+}
+
+/// Prints what will change in a file before it is overwritten, if anything
+pub fn print(label: &str, old: &str, new: &str) {
+    let diff = render(old, new, DEFAULT_MAX_LINES, io::stdout().is_terminal());
+    if !diff.is_empty() {
+        println!("Changes to {label}:");
+        print!("{diff}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_texts_have_no_changes() {
+        let text = "motd=hello\npvp=true\n";
+        assert!(changes(text, text).is_empty());
+        assert_eq!(render(text, text, DEFAULT_MAX_LINES, false), "");
+    }
+
+    #[test]
+    fn unchanged_lines_are_left_out() {
+        assert_eq!(
+            changes("a\nb\nc\n", "a\nx\nc\nd\n"),
+            [
+                Change::Removed(2, "b"),
+                Change::Added(2, "x"),
+                Change::Added(4, "d")
+            ]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_lines_keep_their_own_line_numbers() {
+        assert_eq!(
+            changes("a\nb\nc\nd\n", "b\nc\nd\ne\n"),
+            [Change::Removed(1, "a"), Change::Added(4, "e")]
+        );
+        assert_eq!(changes("", "a\n"), [Change::Added(1, "a")]);
+        assert_eq!(changes("a\n", ""), [Change::Removed(1, "a")]);
+    }
+
+    #[test]
+    fn line_numbers_are_aligned() {
+        let old: String = (1..=10).map(|i| format!("{i}\n")).collect();
+        let new = old.replace("1\n", "one\n").replace("10\n", "ten\n");
+
+        assert_eq!(
+            render(&old, &new, DEFAULT_MAX_LINES, false),
+            " 1 - 1\n 1 + one\n10 - 10\n10 + ten\n"
+        );
+    }
+
+    #[test]
+    fn color_wraps_each_line() {
+        assert_eq!(
+            render("a\n", "b\n", DEFAULT_MAX_LINES, true),
+            "\x1b[31m1 - a\x1b[0m\n\x1b[32m1 + b\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn long_diffs_are_cut_off() {
+        let new: String = (1..=5).map(|i| format!("{i}\n")).collect();
+
+        assert_eq!(render("", &new, 2, false), "1 + 1\n2 + 2\n… 3 more lines\n");
+        assert!(!render("", &new, 5, false).contains("more lines"));
+    }
+
+    #[test]
+    fn huge_files_are_treated_as_replaced() {
+        // even identical lines are listed once the comparison would be too expensive
+        let text = "same\n".repeat(2001);
+        let changes = changes(&text, &text);
+
+        assert_eq!(changes.len(), 2 * 2001);
+        assert_eq!(changes[0], Change::Removed(1, "same"));
+        assert_eq!(changes[2001], Change::Added(1, "same"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Parsing and arithmetic for durations and times of day, shared by everything that schedules or
+//! reports on time.
+
+use crate::{
+    config,
+    error::{Error, Result},
+    humanize,
+};
+use jiff::{Zoned, civil, tz::TimeZone};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::TimeWentBackwards)?
+        .as_secs())
+}
+
+/// Formats how long ago a unix timestamp was, e.g. `2h 3m 4s`
+pub fn since(timestamp: u64) -> Result<String> {
+    Ok(humanize::duration(unix_now()?.saturating_sub(timestamp)))
+}
+
+/// Parses durations such as `90s`, `15m` or `1d12h`, where the units are `w`, `d`, `h`, `m` and
+/// `s`. A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let invalid = || Error::InvalidDuration(input.to_string());
+    let input = input.trim();
+
+    if let Ok(secs) = input.parse() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'w' => 7 * 24 * 60 * 60,
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        secs = value
+            .checked_mul(unit)
+            .and_then(|value| secs.checked_add(value))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || input.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(secs))
+}
+
+/// Parses a 24 hour time of day such as `04:30`
+pub fn parse_time_of_day(input: &str) -> Result<civil::Time> {
+    let invalid = || Error::InvalidTimeOfDay(input.to_string());
+
+    let (hour, minute) = input.trim().split_once(':').ok_or_else(invalid)?;
+    let hour: i8 = hour.parse().map_err(|_| invalid())?;
+    let minute: i8 = minute.parse().map_err(|_| invalid())?;
+
+    civil::Time::new(hour, minute, 0, 0).map_err(|_| invalid())
+}
+
+/// Returns the time zone configured with `time_zone`, or the system's own
+pub fn time_zone() -> Result<TimeZone> {
+    let name = config::snapshot()?.time_zone;
+
+    match name {
+        Some(name) => TimeZone::get(&name).map_err(|_| Error::UnknownTimeZone(name)),
+        None => Ok(TimeZone::system()),
+    }
+}
+
+/// Returns the next moment a time of day occurs after now. On nights where the clocks go back and
+/// the time happens twice, the first occurrence is used, and on nights where the clocks go
+/// forward past it, the time is shifted forward by the length of the gap.
+pub fn next_occurrence(time: civil::Time) -> Result<Zoned> {
+    next_occurrence_after(time, &Zoned::now().with_time_zone(time_zone()?))
+}
+
+/// Returns the next moment a time of day occurs after `now`, in `now`'s time zone
+fn next_occurrence_after(time: civil::Time, now: &Zoned) -> Result<Zoned> {
+    let time_zone = now.time_zone();
+    let mut date = now.date();
+
+    loop {
+        let candidate = date
+            .to_datetime(time)
+            .to_zoned(time_zone.clone())
+            .map_err(|err| Error::InvalidTimeOfDay(err.to_string()))?;
+
+        if candidate > *now {
+            return Ok(candidate);
+        }
+
+        date = date
+            .tomorrow()
+            .map_err(|err| Error::InvalidTimeOfDay(err.to_string()))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn london(datetime: &str) -> Zoned {
+        datetime
+            .parse::<civil::DateTime>()
+            .unwrap()
+            .in_tz("Europe/London")
+            .unwrap()
+    }
+
+    fn next(time: &str, now: &str) -> Zoned {
+        next_occurrence_after(parse_time_of_day(time).unwrap(), &london(now)).unwrap()
+    }
+
+    #[test]
+    fn durations_parse_units_and_bare_seconds() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(
+            parse_duration("1d12h").unwrap(),
+            Duration::from_secs(36 * 60 * 60)
+        );
+        assert_eq!(
+            parse_duration(" 1w ").unwrap(),
+            Duration::from_secs(7 * 24 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn durations_reject_bad_input() {
+        for input in ["", "m", "5x", "1d2", "1.5h", "-1m", "99999999999999999999w"] {
+            assert!(parse_duration(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn times_of_day_parse() {
+        assert_eq!(
+            parse_time_of_day("04:30").unwrap(),
+            civil::time(4, 30, 0, 0)
+        );
+        assert_eq!(parse_time_of_day("0:05").unwrap(), civil::time(0, 5, 0, 0));
+
+        for input in ["24:00", "12:60", "4", "4:30:00", "a:b", ""] {
+            assert!(parse_time_of_day(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn next_occurrence_is_today_or_tomorrow() {
+        assert_eq!(
+            next("04:30", "2026-06-01T03:00"),
+            london("2026-06-01T04:30")
+        );
+        assert_eq!(
+            next("04:30", "2026-06-01T05:00"),
+            london("2026-06-02T04:30")
+        );
+        // a time that is exactly now has already happened
+        assert_eq!(
+            next("04:30", "2026-06-01T04:30"),
+            london("2026-06-02T04:30")
+        );
+    }
+
+    #[test]
+    fn time_skipped_by_the_clocks_going_forward_is_shifted_past_the_gap() {
+        // on 29 March 2026 the clocks go from 01:00 GMT straight to 02:00 BST
+        let next = next("01:30", "2026-03-29T00:30");
+        assert_eq!(next.datetime(), "2026-03-29T02:30".parse().unwrap());
+        assert_eq!(next.offset(), jiff::tz::offset(1));
+    }
+
+    #[test]
+    fn time_repeated_by_the_clocks_going_back_happens_once() {
+        // on 25 October 2026 the clocks go from 02:00 BST back to 01:00 GMT
+        let first = next("01:30", "2026-10-25T00:30");
+        assert_eq!(first.datetime(), "2026-10-25T01:30".parse().unwrap());
+        assert_eq!(first.offset(), jiff::tz::offset(1));
+
+        // once the first 01:30 has passed, the repeat of it is skipped
+        let after_first = first
+            .checked_add(jiff::SignedDuration::from_mins(1))
+            .unwrap();
+        let next = next_occurrence_after(civil::time(1, 30, 0, 0), &after_first).unwrap();
+        assert_eq!(next.date(), civil::date(2026, 10, 26));
+    }
+}
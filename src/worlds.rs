@@ -0,0 +1,124 @@
+//! World directories, which are named after the `level-name` in `server.properties` rather than
+//! always being `world`.
+//!
+//! Vanilla keeps the nether and the end inside the overworld as `DIM-1` and `DIM1`, while Bukkit
+//! based servers (Spigot, Paper, Purpur...) give each its own directory next to the overworld,
+//! suffixed with `_nether` and `_the_end`.
+
+use crate::{
+    error::{Error, Result},
+    properties::Properties,
+    server::get_server_dir_required,
+    session::get_alive_server_sessions,
+};
+use std::{fs, path::PathBuf};
+
+pub const DEFAULT_LEVEL_NAME: &str = "world";
+pub const LEVEL_NAME_KEY: &str = "level-name";
+
+const NETHER_SUFFIX: &str = "_nether";
+const END_SUFFIX: &str = "_the_end";
+const NETHER_DIM: &str = "DIM-1";
+const END_DIM: &str = "DIM1";
+
+/// Where a server keeps each dimension of its world
+#[derive(Debug)]
+pub struct WorldDirs {
+    pub level_name: String,
+    pub overworld: PathBuf,
+    pub nether: PathBuf,
+    pub end: PathBuf,
+}
+
+impl WorldDirs {
+    /// The directories at the top of the server that hold the world, which includes the nether and
+    /// the end only for Bukkit style layouts
+    pub fn top_level(&self) -> Vec<&PathBuf> {
+        [&self.overworld, &self.nether, &self.end]
+            .into_iter()
+            .filter(|dir| dir.parent() != Some(self.overworld.as_path()))
+            .collect()
+    }
+}
+
+pub fn level_name(properties: &Properties) -> String {
+    properties
+        .get(LEVEL_NAME_KEY)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_LEVEL_NAME)
+        .to_string()
+}
+
+/// Resolves the world directories of a server from its level-name, picking the Bukkit style
+/// directories when they exist
+pub fn get_world_dirs(server: &str) -> Result<WorldDirs> {
+    let server_dir = get_server_dir_required(server)?;
+    let level_name = level_name(&Properties::read(&server_dir)?);
+
+    let overworld = server_dir.join(&level_name);
+    let pick = |suffix: &str, dim: &str| {
+        let bukkit = server_dir.join(format!("{level_name}{suffix}"));
+        if bukkit.is_dir() {
+            bukkit
+        } else {
+            overworld.join(dim)
+        }
+    };
+
+    Ok(WorldDirs {
+        nether: pick(NETHER_SUFFIX, NETHER_DIM),
+        end: pick(END_SUFFIX, END_DIM),
+        overworld,
+        level_name,
+    })
+}
+
+/// Changes a server's level-name, renaming its world directories to match so that the server
+/// keeps loading the same world
+pub fn rename_level(server: &str, new_name: &str) -> Result<()> {
+    if get_alive_server_sessions()?.contains(server) {
+        return Err(Error::ServerRunning(server.to_string()));
+    }
+
+    let server_dir = get_server_dir_required(server)?;
+    let dirs = get_world_dirs(server)?;
+    if dirs.level_name == new_name {
+        return Ok(());
+    }
+
+    let renames: Vec<_> = dirs
+        .top_level()
+        .into_iter()
+        .filter(|dir| dir.exists())
+        .map(|dir| {
+            let suffix = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+                .split_off(dirs.level_name.len());
+            (dir.clone(), server_dir.join(format!("{new_name}{suffix}")))
+        })
+        .collect();
+
+    // every target is checked first so that a conflict doesn't leave the world half renamed
+    if let Some((_, target)) = renames.iter().find(|(_, target)| target.exists()) {
+        return Err(Error::WorldDirExists {
+            dir: target.clone(),
+        });
+    }
+
+    for (from, to) in &renames {
+        fs::rename(from, to)?;
+        println!(
+            "Renamed {} to {}",
+            from.file_name().unwrap_or_default().to_string_lossy(),
+            to.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+
+    let mut properties = Properties::read(&server_dir)?;
+    properties.set(LEVEL_NAME_KEY, new_name);
+    properties.write(&server_dir)?;
+
+    Ok(())
+}
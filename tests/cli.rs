@@ -0,0 +1,591 @@
+//! Runs the built binary end to end against a temporary home, configuration and cache directory,
+//! a fake API served by the test and a fake zellij on the `PATH`, so that nothing outside the
+//! temporary directory is read or written.
+
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+};
+use tempfile::TempDir;
+
+const PAPER_VERSION: &str = "1.21.4";
+const PAPER_JAR: &[u8] = b"not really a paper jar";
+
+/// Answers `list-sessions` with the contents of `sessions.txt` beside it, if there is one
+const FAKE_ZELLIJ: &str = r#"#!/bin/sh
+case "$1" in
+  --version) echo "zellij 0.41.2" ;;
+  list-sessions) cat "$(dirname "$0")/sessions.txt" 2>/dev/null ;;
+esac
+exit 0
+"#;
+
+/// Serves the PaperMC API for a single Paper build until the test process exits, returning the
+/// url to point `MCSERVER_API_URL` at
+fn serve_fake_api() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let sha256 = format!("{:x}", Sha256::digest(PAPER_JAR));
+    let jar_name = format!("paper-{PAPER_VERSION}-7.jar");
+    let project = "/api.papermc.io/v2/projects/paper".to_string();
+    let routes: Vec<(String, Vec<u8>)> = vec![
+        (
+            project.clone(),
+            format!(r#"{{"versions": ["{PAPER_VERSION}"]}}"#).into_bytes(),
+        ),
+        (
+            format!("{project}/versions/{PAPER_VERSION}/builds"),
+            format!(
+                r#"{{"builds": [{{"build": 7, "downloads": {{"application": {{"name": "{jar_name}", "sha256": "{sha256}"}}}}}}]}}"#
+            )
+            .into_bytes(),
+        ),
+        (
+            format!("/fill-data.papermc.io/v1/objects/{sha256}/{jar_name}"),
+            PAPER_JAR.to_vec(),
+        ),
+    ];
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            let path = request.split(' ').nth(1).unwrap_or_default();
+            let (status, body) = match routes.iter().find(|(route, _)| route == path) {
+                Some((_, body)) => ("200 OK", body.as_slice()),
+                None => ("404 Not Found", &[][..]),
+            };
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(body);
+        }
+    });
+
+    url
+}
+
+struct Sandbox {
+    dir: TempDir,
+    api_url: String,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = Self {
+            dir,
+            api_url: serve_fake_api(),
+        };
+
+        fs::create_dir_all(sandbox.servers_dir()).unwrap();
+        fs::create_dir_all(sandbox.path("config")).unwrap();
+        sandbox.write_config(&format!("servers_directory = {:?}", sandbox.servers_dir()));
+
+        let zellij = sandbox.path("bin/zellij");
+        fs::create_dir_all(zellij.parent().unwrap()).unwrap();
+        fs::write(&zellij, FAKE_ZELLIJ).unwrap();
+        fs::set_permissions(&zellij, fs::Permissions::from_mode(0o755)).unwrap();
+
+        sandbox
+    }
+
+    /// Replaces the configuration with the required keys and `rest`, which must set
+    /// `servers_directory`
+    fn write_config(&self, rest: &str) {
+        fs::write(
+            self.path("config/config.toml"),
+            format!("config_version = 1\ndefault_java_args = \"\"\nnogui = true\n{rest}\n"),
+        )
+        .unwrap();
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.dir.path().join(relative)
+    }
+
+    fn servers_dir(&self) -> PathBuf {
+        self.path("home/Servers")
+    }
+
+    fn set_sessions(&self, sessions: &str) {
+        fs::write(self.path("bin/sessions.txt"), sessions).unwrap();
+    }
+
+    /// A command running mcserver with only the sandbox in its environment, from the sandbox
+    fn command(&self, args: &[&str]) -> Command {
+        let path = env::join_paths(
+            [self.path("bin")]
+                .into_iter()
+                .chain(env::split_paths(&env::var_os("PATH").unwrap_or_default())),
+        )
+        .unwrap();
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mcserver"));
+        command
+            .args(args)
+            .env_clear()
+            .env("PATH", path)
+            .env("HOME", self.path("home"))
+            .env("MCSERVER_CONFIG_DIR", self.path("config"))
+            .env("MCSERVER_CACHE_DIR", self.path("cache"))
+            .env("MCSERVER_API_URL", &self.api_url)
+            .env("RUST_BACKTRACE", "0")
+            .current_dir(self.dir.path());
+        command
+    }
+
+    /// Runs mcserver, feeding it `stdin`
+    fn run_with_input(&self, args: &[&str], stdin: &str) -> Output {
+        feed(self.command(args), stdin)
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_with_input(args, "")
+    }
+
+    /// Runs mcserver, failing the test unless it succeeds, and returns its stdout
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "mcserver {} failed\nstdout: {}\nstderr: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// The names `list` prints with `args`, sorted since it doesn't list in name order
+    fn listed(&self, args: &[&str]) -> Vec<String> {
+        let args: Vec<&str> = ["list", "--names-only"]
+            .iter()
+            .chain(args)
+            .copied()
+            .collect();
+        let mut names: Vec<String> = self.ok(&args).lines().map(String::from).collect();
+        names.sort();
+        names
+    }
+}
+
+/// Runs a command, feeding it `stdin`
+fn feed(mut command: Command, stdin: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn jar_in(server_dir: &Path) -> Vec<u8> {
+    let jar_name = fs::read_to_string(server_dir.join(".mcserver/jar_file.txt")).unwrap();
+    fs::read(server_dir.join(jar_name.trim())).unwrap()
+}
+
+#[test]
+fn create_downloads_the_jar_from_the_api() {
+    let sandbox = Sandbox::new();
+
+    let name = sandbox.ok(&["new", "paper", "--quiet"]);
+    assert_eq!(name.trim(), "paper-server");
+
+    let server_dir = sandbox.servers_dir().join("paper-server");
+    assert_eq!(jar_in(&server_dir), PAPER_JAR);
+
+    // a second server of the same platform is numbered
+    let name = sandbox.ok(&["new", "paper", "--quiet"]);
+    assert_eq!(name.trim(), "paper-server-2");
+    assert_eq!(sandbox.listed(&[]), ["paper-server", "paper-server-2"]);
+}
+
+#[test]
+fn create_leaves_the_working_directory_alone() {
+    let sandbox = Sandbox::new();
+    // a relative servers directory only resolves to the same place while the working directory
+    // stays where mcserver was started, which the profile applied after creating relies on
+    sandbox.write_config(
+        "servers_directory = \"home/Servers\"\n\
+         [profiles.quiet]\n\
+         properties = { motd = \"hello\" }",
+    );
+
+    sandbox.ok(&[
+        "new",
+        "paper",
+        "--name",
+        "cwd",
+        "--profile",
+        "quiet",
+        "--quiet",
+    ]);
+
+    let server_dir = sandbox.servers_dir().join("cwd");
+    assert_eq!(
+        fs::read_to_string(server_dir.join("server.properties")).unwrap(),
+        "motd=hello\n"
+    );
+    assert!(!server_dir.join("home").exists());
+    assert_eq!(jar_in(&server_dir), PAPER_JAR);
+}
+
+#[test]
+fn create_reports_unknown_versions() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.run(&["new", "paper", "--version", "0.0.1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!(
+        "Version 0.0.1 not found for paper (recent versions: {PAPER_VERSION})"
+    )));
+    assert!(sandbox.listed(&[]).is_empty());
+}
+
+#[test]
+fn list_filters_by_session() {
+    let sandbox = Sandbox::new();
+    for name in ["alive", "dead", "idle"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+
+    sandbox.set_sessions(
+        "\x1b[32;1malive.mcserver\x1b[m [Created \x1b[35;1m1m\x1b[m ago] \n\
+         dead.mcserver [Created 2h ago] (EXITED - attach to resurrect)\n\
+         unrelated [Created 5s ago] \n",
+    );
+
+    assert_eq!(sandbox.listed(&[]), ["alive", "dead", "idle"]);
+
+    // sessions mcserver didn't create are ignored until they are adopted
+    assert!(sandbox.listed(&["--active"]).is_empty());
+    sandbox.ok(&["sessions", "--repair"]);
+
+    assert_eq!(sandbox.listed(&["--active"]), ["alive"]);
+    assert_eq!(sandbox.listed(&["--dead"]), ["dead"]);
+    assert_eq!(sandbox.listed(&["--inactive"]), ["dead", "idle"]);
+}
+
+#[test]
+fn remove_asks_for_the_name_unless_forced() {
+    let sandbox = Sandbox::new();
+    for name in ["keep", "drop", "gone"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+
+    // nothing entered cancels
+    let output = sandbox.run_with_input(&["remove", "keep"], "\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("skipped"));
+
+    let output = sandbox.run_with_input(&["remove", "drop"], "drop\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("removed"));
+
+    sandbox.ok(&["remove", "--force", "gone"]);
+
+    assert_eq!(sandbox.listed(&[]), ["keep"]);
+    assert!(!sandbox.servers_dir().join("drop").exists());
+
+    let output = sandbox.run(&["remove", "--force", "missing"]);
+    assert!(!output.status.success());
+}
+
+/// Runs mcserver from inside a server, which it can tell from the session it runs in or from
+/// the directory it runs from
+fn run_inside(sandbox: &Sandbox, server: &str, in_session: bool, args: &[&str]) -> Output {
+    let mut command = sandbox.command(args);
+    if in_session {
+        command.env("ZELLIJ_SESSION_NAME", format!("{server}.mcserver"));
+    } else {
+        command.current_dir(sandbox.servers_dir().join(server).join("world"));
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn the_current_server_is_only_removed_or_updated_when_allowed() {
+    let sandbox = Sandbox::new();
+    for name in ["here", "other"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+    let here = sandbox.servers_dir().join("here");
+    fs::create_dir_all(here.join("world")).unwrap();
+
+    for in_session in [true, false] {
+        for action in ["remove", "update"] {
+            let output = run_inside(&sandbox, "here", in_session, &[action, "--force", "here"]);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(!output.status.success(), "{action} succeeded");
+            assert!(
+                stderr.contains(&format!(
+                    "here is the server this shell is running in, pass --allow-current to \
+                     {action} it"
+                )),
+                "{stderr}"
+            );
+            assert_eq!(jar_in(&here), PAPER_JAR);
+        }
+
+        // other servers aren't guarded
+        let output = run_inside(&sandbox, "here", in_session, &["update", "other"]);
+        assert!(output.status.success());
+    }
+    let output = run_inside(&sandbox, "here", true, &["remove", "--force", "other"]);
+    assert!(output.status.success());
+
+    // restarting is what the current server is there for
+    let output = run_inside(&sandbox, "here", true, &["restart"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_inside(
+        &sandbox,
+        "here",
+        true,
+        &["remove", "--force", "--allow-current", "here"],
+    );
+    assert!(output.status.success());
+    assert!(!here.exists());
+}
+
+#[test]
+fn remove_takes_each_server_once() {
+    let sandbox = Sandbox::new();
+    for name in ["here", "twice", "kept"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+    let here = sandbox.servers_dir().join("here");
+
+    let stdout = sandbox.ok(&["remove", "--force", "twice", "twice"]);
+    assert_eq!(stdout.matches("twice").count(), 1, "{stdout}");
+
+    // "." is the server being run from, and the same server named again is dropped
+    let mut command = sandbox.command(&["remove", "--force", "--allow-current", ".", "here"]);
+    command.current_dir(&here);
+    let output = feed(command, "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert_eq!(stdout.matches("here").count(), 1, "{stdout}");
+
+    assert_eq!(sandbox.listed(&[]), ["kept"]);
+}
+
+#[test]
+fn remove_dot_asks_for_the_current_server() {
+    let sandbox = Sandbox::new();
+    sandbox.ok(&["new", "paper", "--name", "here", "--quiet"]);
+    let here = sandbox.servers_dir().join("here");
+
+    // refused without --allow-current before anything is asked
+    let mut command = sandbox.command(&["remove", "."]);
+    command.current_dir(&here);
+    let output = feed(command, "here\n");
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Enter"));
+    assert!(here.exists());
+
+    // the name asked for is the one "." resolved to, so entering "." doesn't confirm it
+    let mut command = sandbox.command(&["remove", "--allow-current", "."]);
+    command.current_dir(&here);
+    let output = feed(command, ".\n\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("Enter `here`"), "{stdout}");
+    assert!(stdout.contains("skipped"), "{stdout}");
+    assert!(here.exists());
+
+    let mut command = sandbox.command(&["remove", "--allow-current", "."]);
+    command.current_dir(&here);
+    let output = feed(command, "here\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("removed"));
+    assert!(!here.exists());
+}
+
+/// The server `info` picks when given `server`, with `MCSERVER_SERVER` set to `env` and run from
+/// `dir`, or what it fails with
+fn picked(
+    sandbox: &Sandbox,
+    server: Option<&str>,
+    env: Option<&str>,
+    dir: &Path,
+) -> Result<String, String> {
+    let mut command = sandbox.command(&["info"]);
+    command.args(server).current_dir(dir);
+    if let Some(env) = env {
+        command.env("MCSERVER_SERVER", env);
+    }
+
+    let output = command.output().unwrap();
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    Ok(stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Name: "))
+        .unwrap()
+        .to_string())
+}
+
+#[test]
+fn servers_are_picked_by_argument_then_environment_then_default() {
+    let sandbox = Sandbox::new();
+    for name in ["arg", "env", "default", "here"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+    sandbox.write_config(&format!(
+        "servers_directory = {:?}\ndefault_server = \"default\"",
+        sandbox.servers_dir()
+    ));
+    let (outside, here) = (sandbox.path(""), sandbox.servers_dir().join("here"));
+
+    let cases = [
+        (Some("arg"), Some("env"), &outside, "arg"),
+        (Some("arg"), None, &outside, "arg"),
+        (Some("arg"), Some("env"), &here, "arg"),
+        (None, Some("env"), &outside, "env"),
+        (None, Some("env"), &here, "env"),
+        (None, Some(""), &outside, "default"),
+        (None, None, &outside, "default"),
+        // "." is only taken from the directory when it is passed
+        (None, None, &here, "default"),
+        (Some("."), Some("env"), &here, "here"),
+        (Some("."), None, &here, "here"),
+    ];
+    for (server, env, dir, expected) in cases {
+        assert_eq!(
+            picked(&sandbox, server, env, dir).as_deref(),
+            Ok(expected),
+            "{server:?} with {env:?} from {}",
+            dir.display()
+        );
+    }
+
+    let output = sandbox
+        .command(&["default", "get"])
+        .env("MCSERVER_SERVER", "env")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\"default\"\n");
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("MCSERVER_SERVER is set to \"env\", which takes precedence")
+    );
+
+    // without a default, only an argument or the environment picks a server
+    sandbox.write_config(&format!("servers_directory = {:?}", sandbox.servers_dir()));
+    assert_eq!(
+        picked(&sandbox, None, Some("env"), &outside).as_deref(),
+        Ok("env")
+    );
+    let err = picked(&sandbox, None, None, &here).unwrap_err();
+    assert!(err.contains("There is no default server"), "{err}");
+}
+
+#[test]
+fn dot_is_the_server_containing_the_working_directory() {
+    let sandbox = Sandbox::new();
+    for name in ["here", "there"] {
+        sandbox.ok(&["new", "paper", "--name", name, "--quiet"]);
+    }
+    let here = sandbox.servers_dir().join("here");
+    fs::create_dir_all(here.join("world/region")).unwrap();
+
+    for dir in [here.clone(), here.join("world"), here.join("world/region")] {
+        assert_eq!(
+            picked(&sandbox, Some("."), None, &dir).as_deref(),
+            Ok("here"),
+            "from {}",
+            dir.display()
+        );
+
+        // commands that take a server without a default resolve it the same way
+        let mut command = sandbox.command(&["update", ".", "paper"]);
+        let output = command.current_dir(&dir).output().unwrap();
+        assert!(
+            String::from_utf8_lossy(&output.stderr)
+                .contains("here is the server this shell is running in")
+        );
+    }
+
+    let err = picked(&sandbox, Some("."), None, &sandbox.servers_dir()).unwrap_err();
+    assert!(
+        err.contains(
+            "The current directory is the servers directory, not a server (servers: here, there)"
+        ),
+        "{err}"
+    );
+
+    let err = picked(&sandbox, Some("."), None, &sandbox.path("")).unwrap_err();
+    assert!(err.contains("Invalid servers directory"), "{err}");
+}
+
+#[test]
+fn template_round_trip() {
+    let sandbox = Sandbox::new();
+    sandbox.ok(&["new", "paper", "--name", "base", "--quiet"]);
+
+    let base_dir = sandbox.servers_dir().join("base");
+    fs::write(
+        base_dir.join("server.properties"),
+        "motd=from the template\n",
+    )
+    .unwrap();
+    fs::create_dir_all(base_dir.join("plugins")).unwrap();
+    fs::write(base_dir.join("plugins/config.yml"), "enabled: true\n").unwrap();
+
+    sandbox.ok(&["template", "new", "base"]);
+    sandbox.ok(&["template", "from", "base", "--server", "copy"]);
+
+    let copy_dir = sandbox.servers_dir().join("copy");
+    assert_eq!(
+        fs::read_to_string(copy_dir.join("server.properties")).unwrap(),
+        "motd=from the template\n"
+    );
+    assert_eq!(
+        fs::read_to_string(copy_dir.join("plugins/config.yml")).unwrap(),
+        "enabled: true\n"
+    );
+    assert_eq!(jar_in(&copy_dir), PAPER_JAR);
+
+    // the template and the server made from it are independent of the original
+    sandbox.ok(&["remove", "--force", "base"]);
+    assert!(copy_dir.join("server.properties").exists());
+    assert!(
+        sandbox
+            .ok(&["template", "children", "base"])
+            .contains("copy")
+    );
+}